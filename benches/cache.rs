@@ -0,0 +1,47 @@
+//! Lookup throughput of `RrCache` by thread count, one shard against the default.
+//!
+//! Run with `cargo bench --bench cache`.
+
+use std::thread;
+use std::time::Instant;
+use dns::cache::{CacheLimits, RrCache, DEFAULT_SHARDS};
+use dns::types::{self, RecourseRecord};
+
+const NAMES: usize = 10_000;
+const LOOKUPS: usize = 200_000;
+
+fn name(i: usize) -> String {
+    format!("host{}.example.com.", i)
+}
+
+fn run(shards: usize, threads: usize) -> f64 {
+    // headroom so uneven shard fill doesn't evict
+    let limits = CacheLimits { max_entries: 4 * NAMES, ..Default::default() };
+    let cache = RrCache::with_shards(limits, shards);
+    for i in 0..NAMES {
+        let ip = [192, 0, (i >> 8) as u8, i as u8];
+        cache.insert(vec![RecourseRecord::new_ip(name(i).into(), types::CLASS_INET, 3600, ip.into())]);
+    }
+    let names: Vec<String> = (0..NAMES).map(name).collect();
+
+    let start = Instant::now();
+    thread::scope(|s| {
+        for t in 0..threads {
+            let (cache, names) = (&cache, &names);
+            s.spawn(move || {
+                for i in 0..LOOKUPS {
+                    let name = &names[(i * 7919 + t * 104729) % NAMES];
+                    assert!(cache.get(name, types::TYPE_A, types::CLASS_INET).is_some());
+                }
+            });
+        }
+    });
+    (threads * LOOKUPS) as f64 / start.elapsed().as_secs_f64()
+}
+
+fn main() {
+    println!("{:>8} {:>16} {:>16}", "threads", "1 shard op/s", format!("{} shards op/s", DEFAULT_SHARDS));
+    for threads in [1, 2, 4, 8] {
+        println!("{:>8} {:>16.0} {:>16.0}", threads, run(1, threads), run(DEFAULT_SHARDS, threads));
+    }
+}
@@ -0,0 +1,48 @@
+//! Packing throughput of a typical response: uncompressed, compressed with
+//! a fresh `CompressionContext` per message, and with one reused.
+//!
+//! Run with `cargo bench --bench pack`.
+
+use std::net::Ipv4Addr;
+use std::time::Instant;
+use bytes::BytesMut;
+use dns::msg::CompressionContext;
+use dns::{full_domain, types, Msg};
+
+const MESSAGES: usize = 200_000;
+
+fn response() -> Msg {
+    let mut msg = Msg::new();
+    msg.set_question(full_domain("www.example.com"), types::TYPE_A);
+    msg.answer.push(types::CNAME::new(full_domain("www.example.com"), types::CLASS_INET, 300, "edge.cdn.example.net.".into()).into());
+    for last in 1..=4 {
+        msg.answer.push(types::A::new(full_domain("edge.cdn.example.net"), types::CLASS_INET, 60, Ipv4Addr::new(192, 0, 2, last)).into());
+    }
+    for ns in ["ns1", "ns2"] {
+        msg.authority.push(types::NS::new(full_domain("cdn.example.net"), types::CLASS_INET, 3600, full_domain(format!("{}.cdn.example.net", ns))).into());
+    }
+    msg
+}
+
+fn run(mut pack: impl FnMut(&mut BytesMut)) -> (f64, usize) {
+    let mut buf = BytesMut::with_capacity(512);
+    let start = Instant::now();
+    for _ in 0..MESSAGES {
+        buf.clear();
+        pack(&mut buf);
+    }
+    (MESSAGES as f64 / start.elapsed().as_secs_f64(), buf.len())
+}
+
+fn main() {
+    let msg = response();
+    let mut ctx = CompressionContext::new();
+    println!("{:>16} {:>12} {:>8}", "", "msg/s", "bytes");
+    for (name, (rate, len)) in [
+        ("uncompressed", run(|buf| msg.pack(buf).unwrap())),
+        ("fresh context", run(|buf| msg.pack_compressed(&mut CompressionContext::new(), buf).unwrap())),
+        ("reused context", run(|buf| msg.pack_compressed(&mut ctx, buf).unwrap())),
+    ] {
+        println!("{:>16} {:>12.0} {:>8}", name, rate, len);
+    }
+}
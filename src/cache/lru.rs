@@ -0,0 +1,118 @@
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use super::CacheLimits;
+
+#[derive(Debug)]
+struct Slot<V> {
+    value: V,
+    size: usize,
+    tick: u64,
+}
+
+/// Map evicting its least recently used entries past an entry or byte budget.
+#[derive(Debug)]
+pub(crate) struct Lru<K, V> {
+    map: HashMap<K, Slot<V>>,
+    order: BTreeMap<u64, K>,
+    tick: u64,
+    bytes: usize,
+    limits: CacheLimits,
+}
+
+impl<K: Hash + Eq + Clone, V> Lru<K, V> {
+    pub(crate) fn new(limits: CacheLimits) -> Self {
+        Self {
+            map: HashMap::new(),
+            order: BTreeMap::new(),
+            tick: 0,
+            bytes: 0,
+            limits,
+        }
+    }
+
+    /// Looks up and marks as recently used.
+    pub(crate) fn get(&mut self, key: &K) -> Option<&mut V> {
+        let slot = self.map.get_mut(key)?;
+        self.order.remove(&slot.tick);
+        self.tick += 1;
+        slot.tick = self.tick;
+        self.order.insert(self.tick, key.clone());
+        Some(&mut slot.value)
+    }
+
+    /// Inserts and returns how many entries were evicted to make room.
+    /// Entries larger than the whole byte budget are not stored.
+    pub(crate) fn insert(&mut self, key: K, value: V, size: usize) -> usize {
+        self.remove(&key);
+        if size > self.limits.max_bytes || self.limits.max_entries == 0 {
+            return 0;
+        }
+        self.tick += 1;
+        self.order.insert(self.tick, key.clone());
+        self.map.insert(key, Slot { value, size, tick: self.tick });
+        self.bytes += size;
+
+        let mut evicted = 0;
+        while self.map.len() > self.limits.max_entries || self.bytes > self.limits.max_bytes {
+            let Some((_, key)) = self.order.pop_first() else { break; };
+            if let Some(slot) = self.map.remove(&key) {
+                self.bytes -= slot.size;
+                evicted += 1;
+            }
+        }
+        evicted
+    }
+
+    pub(crate) fn remove(&mut self, key: &K) -> Option<V> {
+        let slot = self.map.remove(key)?;
+        self.order.remove(&slot.tick);
+        self.bytes -= slot.size;
+        Some(slot.value)
+    }
+
+    pub(crate) fn retain<F: FnMut(&V) -> bool>(&mut self, mut f: F) {
+        let dropped: Vec<K> = self.map.iter().filter(|(_, slot)| !f(&slot.value)).map(|(k, _)| k.clone()).collect();
+        for key in dropped {
+            self.remove(&key);
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.map.clear();
+        self.order.clear();
+        self.bytes = 0;
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub(crate) fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    #[cfg(test)]
+    pub(crate) fn limits(&self) -> CacheLimits {
+        self.limits
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CacheLimits, Lru};
+
+    #[test]
+    fn test_lru() {
+        let mut lru = Lru::new(CacheLimits { max_entries: 2, max_bytes: 100 });
+        assert_eq!(lru.insert(1, "a", 10), 0);
+        assert_eq!(lru.insert(2, "b", 10), 0);
+        lru.get(&1);
+        assert_eq!(lru.insert(3, "c", 10), 1);
+        assert!(lru.get(&2).is_none());
+        assert_eq!(lru.insert(4, "d", 90), 1);
+        assert_eq!((lru.len(), lru.bytes()), (2, 100));
+        assert!(lru.get(&1).is_none() && lru.get(&3).is_some());
+        assert_eq!(lru.insert(5, "e", 101), 0);
+        assert!(lru.get(&5).is_none());
+    }
+}
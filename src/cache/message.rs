@@ -0,0 +1,415 @@
+use std::io::Cursor;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use byteorder::{BigEndian, ReadBytesExt};
+use bytes::BytesMut;
+use parking_lot::RwLock;
+use crate::msg::{PktMsgHeader, Question};
+use crate::types::edns::edns0::{mask_address, IEdns0, SubNet, EDNS0SUBNET};
+use crate::{types, util};
+use super::shard::{Shards, DEFAULT_SHARDS};
+use super::{CacheKey, CacheLimits, CacheStats, Stale, STALE_TTL};
+
+const FLAG_RESPONSE: u16 = 0x8000;
+const FLAG_TRUNCATED: u16 = 0x0200;
+const EDNS_DO: u32 = 0x8000;
+
+/// Question and DO bit of a message, the key of [`MsgCache`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MsgKey {
+    pub question: CacheKey,
+    pub dnssec_ok: bool,
+    /// Client subnet and prefix a response is scoped to (RFC 7871), `None`
+    /// when it holds for every client.
+    pub subnet: Option<(IpAddr, u8)>,
+}
+
+/// What [`scan`] learns from a packed message without unpacking its records.
+struct Scan {
+    bits: u16,
+    key: MsgKey,
+    /// Offsets of every TTL field, OPT excluded.
+    ttl_offsets: Vec<usize>,
+    min_ttl: Option<u32>,
+    subnet: Option<SubNet>,
+    /// Data offsets of the records of each A and AAAA RRset in the answer
+    /// section, with the data length.
+    rrsets: Vec<(usize, Vec<usize>)>,
+}
+
+fn scan(msg: &[u8]) -> Option<Scan> {
+    let mut cur = Cursor::new(msg);
+    let hdr = PktMsgHeader::unpack(&mut cur).ok()?;
+    if hdr.question_count != 1 {
+        return None;
+    }
+    let q = Question::unpack(&mut cur).ok()?;
+    let mut ret = Scan {
+        bits: hdr.bits,
+        key: MsgKey {
+            question: CacheKey::new(&q.name, q.q_type, q.q_class),
+            dnssec_ok: false,
+            subnet: None,
+        },
+        ttl_offsets: Vec::new(),
+        min_ttl: None,
+        subnet: None,
+        rrsets: Vec::new(),
+    };
+    let mut owners = Vec::new();
+    let count = hdr.answer_count as usize + hdr.authority_count as usize + hdr.additional_count as usize;
+    for i in 0..count {
+        let start = cur.position();
+        if !util::skip_domain_name(&mut cur) {
+            return None;
+        }
+        let typ = cur.read_u16::<BigEndian>().ok()?;
+        let _class = cur.read_u16::<BigEndian>().ok()?;
+        let offset = cur.position() as usize;
+        let ttl = cur.read_u32::<BigEndian>().ok()?;
+        let rd_length = cur.read_u16::<BigEndian>().ok()?;
+        let end = cur.position() as usize + rd_length as usize;
+        if end > msg.len() {
+            return None;
+        }
+        cur.set_position(end as u64);
+        if typ == types::TYPE_OPT {
+            ret.key.dnssec_ok = ttl & EDNS_DO != 0;
+            ret.subnet = scan_subnet(&msg[end - rd_length as usize..end]);
+        } else {
+            ret.ttl_offsets.push(offset);
+            ret.min_ttl = Some(ret.min_ttl.map_or(ttl, |min| min.min(ttl)));
+        }
+        if i < hdr.answer_count as usize && (typ == types::TYPE_A || typ == types::TYPE_AAAA) {
+            let mut name = Cursor::new(msg);
+            name.set_position(start);
+            let owner = (util::unpack_domain_name_cur(&mut name).ok()?.to_ascii_lowercase(), typ);
+            match owners.iter().position(|o| *o == owner) {
+                Some(set) => ret.rrsets[set].1.push(end - rd_length as usize),
+                None => {
+                    owners.push(owner);
+                    ret.rrsets.push((rd_length as usize, vec![end - rd_length as usize]));
+                }
+            }
+        }
+    }
+    ret.rrsets.retain(|(_, offsets)| offsets.len() > 1);
+    Some(ret)
+}
+
+/// The client subnet option among the options of an OPT record.
+fn scan_subnet(mut options: &[u8]) -> Option<SubNet> {
+    while options.len() >= 4 {
+        let code = u16::from_be_bytes([options[0], options[1]]);
+        let len = u16::from_be_bytes([options[2], options[3]]) as usize;
+        let data = options.get(4..4 + len)?;
+        if code == EDNS0SUBNET {
+            return SubNet::unpack(code, data).ok();
+        }
+        options = &options[4 + len..];
+    }
+    None
+}
+
+#[derive(Debug)]
+struct Entry {
+    data: Vec<u8>,
+    ttl_offsets: Vec<usize>,
+    rrsets: Vec<(usize, Vec<usize>)>,
+    inserted: Instant,
+    expires: Instant,
+    /// A refresh was requested since the entry went stale.
+    refreshing: bool,
+}
+
+/// Whole responses keyed by question and DO bit, stored packed with their
+/// original TTLs. A hit is the stored message with the request's ID and
+/// TTLs lowered by the time spent in the cache; meant for forwarders that
+/// pass upstream answers through.
+///
+/// Responses with a client subnet scope are only returned to requests
+/// whose subnet falls within it, so look up requests as they are sent
+/// upstream, after [`EcsPolicy::apply`](crate::client::EcsPolicy::apply).
+#[derive(Debug)]
+pub struct MsgCache {
+    entries: Shards<MsgKey, Entry>,
+    stats: CacheStats,
+    stale: Stale<MsgKey>,
+    /// Scope prefixes seen in responses, as (IPv4, prefix), longest first.
+    scopes: RwLock<Vec<(bool, u8)>>,
+    /// Places hits rotate address RRsets by, see [`MsgCache::with_rotation`].
+    rotation: Option<AtomicUsize>,
+}
+
+impl Default for MsgCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MsgCache {
+    pub fn new() -> Self {
+        Self::with_limits(CacheLimits::default())
+    }
+
+    pub fn with_limits(limits: CacheLimits) -> Self {
+        Self::with_shards(limits, DEFAULT_SHARDS)
+    }
+
+    /// Splits the cache into `shards` independently locked parts, each with
+    /// an equal share of `limits`, and at most `limits.max_entries` of them.
+    pub fn with_shards(limits: CacheLimits, shards: usize) -> Self {
+        Self {
+            entries: Shards::new(limits, shards),
+            stats: CacheStats::default(),
+            stale: Stale::default(),
+            scopes: RwLock::default(),
+            rotation: None,
+        }
+    }
+
+    /// Keeps entries up to `max_stale` past expiry for [`MsgCache::get_stale`].
+    pub fn with_serve_stale(mut self, max_stale: Duration) -> Self {
+        self.stale.max_stale = max_stale;
+        self
+    }
+
+    /// Rotates the A and AAAA RRsets of each hit one place further, as
+    /// [`Msg::rotate_answers`](crate::Msg::rotate_answers) does, for round
+    /// robin across the addresses.
+    pub fn with_rotation(mut self) -> Self {
+        self.rotation = Some(AtomicUsize::new(0));
+        self
+    }
+
+    /// Called once when a stale entry is first served, to refresh it in the
+    /// background; inserting the fresh response ends the stale period.
+    pub fn on_refresh<F: Fn(&MsgKey) + Send + Sync + 'static>(mut self, f: F) -> Self {
+        self.stale.refresh = Some(Box::new(f));
+        self
+    }
+
+    pub fn stats(&self) -> &CacheStats {
+        &self.stats
+    }
+
+    /// Size of the cached messages in bytes.
+    pub fn bytes(&self) -> usize {
+        self.entries.bytes()
+    }
+
+    /// Caches a packed NOERROR or NXDOMAIN response for its smallest TTL.
+    /// Truncated responses and ones without records are ignored; returns
+    /// whether the response was cached.
+    pub fn insert(&self, response: &[u8]) -> bool {
+        self.insert_at(response, Instant::now())
+    }
+
+    /// Cached response to a packed request.
+    pub fn get(&self, request: &[u8]) -> Option<BytesMut> {
+        self.get_at(request, Instant::now(), false)
+    }
+
+    /// Like [`MsgCache::get`], but a response that expired less than the
+    /// serve-stale age ago is returned with every TTL set to [`STALE_TTL`].
+    /// Meant for when every upstream failed (RFC 8767).
+    pub fn get_stale(&self, request: &[u8]) -> Option<BytesMut> {
+        self.get_at(request, Instant::now(), true)
+    }
+
+    pub fn remove(&self, key: &MsgKey) -> bool {
+        self.entries.lock(key).remove(key).is_some()
+    }
+
+    /// Drops expired entries, keeping ones still servable as stale.
+    pub fn purge(&self) {
+        let now = Instant::now();
+        let max_stale = self.stale.max_stale;
+        self.entries.retain(|e| e.expires + max_stale > now);
+    }
+
+    pub fn clear(&self) {
+        self.entries.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn insert_at(&self, response: &[u8], now: Instant) -> bool {
+        let Some(mut scan) = scan(response) else { return false; };
+        let rcode = scan.bits & 0xF;
+        if scan.bits & FLAG_RESPONSE == 0
+            || scan.bits & FLAG_TRUNCATED != 0
+            || !(rcode == types::RCODE_SUCCESS || rcode == types::RCODE_NAME_ERROR)
+        {
+            return false;
+        }
+        let ttl = match scan.min_ttl {
+            Some(ttl) if ttl > 0 => ttl,
+            _ => return false,
+        };
+        if let Some(subnet) = scan.subnet.as_ref().filter(|s| s.source_scope > 0) {
+            let scope = (subnet.address.is_ipv4(), subnet.source_scope);
+            scan.key.subnet = Some((mask_address(subnet.address, scope.1), scope.1));
+            let mut scopes = self.scopes.write();
+            if !scopes.contains(&scope) {
+                scopes.push(scope);
+                scopes.sort_by_key(|(_, prefix)| std::cmp::Reverse(*prefix));
+            }
+        }
+        let size = response.len() + scan.key.question.name.len();
+        let entry = Entry {
+            data: response.to_vec(),
+            ttl_offsets: scan.ttl_offsets,
+            rrsets: scan.rrsets,
+            inserted: now,
+            expires: now + Duration::from_secs(ttl as u64),
+            refreshing: false,
+        };
+        let evicted = self.entries.lock(&scan.key).insert(scan.key, entry, size);
+        self.stats.evicted(evicted);
+        true
+    }
+
+    /// Keys a response to `scan` could be cached under, most specific
+    /// scope first.
+    fn keys(&self, scan: Scan) -> Vec<MsgKey> {
+        let mut keys = Vec::new();
+        if let Some(subnet) = scan.subnet.filter(|s| s.source_netmask > 0) {
+            for (v4, scope) in self.scopes.read().iter() {
+                if *v4 == subnet.address.is_ipv4() && *scope <= subnet.source_netmask {
+                    let subnet = Some((mask_address(subnet.address, *scope), *scope));
+                    keys.push(MsgKey { subnet, ..scan.key.clone() });
+                }
+            }
+        }
+        keys.push(scan.key);
+        keys
+    }
+
+    fn get_at(&self, request: &[u8], now: Instant, stale: bool) -> Option<BytesMut> {
+        let scan = scan(request)?;
+        let keys = self.keys(scan);
+        let key = keys.iter().find(|key| self.entries.lock(key).get(key).is_some()).unwrap_or(&keys[keys.len() - 1]);
+        let mut entries = self.entries.lock(key);
+        let Some(entry) = entries.get(key) else {
+            self.stats.miss();
+            return None;
+        };
+        if entry.expires + self.stale.max_stale <= now {
+            entries.remove(key);
+            self.stats.miss();
+            return None;
+        }
+        let fresh = entry.expires > now;
+        if !fresh && !stale {
+            self.stats.miss();
+            return None;
+        }
+
+        let elapsed = (now - entry.inserted).as_secs() as u32;
+        let mut data = BytesMut::from(&entry.data[..]);
+        data[..2].copy_from_slice(&request[..2]);
+        for offset in &entry.ttl_offsets {
+            let ttl = u32::from_be_bytes(data[*offset..*offset + 4].try_into().unwrap());
+            let ttl = if fresh { ttl.saturating_sub(elapsed) } else { STALE_TTL };
+            data[*offset..*offset + 4].copy_from_slice(&ttl.to_be_bytes());
+        }
+        if let Some(rotation) = &self.rotation {
+            let n = rotation.fetch_add(1, Ordering::Relaxed);
+            for (len, offsets) in &entry.rrsets {
+                for (i, to) in offsets.iter().enumerate() {
+                    let from = offsets[(i + n) % offsets.len()];
+                    data[*to..*to + len].copy_from_slice(&entry.data[from..from + len]);
+                }
+            }
+        }
+        if fresh {
+            self.stats.hit();
+            return Some(data);
+        }
+
+        let refresh = !std::mem::replace(&mut entry.refreshing, true);
+        drop(entries);
+        self.stats.stale_hit();
+        if let (true, Some(f)) = (refresh, &self.stale.refresh) {
+            f(key);
+        }
+        Some(data)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+    use crate::msg::RR;
+    use crate::types::edns::edns0::SubNet;
+    use crate::types::{self, RecourseRecord, EDNS0, Opt};
+    use crate::Msg;
+    use super::MsgCache;
+
+    #[test]
+    fn test_msg_cache() {
+        let mut request = Msg::new();
+        request.set_question("www.example.com.", types::TYPE_A);
+        let mut response = Msg::new();
+        response.set_reply(&request);
+        response.answer.push(RecourseRecord::new_ip("www.example.com.".into(), types::CLASS_INET, 300, [192, 0, 2, 1].into()));
+        response.answer.push(RecourseRecord::new_ip("www.example.com.".into(), types::CLASS_INET, 100, [192, 0, 2, 2].into()));
+
+        let cache = MsgCache::new();
+        let now = Instant::now();
+        assert!(!cache.insert_at(&request.to_buf().unwrap(), now));
+        assert!(cache.insert_at(&response.to_buf().unwrap(), now));
+
+        request.set_question("WWW.example.com.", types::TYPE_A);
+        let hit = cache.get_at(&request.to_buf().unwrap(), now + Duration::from_secs(40), false).unwrap();
+        let hit = Msg::unpack(&hit).unwrap();
+        assert_eq!(hit.hdr.id, request.hdr.id);
+        assert_eq!(hit.answer[0].header().ttl, 260);
+        assert_eq!(hit.answer[1].header().ttl, 60);
+        assert!(cache.get_at(&request.to_buf().unwrap(), now + Duration::from_secs(100), true).is_none());
+
+        let cache = MsgCache::new().with_rotation();
+        assert!(cache.insert_at(&response.to_buf().unwrap(), now));
+        let first = |cache: &MsgCache| match &Msg::unpack(&cache.get_at(&request.to_buf().unwrap(), now, false).unwrap()).unwrap().answer[0] {
+            RecourseRecord::A(a) => a.a.octets()[3],
+            _ => 0,
+        };
+        assert_eq!([first(&cache), first(&cache), first(&cache)], [1, 2, 1]);
+    }
+
+    #[test]
+    fn test_msg_cache_scope() {
+        let with_subnet = |msg: &mut Msg, subnet: &str, source, scope| {
+            let mut opt = Opt::new(1232);
+            opt.option.push(EDNS0::SubNet(SubNet::new(subnet.parse().unwrap(), source, scope)));
+            msg.additional.push(opt.into());
+        };
+        let mut request = Msg::new();
+        request.set_question("www.example.com.", types::TYPE_A);
+        with_subnet(&mut request, "198.51.100.0", 24, 0);
+        let mut response = Msg::new();
+        response.set_reply(&request);
+        response.answer.push(RecourseRecord::new_ip("www.example.com.".into(), types::CLASS_INET, 300, [192, 0, 2, 1].into()));
+        with_subnet(&mut response, "198.51.100.0", 24, 16);
+
+        let cache = MsgCache::new();
+        assert!(cache.insert(&response.to_buf().unwrap()));
+        // same /16, other /16, and no subnet at all
+        for (subnet, hit) in [(Some("198.51.7.0"), true), (Some("203.0.113.0"), false), (None, false)] {
+            let mut other = Msg::new();
+            other.set_question("www.example.com.", types::TYPE_A);
+            if let Some(subnet) = subnet {
+                with_subnet(&mut other, subnet, 24, 0);
+            }
+            assert_eq!(cache.get(&other.to_buf().unwrap()).is_some(), hit, "{:?}", subnet);
+        }
+    }
+}
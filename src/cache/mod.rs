@@ -0,0 +1,129 @@
+//! Response caches shared by the client resolver and server middleware.
+
+mod lru;
+mod message;
+mod rrset;
+mod sanitize;
+mod shard;
+
+pub use message::{MsgCache, MsgKey};
+pub use rrset::RrCache;
+pub use sanitize::sanitize;
+pub use shard::DEFAULT_SHARDS;
+
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use crate::{full_domain, DomainString};
+
+/// Cache key of an RRset.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    /// Lowercased, fully qualified owner name.
+    pub name: DomainString,
+    pub typ: u16,
+    pub class: u16,
+}
+
+impl CacheKey {
+    pub fn new(name: &str, typ: u16, class: u16) -> Self {
+        Self {
+            name: full_domain(name.to_ascii_lowercase()),
+            typ,
+            class,
+        }
+    }
+}
+
+/// TTL of stale answers (RFC 8767 section 4).
+pub const STALE_TTL: u32 = 30;
+
+type RefreshFn<K> = Box<dyn Fn(&K) + Send + Sync>;
+
+/// Serve-stale (RFC 8767) settings: how long past expiry entries are kept,
+/// and who to ask for a refresh when one is served.
+pub(crate) struct Stale<K> {
+    max_stale: Duration,
+    refresh: Option<RefreshFn<K>>,
+}
+
+impl<K> Default for Stale<K> {
+    fn default() -> Self {
+        Self {
+            max_stale: Duration::ZERO,
+            refresh: None,
+        }
+    }
+}
+
+impl<K> fmt::Debug for Stale<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Stale")
+            .field("max_stale", &self.max_stale)
+            .field("refresh", &self.refresh.is_some())
+            .finish()
+    }
+}
+
+/// Size budget of a cache; least recently used entries are evicted past it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheLimits {
+    pub max_entries: usize,
+    /// Approximate, counted as the wire size of the cached data.
+    pub max_bytes: usize,
+}
+
+impl Default for CacheLimits {
+    fn default() -> Self {
+        Self {
+            max_entries: 10_000,
+            max_bytes: 32 << 20,
+        }
+    }
+}
+
+/// Counters of a cache.
+#[derive(Debug, Default)]
+pub struct CacheStats {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+    stale_hits: AtomicU64,
+}
+
+impl CacheStats {
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Lookups that found nothing or an expired entry.
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    /// Entries dropped to stay within [`CacheLimits`], expiry not counted.
+    pub fn evictions(&self) -> u64 {
+        self.evictions.load(Ordering::Relaxed)
+    }
+
+    /// Expired entries served because the caller asked for stale data.
+    pub fn stale_hits(&self) -> u64 {
+        self.stale_hits.load(Ordering::Relaxed)
+    }
+
+    fn hit(&self) {
+        self.hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn miss(&self) {
+        self.misses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn stale_hit(&self) {
+        self.stale_hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn evicted(&self, n: usize) {
+        self.evictions.fetch_add(n as u64, Ordering::Relaxed);
+    }
+}
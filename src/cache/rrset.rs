@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use bytes::BytesMut;
+use crate::msg::RR;
+use crate::types::{self, RecourseRecord};
+use crate::Msg;
+use super::shard::{Shards, DEFAULT_SHARDS};
+use super::sanitize::sanitize;
+use super::{CacheKey, CacheLimits, CacheStats, Stale, STALE_TTL};
+
+#[derive(Debug)]
+struct Entry {
+    rrset: Vec<RecourseRecord>,
+    expires: Instant,
+    /// A refresh was requested since the entry went stale.
+    refreshing: bool,
+}
+
+/// RRsets keyed by owner, type and class, expiring by TTL.
+///
+/// Hits return copies whose TTLs are the time left in the cache.
+#[derive(Debug)]
+pub struct RrCache {
+    entries: Shards<CacheKey, Entry>,
+    stats: CacheStats,
+    stale: Stale<CacheKey>,
+}
+
+impl Default for RrCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RrCache {
+    pub fn new() -> Self {
+        Self::with_limits(CacheLimits::default())
+    }
+
+    pub fn with_limits(limits: CacheLimits) -> Self {
+        Self::with_shards(limits, DEFAULT_SHARDS)
+    }
+
+    /// Splits the cache into `shards` independently locked parts, each with
+    /// an equal share of `limits`, and at most `limits.max_entries` of them.
+    pub fn with_shards(limits: CacheLimits, shards: usize) -> Self {
+        Self {
+            entries: Shards::new(limits, shards),
+            stats: CacheStats::default(),
+            stale: Stale::default(),
+        }
+    }
+
+    /// Keeps entries up to `max_stale` past expiry for [`RrCache::get_stale`].
+    pub fn with_serve_stale(mut self, max_stale: Duration) -> Self {
+        self.stale.max_stale = max_stale;
+        self
+    }
+
+    /// Called once when a stale entry is first served, to refresh it in the
+    /// background; inserting the fresh RRset ends the stale period.
+    pub fn on_refresh<F: Fn(&CacheKey) + Send + Sync + 'static>(mut self, f: F) -> Self {
+        self.stale.refresh = Some(Box::new(f));
+        self
+    }
+
+    pub fn stats(&self) -> &CacheStats {
+        &self.stats
+    }
+
+    /// Approximate size of the cached data in bytes.
+    pub fn bytes(&self) -> usize {
+        self.entries.bytes()
+    }
+
+    /// Caches an RRset for its smallest TTL; the key comes from the first record.
+    pub fn insert(&self, rrset: Vec<RecourseRecord>) {
+        self.insert_at(rrset, Instant::now())
+    }
+
+    /// Groups records into RRsets and caches each of them.
+    pub fn insert_records(&self, records: &[RecourseRecord]) {
+        let mut rrsets: HashMap<CacheKey, Vec<RecourseRecord>> = HashMap::new();
+        for rr in records {
+            rrsets.entry(key_of(rr)).or_default().push(rr.clone());
+        }
+        for rrset in rrsets.into_values() {
+            self.insert(rrset);
+        }
+    }
+
+    /// Caches the records of a response from servers for `bailiwick` that
+    /// pass [`sanitize`](super::sanitize()).
+    pub fn insert_response(&self, response: &Msg, bailiwick: &str) {
+        let mut response = response.clone();
+        sanitize(&mut response, bailiwick);
+        response.additional.retain(|rr| rr.header().typ != types::TYPE_OPT);
+        self.insert_records(&response.answer);
+        self.insert_records(&response.authority);
+        self.insert_records(&response.additional);
+    }
+
+    pub fn get(&self, name: &str, typ: u16, class: u16) -> Option<Vec<RecourseRecord>> {
+        self.get_at(&CacheKey::new(name, typ, class), Instant::now(), false)
+    }
+
+    /// Like [`RrCache::get`], but when the entry expired less than the
+    /// serve-stale age ago it is returned with [`STALE_TTL`]. Meant for when
+    /// every upstream failed (RFC 8767).
+    pub fn get_stale(&self, name: &str, typ: u16, class: u16) -> Option<Vec<RecourseRecord>> {
+        self.get_at(&CacheKey::new(name, typ, class), Instant::now(), true)
+    }
+
+    pub fn remove(&self, name: &str, typ: u16, class: u16) -> Option<Vec<RecourseRecord>> {
+        let key = CacheKey::new(name, typ, class);
+        self.entries.lock(&key).remove(&key).map(|e| e.rrset)
+    }
+
+    /// Drops expired entries, keeping ones still servable as stale.
+    pub fn purge(&self) {
+        let now = Instant::now();
+        let max_stale = self.stale.max_stale;
+        self.entries.retain(|e| e.expires + max_stale > now);
+    }
+
+    pub fn clear(&self) {
+        self.entries.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn insert_at(&self, rrset: Vec<RecourseRecord>, now: Instant) {
+        let Some(first) = rrset.first() else { return; };
+        let key = key_of(first);
+        let ttl = rrset.iter().map(|rr| rr.header().ttl).min().unwrap_or(0);
+        if ttl == 0 {
+            return;
+        }
+        let expires = now + Duration::from_secs(ttl as u64);
+        let size = key.name.len() + rrset.iter().map(wire_size).sum::<usize>();
+        let evicted = self.entries.lock(&key).insert(key, Entry { rrset, expires, refreshing: false }, size);
+        self.stats.evicted(evicted);
+    }
+
+    fn get_at(&self, key: &CacheKey, now: Instant, stale: bool) -> Option<Vec<RecourseRecord>> {
+        let mut entries = self.entries.lock(key);
+        let Some(entry) = entries.get(key) else {
+            self.stats.miss();
+            return None;
+        };
+        if entry.expires > now {
+            let left = (entry.expires - now).as_secs() as u32;
+            self.stats.hit();
+            return Some(entry.rrset.iter().map(|rr| with_ttl(rr, left)).collect());
+        }
+        if entry.expires + self.stale.max_stale <= now {
+            entries.remove(key);
+            self.stats.miss();
+            return None;
+        }
+        if !stale {
+            self.stats.miss();
+            return None;
+        }
+        let refresh = !std::mem::replace(&mut entry.refreshing, true);
+        let ret = entry.rrset.iter().map(|rr| with_ttl(rr, STALE_TTL)).collect();
+        drop(entries);
+        self.stats.stale_hit();
+        if let (true, Some(f)) = (refresh, &self.stale.refresh) {
+            f(key);
+        }
+        Some(ret)
+    }
+}
+
+fn key_of(rr: &RecourseRecord) -> CacheKey {
+    let hdr = rr.header();
+    CacheKey::new(&hdr.name, hdr.typ, hdr.class)
+}
+
+fn wire_size(rr: &RecourseRecord) -> usize {
+    let mut buf = BytesMut::new();
+    let _ = rr.header().pack(&mut buf);
+    let _ = rr.pack(&mut buf);
+    buf.len()
+}
+
+fn with_ttl(rr: &RecourseRecord, ttl: u32) -> RecourseRecord {
+    let mut rr = rr.clone();
+    rr.header_mut().ttl = ttl;
+    rr
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+    use crate::msg::RR;
+    use crate::types::{self, RecourseRecord};
+    use super::{CacheKey, RrCache, STALE_TTL};
+
+    #[test]
+    fn test_rr_cache() {
+        let cache = RrCache::new();
+        let now = Instant::now();
+        let a = |ttl, ip: [u8; 4]| RecourseRecord::new_ip("WWW.example.com.".into(), types::CLASS_INET, ttl, ip.into());
+        cache.insert_at(vec![a(300, [192, 0, 2, 1]), a(60, [192, 0, 2, 2])], now);
+        cache.insert_at(vec![a(0, [192, 0, 2, 3])], now);
+
+        let key = CacheKey::new("www.example.com", types::TYPE_A, types::CLASS_INET);
+        let hit = cache.get_at(&key, now + Duration::from_secs(20), false).unwrap();
+        assert_eq!(hit.len(), 2);
+        assert!(hit.iter().all(|rr| rr.header().ttl == 40));
+        assert!(cache.get_at(&key, now + Duration::from_secs(60), true).is_none());
+        assert!(cache.is_empty());
+        assert_eq!((cache.stats().hits(), cache.stats().misses()), (1, 1));
+    }
+
+    #[test]
+    fn test_serve_stale() {
+        let refreshed = Arc::new(AtomicUsize::new(0));
+        let counter = refreshed.clone();
+        let cache = RrCache::new()
+            .with_serve_stale(Duration::from_secs(3600))
+            .on_refresh(move |_| {
+                counter.fetch_add(1, Ordering::Relaxed);
+            });
+        let now = Instant::now();
+        let rr = RecourseRecord::new_ip("example.com.".into(), types::CLASS_INET, 60, [192, 0, 2, 1].into());
+        cache.insert_at(vec![rr], now);
+
+        let key = CacheKey::new("example.com.", types::TYPE_A, types::CLASS_INET);
+        let later = now + Duration::from_secs(120);
+        assert!(cache.get_at(&key, later, false).is_none());
+        for _ in 0..2 {
+            let hit = cache.get_at(&key, later, true).unwrap();
+            assert_eq!(hit[0].header().ttl, STALE_TTL);
+        }
+        assert_eq!(refreshed.load(Ordering::Relaxed), 1);
+        assert!(cache.get_at(&key, now + Duration::from_secs(3660), true).is_none());
+        assert_eq!(cache.stats().stale_hits(), 2);
+    }
+}
@@ -0,0 +1,106 @@
+use crate::msg::RR;
+use crate::types::{self, RecourseRecord};
+use crate::{util, DomainString, Msg};
+
+/// Drops records a cache must not trust from a response to a query sent to
+/// servers authoritative for `bailiwick`, and returns how many were dropped.
+///
+/// Kept are: answers along the CNAME chain starting at the question name,
+/// NS, SOA and DS in the authority section for an ancestor of a name in that
+/// chain, and additional A/AAAA records for kept NS targets. Every kept owner
+/// must be at or below `bailiwick`. OPT is left alone.
+pub fn sanitize(response: &mut Msg, bailiwick: &str) -> usize {
+    let Some(q) = response.question.first() else {
+        let count = response.answer.len() + response.authority.len() + response.additional.len();
+        response.answer.clear();
+        response.authority.clear();
+        response.additional.retain(|rr| rr.header().typ == types::TYPE_OPT);
+        return count - response.additional.len();
+    };
+    let (qname, qtype) = (q.name.clone(), q.q_type);
+    let in_bailiwick = |name: &str| util::is_subdomain(name, bailiwick);
+    let before = response.answer.len() + response.authority.len() + response.additional.len();
+
+    // Follow the chain in whatever order the records came in.
+    let mut chain: Vec<DomainString> = vec![qname];
+    let mut keep = vec![false; response.answer.len()];
+    loop {
+        let mut grown = false;
+        for (i, rr) in response.answer.iter().enumerate() {
+            let hdr = rr.header();
+            if keep[i] || !in_bailiwick(&hdr.name) || !chain.iter().any(|n| util::equal_names(n, &hdr.name)) {
+                continue;
+            }
+            match rr {
+                RecourseRecord::CNAME(cname) => {
+                    keep[i] = true;
+                    if !chain.iter().any(|n| util::equal_names(n, &cname.target)) {
+                        chain.push(cname.target.clone());
+                        grown = true;
+                    }
+                }
+                _ => keep[i] = qtype == types::TYPE_ANY || hdr.typ == qtype || hdr.typ == types::TYPE_RRSIG,
+            }
+        }
+        if !grown {
+            break;
+        }
+    }
+    let mut keep = keep.into_iter();
+    response.answer.retain(|_| keep.next().unwrap_or(false));
+
+    response.authority.retain(|rr| {
+        let hdr = rr.header();
+        matches!(hdr.typ, types::TYPE_NS | types::TYPE_SOA | types::TYPE_DS)
+            && in_bailiwick(&hdr.name)
+            && chain.iter().any(|n| util::is_subdomain(n, &hdr.name))
+    });
+
+    let ns_targets: Vec<DomainString> = response.answer.iter()
+        .chain(response.authority.iter())
+        .filter_map(|rr| match rr {
+            RecourseRecord::NS(ns) => Some(ns.ns.clone()),
+            _ => None,
+        })
+        .collect();
+    response.additional.retain(|rr| {
+        let hdr = rr.header();
+        match hdr.typ {
+            types::TYPE_OPT => true,
+            types::TYPE_A | types::TYPE_AAAA => {
+                in_bailiwick(&hdr.name) && ns_targets.iter().any(|n| util::equal_names(n, &hdr.name))
+            }
+            _ => false,
+        }
+    });
+
+    before - (response.answer.len() + response.authority.len() + response.additional.len())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::msg::RR;
+    use crate::types::{self, RecourseRecord, CNAME, NS};
+    use crate::Msg;
+    use super::sanitize;
+
+    #[test]
+    fn test_sanitize() {
+        let a = |name: &str, ip: [u8; 4]| RecourseRecord::new_ip(name.into(), types::CLASS_INET, 60, ip.into());
+        let mut msg = Msg::new();
+        msg.set_question("www.example.com.", types::TYPE_A);
+        msg.answer.push(a("web.example.com.", [192, 0, 2, 1]));
+        msg.answer.push(CNAME::new("www.example.com.".into(), types::CLASS_INET, 60, "web.example.com.".into()).into());
+        msg.answer.push(a("bank.example.net.", [203, 0, 113, 1]));
+        msg.authority.push(NS::new("example.com.".into(), types::CLASS_INET, 60, "ns.example.com.".into()).into());
+        msg.authority.push(NS::new("com.".into(), types::CLASS_INET, 60, "ns.evil.com.".into()).into());
+        msg.additional.push(a("ns.example.com.", [192, 0, 2, 53]));
+        msg.additional.push(a("ns.example.net.", [203, 0, 113, 53]));
+
+        assert_eq!(sanitize(&mut msg, "example.com."), 3);
+        let names: Vec<&str> = msg.answer.iter().chain(&msg.authority).chain(&msg.additional)
+            .map(|rr| rr.header().name.as_str())
+            .collect();
+        assert_eq!(names, ["web.example.com.", "www.example.com.", "example.com.", "ns.example.com."]);
+    }
+}
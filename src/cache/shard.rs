@@ -0,0 +1,105 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash};
+use parking_lot::{Mutex, MutexGuard};
+use super::lru::Lru;
+use super::CacheLimits;
+
+/// Default shard count, enough that a few dozen workers rarely contend.
+pub const DEFAULT_SHARDS: usize = 16;
+
+/// [`Lru`] split into independently locked shards by key hash. Each shard
+/// gets an equal part of the limits, so eviction is LRU per shard; there
+/// are never more shards than entries allowed, and the parts add up to the
+/// limits exactly.
+#[derive(Debug)]
+pub(crate) struct Shards<K, V> {
+    shards: Vec<Mutex<Lru<K, V>>>,
+    hasher: RandomState,
+}
+
+impl<K: Hash + Eq + Clone, V> Shards<K, V> {
+    pub(crate) fn new(limits: CacheLimits, count: usize) -> Self {
+        let count = count.clamp(1, limits.max_entries.max(1));
+        // the remainder goes one apiece to the first shards
+        let part = |total: usize, i: usize| total / count + usize::from(i < total % count);
+        Self {
+            shards: (0..count)
+                .map(|i| {
+                    Mutex::new(Lru::new(CacheLimits {
+                        max_entries: part(limits.max_entries, i),
+                        max_bytes: part(limits.max_bytes, i),
+                    }))
+                })
+                .collect(),
+            hasher: RandomState::new(),
+        }
+    }
+
+    pub(crate) fn lock(&self, key: &K) -> MutexGuard<'_, Lru<K, V>> {
+        let i = self.hasher.hash_one(key) as usize % self.shards.len();
+        self.shards[i].lock()
+    }
+
+    pub(crate) fn retain<F: FnMut(&V) -> bool>(&self, mut f: F) {
+        for shard in &self.shards {
+            shard.lock().retain(&mut f);
+        }
+    }
+
+    pub(crate) fn clear(&self) {
+        for shard in &self.shards {
+            shard.lock().clear();
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.shards.iter().map(|shard| shard.lock().len()).sum()
+    }
+
+    pub(crate) fn bytes(&self) -> usize {
+        self.shards.iter().map(|shard| shard.lock().bytes()).sum()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CacheLimits, Shards, DEFAULT_SHARDS};
+
+    #[test]
+    fn test_limits() {
+        // fewer entries allowed than shards
+        let shards = Shards::new(CacheLimits { max_entries: 5, max_bytes: 1000 }, DEFAULT_SHARDS);
+        assert_eq!(shards.shards.len(), 5);
+        for i in 0..200u32 {
+            shards.lock(&i).insert(i, (), 1);
+        }
+        assert!(shards.len() <= 5);
+
+        let shards = Shards::new(CacheLimits { max_entries: 100, max_bytes: 10 }, 4);
+        let parts: Vec<_> = shards.shards.iter().map(|s| s.lock().limits()).collect();
+        assert_eq!(parts.iter().map(|l| l.max_entries).sum::<usize>(), 100);
+        assert_eq!(parts.iter().map(|l| l.max_bytes).collect::<Vec<_>>(), [3, 3, 2, 2]);
+        for i in 0..200u32 {
+            shards.lock(&i).insert(i, (), 1);
+        }
+        assert!(shards.len() <= 10 && shards.bytes() <= 10);
+    }
+
+    #[test]
+    fn test_eviction() {
+        let shards = Shards::new(CacheLimits { max_entries: 4, max_bytes: 1000 }, 2);
+        let mut evicted = 0;
+        for i in 0..50u32 {
+            evicted += shards.lock(&i).insert(i, i, 1);
+            // a key used before every insert stays in its shard
+            assert!(shards.lock(&0).get(&0).is_some());
+        }
+        assert_eq!(shards.len(), 4);
+        assert_eq!(evicted, 46);
+        assert!(shards.lock(&49).get(&49).is_some());
+        shards.retain(|v| *v != 49);
+        assert_eq!(shards.len(), 3);
+        shards.clear();
+        assert_eq!((shards.len(), shards.bytes()), (0, 0));
+    }
+}
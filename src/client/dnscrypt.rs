@@ -0,0 +1,294 @@
+//! DNSCrypt v2. Resolvers publish short-lived certificates, signed with
+//! the provider key of their stamp, in a TXT record; queries are boxed to
+//! the certificate key with X25519 and XSalsa20-Poly1305 (or
+//! XChaCha20-Poly1305) under a fresh client key each.
+//!
+//! This module does no I/O; the caller owns the sockets. An exchange is:
+//! send [`cert_query`] as plain DNS to the resolver's address, pick the
+//! certificate from the reply with [`DnsCryptCert::select`], send the packet
+//! from [`DnsCryptCert::encrypt_query`] over UDP, and open what comes back
+//! with [`DnsCryptQuery::decrypt_response`]. Certificates can be reused
+//! until [`DnsCryptCert::valid_until`].
+
+use core::fmt;
+use crypto_box::aead::Aead;
+use crypto_box::{ChaChaBox, PublicKey, SalsaBox, SecretKey};
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use rand::RngCore;
+use crate::types::RecourseRecord;
+use crate::{types, Error, Msg, Result};
+
+const CERT_MAGIC: &[u8; 4] = b"DNSC";
+/// Starts every response.
+pub const RESOLVER_MAGIC: &[u8; 8] = b"r6fnvWj8";
+const ES_XSALSA20: u16 = 0x0001;
+const ES_XCHACHA20: u16 = 0x0002;
+/// Magic, version, minor version and signature, then the signed part.
+const SIGNED_START: usize = 4 + 2 + 2 + 64;
+/// Resolver key, client magic, serial and validity.
+const SIGNED_LEN: usize = 32 + 8 + 4 + 4 + 4;
+const HALF_NONCE: usize = 12;
+const NONCE: usize = 24;
+const TAG: usize = 16;
+/// UDP queries are padded to at least this and to a multiple of
+/// [`PADDING_BLOCK`].
+const MIN_QUERY_LEN: usize = 256;
+const PADDING_BLOCK: usize = 64;
+
+/// The question whose TXT answer holds the certificates of the resolver
+/// with provider name `provider_name`, e.g. `2.dnscrypt-cert.example.com`.
+pub fn cert_query(provider_name: &str) -> Msg {
+    let mut msg = Msg::new();
+    msg.set_question(crate::full_domain(provider_name), types::TYPE_TXT);
+    msg
+}
+
+/// A resolver certificate whose signature has been checked.
+#[derive(Clone)]
+pub struct DnsCryptCert {
+    es_version: u16,
+    resolver_key: PublicKey,
+    client_magic: [u8; 8],
+    serial: u32,
+    valid_from: u32,
+    valid_until: u32,
+}
+
+/// State of one encrypted query, needed to open its response.
+pub struct DnsCryptQuery {
+    id: u16,
+    /// The query as sent, with ID 0.
+    query: Msg,
+    nonce: [u8; HALF_NONCE],
+    cipher: Cipher,
+}
+
+enum Cipher {
+    XSalsa20(SalsaBox),
+    XChaCha20(ChaChaBox),
+}
+
+impl fmt::Debug for DnsCryptCert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DnsCryptCert")
+            .field("es_version", &self.es_version)
+            .field("client_magic", &hex::encode(self.client_magic))
+            .field("serial", &self.serial)
+            .field("valid_from", &self.valid_from)
+            .field("valid_until", &self.valid_until)
+            .finish()
+    }
+}
+
+impl DnsCryptCert {
+    /// Parses one certificate and checks its signature by `provider_key`.
+    pub fn parse(data: &[u8], provider_key: &[u8; 32]) -> Result<Self> {
+        if data.len() < SIGNED_START + SIGNED_LEN || &data[..4] != CERT_MAGIC {
+            return Err(Error::new("DNSCrypt: not a certificate"));
+        }
+        let es_version = u16::from_be_bytes([data[4], data[5]]);
+        if es_version != ES_XSALSA20 && es_version != ES_XCHACHA20 {
+            return Err(Error::new(format!("DNSCrypt: unsupported encryption system {}", es_version)));
+        }
+        let key = VerifyingKey::from_bytes(provider_key).map_err(|e| Error::new(format!("DNSCrypt: bad provider key: {}", e)))?;
+        let signature = Signature::from_bytes(data[8..SIGNED_START].try_into().unwrap());
+        let signed = &data[SIGNED_START..];
+        key.verify(signed, &signature).map_err(|_| Error::new("DNSCrypt: bad certificate signature"))?;
+
+        let u32_at = |at: usize| u32::from_be_bytes(signed[at..at + 4].try_into().unwrap());
+        Ok(Self {
+            es_version,
+            resolver_key: PublicKey::from_slice(&signed[..32]).unwrap(),
+            client_magic: signed[32..40].try_into().unwrap(),
+            serial: u32_at(40),
+            valid_from: u32_at(44),
+            valid_until: u32_at(48),
+        })
+    }
+
+    /// The certificate to use among the TXT records of a response to
+    /// [`cert_query`]: the one with the highest serial of those signed by
+    /// `provider_key` and valid at `now` (UNIX seconds).
+    pub fn select(response: &Msg, provider_key: &[u8; 32], now: u32) -> Result<Self> {
+        response
+            .answer
+            .iter()
+            .filter_map(txt_data)
+            .filter_map(|data| Self::parse(&data, provider_key).ok())
+            .filter(|cert| cert.is_valid_at(now))
+            .max_by_key(|cert| (cert.serial, cert.es_version))
+            .ok_or_else(|| Error::new("DNSCrypt: no valid certificate"))
+    }
+
+    pub fn is_valid_at(&self, now: u32) -> bool {
+        self.valid_from <= now && now <= self.valid_until
+    }
+
+    pub fn serial(&self) -> u32 {
+        self.serial
+    }
+
+    /// End of the validity period, in UNIX seconds; fetch certificates
+    /// again before then.
+    pub fn valid_until(&self) -> u32 {
+        self.valid_until
+    }
+
+    /// Encrypts `msg` for the resolver, padded for UDP. The query is sent
+    /// with ID 0; the response gets the ID of `msg` back.
+    pub fn encrypt_query(&self, msg: &Msg) -> Result<(Vec<u8>, DnsCryptQuery)> {
+        let mut query = msg.clone();
+        query.hdr.id = 0;
+        let plaintext = pad(&query.to_buf()?);
+
+        let mut rng = rand::thread_rng();
+        let mut secret = [0u8; 32];
+        rng.fill_bytes(&mut secret);
+        let secret = SecretKey::from(secret);
+        let mut nonce = [0u8; HALF_NONCE];
+        rng.fill_bytes(&mut nonce);
+        let cipher = match self.es_version {
+            ES_XCHACHA20 => Cipher::XChaCha20(ChaChaBox::new(&self.resolver_key, &secret)),
+            _ => Cipher::XSalsa20(SalsaBox::new(&self.resolver_key, &secret)),
+        };
+        let mut full_nonce = [0u8; NONCE];
+        full_nonce[..HALF_NONCE].copy_from_slice(&nonce);
+        let encrypted = cipher.encrypt(&full_nonce, &plaintext)?;
+
+        let mut packet = Vec::with_capacity(8 + 32 + HALF_NONCE + encrypted.len());
+        packet.extend_from_slice(&self.client_magic);
+        packet.extend_from_slice(secret.public_key().as_bytes());
+        packet.extend_from_slice(&nonce);
+        packet.extend_from_slice(&encrypted);
+        Ok((packet, DnsCryptQuery { id: msg.hdr.id, query, nonce, cipher }))
+    }
+}
+
+impl DnsCryptQuery {
+    /// Opens the resolver's response to this query and checks it answers
+    /// the query with [`validate_response`](super::validate_response).
+    pub fn decrypt_response(&self, data: &[u8]) -> Result<Msg> {
+        if data.len() < 8 + NONCE + TAG || &data[..8] != RESOLVER_MAGIC {
+            return Err(Error::new("DNSCrypt: not a response"));
+        }
+        let nonce: [u8; NONCE] = data[8..8 + NONCE].try_into().unwrap();
+        if nonce[..HALF_NONCE] != self.nonce {
+            return Err(Error::new("DNSCrypt: response to another query"));
+        }
+        let plaintext = self.cipher.decrypt(&nonce, &data[8 + NONCE..])?;
+        let mut reply = Msg::unpack(unpad(&plaintext)?)?;
+        super::validate_response(&self.query, &reply, None, None, false)?;
+        reply.hdr.id = self.id;
+        Ok(reply)
+    }
+}
+
+impl Cipher {
+    fn encrypt(&self, nonce: &[u8; NONCE], plaintext: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Cipher::XSalsa20(b) => b.encrypt(nonce.into(), plaintext),
+            Cipher::XChaCha20(b) => b.encrypt(nonce.into(), plaintext),
+        }
+        .map_err(|_| Error::new("DNSCrypt: encryption failed"))
+    }
+
+    fn decrypt(&self, nonce: &[u8; NONCE], encrypted: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Cipher::XSalsa20(b) => b.decrypt(nonce.into(), encrypted),
+            Cipher::XChaCha20(b) => b.decrypt(nonce.into(), encrypted),
+        }
+        .map_err(|_| Error::new("DNSCrypt: response does not decrypt"))
+    }
+}
+
+/// The character strings of a TXT record joined.
+fn txt_data(rr: &RecourseRecord) -> Option<Vec<u8>> {
+    match rr {
+        RecourseRecord::TXT(val) => Some(val.data()),
+        _ => None,
+    }
+}
+
+/// ISO/IEC 7816-4 padding: 0x80, then zeros.
+fn pad(dns: &[u8]) -> Vec<u8> {
+    let len = (dns.len() + 1).div_ceil(PADDING_BLOCK) * PADDING_BLOCK;
+    let mut buf = Vec::with_capacity(len.max(MIN_QUERY_LEN));
+    buf.extend_from_slice(dns);
+    buf.push(0x80);
+    buf.resize(len.max(MIN_QUERY_LEN), 0);
+    buf
+}
+
+fn unpad(plaintext: &[u8]) -> Result<&[u8]> {
+    let end = plaintext.iter().rposition(|b| *b != 0).filter(|i| plaintext[*i] == 0x80);
+    end.map(|i| &plaintext[..i]).ok_or_else(|| Error::new("DNSCrypt: bad padding"))
+}
+
+#[cfg(test)]
+mod test {
+    use crypto_box::aead::Aead;
+    use crypto_box::{PublicKey, SalsaBox, SecretKey};
+    use ed25519_dalek::{Signer, SigningKey};
+    use crate::{full_domain, types, Msg};
+    use super::{cert_query, pad, unpad, DnsCryptCert, RESOLVER_MAGIC};
+
+    fn cert(provider: &SigningKey, resolver: &PublicKey, serial: u32, valid: (u32, u32)) -> Vec<u8> {
+        let mut signed = resolver.as_bytes().to_vec();
+        signed.extend_from_slice(b"magic-01");
+        for v in [serial, valid.0, valid.1] {
+            signed.extend_from_slice(&v.to_be_bytes());
+        }
+        let mut cert = b"DNSC\x00\x01\x00\x00".to_vec();
+        cert.extend_from_slice(&provider.sign(&signed).to_bytes());
+        cert.extend_from_slice(&signed);
+        cert
+    }
+
+    #[test]
+    fn test_dnscrypt() {
+        let provider = SigningKey::from_bytes(&[7; 32]);
+        let provider_key = provider.verifying_key().to_bytes();
+        let resolver_secret = SecretKey::from([9; 32]);
+        let resolver_key = resolver_secret.public_key();
+
+        let mut certs = cert_query("2.dnscrypt-cert.example.com");
+        assert_eq!(certs.question[0].q_type, types::TYPE_TXT);
+        let mut forged = cert(&provider, &resolver_key, 9, (100, 200));
+        // a higher serial, but not as signed
+        forged[super::SIGNED_START + 40 + 3] ^= 1;
+        for data in [cert(&provider, &resolver_key, 1, (100, 200)), cert(&provider, &resolver_key, 2, (100, 200)), cert(&provider, &resolver_key, 3, (300, 400)), forged] {
+            certs.answer.push(types::TXT::new(full_domain("2.dnscrypt-cert.example.com"), types::CLASS_INET, 60, vec![data]).into());
+        }
+        let certs = Msg::unpack(&certs.to_buf().unwrap()).unwrap();
+        let cert = DnsCryptCert::select(&certs, &provider_key, 150).unwrap();
+        assert_eq!((cert.serial(), cert.valid_until()), (2, 200));
+        assert!(DnsCryptCert::select(&certs, &provider_key, 250).is_err());
+        assert!(DnsCryptCert::select(&certs, &[1; 32], 150).is_err());
+
+        let mut msg = Msg::new();
+        msg.set_question(full_domain("www.example.com"), types::TYPE_A);
+        let (packet, query) = cert.encrypt_query(&msg).unwrap();
+        assert_eq!(&packet[..8], b"magic-01");
+        assert_eq!(packet.len(), 8 + 32 + 12 + 256 + 16);
+
+        // the resolver's side
+        let client_key = PublicKey::from_slice(&packet[8..40]).unwrap();
+        let shared = SalsaBox::new(&client_key, &resolver_secret);
+        let mut nonce = [0u8; 24];
+        nonce[..12].copy_from_slice(&packet[40..52]);
+        let plaintext = shared.decrypt(&nonce.into(), &packet[52..]).unwrap();
+        let mut reply = Msg::unpack(unpad(&plaintext).unwrap()).unwrap();
+        assert_eq!((reply.hdr.id, reply.question.len()), (0, 1));
+        reply.hdr.response = true;
+        nonce[12..].copy_from_slice(&[5; 12]);
+        let response = [&RESOLVER_MAGIC[..], &nonce, &shared.encrypt(&nonce.into(), &pad(&reply.to_buf().unwrap())[..]).unwrap()].concat();
+
+        let reply = query.decrypt_response(&response).unwrap();
+        assert!(reply.hdr.response);
+        assert_eq!(reply.hdr.id, msg.hdr.id);
+        let mut tampered = response.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(query.decrypt_response(&tampered).is_err());
+        assert!(cert.encrypt_query(&msg).unwrap().1.decrypt_response(&response).is_err());
+    }
+}
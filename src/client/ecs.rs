@@ -0,0 +1,149 @@
+//! EDNS Client Subnet (RFC 7871) on forwarded queries: a shortened client
+//! address lets upstreams pick nearby answers without learning who asked.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use crate::types::edns::edns0::{mask_address, SubNet};
+use crate::types::EDNS0;
+use crate::Msg;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EcsPolicy {
+    /// Most bits of an IPv4 client address sent upstream.
+    pub v4_prefix: u8,
+    /// Most bits of an IPv6 client address sent upstream.
+    pub v6_prefix: u8,
+    /// Add an option from the client address to requests without one.
+    pub inject: bool,
+    /// Drop options clients send rather than passing them on shortened.
+    pub strip: bool,
+}
+
+impl Default for EcsPolicy {
+    fn default() -> Self {
+        Self { v4_prefix: 24, v6_prefix: 56, inject: true, strip: false }
+    }
+}
+
+impl EcsPolicy {
+    /// Only strips, never sends a client subnet.
+    pub fn strip_only() -> Self {
+        Self { inject: false, strip: true, ..Default::default() }
+    }
+
+    /// Rewrites the client subnet of `request`, about to be forwarded on
+    /// behalf of `client`. An option the client sent is kept but cut to the
+    /// policy prefix, or dropped with `strip`; one is then added if there
+    /// is none and `client` is a public address.
+    pub fn apply(&self, request: &mut Msg, client: IpAddr) {
+        if let Some(opt) = request.get_edns0_mut() {
+            if self.strip {
+                opt.option.retain(|o| !matches!(o, EDNS0::SubNet(_)));
+            } else if let Some(subnet) = opt.option.iter_mut().find_map(|o| match o {
+                EDNS0::SubNet(val) => Some(val),
+                _ => None,
+            }) {
+                let prefix = subnet.source_netmask.min(self.prefix(subnet.address));
+                *subnet = SubNet::new(mask_address(subnet.address, prefix), prefix, 0);
+                return;
+            }
+        }
+        if !self.inject || !is_global(client) {
+            return;
+        }
+        let client = match client {
+            IpAddr::V6(ip) => ip.to_ipv4_mapped().map_or(client, IpAddr::V4),
+            ip => ip,
+        };
+        let prefix = self.prefix(client);
+        let subnet = EDNS0::SubNet(SubNet::new(mask_address(client, prefix), prefix, 0));
+        request.edns_mut().option.push(subnet);
+    }
+
+    fn prefix(&self, ip: IpAddr) -> u8 {
+        match ip {
+            IpAddr::V4(_) => self.v4_prefix.min(32),
+            IpAddr::V6(_) => self.v6_prefix.min(128),
+        }
+    }
+}
+
+/// Whether `ip` says anything about where a client is: not loopback,
+/// private, link-local or unspecified.
+fn is_global(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_global_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_global_v4(ip),
+            None => !(ip.is_loopback() || ip.is_unspecified() || is_unique_local(ip) || is_unicast_link_local(ip)),
+        },
+    }
+}
+
+fn is_global_v4(ip: Ipv4Addr) -> bool {
+    !(ip.is_private() || ip.is_loopback() || ip.is_link_local() || ip.is_unspecified())
+}
+
+/// fc00::/7
+fn is_unique_local(ip: Ipv6Addr) -> bool {
+    ip.segments()[0] & 0xfe00 == 0xfc00
+}
+
+/// fe80::/10
+fn is_unicast_link_local(ip: Ipv6Addr) -> bool {
+    ip.segments()[0] & 0xffc0 == 0xfe80
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::IpAddr;
+    use crate::msg::RR;
+    use crate::types::{EDNS0, Opt};
+    use crate::types::edns::edns0::SubNet;
+    use crate::{full_domain, types, Msg};
+    use super::EcsPolicy;
+
+    fn subnet(msg: &Msg) -> Option<(IpAddr, u8)> {
+        msg.is_edns0().and_then(Opt::subnet).map(|s| (s.address, s.source_netmask))
+    }
+
+    #[test]
+    fn test_ecs() {
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+        let policy = EcsPolicy::default();
+        let mut request = Msg::new();
+        request.set_question(full_domain("www.example.com"), types::TYPE_A);
+
+        let mut private = request.clone();
+        policy.apply(&mut private, ip("192.168.1.20"));
+        assert!(private.is_edns0().is_none());
+
+        let mut injected = request.clone();
+        policy.apply(&mut injected, ip("203.0.113.77"));
+        assert_eq!(subnet(&injected), Some((ip("203.0.113.0"), 24)));
+        policy.apply(&mut injected, ip("198.51.100.1"));
+        assert_eq!(injected.is_edns0().unwrap().option.len(), 1);
+        let mut v6 = request.clone();
+        policy.apply(&mut v6, ip("2001:db8:aaaa:bbbb::1"));
+        assert_eq!(subnet(&v6), Some((ip("2001:db8:aaaa:bb00::"), 56)));
+
+        // a client's own option is shortened, or replaced when stripped
+        let mut opt = Opt::new(4096);
+        opt.option.push(EDNS0::SubNet(SubNet::new(ip("198.51.100.129"), 32, 0)));
+        request.additional.push(opt.into());
+        let mut passed = request.clone();
+        policy.apply(&mut passed, ip("203.0.113.77"));
+        assert_eq!(subnet(&passed), Some((ip("198.51.100.0"), 24)));
+        let mut stripped = request.clone();
+        EcsPolicy { strip: true, ..policy }.apply(&mut stripped, ip("203.0.113.77"));
+        assert_eq!(subnet(&stripped), Some((ip("203.0.113.0"), 24)));
+        let mut stripped_only = request.clone();
+        EcsPolicy::strip_only().apply(&mut stripped_only, ip("203.0.113.77"));
+        assert_eq!(subnet(&stripped_only), None);
+
+        // shortened addresses go on the wire in as few bytes as the prefix needs
+        let back = Msg::unpack(&passed.to_buf().unwrap()).unwrap();
+        // option code and length, family and prefixes, three address bytes
+        assert_eq!(back.additional[0].header().rd_length, 4 + 4 + 3);
+        assert_eq!(subnet(&back), Some((ip("198.51.100.0"), 24)));
+    }
+}
@@ -0,0 +1,113 @@
+use std::io;
+use js_sys::{Promise, Uint8Array};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Headers, Request, RequestInit, Response};
+use crate::{full_domain, Msg};
+
+const CONTENT_TYPE: &str = "application/dns-message";
+
+#[wasm_bindgen]
+extern "C" {
+    /// `fetch` of the global scope, so it works in windows and workers alike.
+    #[wasm_bindgen(js_name = fetch)]
+    fn global_fetch(request: &Request) -> Promise;
+}
+
+/// Sends `msg` to the DoH endpoint `url` (RFC 8484 POST) with the browser
+/// fetch API. The query goes out with ID 0 as section 4.1 recommends; the
+/// reply must answer it, and gets the ID of `msg` back.
+pub async fn fetch_doh(url: &str, msg: &Msg) -> io::Result<Msg> {
+    let (buf, query) = super::doh_query(msg)?;
+
+    let headers = Headers::new().map_err(js_error)?;
+    headers.set("content-type", CONTENT_TYPE).map_err(js_error)?;
+    headers.set("accept", CONTENT_TYPE).map_err(js_error)?;
+    let init = RequestInit::new();
+    init.set_method("POST");
+    init.set_headers(&headers);
+    init.set_body(&Uint8Array::from(&buf[..]));
+    let request = Request::new_with_str_and_init(url, &init).map_err(js_error)?;
+
+    let body = bytes(send(&request).await?).await?;
+    Ok(super::doh_reply(msg, &query, &body)?)
+}
+
+/// Asks `endpoint` for `qtype` records at `name` with the JSON API, see
+/// [`parse_json_answer`](super::parse_json_answer).
+#[cfg(feature = "json")]
+pub async fn fetch_doh_json(endpoint: &str, name: &str, qtype: u16) -> io::Result<Msg> {
+    let headers = Headers::new().map_err(js_error)?;
+    headers.set("accept", super::JSON_CONTENT_TYPE).map_err(js_error)?;
+    let init = RequestInit::new();
+    init.set_method("GET");
+    init.set_headers(&headers);
+    let url = super::json_query_url(endpoint, name, qtype);
+    let request = Request::new_with_str_and_init(&url, &init).map_err(js_error)?;
+
+    let response = send(&request).await?;
+    let body = JsFuture::from(response.text().map_err(js_error)?).await.map_err(js_error)?;
+    let body = body.as_string().ok_or_else(|| io::Error::other("DoH JSON body is not text"))?;
+    let reply = super::parse_json_answer(&body)?;
+    // JSON answers have no ID, so only the question is compared
+    let mut query = Msg::new();
+    query.set_question(full_domain(name), qtype);
+    query.hdr.id = 0;
+    super::validate_response(&query, &reply, None, None, false)?;
+    Ok(reply)
+}
+
+/// Fetches the `ObliviousDoHConfigs` of `target` (scheme and host, e.g.
+/// `https://odoh.example`).
+#[cfg(feature = "odoh")]
+pub async fn fetch_odoh_configs(target: &str) -> io::Result<Vec<super::OdohConfig>> {
+    let url = format!("{}{}", target.trim_end_matches('/'), super::ODOH_CONFIGS_PATH);
+    let request = Request::new_with_str(&url).map_err(js_error)?;
+    let body = bytes(send(&request).await?).await?;
+    Ok(super::OdohConfig::parse_list(&body)?)
+}
+
+/// Sends `msg` through the ODoH `proxy` URL to the target at `host` and
+/// `path`, encrypted with `config`. The reply gets the ID of `msg` back.
+#[cfg(feature = "odoh")]
+pub async fn fetch_odoh(proxy: &str, host: &str, path: &str, config: &super::OdohConfig, msg: &Msg) -> io::Result<Msg> {
+    let (body, query) = config.encrypt_query(msg)?;
+    let headers = Headers::new().map_err(js_error)?;
+    headers.set("content-type", super::ODOH_CONTENT_TYPE).map_err(js_error)?;
+    headers.set("accept", super::ODOH_CONTENT_TYPE).map_err(js_error)?;
+    let init = RequestInit::new();
+    init.set_method("POST");
+    init.set_headers(&headers);
+    init.set_body(&Uint8Array::from(&body[..]));
+    let mut url = String::from(proxy);
+    url.push(if proxy.contains('?') { '&' } else { '?' });
+    url.push_str("targethost=");
+    super::percent_encode(host, &mut url);
+    url.push_str("&targetpath=");
+    super::percent_encode(path, &mut url);
+    let request = Request::new_with_str_and_init(&url, &init).map_err(js_error)?;
+
+    let response = bytes(send(&request).await?).await?;
+    Ok(query.decrypt_response(&response)?)
+}
+
+async fn bytes(response: Response) -> io::Result<Vec<u8>> {
+    let body = JsFuture::from(response.array_buffer().map_err(js_error)?).await.map_err(js_error)?;
+    Ok(Uint8Array::new(&body).to_vec())
+}
+
+async fn send(request: &Request) -> io::Result<Response> {
+    let response: Response = JsFuture::from(global_fetch(request))
+        .await
+        .and_then(|val| val.dyn_into())
+        .map_err(js_error)?;
+    if !response.ok() {
+        return Err(io::Error::other(format!("DoH server answered HTTP {}", response.status())));
+    }
+    Ok(response)
+}
+
+fn js_error(value: JsValue) -> io::Error {
+    io::Error::other(format!("{:?}", value))
+}
@@ -0,0 +1,292 @@
+//! Upstream health: passive failure tracking plus active probes, so
+//! callers choosing among upstreams skip dead ones and try them again
+//! once a probe answers. Round trips and errors are averaged per upstream
+//! for [`Health::select`].
+
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use log::*;
+use parking_lot::Mutex;
+use rand::Rng;
+use tokio::net::UdpSocket;
+use crate::msg::Question;
+use crate::{full_domain, types, Msg};
+
+/// Answers of a probe can be this big before they are cut off; only the
+/// header matters.
+const PROBE_BUF_SIZE: usize = 512;
+/// Weight of the newest sample in the moving averages.
+const EWMA_ALPHA: f64 = 0.2;
+
+#[derive(Debug, Clone)]
+pub struct HealthPolicy {
+    /// Consecutive failures after which an upstream is marked down.
+    pub max_failures: u32,
+    /// How long the first time down lasts; doubles while probes keep failing.
+    pub down_for: Duration,
+    pub max_down_for: Duration,
+    /// What probes ask, `id.server. CH TXT` by default.
+    pub probe: Question,
+    pub probe_timeout: Duration,
+    /// Share of [`Health::select`] calls that pick a random upstream, so
+    /// the averages of the others stay current.
+    pub explore: f64,
+}
+
+impl Default for HealthPolicy {
+    fn default() -> Self {
+        Self {
+            max_failures: 3,
+            down_for: Duration::from_secs(5),
+            max_down_for: Duration::from_secs(300),
+            probe: Question {
+                name: full_domain("id.server"),
+                q_type: types::TYPE_TXT,
+                q_class: types::CLASS_CHAOS,
+            },
+            probe_timeout: Duration::from_secs(2),
+            explore: 0.05,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct State {
+    failures: u32,
+    /// Times marked down in a row, for the backoff.
+    downs: u32,
+    down_until: Option<Instant>,
+    /// Moving averages of the round trip in seconds and of the failure rate.
+    rtt: Option<f64>,
+    errors: f64,
+}
+
+/// The view of one upstream exported to metrics.
+#[derive(Debug, Clone, Copy)]
+pub struct UpstreamStats {
+    pub addr: SocketAddr,
+    pub rtt: Option<Duration>,
+    /// Moving average of failures, 0 to 1.
+    pub error_rate: f64,
+    pub down: bool,
+}
+
+/// Health of a set of upstreams, shared by everything sending to them.
+#[derive(Debug, Default)]
+pub struct Health {
+    policy: HealthPolicy,
+    states: Mutex<HashMap<SocketAddr, State>>,
+}
+
+impl Health {
+    pub fn new(policy: HealthPolicy) -> Self {
+        Self { policy, states: Mutex::default() }
+    }
+
+    pub fn policy(&self) -> &HealthPolicy {
+        &self.policy
+    }
+
+    /// An answer came back from `addr` after `rtt`.
+    pub fn success(&self, addr: SocketAddr, rtt: Duration) {
+        let mut states = self.states.lock();
+        let state = states.entry(addr).or_default();
+        if state.down_until.is_some() {
+            info!("upstream {} is back", addr);
+        }
+        let rtt = rtt.as_secs_f64();
+        *state = State {
+            rtt: Some(state.rtt.map_or(rtt, |avg| avg + EWMA_ALPHA * (rtt - avg))),
+            errors: state.errors * (1.0 - EWMA_ALPHA),
+            ..Default::default()
+        };
+    }
+
+    /// A query to `addr` timed out or failed.
+    pub fn failure(&self, addr: SocketAddr) {
+        let mut states = self.states.lock();
+        let state = states.entry(addr).or_default();
+        state.failures += 1;
+        state.errors = state.errors * (1.0 - EWMA_ALPHA) + EWMA_ALPHA;
+        if state.failures >= self.policy.max_failures {
+            let backoff = self.policy.down_for.saturating_mul(1 << state.downs.min(16));
+            state.down_until = Some(Instant::now() + backoff.min(self.policy.max_down_for));
+            state.downs += 1;
+            state.failures = 0;
+            warn!("upstream {} marked down for {:?}", addr, backoff.min(self.policy.max_down_for));
+        }
+    }
+
+    /// Whether `addr` is marked down right now.
+    pub fn is_down(&self, addr: SocketAddr) -> bool {
+        self.states.lock().get(&addr).and_then(|s| s.down_until).is_some_and(|t| t > Instant::now())
+    }
+
+    /// The upstreams of `addrs` that are not down, in order; all of them if
+    /// every one is down, since a dead upstream beats no upstream.
+    pub fn available(&self, addrs: &[SocketAddr]) -> Vec<SocketAddr> {
+        let up: Vec<_> = addrs.iter().copied().filter(|a| !self.is_down(*a)).collect();
+        if up.is_empty() {
+            addrs.to_vec()
+        } else {
+            up
+        }
+    }
+
+    /// The upstream of `addrs` to send to: usually the one with the lowest
+    /// average round trip, weighted up by its error rate, among those not
+    /// down. Upstreams without samples go first; a share of calls set by
+    /// [`HealthPolicy::explore`] picks at random instead.
+    pub fn select(&self, addrs: &[SocketAddr]) -> Option<SocketAddr> {
+        let available = self.available(addrs);
+        let mut rng = rand::thread_rng();
+        if available.len() > 1 && rng.gen_bool(self.policy.explore.clamp(0.0, 1.0)) {
+            return Some(available[rng.gen_range(0..available.len())]);
+        }
+        let states = self.states.lock();
+        let score = |addr: &SocketAddr| match states.get(addr) {
+            Some(State { rtt: Some(rtt), errors, .. }) => rtt * (1.0 + 4.0 * errors),
+            _ => 0.0,
+        };
+        available.into_iter().min_by(|a, b| score(a).total_cmp(&score(b)))
+    }
+
+    /// Averages of every upstream seen so far.
+    pub fn stats(&self) -> Vec<UpstreamStats> {
+        let now = Instant::now();
+        let mut stats: Vec<_> = self
+            .states
+            .lock()
+            .iter()
+            .map(|(addr, s)| UpstreamStats {
+                addr: *addr,
+                rtt: s.rtt.map(Duration::from_secs_f64),
+                error_rate: s.errors,
+                down: s.down_until.is_some_and(|t| t > now),
+            })
+            .collect();
+        stats.sort_by_key(|s| s.addr);
+        stats
+    }
+
+    /// Upstreams marked down whose time is up, to be probed again.
+    pub fn due(&self) -> Vec<SocketAddr> {
+        let now = Instant::now();
+        let states = self.states.lock();
+        states.iter().filter(|(_, s)| s.down_until.is_some_and(|t| t <= now)).map(|(a, _)| *a).collect()
+    }
+
+    /// Sends the probe query to `addr` and records the outcome. Any answer
+    /// with a matching ID counts, REFUSED or NOTIMP included: the server is
+    /// alive.
+    pub async fn probe(&self, socket: &UdpSocket, addr: SocketAddr) -> io::Result<Duration> {
+        let ret = tokio::time::timeout(self.policy.probe_timeout, send_probe(socket, addr, &self.policy.probe))
+            .await
+            .unwrap_or_else(|_| Err(io::ErrorKind::TimedOut.into()));
+        match &ret {
+            Ok(rtt) => self.success(addr, *rtt),
+            Err(_) => self.failure(addr),
+        }
+        ret
+    }
+
+    /// Probes every upstream in `addrs` that is down and due, then sleeps
+    /// for `interval`, forever. Spawn it next to the code sending queries.
+    pub async fn run(&self, socket: &UdpSocket, addrs: &[SocketAddr], interval: Duration) {
+        loop {
+            for addr in self.due() {
+                if addrs.contains(&addr) {
+                    let _ = self.probe(socket, addr).await;
+                }
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+}
+
+async fn send_probe(socket: &UdpSocket, addr: SocketAddr, probe: &Question) -> io::Result<Duration> {
+    let mut msg = Msg::new();
+    msg.question.push(probe.clone());
+    let buf = msg.to_buf()?;
+    let start = Instant::now();
+    socket.send_to(&buf, addr).await?;
+    let mut reply = [0u8; PROBE_BUF_SIZE];
+    loop {
+        let (n, from) = socket.recv_from(&mut reply).await?;
+        if from == addr && n >= 2 && u16::from_be_bytes([reply[0], reply[1]]) == msg.hdr.id {
+            return Ok(start.elapsed());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::SocketAddr;
+    use std::time::Duration;
+    use tokio::net::UdpSocket;
+    use super::{Health, HealthPolicy};
+
+    #[tokio::test]
+    async fn test_health() {
+        let policy = HealthPolicy {
+            max_failures: 2,
+            down_for: Duration::from_millis(20),
+            probe_timeout: Duration::from_millis(50),
+            ..Default::default()
+        };
+        let health = Health::new(policy);
+        let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let (a, b) = (server.local_addr().unwrap(), "127.0.0.1:9".parse().unwrap());
+
+        health.failure(a);
+        assert!(!health.is_down(a));
+        health.failure(a);
+        assert!(health.is_down(a));
+        assert_eq!(health.available(&[a, b]), vec![b]);
+        health.failure(b);
+        health.failure(b);
+        assert_eq!(health.available(&[a, b]), vec![a, b]);
+        assert!(health.due().is_empty());
+
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert_eq!(health.due().len(), 2);
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let echo = async {
+            let mut buf = [0u8; 512];
+            let (n, from) = server.recv_from(&mut buf).await.unwrap();
+            buf[2] |= 0x80;
+            server.send_to(&buf[..n], from).await.unwrap();
+        };
+        let (rtt, ()) = tokio::join!(health.probe(&client, a), echo);
+        assert!(rtt.is_ok());
+        assert!(!health.is_down(a) && health.due() == vec![b]);
+    }
+
+    #[test]
+    fn test_select() {
+        let health = Health::new(HealthPolicy { explore: 0.0, ..Default::default() });
+        let addrs: Vec<SocketAddr> = ["10.0.0.1:53", "10.0.0.2:53", "10.0.0.3:53"].iter().map(|a| a.parse().unwrap()).collect();
+        health.success(addrs[0], Duration::from_millis(40));
+        health.success(addrs[1], Duration::from_millis(10));
+        // never measured goes first
+        assert_eq!(health.select(&addrs), Some(addrs[2]));
+        health.success(addrs[2], Duration::from_millis(15));
+        assert_eq!(health.select(&addrs), Some(addrs[1]));
+        // errors outweigh a slightly better round trip
+        health.failure(addrs[1]);
+        health.failure(addrs[1]);
+        assert_eq!(health.select(&addrs), Some(addrs[2]));
+
+        health.success(addrs[1], Duration::from_millis(20));
+        let stats = health.stats();
+        assert!((stats[1].rtt.unwrap().as_secs_f64() - 0.012).abs() < 1e-9);
+        assert!((stats[1].error_rate - 0.288).abs() < 1e-9);
+        assert!(stats.iter().all(|s| !s.down));
+
+        let explorer = Health::new(HealthPolicy { explore: 1.0, ..Default::default() });
+        assert!((0..50).map(|_| explorer.select(&addrs).unwrap()).any(|a| a != addrs[0]));
+        assert_eq!(explorer.select(&[]), None);
+    }
+}
@@ -0,0 +1,130 @@
+//! The `application/dns-json` API of Google and Cloudflare (`?name=&type=`),
+//! for when only those endpoints are reachable. The crate has no native
+//! HTTPS client, so [`json_query_url`] and [`parse_json_answer`] are meant
+//! for whatever HTTP stack the caller has; `fetch_doh_json` does both with
+//! the browser fetch API.
+
+use serde_json::Value;
+use crate::msg::{Msg, MsgHdr, Question};
+use crate::types::{self, RecourseRecord};
+use crate::zone::Parser;
+use crate::{full_domain, util, Error, Result};
+
+pub const JSON_CONTENT_TYPE: &str = "application/dns-json";
+
+/// GET URL asking `endpoint` (e.g. `https://dns.google/resolve`) for
+/// records of type `qtype` at `name`.
+pub fn json_query_url(endpoint: &str, name: &str, qtype: u16) -> String {
+    let mut url = String::with_capacity(endpoint.len() + name.len() + 16);
+    url.push_str(endpoint);
+    url.push(if endpoint.contains('?') { '&' } else { '?' });
+    url.push_str("name=");
+    super::percent_encode(name, &mut url);
+    url.push_str(&format!("&type={}", qtype));
+    url
+}
+
+/// Converts a JSON answer into a message. `Status` becomes the RCODE and
+/// the `TC`/`RD`/`RA`/`AD`/`CD` flags the header bits; the ID is 0. The
+/// `data` of each record is read as presentation format, so types the zone
+/// parser does not know must come as RFC 3597 `\#` data.
+pub fn parse_json_answer(body: &str) -> Result<Msg> {
+    let json: Value = serde_json::from_str(body).map_err(|e| Error::new(format!("bad DNS JSON: {}", e)))?;
+    let flag = |key: &str| json[key].as_bool().unwrap_or(false);
+    let status = json["Status"].as_u64().ok_or_else(|| Error::new("DNS JSON without Status"))?;
+    let mut msg = Msg {
+        hdr: MsgHdr {
+            response: true,
+            truncated: flag("TC"),
+            recursion_desired: flag("RD"),
+            recursion_available: flag("RA"),
+            authenticated_data: flag("AD"),
+            checking_disabled: flag("CD"),
+            response_code: u16::try_from(status).map_err(|_| Error::BadResponseCode)?,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    for q in entries(&json, "Question") {
+        msg.question.push(Question {
+            name: full_domain(field_str(q, "name")?),
+            q_type: field_u16(q, "type")?,
+            q_class: types::CLASS_INET,
+        });
+    }
+    for (key, section) in [("Answer", &mut msg.answer), ("Authority", &mut msg.authority), ("Additional", &mut msg.additional)] {
+        for rr in entries(&json, key) {
+            section.push(record(rr)?);
+        }
+    }
+    Ok(msg)
+}
+
+fn record(rr: &Value) -> Result<RecourseRecord> {
+    let name = field_str(rr, "name")?;
+    let typ = field_u16(rr, "type")?;
+    let ttl = rr["TTL"].as_u64().and_then(|v| u32::try_from(v).ok()).ok_or_else(|| missing("TTL"))?;
+    let data = field_str(rr, "data")?;
+    let typ = match util::type_to_string(typ) {
+        Some(s) => s.to_string(),
+        None => format!("TYPE{}", typ),
+    };
+    let line = format!("{} {} IN {} {}\n", full_domain(name), ttl, typ, data);
+    Parser::new(&line, ".").next_record()?.ok_or_else(|| missing("data"))
+}
+
+fn entries<'a>(json: &'a Value, key: &str) -> &'a [Value] {
+    json[key].as_array().map(Vec::as_slice).unwrap_or_default()
+}
+
+fn field_str<'a>(v: &'a Value, key: &str) -> Result<&'a str> {
+    v[key].as_str().ok_or_else(|| missing(key))
+}
+
+fn field_u16(v: &Value, key: &str) -> Result<u16> {
+    v[key].as_u64().and_then(|v| u16::try_from(v).ok()).ok_or_else(|| missing(key))
+}
+
+fn missing(key: &str) -> Error {
+    Error::new(format!("DNS JSON record without valid {}", key))
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::Ipv4Addr;
+    use crate::types::{self, RecourseRecord};
+    use super::{json_query_url, parse_json_answer};
+
+    #[test]
+    fn test_json_answer() {
+        assert_eq!(
+            json_query_url("https://dns.google/resolve", "bücher.example.", types::TYPE_AAAA),
+            "https://dns.google/resolve?name=b%C3%BCcher.example.&type=28"
+        );
+        assert_eq!(json_query_url("https://h/q?ct=x", "a.", 1), "https://h/q?ct=x&name=a.&type=1");
+
+        let body = r#"{"Status":0,"TC":false,"RD":true,"RA":true,"AD":false,"CD":false,
+            "Question":[{"name":"www.example.com.","type":1}],
+            "Answer":[{"name":"www.example.com.","type":5,"TTL":300,"data":"example.com."},
+                      {"name":"example.com.","type":1,"TTL":60,"data":"192.0.2.1"}],
+            "Comment":"ignored"}"#;
+        let msg = parse_json_answer(body).unwrap();
+        assert!(msg.hdr.response && msg.hdr.recursion_desired && msg.hdr.recursion_available);
+        assert_eq!(msg.hdr.response_code, 0);
+        assert_eq!(msg.question[0].name.as_str(), "www.example.com.");
+        assert_eq!(msg.answer.len(), 2);
+        match &msg.answer[1] {
+            RecourseRecord::A(a) => assert_eq!((a.hdr.ttl, a.a), (60, Ipv4Addr::new(192, 0, 2, 1))),
+            other => panic!("{:?}", other),
+        }
+
+        let nx = parse_json_answer(r#"{"Status":3,"Question":[{"name":"nx.example.","type":1}],
+            "Authority":[{"name":"example.","type":6,"TTL":900,
+            "data":"ns.example. host.example. 1 7200 3600 1209600 900"}]}"#).unwrap();
+        assert_eq!((nx.hdr.response_code, nx.authority.len()), (3, 1));
+
+        assert!(parse_json_answer("{}").is_err());
+        assert!(parse_json_answer(r#"{"Status":0,"Answer":[{"name":"a.","type":1,"TTL":1,"data":"x"}]}"#).is_err());
+    }
+}
@@ -1,75 +1,102 @@
-use std::io;
-use std::net::{IpAddr, SocketAddr, SocketAddrV6};
-use bytes::BytesMut;
+#[cfg(feature = "dnscrypt")]
+mod dnscrypt;
+mod ecs;
+#[cfg(all(target_arch = "wasm32", feature = "fetch"))]
+mod fetch;
+#[cfg(not(target_arch = "wasm32"))]
+mod health;
+#[cfg(feature = "json")]
+mod json;
+#[cfg(feature = "odoh")]
+mod odoh;
+mod target;
+#[cfg(not(target_arch = "wasm32"))]
+mod udp;
+mod validate;
+
+#[cfg(feature = "dnscrypt")]
+pub use dnscrypt::{cert_query, DnsCryptCert, DnsCryptQuery, RESOLVER_MAGIC};
+pub use ecs::EcsPolicy;
+#[cfg(all(target_arch = "wasm32", feature = "fetch"))]
+pub use fetch::fetch_doh;
+#[cfg(all(target_arch = "wasm32", feature = "fetch", feature = "json"))]
+pub use fetch::fetch_doh_json;
+#[cfg(all(target_arch = "wasm32", feature = "fetch", feature = "odoh"))]
+pub use fetch::{fetch_odoh, fetch_odoh_configs};
+#[cfg(not(target_arch = "wasm32"))]
+pub use health::{Health, HealthPolicy, UpstreamStats};
+#[cfg(feature = "json")]
+pub use json::{json_query_url, parse_json_answer, JSON_CONTENT_TYPE};
+#[cfg(feature = "odoh")]
+pub use odoh::{OdohConfig, OdohQuery, ODOH_CONFIGS_PATH, ODOH_CONTENT_TYPE};
+pub use target::{srv_targets, svcb_targets, weighted_order, Target};
+#[cfg(not(target_arch = "wasm32"))]
+pub use udp::{lookup_host, lookup_host_with_timeout};
+pub use validate::{randomize_case, validate_response};
+
+use std::net::IpAddr;
 use smallvec::SmallVec;
-use crate::{full_domain, Msg, types};
-use crate::msg::Question;
+#[cfg(any(test, all(target_arch = "wasm32", feature = "fetch")))]
+use crate::{Msg, Result};
 
 pub type DnsIpVec = SmallVec<[IpAddr; 5]>;
 
-/// Lookup host
-pub async fn lookup_host(
-    socket: tokio::net::UdpSocket,
-    mut ns: SocketAddr,
-    domain: &str,
-    ipv4: bool,
-    ipv6: bool,
-) -> io::Result<DnsIpVec> {
-    let mut buf = BytesMut::new();
-    let mut ips = DnsIpVec::with_capacity(5);
+/// The RFC 8484 body for `msg`, sent with ID 0 as section 4.1 recommends,
+/// and the query it holds.
+#[cfg(any(test, all(target_arch = "wasm32", feature = "fetch")))]
+fn doh_query(msg: &Msg) -> Result<(Vec<u8>, Msg)> {
+    let mut query = msg.clone();
+    query.hdr.id = 0;
+    Ok((query.to_buf()?.to_vec(), query))
+}
+
+/// Unpacks the reply to a [`doh_query`], checks it answers `query` and
+/// gives it the ID of `msg` back.
+#[cfg(any(test, all(target_arch = "wasm32", feature = "fetch")))]
+fn doh_reply(msg: &Msg, query: &Msg, body: &[u8]) -> Result<Msg> {
+    let mut reply = Msg::unpack(body)?;
+    validate_response(query, &reply, None, None, false)?;
+    reply.hdr.id = msg.hdr.id;
+    Ok(reply)
+}
 
-    if let Ok(addr) = socket.local_addr() {
-        match (ns, addr) {
-            (SocketAddr::V4(val), SocketAddr::V6(_)) => {
-                ns = SocketAddrV6::new(val.ip().to_ipv6_mapped(), val.port(), 0, 0).into();
-            }
-            _ => {}
+/// Appends `s` to a URL query, escaping all but unreserved characters.
+#[cfg(any(feature = "json", all(target_arch = "wasm32", feature = "fetch", feature = "odoh")))]
+fn percent_encode(s: &str, url: &mut String) {
+    for b in s.bytes() {
+        if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+            url.push(b as char);
+        } else {
+            url.push_str(&format!("%{:02X}", b));
         }
     }
+}
 
-    async fn do_request(
-        ns: SocketAddr,
-        socket: &tokio::net::UdpSocket,
-        domain: &str,
-        buf: &mut BytesMut,
-        typ: u16,
-        ips: &mut SmallVec<[IpAddr; 5]>,
-    ) -> io::Result<()> {
-        const BUF_SIZE: usize = 512; // MinMsgSize = 512, MAX: 65535
+#[cfg(test)]
+mod test {
+    use crate::{full_domain, types, Msg};
+    use super::{doh_query, doh_reply};
 
-        buf.clear();
-        {
-            let mut msg = Msg::new();
-            msg.hdr.recursion_desired = true;
-            msg.question.push(Question {
-                name: full_domain(domain),
-                q_type: typ,
-                q_class: types::CLASS_INET,
-            });
-            if let Err(err) = msg.to_buf_with(buf) {
-                return Err(err.into())
-            }
-        }
+    #[test]
+    fn test_doh_query_reply() {
+        let mut msg = Msg::new();
+        msg.set_question(full_domain("www.example.com"), types::TYPE_A);
+        msg.hdr.id = 0x1234;
+        let (body, query) = doh_query(&msg).unwrap();
+        assert_eq!(&body[..2], [0, 0]);
+        assert_eq!(Msg::unpack(&body).unwrap().to_string(), query.to_string());
+        assert_eq!((query.question[0].name.as_str(), query.question[0].q_type), ("www.example.com.", types::TYPE_A));
 
-        socket.send_to(buf.as_ref(), ns).await?;
-        buf.resize(BUF_SIZE, 0);
-        let n = socket.recv(&mut buf[..]).await?;
+        let mut reply = Msg::new();
+        reply.set_reply(&query);
+        let wire = reply.to_buf().unwrap();
+        let back = doh_reply(&msg, &query, &wire).unwrap();
+        assert_eq!(back.hdr.id, 0x1234);
+        assert!(back.hdr.response);
+        assert!(doh_reply(&msg, &query, &body[..5]).is_err());
 
-        if let Some(an) = Msg::unpack_answer(&buf[..n]) {
-            let res = an.ips();
-            if !res.is_empty() {
-                ips.extend(res);
-            }
-        }
-        Ok(())
+        // a reply for another name is refused
+        reply.question[0].name = full_domain("www.example.net");
+        assert!(doh_reply(&msg, &query, &reply.to_buf().unwrap()).is_err());
     }
-
-    if ipv4 {
-        do_request(ns, &socket, domain, &mut buf, types::TYPE_A, &mut ips).await?;
-    }
-    if ipv6 {
-        do_request(ns, &socket, domain, &mut buf, types::TYPE_AAAA, &mut ips).await?;
-    }
-
-    Ok(ips)
-}
\ No newline at end of file
+}
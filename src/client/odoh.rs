@@ -0,0 +1,290 @@
+//! Oblivious DoH (RFC 9230). Queries are HPKE-encrypted to the target
+//! resolver and relayed by a proxy, so the proxy sees who asks but not what,
+//! and the target sees what but not who. Only the mandatory suite (X25519,
+//! HKDF-SHA256, AES-128-GCM) is implemented; configs using others are
+//! skipped.
+
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::Aes128Gcm;
+use bytes::{BufMut, BytesMut};
+use byteorder::BigEndian;
+use hkdf::Hkdf;
+use hpke::aead::AesGcm128;
+use hpke::kdf::HkdfSha256;
+use hpke::kem::X25519HkdfSha256;
+use hpke::{Deserializable, Kem, OpModeS, Serializable};
+use sha2::Sha256;
+use crate::io::{Cursor, Read, ReadBytesExt};
+use crate::msg::Msg;
+use crate::{Error, Result};
+
+pub const ODOH_CONTENT_TYPE: &str = "application/oblivious-dns-message";
+/// Where targets publish their `ObliviousDoHConfigs`.
+pub const ODOH_CONFIGS_PATH: &str = "/.well-known/odohconfigs";
+
+const ODOH_VERSION: u16 = 0x0001;
+const KEM_X25519_SHA256: u16 = 0x0020;
+const KDF_HKDF_SHA256: u16 = 0x0001;
+const AEAD_AES_128_GCM: u16 = 0x0001;
+const MESSAGE_QUERY: u8 = 0x01;
+const MESSAGE_RESPONSE: u8 = 0x02;
+/// Key and nonce sizes of AES-128-GCM.
+const NK: usize = 16;
+const NN: usize = 12;
+/// Queries are padded to a multiple of this many octets.
+const PADDING_BLOCK: usize = 128;
+
+type PublicKey = <X25519HkdfSha256 as Kem>::PublicKey;
+
+/// One `ObliviousDoHConfigContents` of a target.
+#[derive(Debug, Clone)]
+pub struct OdohConfig {
+    public_key: PublicKey,
+}
+
+/// State of one encrypted query, needed to open its response.
+pub struct OdohQuery {
+    id: u16,
+    /// The query as sent, with ID 0.
+    query: Msg,
+    plaintext: Vec<u8>,
+    secret: [u8; NK],
+}
+
+impl OdohConfig {
+    /// Parses `ObliviousDoHConfigs`, keeping the supported configs in the
+    /// target's order of preference.
+    pub fn parse_list(data: &[u8]) -> Result<Vec<OdohConfig>> {
+        let mut cur = Cursor::new(data);
+        let list = read_vec(&mut cur)?;
+        let mut cur = Cursor::new(&list[..]);
+        let mut configs = Vec::new();
+        while (cur.position() as usize) < list.len() {
+            let version = cur.read_u16::<BigEndian>()?;
+            let contents = read_vec(&mut cur)?;
+            if version != ODOH_VERSION {
+                continue;
+            }
+            let mut c = Cursor::new(&contents[..]);
+            let suite = (c.read_u16::<BigEndian>()?, c.read_u16::<BigEndian>()?, c.read_u16::<BigEndian>()?);
+            let public_key = read_vec(&mut c)?;
+            if suite != (KEM_X25519_SHA256, KDF_HKDF_SHA256, AEAD_AES_128_GCM) {
+                continue;
+            }
+            let public_key = PublicKey::from_bytes(&public_key).map_err(hpke_error)?;
+            configs.push(OdohConfig { public_key });
+        }
+        Ok(configs)
+    }
+
+    /// The config a target with this public key would publish.
+    pub fn from_public_key(public_key: &[u8]) -> Result<Self> {
+        Ok(Self {
+            public_key: PublicKey::from_bytes(public_key).map_err(hpke_error)?,
+        })
+    }
+
+    /// The serialized `ObliviousDoHConfigContents`.
+    pub fn contents(&self) -> Vec<u8> {
+        let key = self.public_key.to_bytes();
+        let mut buf = BytesMut::with_capacity(8 + key.len());
+        buf.put_u16(KEM_X25519_SHA256);
+        buf.put_u16(KDF_HKDF_SHA256);
+        buf.put_u16(AEAD_AES_128_GCM);
+        buf.put_u16(key.len() as u16);
+        buf.put_slice(&key);
+        buf.to_vec()
+    }
+
+    /// Identifies this config in queries: Expand(Extract("", contents), "odoh key id", Nh).
+    pub fn key_id(&self) -> [u8; 32] {
+        let mut id = [0u8; 32];
+        Hkdf::<Sha256>::new(None, &self.contents())
+            .expand(b"odoh key id", &mut id)
+            .expect("32 octets is a valid HKDF-SHA256 length");
+        id
+    }
+
+    /// Encrypts `msg` for the target. The query is sent with ID 0; the
+    /// response gets the ID of `msg` back.
+    pub fn encrypt_query(&self, msg: &Msg) -> Result<(Vec<u8>, OdohQuery)> {
+        let mut query = msg.clone();
+        query.hdr.id = 0;
+        let dns = query.to_buf()?;
+        let plaintext = pad(&dns);
+
+        let (enc, mut ctx) = hpke::setup_sender::<AesGcm128, HkdfSha256, X25519HkdfSha256, _>(
+            &OpModeS::Base,
+            &self.public_key,
+            b"odoh query",
+            &mut rand::thread_rng(),
+        )
+        .map_err(hpke_error)?;
+        let key_id = self.key_id();
+        let ct = ctx.seal(&plaintext, &aad(MESSAGE_QUERY, &key_id)).map_err(hpke_error)?;
+        let mut secret = [0u8; NK];
+        ctx.export(b"odoh response", &mut secret).map_err(hpke_error)?;
+
+        let mut encrypted = enc.to_bytes().to_vec();
+        encrypted.extend_from_slice(&ct);
+        let body = message(MESSAGE_QUERY, &key_id, &encrypted);
+        Ok((body, OdohQuery { id: msg.hdr.id, query, plaintext, secret }))
+    }
+}
+
+impl OdohQuery {
+    /// Opens the target's response to this query and checks it answers
+    /// the query with [`validate_response`](super::validate_response).
+    pub fn decrypt_response(&self, body: &[u8]) -> Result<Msg> {
+        let mut cur = Cursor::new(body);
+        if cur.read_u8()? != MESSAGE_RESPONSE {
+            return Err(Error::new("ODoH: not a response message"));
+        }
+        let nonce = read_vec(&mut cur)?;
+        let encrypted = read_vec(&mut cur)?;
+
+        let mut salt = self.plaintext.clone();
+        salt.put_u16(nonce.len() as u16);
+        salt.extend_from_slice(&nonce);
+        let prk = Hkdf::<Sha256>::new(Some(&salt), &self.secret);
+        let (mut key, mut iv) = ([0u8; NK], [0u8; NN]);
+        prk.expand(b"odoh key", &mut key).map_err(|_| Error::new("ODoH: key derivation failed"))?;
+        prk.expand(b"odoh nonce", &mut iv).map_err(|_| Error::new("ODoH: key derivation failed"))?;
+        let plaintext = Aes128Gcm::new(&key.into())
+            .decrypt(&iv.into(), Payload { msg: &encrypted, aad: &aad(MESSAGE_RESPONSE, &nonce) })
+            .map_err(|_| Error::new("ODoH: response does not decrypt"))?;
+
+        let mut reply = Msg::unpack(unpad(&plaintext)?)?;
+        super::validate_response(&self.query, &reply, None, None, false)?;
+        reply.hdr.id = self.id;
+        Ok(reply)
+    }
+}
+
+/// `ObliviousDoHMessage`.
+fn message(typ: u8, key_id: &[u8], encrypted: &[u8]) -> Vec<u8> {
+    let mut buf = BytesMut::with_capacity(5 + key_id.len() + encrypted.len());
+    buf.put_u8(typ);
+    buf.put_u16(key_id.len() as u16);
+    buf.put_slice(key_id);
+    buf.put_u16(encrypted.len() as u16);
+    buf.put_slice(encrypted);
+    buf.to_vec()
+}
+
+fn aad(typ: u8, key_id: &[u8]) -> Vec<u8> {
+    let mut aad = Vec::with_capacity(3 + key_id.len());
+    aad.put_u8(typ);
+    aad.put_u16(key_id.len() as u16);
+    aad.extend_from_slice(key_id);
+    aad
+}
+
+/// `ObliviousDoHMessagePlaintext` with zero padding.
+fn pad(dns: &[u8]) -> Vec<u8> {
+    let padding = (PADDING_BLOCK - (dns.len() + 4) % PADDING_BLOCK) % PADDING_BLOCK;
+    let mut buf = Vec::with_capacity(dns.len() + 4 + padding);
+    buf.put_u16(dns.len() as u16);
+    buf.extend_from_slice(dns);
+    buf.put_u16(padding as u16);
+    buf.resize(buf.len() + padding, 0);
+    buf
+}
+
+fn unpad(plaintext: &[u8]) -> Result<&[u8]> {
+    let mut cur = Cursor::new(plaintext);
+    let len = cur.read_u16::<BigEndian>()? as usize;
+    let dns = plaintext.get(2..2 + len).ok_or(Error::BufTooSmall)?;
+    cur.set_position(2 + len as u64);
+    let padding = read_vec(&mut cur)?;
+    if padding.iter().any(|b| *b != 0) || cur.position() as usize != plaintext.len() {
+        return Err(Error::new("ODoH: bad padding"));
+    }
+    Ok(dns)
+}
+
+/// A `opaque<0..2^16-1>` field.
+fn read_vec(cur: &mut Cursor<&[u8]>) -> Result<Vec<u8>> {
+    let len = cur.read_u16::<BigEndian>()? as usize;
+    let mut buf = vec![0u8; len];
+    cur.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn hpke_error(err: hpke::HpkeError) -> Error {
+    Error::new(format!("ODoH: {}", err))
+}
+
+#[cfg(test)]
+mod test {
+    use hpke::OpModeR;
+    use crate::{full_domain, types};
+    use super::*;
+
+    /// The target's side of RFC 9230 section 6, to answer a query.
+    fn answer(sk: &<X25519HkdfSha256 as Kem>::PrivateKey, config: &OdohConfig, body: &[u8]) -> Vec<u8> {
+        let mut cur = Cursor::new(body);
+        assert_eq!(cur.read_u8().unwrap(), MESSAGE_QUERY);
+        let key_id = read_vec(&mut cur).unwrap();
+        assert_eq!(key_id, config.key_id());
+        let encrypted = read_vec(&mut cur).unwrap();
+        let enc = <X25519HkdfSha256 as Kem>::EncappedKey::from_bytes(&encrypted[..32]).unwrap();
+        let mut ctx = hpke::setup_receiver::<AesGcm128, HkdfSha256, X25519HkdfSha256>(&OpModeR::Base, sk, &enc, b"odoh query").unwrap();
+        let q_plain = ctx.open(&encrypted[32..], &aad(MESSAGE_QUERY, &key_id)).unwrap();
+        assert_eq!(q_plain.len() % PADDING_BLOCK, 0);
+        let mut query = Msg::unpack(unpad(&q_plain).unwrap()).unwrap();
+        assert_eq!(query.hdr.id, 0);
+        query.hdr.response = true;
+        query.answer.push(types::A::new(full_domain("example.com"), types::CLASS_INET, 60, [192, 0, 2, 1].into()).into());
+
+        let mut secret = [0u8; NK];
+        ctx.export(b"odoh response", &mut secret).unwrap();
+        let nonce = [7u8; NK];
+        let mut salt = q_plain.clone();
+        salt.put_u16(NK as u16);
+        salt.extend_from_slice(&nonce);
+        let prk = Hkdf::<Sha256>::new(Some(&salt), &secret);
+        let (mut key, mut iv) = ([0u8; NK], [0u8; NN]);
+        prk.expand(b"odoh key", &mut key).unwrap();
+        prk.expand(b"odoh nonce", &mut iv).unwrap();
+        let r_plain = pad(&query.to_buf().unwrap());
+        let ct = Aes128Gcm::new(&key.into())
+            .encrypt(&iv.into(), Payload { msg: &r_plain, aad: &aad(MESSAGE_RESPONSE, &nonce) })
+            .unwrap();
+        message(MESSAGE_RESPONSE, &nonce, &ct)
+    }
+
+    #[test]
+    fn test_odoh() {
+        let (sk, pk) = X25519HkdfSha256::gen_keypair(&mut rand::thread_rng());
+        let config = OdohConfig::from_public_key(&pk.to_bytes()).unwrap();
+
+        let mut list = Vec::new();
+        let unsupported = [0x00, 0x10, 0x00, 0x01, 0x00, 0x01, 0x00, 0x01, 0xff];
+        for (version, contents) in [(ODOH_VERSION, &unsupported[..]), (0xff00, &[][..]), (ODOH_VERSION, &config.contents()[..])] {
+            list.put_u16(version);
+            list.put_u16(contents.len() as u16);
+            list.extend_from_slice(contents);
+        }
+        let mut configs = Vec::new();
+        configs.put_u16(list.len() as u16);
+        configs.extend_from_slice(&list);
+        let parsed = OdohConfig::parse_list(&configs).unwrap();
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].key_id(), config.key_id());
+        assert!(OdohConfig::parse_list(&configs[..configs.len() - 1]).is_err());
+
+        let mut msg = Msg::new();
+        msg.set_question(full_domain("example.com"), types::TYPE_A);
+        msg.hdr.id = 0x4242;
+        let (body, query) = parsed[0].encrypt_query(&msg).unwrap();
+        let mut response = answer(&sk, &config, &body);
+        let reply = query.decrypt_response(&response).unwrap();
+        assert_eq!(reply.hdr.id, 0x4242);
+        assert_eq!(reply.answer.len(), 1);
+
+        let last = response.len() - 1;
+        response[last] ^= 1;
+        assert!(query.decrypt_response(&response).is_err());
+    }
+}
@@ -0,0 +1,148 @@
+//! The order to try the targets of SRV (RFC 2782) and SVCB/HTTPS
+//! (RFC 9460) records in.
+
+use rand::Rng;
+use crate::types::svcb::SVC_KEY_PORT;
+use crate::types::RecourseRecord;
+use crate::DomainString;
+
+/// One endpoint to connect to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    pub name: DomainString,
+    /// The SRV port, or the `port` parameter of SVCB/HTTPS; `None` means
+    /// the default port of the scheme.
+    pub port: Option<u16>,
+}
+
+/// The targets of the SRV records among `records`, lowest priority first
+/// and weighted at random within a priority. Empty when the service is
+/// decidedly not available (a lone `.` target).
+pub fn srv_targets(records: &[RecourseRecord]) -> impl Iterator<Item = Target> {
+    let items = records
+        .iter()
+        .filter_map(|rr| match rr {
+            RecourseRecord::SRV(val) if val.target != "." => {
+                Some((val.priority, val.weight, Target { name: val.target.clone(), port: Some(val.port) }))
+            }
+            _ => None,
+        })
+        .collect();
+    weighted_order(items, &mut rand::thread_rng()).into_iter()
+}
+
+/// The targets of the SVCB or HTTPS records (`qtype`) among `records`:
+/// the alias if there is one, else the services lowest priority first, in
+/// random order within a priority. A `.` service target stands for the
+/// owner name.
+pub fn svcb_targets(records: &[RecourseRecord], qtype: u16) -> impl Iterator<Item = Target> {
+    let mut aliases = Vec::new();
+    let mut services = Vec::new();
+    for rr in records {
+        let RecourseRecord::SVCB(val) = rr else { continue; };
+        if val.hdr.typ != qtype {
+            continue;
+        }
+        let port = val.param(SVC_KEY_PORT).and_then(|v| v.try_into().ok()).map(u16::from_be_bytes);
+        let mut target = Target { name: val.target.clone(), port };
+        if val.is_alias() {
+            if target.name != "." {
+                aliases.push((0, 1, target));
+            }
+        } else {
+            if target.name == "." {
+                target.name = val.hdr.name.clone();
+            }
+            services.push((val.priority, 1, target));
+        }
+    }
+    let mut rng = rand::thread_rng();
+    if aliases.is_empty() {
+        return weighted_order(services, &mut rng).into_iter();
+    }
+    // more than one alias is a misconfiguration, pick any
+    let mut order = weighted_order(aliases, &mut rng);
+    order.truncate(1);
+    order.into_iter()
+}
+
+/// Orders `(priority, weight, item)` the RFC 2782 way: ascending priority,
+/// and within a priority a random order where each next item is chosen with
+/// probability proportional to its weight, so weight 0 items seldom come
+/// early.
+pub fn weighted_order<T, R: Rng + ?Sized>(mut items: Vec<(u16, u16, T)>, rng: &mut R) -> Vec<T> {
+    // zero weights first, as the RFC has them at the start of the running sum
+    items.sort_by_key(|(priority, weight, _)| (*priority, *weight != 0));
+    let mut ordered = Vec::with_capacity(items.len());
+    let mut items = items.into_iter().peekable();
+    while let Some(&(priority, _, _)) = items.peek() {
+        let mut group: Vec<_> = std::iter::from_fn(|| items.next_if(|(p, _, _)| *p == priority)).map(|(_, w, t)| (w, t)).collect();
+        while !group.is_empty() {
+            let total: u32 = group.iter().map(|(w, _)| *w as u32).sum();
+            let pick = rng.gen_range(0..=total);
+            let mut sum = 0;
+            let i = group
+                .iter()
+                .position(|(w, _)| {
+                    sum += *w as u32;
+                    sum >= pick
+                })
+                .unwrap_or(0);
+            ordered.push(group.remove(i).1);
+        }
+    }
+    ordered
+}
+
+#[cfg(test)]
+mod test {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use crate::types::{self, svcb, RecourseRecord, SvcParam};
+    use crate::{full_domain, DomainString};
+    use super::{srv_targets, svcb_targets, weighted_order, Target};
+
+    fn srv(priority: u16, weight: u16, port: u16, target: &str) -> RecourseRecord {
+        let name = full_domain("_sip._tcp.example.com");
+        types::SRV::new(name, types::CLASS_INET, 60, priority, weight, port, DomainString::from(target)).into()
+    }
+
+    fn https(priority: u16, target: &str, params: Vec<SvcParam>) -> RecourseRecord {
+        let name = full_domain("_sip._tcp.example.com");
+        types::SVCB::new_https(name, types::CLASS_INET, 60, priority, DomainString::from(target), params).into()
+    }
+
+    #[test]
+    fn test_targets() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let items = vec![(20, 0, "backup"), (10, 0, "zero"), (10, 60, "a"), (10, 40, "b")];
+        let mut first = [0; 3];
+        for _ in 0..1000 {
+            let order = weighted_order(items.clone(), &mut rng);
+            assert_eq!(order[3], "backup");
+            first[["zero", "a", "b"].iter().position(|n| *n == order[0]).unwrap()] += 1;
+        }
+        assert!(first[0] < 30 && first[1] > first[2] && first[2] > 300, "{:?}", first);
+        assert_eq!(weighted_order(vec![(1, 0, 'x'), (1, 0, 'y')], &mut rng).len(), 2);
+
+        let records = [
+            srv(10, 60, 5060, "sip.example.com."),
+            srv(20, 0, 5061, "."),
+            types::A::new(full_domain("sip.example.com"), types::CLASS_INET, 60, [192, 0, 2, 1].into()).into(),
+        ];
+        let targets: Vec<_> = srv_targets(&records).collect();
+        assert_eq!(targets, vec![Target { name: full_domain("sip.example.com"), port: Some(5060) }]);
+
+        let port = SvcParam { key: svcb::SVC_KEY_PORT, value: 8443u16.to_be_bytes().to_vec() };
+        let services = [https(1, ".", vec![port]), https(2, "svc.example.net.", vec![])];
+        assert_eq!(svcb_targets(&services, types::TYPE_SVCB).count(), 0);
+        let targets: Vec<_> = svcb_targets(&services, types::TYPE_HTTPS).collect();
+        assert_eq!(targets[0], Target { name: full_domain("_sip._tcp.example.com"), port: Some(8443) });
+        assert_eq!(targets[1].name, "svc.example.net.");
+        // an alias wins over services
+        let mut aliased = services.to_vec();
+        aliased.push(https(0, "cdn.example.net.", vec![]));
+        let targets: Vec<_> = svcb_targets(&aliased, types::TYPE_HTTPS).collect();
+        assert_eq!(targets, vec![Target { name: full_domain("cdn.example.net"), port: None }]);
+    }
+}
@@ -0,0 +1,136 @@
+use std::io;
+use std::net::{IpAddr, SocketAddr, SocketAddrV6};
+use std::time::Duration;
+use bytes::BytesMut;
+use smallvec::SmallVec;
+use crate::{full_domain, Msg, types};
+use super::{randomize_case, validate_response, DnsIpVec};
+
+/// How long [`lookup_host`] waits for the answer to each query.
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Lookup host
+pub async fn lookup_host(
+    socket: tokio::net::UdpSocket,
+    ns: SocketAddr,
+    domain: &str,
+    ipv4: bool,
+    ipv6: bool,
+) -> io::Result<DnsIpVec> {
+    lookup_host_with_timeout(socket, ns, domain, ipv4, ipv6, LOOKUP_TIMEOUT).await
+}
+
+/// Like [`lookup_host`], with `timeout` for the answer to each query in
+/// place of the default 5 seconds. It holds however many stray packets
+/// arrive meanwhile; past it the lookup fails with `TimedOut`.
+pub async fn lookup_host_with_timeout(
+    socket: tokio::net::UdpSocket,
+    mut ns: SocketAddr,
+    domain: &str,
+    ipv4: bool,
+    ipv6: bool,
+    timeout: Duration,
+) -> io::Result<DnsIpVec> {
+    let mut buf = BytesMut::new();
+    let mut ips = DnsIpVec::with_capacity(5);
+
+    if let Ok(addr) = socket.local_addr() {
+        if let (SocketAddr::V4(val), SocketAddr::V6(_)) = (ns, addr) {
+            ns = SocketAddrV6::new(val.ip().to_ipv6_mapped(), val.port(), 0, 0).into();
+        }
+    }
+
+    async fn do_request(
+        ns: SocketAddr,
+        socket: &tokio::net::UdpSocket,
+        domain: &str,
+        buf: &mut BytesMut,
+        typ: u16,
+        timeout: Duration,
+        ips: &mut SmallVec<[IpAddr; 5]>,
+    ) -> io::Result<()> {
+        const BUF_SIZE: usize = 512; // MinMsgSize = 512, MAX: 65535
+
+        buf.clear();
+        let mut msg = Msg::new();
+        msg.set_question(randomize_case(&full_domain(domain).to_ascii_lowercase()), typ);
+        if let Err(err) = msg.to_buf_with(buf) {
+            return Err(err.into())
+        }
+
+        #[cfg(feature = "metrics")]
+        let (_in_flight, start) = (crate::metrics::global().start(), std::time::Instant::now());
+        let exchange = async {
+            socket.send_to(buf.as_ref(), ns).await?;
+            // anything else that arrives meanwhile, forged or late, is dropped
+            loop {
+                buf.resize(BUF_SIZE, 0);
+                let (n, from) = socket.recv_from(&mut buf[..]).await?;
+                if let Ok(reply) = Msg::unpack(&buf[..n]) {
+                    if validate_response(&msg, &reply, Some(from), Some(ns), true).is_ok() {
+                        return io::Result::Ok(reply);
+                    }
+                }
+            }
+        };
+        let reply = tokio::time::timeout(timeout, exchange)
+            .await
+            .unwrap_or_else(|_| Err(io::ErrorKind::TimedOut.into()))?;
+        #[cfg(feature = "metrics")]
+        {
+            let metrics = crate::metrics::global();
+            metrics.upstream_latency(start.elapsed());
+            metrics.query(typ, reply.hdr.response_code);
+        }
+
+        ips.extend(reply.resolve_chain(&msg.question[0].name).ips());
+        Ok(())
+    }
+
+    if ipv4 {
+        do_request(ns, &socket, domain, &mut buf, types::TYPE_A, timeout, &mut ips).await?;
+    }
+    if ipv6 {
+        do_request(ns, &socket, domain, &mut buf, types::TYPE_AAAA, timeout, &mut ips).await?;
+    }
+
+    Ok(ips)
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+    use std::time::{Duration, Instant};
+    use tokio::net::UdpSocket;
+    use crate::Msg;
+    use super::lookup_host_with_timeout;
+
+    #[tokio::test]
+    async fn test_lookup_timeout() {
+        let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let ns = server.local_addr().unwrap();
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+
+        // a steady stream of replies that never match keeps arriving well
+        // within the timeout, yet must not extend it
+        let noise = async {
+            let mut buf = [0u8; 512];
+            let (n, from) = server.recv_from(&mut buf).await.unwrap();
+            let mut reply = Msg::unpack(&buf[..n]).unwrap();
+            reply.hdr.response = true;
+            reply.hdr.id = reply.hdr.id.wrapping_add(1);
+            let reply = reply.to_buf().unwrap();
+            loop {
+                let _ = server.send_to(&reply, from).await;
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+        let start = Instant::now();
+        let ret = tokio::select! {
+            ret = lookup_host_with_timeout(client, ns, "www.example.com", true, false, Duration::from_millis(200)) => ret,
+            _ = noise => unreachable!(),
+        };
+        assert_eq!(ret.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+}
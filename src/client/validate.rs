@@ -0,0 +1,105 @@
+//! Matching responses to the queries they answer (RFC 5452 section 9), so a
+//! forged or stray datagram is not taken for the answer.
+
+use std::net::{IpAddr, SocketAddr};
+use rand::Rng;
+use crate::{DomainString, Error, Msg, Result};
+
+/// Checks that `response` answers `request`, as [`Msg::is_answer_for`]
+/// tells. With `match_case`, for a request name from [`randomize_case`],
+/// the name must come back in exactly its case. `source_addr` must equal
+/// `expected_addr` when both are given; transports without one, like
+/// HTTPS, pass `None`.
+pub fn validate_response(
+    request: &Msg,
+    response: &Msg,
+    source_addr: Option<SocketAddr>,
+    expected_addr: Option<SocketAddr>,
+    match_case: bool,
+) -> Result<()> {
+    if let (Some(source), Some(expected)) = (source_addr, expected_addr) {
+        if canonical(source) != canonical(expected) {
+            return Err(Error::new(format!("response from {} rather than {}", source, expected)));
+        }
+    }
+    if !response.is_answer_for(request) {
+        return Err(Error::new("response does not match the query"));
+    }
+    for (q, r) in request.question.iter().zip(&response.question) {
+        if match_case && q.name != r.name {
+            return Err(Error::new("response question is not in the case of the query"));
+        }
+    }
+    Ok(())
+}
+
+/// `name` with each letter upper or lower case at random, for the 0x20
+/// check of [`validate_response`].
+pub fn randomize_case(name: &str) -> DomainString {
+    let mut rng = rand::thread_rng();
+    name.chars()
+        .map(|c| if rng.gen() { c.to_ascii_uppercase() } else { c.to_ascii_lowercase() })
+        .collect()
+}
+
+/// IPv4-mapped addresses, as dual-stack sockets report them, as IPv4.
+fn canonical(addr: SocketAddr) -> SocketAddr {
+    match addr.ip() {
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(v4) => SocketAddr::new(v4.into(), addr.port()),
+            None => addr,
+        },
+        IpAddr::V4(_) => addr,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::SocketAddr;
+    use crate::{full_domain, types, Msg};
+    use super::{randomize_case, validate_response};
+
+    #[test]
+    fn test_validate_response() {
+        let mut request = Msg::new();
+        request.set_question(full_domain("www.example.com"), types::TYPE_A);
+        let mut response = Msg::new();
+        response.set_reply(&request);
+        let ns: SocketAddr = "192.0.2.53:53".parse().unwrap();
+        let mapped: SocketAddr = "[::ffff:192.0.2.53]:53".parse().unwrap();
+        let other: SocketAddr = "192.0.2.54:53".parse().unwrap();
+        assert!(validate_response(&request, &response, Some(mapped), Some(ns), false).is_ok());
+        assert!(validate_response(&request, &response, None, None, false).is_ok());
+        assert!(validate_response(&request, &response, Some(other), Some(ns), false).is_err());
+
+        let mut wrong = response.clone();
+        wrong.hdr.id = wrong.hdr.id.wrapping_add(1);
+        assert!(validate_response(&request, &wrong, None, None, false).is_err());
+        let mut wrong = response.clone();
+        wrong.hdr.response = false;
+        assert!(validate_response(&request, &wrong, None, None, false).is_err());
+        let mut wrong = response.clone();
+        wrong.question[0].q_type = types::TYPE_AAAA;
+        assert!(validate_response(&request, &wrong, None, None, false).is_err());
+        let mut wrong = response.clone();
+        wrong.question[0].name = full_domain("www.example.net");
+        assert!(validate_response(&request, &wrong, None, None, false).is_err());
+        let mut wrong = response.clone();
+        wrong.question.clear();
+        assert!(validate_response(&request, &wrong, None, None, false).is_err());
+        wrong.hdr.response_code = types::RCODE_FORMAT_ERROR;
+        assert!(validate_response(&request, &wrong, None, None, false).is_ok());
+
+        // 0x20: case only counts when asked for
+        request.question[0].name = full_domain("wWw.ExAmple.com");
+        let mut upper = response.clone();
+        upper.question[0].name = full_domain("WWW.example.com");
+        assert!(validate_response(&request, &upper, None, None, false).is_ok());
+        assert!(validate_response(&request, &upper, None, None, true).is_err());
+        upper.question[0].name = full_domain("wWw.ExAmple.com");
+        assert!(validate_response(&request, &upper, None, None, true).is_ok());
+
+        let name = randomize_case("www.example.com.");
+        assert!(name.eq_ignore_ascii_case("www.example.com."));
+    }
+}
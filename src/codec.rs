@@ -0,0 +1,111 @@
+//! `tokio_util::codec` framing, so messages can be read and written with
+//! `UdpFramed` and `Framed`.
+
+use bytes::{Buf, BufMut, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+use crate::{util, Error, Msg, Result};
+
+/// One message per datagram.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DnsUdpCodec;
+
+impl Encoder<Msg> for DnsUdpCodec {
+    type Error = Error;
+
+    fn encode(&mut self, item: Msg, dst: &mut BytesMut) -> Result<()> {
+        item.pack(dst)
+    }
+}
+
+impl Decoder for DnsUdpCodec {
+    type Item = Msg;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Msg>> {
+        if src.is_empty() {
+            return Ok(None);
+        }
+        let datagram = src.split();
+        Msg::unpack(&datagram).map(Some)
+    }
+}
+
+/// Messages prefixed with their two-octet length (RFC 1035 section 4.2.2).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DnsTcpCodec;
+
+impl Encoder<Msg> for DnsTcpCodec {
+    type Error = Error;
+
+    fn encode(&mut self, item: Msg, dst: &mut BytesMut) -> Result<()> {
+        let start = dst.len();
+        dst.put_u16(0);
+        let len = item.pack(dst).and_then(|_| {
+            u16::try_from(dst.len() - start - 2).map_err(|_| Error::new("message longer than 65535 octets"))
+        });
+        match len {
+            Ok(len) => {
+                util::set_value_offset(dst.as_mut(), start, len);
+                Ok(())
+            }
+            Err(err) => {
+                dst.truncate(start);
+                Err(err)
+            }
+        }
+    }
+}
+
+impl Decoder for DnsTcpCodec {
+    type Item = Msg;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Msg>> {
+        if src.len() < 2 {
+            return Ok(None);
+        }
+        let len = u16::from_be_bytes([src[0], src[1]]) as usize;
+        if src.len() < 2 + len {
+            src.reserve(2 + len - src.len());
+            return Ok(None);
+        }
+        src.advance(2);
+        let frame = src.split_to(len);
+        Msg::unpack(&frame).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::BytesMut;
+    use tokio_util::codec::{Decoder, Encoder};
+    use crate::{full_domain, types, Msg};
+    use super::{DnsTcpCodec, DnsUdpCodec};
+
+    #[test]
+    fn test_codec() {
+        let mut msg = Msg::new();
+        msg.set_question(full_domain("example.com"), types::TYPE_A);
+        let wire = msg.to_buf().unwrap();
+
+        let mut buf = BytesMut::new();
+        DnsUdpCodec.encode(msg.clone(), &mut buf).unwrap();
+        assert_eq!(buf, wire);
+        let got = DnsUdpCodec.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(got.hdr.id, msg.hdr.id);
+        assert!(buf.is_empty());
+
+        let mut tcp = DnsTcpCodec;
+        tcp.encode(msg.clone(), &mut buf).unwrap();
+        tcp.encode(msg.clone(), &mut buf).unwrap();
+        assert_eq!(buf.len(), 2 * (2 + wire.len()));
+        assert_eq!(&buf[..2], &(wire.len() as u16).to_be_bytes());
+
+        let mut stream = buf.split_to(wire.len());
+        assert!(tcp.decode(&mut stream).unwrap().is_none());
+        stream.unsplit(buf);
+        assert_eq!(tcp.decode(&mut stream).unwrap().unwrap().question[0].name.as_str(), "example.com.");
+        assert!(tcp.decode(&mut stream).unwrap().is_some());
+        assert!(tcp.decode(&mut stream).unwrap().is_none());
+    }
+}
@@ -0,0 +1,266 @@
+//! Server side of DNS cookies (RFC 7873) with the interoperable server
+//! cookie of RFC 9018: a SipHash-2-4 over the client cookie, a timestamp
+//! and the client address, keyed by a secret that can be rotated.
+
+use std::net::IpAddr;
+use std::time::{SystemTime, UNIX_EPOCH};
+use parking_lot::RwLock;
+use rand::RngCore;
+use crate::types::edns::edns0::{EDNS0COOKIE, LOCAL};
+use crate::types::{EDNS0, Opt};
+use crate::{types, Msg};
+
+const CLIENT_COOKIE_LEN: usize = 8;
+const SERVER_COOKIE_LEN: usize = 16;
+/// Server cookies from other servers can be 8 to 32 bytes.
+const MAX_COOKIE_LEN: usize = CLIENT_COOKIE_LEN + 32;
+const VERSION: u8 = 1;
+/// Server cookies older than this are not accepted (RFC 9018 section 4.3).
+const MAX_AGE: u32 = 3600;
+/// Nor ones from further in the future than this.
+const MAX_SKEW: u32 = 300;
+
+/// What the cookie of a request shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CookieCheck {
+    /// No cookie option.
+    Missing,
+    /// A cookie option of a bad length, to be answered with FORMERR.
+    Malformed,
+    /// A client cookie without a server cookie this server accepts.
+    ClientOnly,
+    /// A server cookie this server issued to this client: the client gets
+    /// answers at its address, so it can be exempt from rate limits.
+    Valid,
+}
+
+/// Issues and verifies server cookies.
+#[derive(Debug)]
+pub struct ServerCookies {
+    /// The current secret and the one before it, still accepted.
+    secrets: RwLock<([u8; 16], Option<[u8; 16]>)>,
+    require: bool,
+}
+
+impl ServerCookies {
+    pub fn new(secret: [u8; 16]) -> Self {
+        Self { secrets: RwLock::new((secret, None)), require: false }
+    }
+
+    /// With a random secret; servers of an anycast set need a shared one.
+    pub fn random() -> Self {
+        Self::new(random_secret())
+    }
+
+    /// Whether to answer BADCOOKIE to requests that carry a client cookie
+    /// but no valid server cookie, rather than answering them.
+    pub fn require(mut self, require: bool) -> Self {
+        self.require = require;
+        self
+    }
+
+    /// Starts issuing cookies with `secret`; the ones of the previous
+    /// secret stay valid until the next rotation.
+    pub fn rotate(&self, secret: [u8; 16]) {
+        let mut secrets = self.secrets.write();
+        *secrets = (secret, Some(secrets.0));
+    }
+
+    /// [`rotate`](Self::rotate) to a random secret.
+    pub fn rotate_random(&self) {
+        self.rotate(random_secret());
+    }
+
+    pub fn check(&self, request: &Msg, client: IpAddr) -> CookieCheck {
+        self.check_at(request, client, now())
+    }
+
+    /// The reply when `request` is not to be answered as usual: FORMERR
+    /// for a malformed cookie and, with [`require`](Self::require),
+    /// BADCOOKIE with a fresh cookie for a missing or stale server cookie.
+    pub fn answer_for(&self, request: &Msg, client: IpAddr) -> Option<Msg> {
+        let rcode = match self.check(request, client) {
+            CookieCheck::Malformed => types::RCODE_FORMAT_ERROR,
+            CookieCheck::ClientOnly if self.require => types::RCODE_BAD_COOKIE,
+            _ => return None,
+        };
+        let mut msg = Msg::new();
+        msg.set_reply(request);
+        msg.hdr.response_code = rcode;
+        if rcode == types::RCODE_BAD_COOKIE {
+            self.respond(request, client, &mut msg);
+        }
+        Some(msg)
+    }
+
+    /// Adds the client cookie of `request` with a fresh server cookie to
+    /// `response`, adding an OPT record if needed. Does nothing when the
+    /// request has no well-formed client cookie.
+    pub fn respond(&self, request: &Msg, client: IpAddr, response: &mut Msg) {
+        self.respond_at(request, client, response, now())
+    }
+
+    fn check_at(&self, request: &Msg, client: IpAddr, now: u32) -> CookieCheck {
+        let Some(cookie) = request.is_edns0().and_then(Opt::cookie) else { return CookieCheck::Missing; };
+        if cookie.len() != CLIENT_COOKIE_LEN && !(CLIENT_COOKIE_LEN + 8..=MAX_COOKIE_LEN).contains(&cookie.len()) {
+            return CookieCheck::Malformed;
+        }
+        let (client_cookie, server_cookie) = cookie.split_at(CLIENT_COOKIE_LEN);
+        if server_cookie.len() != SERVER_COOKIE_LEN || server_cookie[0] != VERSION {
+            return CookieCheck::ClientOnly;
+        }
+        let timestamp = u32::from_be_bytes(server_cookie[4..8].try_into().unwrap());
+        let age = now.wrapping_sub(timestamp) as i32;
+        if age > MAX_AGE as i32 || age < -(MAX_SKEW as i32) {
+            return CookieCheck::ClientOnly;
+        }
+        let (current, previous) = *self.secrets.read();
+        let valid = [Some(current), previous]
+            .into_iter()
+            .flatten()
+            .any(|secret| server_cookie == server_cookie_for(&secret, client_cookie, client, timestamp));
+        if valid {
+            CookieCheck::Valid
+        } else {
+            CookieCheck::ClientOnly
+        }
+    }
+
+    fn respond_at(&self, request: &Msg, client: IpAddr, response: &mut Msg, now: u32) {
+        let Some(cookie) = request.is_edns0().and_then(Opt::cookie) else { return; };
+        if cookie.len() < CLIENT_COOKIE_LEN || cookie.len() > MAX_COOKIE_LEN {
+            return;
+        }
+        let client_cookie = &cookie[..CLIENT_COOKIE_LEN];
+        let secret = self.secrets.read().0;
+        let mut data = client_cookie.to_vec();
+        data.extend_from_slice(&server_cookie_for(&secret, client_cookie, client, now));
+        let option = EDNS0::Local(LOCAL { code: EDNS0COOKIE, data });
+
+        response.edns_mut().set_option(option);
+    }
+}
+
+/// Version, reserved bytes, timestamp and hash (RFC 9018 section 4).
+fn server_cookie_for(secret: &[u8; 16], client_cookie: &[u8], client: IpAddr, timestamp: u32) -> [u8; SERVER_COOKIE_LEN] {
+    let mut cookie = [0u8; SERVER_COOKIE_LEN];
+    cookie[0] = VERSION;
+    cookie[4..8].copy_from_slice(&timestamp.to_be_bytes());
+    let mut input = client_cookie.to_vec();
+    input.extend_from_slice(&cookie[..8]);
+    match client {
+        IpAddr::V4(ip) => input.extend_from_slice(&ip.octets()),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => input.extend_from_slice(&ip.octets()),
+            None => input.extend_from_slice(&ip.octets()),
+        },
+    }
+    cookie[8..].copy_from_slice(&siphash24(secret, &input).to_le_bytes());
+    cookie
+}
+
+fn now() -> u32 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as u32).unwrap_or(0)
+}
+
+fn random_secret() -> [u8; 16] {
+    let mut secret = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut secret);
+    secret
+}
+
+fn siphash24(key: &[u8; 16], data: &[u8]) -> u64 {
+    fn round(v: &mut [u64; 4]) {
+        v[0] = v[0].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(13) ^ v[0];
+        v[0] = v[0].rotate_left(32);
+        v[2] = v[2].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(16) ^ v[2];
+        v[0] = v[0].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(21) ^ v[0];
+        v[2] = v[2].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(17) ^ v[2];
+        v[2] = v[2].rotate_left(32);
+    }
+    let compress = |v: &mut [u64; 4], m: u64| {
+        v[3] ^= m;
+        round(v);
+        round(v);
+        v[0] ^= m;
+    };
+
+    let k0 = u64::from_le_bytes(key[..8].try_into().unwrap());
+    let k1 = u64::from_le_bytes(key[8..].try_into().unwrap());
+    let mut v = [k0 ^ 0x736f6d6570736575, k1 ^ 0x646f72616e646f6d, k0 ^ 0x6c7967656e657261, k1 ^ 0x7465646279746573];
+    let chunks = data.chunks_exact(8);
+    let tail = chunks.remainder();
+    for chunk in chunks {
+        compress(&mut v, u64::from_le_bytes(chunk.try_into().unwrap()));
+    }
+    let mut last = [0u8; 8];
+    last[..tail.len()].copy_from_slice(tail);
+    last[7] = data.len() as u8;
+    compress(&mut v, u64::from_le_bytes(last));
+    v[2] ^= 0xff;
+    for _ in 0..4 {
+        round(&mut v);
+    }
+    v[0] ^ v[1] ^ v[2] ^ v[3]
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::IpAddr;
+    use crate::types::edns::edns0::{EDNS0COOKIE, LOCAL};
+    use crate::types::{EDNS0, Opt};
+    use crate::{full_domain, types, Msg};
+    use super::{siphash24, CookieCheck, ServerCookies};
+
+    fn request(cookie: &str) -> Msg {
+        let mut msg = Msg::new();
+        msg.set_question(full_domain("example.com"), types::TYPE_A);
+        let mut opt = Opt::new(1232);
+        opt.option.push(EDNS0::Local(LOCAL { code: EDNS0COOKIE, data: hex::decode(cookie).unwrap() }));
+        msg.additional.push(opt.into());
+        msg
+    }
+
+    #[test]
+    fn test_cookies() {
+        let key: [u8; 16] = core::array::from_fn(|i| i as u8);
+        assert_eq!(siphash24(&key, &[]), 0x726fdb47dd0e0e31);
+        assert_eq!(siphash24(&key, &(0..15).collect::<Vec<u8>>()), 0xa129ca6149be45e5);
+
+        // RFC 9018 appendix A.1
+        let secret: [u8; 16] = hex::decode("e5e973e5a6b2a43f48e7dc849e37bfcf").unwrap().try_into().unwrap();
+        let cookies = ServerCookies::new(secret).require(true);
+        let client: IpAddr = "198.51.100.100".parse().unwrap();
+        let now = 1559731985;
+        let learning = request("2464c4abcf10c957");
+        assert_eq!(cookies.check_at(&learning, client, now), CookieCheck::ClientOnly);
+        let mut response = Msg::new();
+        cookies.respond_at(&learning, client, &mut response, now);
+        let issued = hex::encode(response.is_edns0().and_then(Opt::cookie).unwrap());
+        assert_eq!(issued, "2464c4abcf10c957010000005cf79f111f8130c3eee29480");
+
+        let returning = request(&issued);
+        assert_eq!(cookies.check_at(&returning, client, now + 60), CookieCheck::Valid);
+        assert_eq!(cookies.check_at(&returning, "198.51.100.101".parse().unwrap(), now + 60), CookieCheck::ClientOnly);
+        assert_eq!(cookies.check_at(&returning, client, now + 3601), CookieCheck::ClientOnly);
+        cookies.rotate_random();
+        assert_eq!(cookies.check_at(&returning, client, now + 60), CookieCheck::Valid);
+        cookies.rotate_random();
+        assert_eq!(cookies.check_at(&returning, client, now + 60), CookieCheck::ClientOnly);
+
+        assert_eq!(cookies.check_at(&request("2464c4ab"), client, now), CookieCheck::Malformed);
+        let mut plain = Msg::new();
+        plain.set_question(full_domain("example.com"), types::TYPE_A);
+        assert_eq!(cookies.check(&plain, client), CookieCheck::Missing);
+        assert!(cookies.answer_for(&plain, client).is_none());
+
+        let bad = cookies.answer_for(&learning, client).unwrap();
+        let bad = Msg::unpack(&bad.to_buf().unwrap()).unwrap();
+        assert_eq!(bad.hdr.response_code, types::RCODE_BAD_COOKIE);
+        assert_eq!(bad.is_edns0().and_then(Opt::cookie).map(|c| c.len()), Some(24));
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use crate::{Error, Result};
+
+/// A DNSSEC signature algorithm (RFC 8624 numbering).
+///
+/// Public keys are the DNSKEY public key field and signatures the RRSIG
+/// signature field. Private keys are opaque to callers and specific to the
+/// implementation.
+pub trait Algorithm: Send + Sync {
+    fn number(&self) -> u8;
+
+    /// Mnemonic, e.g. `ECDSAP256SHA256`.
+    fn name(&self) -> &'static str;
+
+    fn verify(&self, public_key: &[u8], data: &[u8], signature: &[u8]) -> Result<()>;
+
+    fn sign(&self, _private_key: &[u8], _data: &[u8]) -> Result<Vec<u8>> {
+        Err(Error::UnsupportedAlgorithm(self.number()))
+    }
+
+    /// DNSKEY public key field of a private key.
+    fn public_key(&self, _private_key: &[u8]) -> Result<Vec<u8>> {
+        Err(Error::UnsupportedAlgorithm(self.number()))
+    }
+}
+
+/// Algorithms known to the validator and signer, by number.
+#[derive(Clone)]
+pub struct Registry {
+    algorithms: BTreeMap<u8, Arc<dyn Algorithm>>,
+}
+
+impl Default for Registry {
+    /// Every algorithm built into the crate.
+    fn default() -> Self {
+        #[allow(unused_mut)]
+        let mut registry = Self::empty();
+        #[cfg(feature = "dnssec")]
+        {
+            use super::crypto::{Ecdsa, Ed25519, RsaSha};
+            registry.register(RsaSha::SHA256);
+            registry.register(RsaSha::SHA512);
+            registry.register(Ecdsa::P256);
+            registry.register(Ecdsa::P384);
+            registry.register(Ed25519);
+        }
+        registry
+    }
+}
+
+impl std::fmt::Debug for Registry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.algorithms.values().map(|alg| alg.name())).finish()
+    }
+}
+
+impl Registry {
+    pub fn empty() -> Self {
+        Self {
+            algorithms: BTreeMap::new(),
+        }
+    }
+
+    /// Adds or replaces the implementation of an algorithm number.
+    pub fn register<A: Algorithm + 'static>(&mut self, algorithm: A) -> &mut Self {
+        self.algorithms.insert(algorithm.number(), Arc::new(algorithm));
+        self
+    }
+
+    pub fn remove(&mut self, number: u8) -> Option<Arc<dyn Algorithm>> {
+        self.algorithms.remove(&number)
+    }
+
+    pub fn get(&self, number: u8) -> Option<&dyn Algorithm> {
+        self.algorithms.get(&number).map(|alg| alg.as_ref())
+    }
+
+    pub fn numbers(&self) -> impl Iterator<Item = u8> + '_ {
+        self.algorithms.keys().copied()
+    }
+
+    pub fn verify(&self, number: u8, public_key: &[u8], data: &[u8], signature: &[u8]) -> Result<()> {
+        self.get(number)
+            .ok_or(Error::UnsupportedAlgorithm(number))?
+            .verify(public_key, data, signature)
+    }
+
+    pub fn sign(&self, number: u8, private_key: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+        self.get(number)
+            .ok_or(Error::UnsupportedAlgorithm(number))?
+            .sign(private_key, data)
+    }
+}
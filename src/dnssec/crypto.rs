@@ -0,0 +1,285 @@
+use ed25519_dalek::{Signer, Verifier};
+use rsa::pkcs1::{DecodeRsaPrivateKey, EncodeRsaPrivateKey};
+use rsa::traits::{PrivateKeyParts, PublicKeyParts};
+use rsa::{BigUint, Pkcs1v15Sign, RsaPrivateKey, RsaPublicKey};
+use sha2::{Digest, Sha256, Sha512};
+use crate::{Error, Result};
+use super::{Algorithm, ALG_ECDSAP256SHA256, ALG_ECDSAP384SHA384, ALG_ED25519, ALG_RSASHA256, ALG_RSASHA512};
+
+fn bad_key<E>(_: E) -> Error {
+    Error::new("bad DNSSEC key")
+}
+
+fn bad_signature<E>(_: E) -> Error {
+    Error::BadSignature
+}
+
+/// RSA/SHA-256 and RSA/SHA-512 (RFC 5702). Private keys are PKCS#1 DER.
+#[derive(Debug, Clone, Copy)]
+pub struct RsaSha {
+    number: u8,
+}
+
+impl RsaSha {
+    pub const SHA256: RsaSha = RsaSha { number: ALG_RSASHA256 };
+    pub const SHA512: RsaSha = RsaSha { number: ALG_RSASHA512 };
+
+    fn padding(&self) -> Pkcs1v15Sign {
+        if self.number == ALG_RSASHA512 {
+            Pkcs1v15Sign::new::<Sha512>()
+        } else {
+            Pkcs1v15Sign::new::<Sha256>()
+        }
+    }
+
+    fn digest(&self, data: &[u8]) -> Vec<u8> {
+        if self.number == ALG_RSASHA512 {
+            Sha512::digest(data).to_vec()
+        } else {
+            Sha256::digest(data).to_vec()
+        }
+    }
+}
+
+/// Parses the RFC 3110 exponent-length, exponent, modulus encoding.
+pub(crate) fn rsa_public_key(key: &[u8]) -> Result<RsaPublicKey> {
+    let (e_len, rest) = match key {
+        [0, hi, lo, rest @ ..] => ((*hi as usize) << 8 | *lo as usize, rest),
+        [len, rest @ ..] => (*len as usize, rest),
+        [] => return Err(bad_key(())),
+    };
+    if e_len == 0 || rest.len() <= e_len {
+        return Err(bad_key(()));
+    }
+    let (e, n) = rest.split_at(e_len);
+    RsaPublicKey::new(BigUint::from_bytes_be(n), BigUint::from_bytes_be(e)).map_err(bad_key)
+}
+
+pub(crate) fn rsa_dnskey(key: &RsaPublicKey) -> Vec<u8> {
+    let (e, n) = (key.e().to_bytes_be(), key.n().to_bytes_be());
+    let mut ret = Vec::with_capacity(3 + e.len() + n.len());
+    if e.len() > 255 {
+        ret.push(0);
+        ret.extend_from_slice(&(e.len() as u16).to_be_bytes());
+    } else {
+        ret.push(e.len() as u8);
+    }
+    ret.extend_from_slice(&e);
+    ret.extend_from_slice(&n);
+    ret
+}
+
+impl Algorithm for RsaSha {
+    fn number(&self) -> u8 {
+        self.number
+    }
+
+    fn name(&self) -> &'static str {
+        if self.number == ALG_RSASHA512 { "RSASHA512" } else { "RSASHA256" }
+    }
+
+    fn verify(&self, public_key: &[u8], data: &[u8], signature: &[u8]) -> Result<()> {
+        rsa_public_key(public_key)?
+            .verify(self.padding(), &self.digest(data), signature)
+            .map_err(bad_signature)
+    }
+
+    fn sign(&self, private_key: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+        RsaPrivateKey::from_pkcs1_der(private_key)
+            .map_err(bad_key)?
+            .sign(self.padding(), &self.digest(data))
+            .map_err(|e| Error::new(e.to_string()))
+    }
+
+    fn public_key(&self, private_key: &[u8]) -> Result<Vec<u8>> {
+        let key = RsaPrivateKey::from_pkcs1_der(private_key).map_err(bad_key)?;
+        Ok(rsa_dnskey(&key.to_public_key()))
+    }
+}
+
+/// ECDSA P-256/SHA-256 and P-384/SHA-384 (RFC 6605). Private keys are the
+/// big-endian scalar.
+#[derive(Debug, Clone, Copy)]
+pub struct Ecdsa {
+    number: u8,
+}
+
+impl Ecdsa {
+    pub const P256: Ecdsa = Ecdsa { number: ALG_ECDSAP256SHA256 };
+    pub const P384: Ecdsa = Ecdsa { number: ALG_ECDSAP384SHA384 };
+}
+
+/// DNSKEY keys are the uncompressed point without its SEC1 `04` prefix.
+fn sec1(public_key: &[u8]) -> Vec<u8> {
+    let mut point = Vec::with_capacity(public_key.len() + 1);
+    point.push(4);
+    point.extend_from_slice(public_key);
+    point
+}
+
+impl Algorithm for Ecdsa {
+    fn number(&self) -> u8 {
+        self.number
+    }
+
+    fn name(&self) -> &'static str {
+        if self.number == ALG_ECDSAP384SHA384 { "ECDSAP384SHA384" } else { "ECDSAP256SHA256" }
+    }
+
+    fn verify(&self, public_key: &[u8], data: &[u8], signature: &[u8]) -> Result<()> {
+        let point = sec1(public_key);
+        if self.number == ALG_ECDSAP384SHA384 {
+            use p384::ecdsa::{Signature, VerifyingKey};
+            let key = VerifyingKey::from_sec1_bytes(&point).map_err(bad_key)?;
+            let signature = Signature::from_slice(signature).map_err(bad_signature)?;
+            key.verify(data, &signature).map_err(bad_signature)
+        } else {
+            use p256::ecdsa::{Signature, VerifyingKey};
+            let key = VerifyingKey::from_sec1_bytes(&point).map_err(bad_key)?;
+            let signature = Signature::from_slice(signature).map_err(bad_signature)?;
+            key.verify(data, &signature).map_err(bad_signature)
+        }
+    }
+
+    fn sign(&self, private_key: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+        if self.number == ALG_ECDSAP384SHA384 {
+            let key = p384::ecdsa::SigningKey::from_slice(private_key).map_err(bad_key)?;
+            let signature: p384::ecdsa::Signature = key.sign(data);
+            Ok(signature.to_bytes().to_vec())
+        } else {
+            let key = p256::ecdsa::SigningKey::from_slice(private_key).map_err(bad_key)?;
+            let signature: p256::ecdsa::Signature = key.sign(data);
+            Ok(signature.to_bytes().to_vec())
+        }
+    }
+
+    fn public_key(&self, private_key: &[u8]) -> Result<Vec<u8>> {
+        let point = if self.number == ALG_ECDSAP384SHA384 {
+            let key = p384::ecdsa::SigningKey::from_slice(private_key).map_err(bad_key)?;
+            key.verifying_key().to_encoded_point(false).as_bytes().to_vec()
+        } else {
+            let key = p256::ecdsa::SigningKey::from_slice(private_key).map_err(bad_key)?;
+            key.verifying_key().to_encoded_point(false).as_bytes().to_vec()
+        };
+        Ok(point[1..].to_vec())
+    }
+}
+
+/// Ed25519 (RFC 8080). Private keys are the 32-octet seed.
+#[derive(Debug, Clone, Copy)]
+pub struct Ed25519;
+
+impl Algorithm for Ed25519 {
+    fn number(&self) -> u8 {
+        ALG_ED25519
+    }
+
+    fn name(&self) -> &'static str {
+        "ED25519"
+    }
+
+    fn verify(&self, public_key: &[u8], data: &[u8], signature: &[u8]) -> Result<()> {
+        let key: [u8; 32] = public_key.try_into().map_err(bad_key)?;
+        let key = ed25519_dalek::VerifyingKey::from_bytes(&key).map_err(bad_key)?;
+        let signature = ed25519_dalek::Signature::from_slice(signature).map_err(bad_signature)?;
+        key.verify(data, &signature).map_err(bad_signature)
+    }
+
+    fn sign(&self, private_key: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+        let seed: [u8; 32] = private_key.try_into().map_err(bad_key)?;
+        Ok(ed25519_dalek::SigningKey::from_bytes(&seed).sign(data).to_bytes().to_vec())
+    }
+
+    fn public_key(&self, private_key: &[u8]) -> Result<Vec<u8>> {
+        let seed: [u8; 32] = private_key.try_into().map_err(bad_key)?;
+        Ok(ed25519_dalek::SigningKey::from_bytes(&seed).verifying_key().to_bytes().to_vec())
+    }
+}
+
+/// RSA modulus size of generated keys.
+const RSA_BITS: usize = 2048;
+
+/// A fresh private key in the form `Algorithm::sign` takes.
+pub(crate) fn generate(number: u8) -> Result<Vec<u8>> {
+    let mut rng = rand::thread_rng();
+    match number {
+        ALG_RSASHA256 | ALG_RSASHA512 => {
+            let key = RsaPrivateKey::new(&mut rng, RSA_BITS).map_err(|e| Error::new(e.to_string()))?;
+            Ok(key.to_pkcs1_der().map_err(bad_key)?.as_bytes().to_vec())
+        }
+        ALG_ECDSAP256SHA256 => Ok(p256::ecdsa::SigningKey::random(&mut rng).to_bytes().to_vec()),
+        ALG_ECDSAP384SHA384 => Ok(p384::ecdsa::SigningKey::random(&mut rng).to_bytes().to_vec()),
+        ALG_ED25519 => Ok(ed25519_dalek::SigningKey::generate(&mut rng).to_bytes().to_vec()),
+        _ => Err(Error::UnsupportedAlgorithm(number)),
+    }
+}
+
+/// Field names of an RSA key in the BIND private-key format, in file order.
+pub(crate) const RSA_FIELDS: [&str; 8] = [
+    "Modulus",
+    "PublicExponent",
+    "PrivateExponent",
+    "Prime1",
+    "Prime2",
+    "Exponent1",
+    "Exponent2",
+    "Coefficient",
+];
+
+/// Splits a PKCS#1 key into the values of `RSA_FIELDS`.
+pub(crate) fn rsa_to_fields(private_key: &[u8]) -> Result<Vec<Vec<u8>>> {
+    let mut key = RsaPrivateKey::from_pkcs1_der(private_key).map_err(bad_key)?;
+    key.precompute().map_err(bad_key)?;
+    let (dp, dq, qinv) = match (key.dp(), key.dq(), key.crt_coefficient()) {
+        (Some(dp), Some(dq), Some(qinv)) => (dp.clone(), dq.clone(), qinv),
+        _ => return Err(bad_key(())),
+    };
+    let primes = key.primes();
+    if primes.len() != 2 {
+        return Err(bad_key(()));
+    }
+    Ok([key.n(), key.e(), key.d(), &primes[0], &primes[1], &dp, &dq, &qinv]
+        .iter()
+        .map(|v| v.to_bytes_be())
+        .collect())
+}
+
+/// Builds a PKCS#1 key from the values of `RSA_FIELDS`; the CRT values are
+/// recomputed.
+pub(crate) fn rsa_from_fields(fields: &[Vec<u8>]) -> Result<Vec<u8>> {
+    let [n, e, d, p, q, ..] = fields else {
+        return Err(bad_key(()));
+    };
+    let int = |v: &Vec<u8>| BigUint::from_bytes_be(v);
+    let key = RsaPrivateKey::from_components(int(n), int(e), int(d), vec![int(p), int(q)]).map_err(bad_key)?;
+    key.validate().map_err(bad_key)?;
+    Ok(key.to_pkcs1_der().map_err(bad_key)?.as_bytes().to_vec())
+}
+
+#[cfg(test)]
+mod test {
+    use rsa::pkcs1::EncodeRsaPrivateKey;
+    use crate::dnssec::{Registry, ALG_ECDSAP256SHA256, ALG_ECDSAP384SHA384, ALG_ED25519, ALG_RSASHA256, ALG_RSASHA512};
+
+    #[test]
+    fn test_algorithms() {
+        let registry = Registry::default();
+        let rsa = rsa::RsaPrivateKey::new(&mut rand::thread_rng(), 1024).unwrap();
+        let rsa = rsa.to_pkcs1_der().unwrap().as_bytes().to_vec();
+        let keys = [
+            (ALG_RSASHA256, rsa.clone()),
+            (ALG_RSASHA512, rsa),
+            (ALG_ECDSAP256SHA256, vec![7u8; 32]),
+            (ALG_ECDSAP384SHA384, vec![7u8; 48]),
+            (ALG_ED25519, vec![7u8; 32]),
+        ];
+        for (number, private) in keys {
+            let alg = registry.get(number).unwrap();
+            let public = alg.public_key(&private).unwrap();
+            let signature = alg.sign(&private, b"signed data").unwrap();
+            assert!(alg.verify(&public, b"signed data", &signature).is_ok(), "{}", alg.name());
+            assert!(alg.verify(&public, b"other data", &signature).is_err(), "{}", alg.name());
+        }
+        assert!(registry.verify(5, &[], &[], &[]).is_err());
+    }
+}
@@ -0,0 +1,264 @@
+use std::cmp::Ordering;
+use crate::types::{self, NSEC, NSEC3};
+use crate::{full_domain, util, DomainString};
+use super::nsec3_digest;
+
+/// What authenticated NSEC or NSEC3 records prove about a query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Denial {
+    /// The name does not exist and no wildcard matches it.
+    NxDomain,
+    /// The name, or the wildcard matching it, exists without the type.
+    NoData,
+    /// The name falls in an NSEC3 opt-out span, so it may be an unsigned
+    /// delegation; the answer is insecure (RFC 5155 section 9.2).
+    OptOut,
+    /// The proof is outside the validator's policy, e.g. too many NSEC3
+    /// iterations; the answer is insecure.
+    Insecure,
+}
+
+/// Records owned by a delegation point in the parent zone prove nothing
+/// about names below it (RFC 6840 section 4.1).
+fn is_delegation(bitmap: &[u16]) -> bool {
+    (bitmap.contains(&types::TYPE_NS) && !bitmap.contains(&types::TYPE_SOA)) || bitmap.contains(&types::TYPE_DNAME)
+}
+
+fn lacks(bitmap: &[u16], qtype: u16) -> bool {
+    !bitmap.contains(&qtype) && !bitmap.contains(&types::TYPE_CNAME)
+}
+
+/// Whether `name` sorts strictly between `owner` and `next`, the last link
+/// of the chain wrapping around to the apex.
+fn in_span<T: Ord + ?Sized>(owner: &T, next: &T, name: &T) -> bool {
+    match owner.cmp(next) {
+        Ordering::Less => owner < name && name < next,
+        _ => name > owner || name < next,
+    }
+}
+
+fn nsec_covers(nsec: &NSEC, name: &str) -> bool {
+    let cmp = |a: &str, b: &str| util::compare_names(a, b);
+    let (owner, next) = (nsec.hdr.name.as_str(), nsec.next_domain.as_str());
+    let inside = match cmp(owner, next) {
+        Ordering::Less => cmp(owner, name).is_lt() && cmp(name, next).is_lt(),
+        _ => cmp(name, owner).is_gt() || cmp(name, next).is_lt(),
+    };
+    let occluded = util::is_subdomain(name, owner) && is_delegation(&nsec.type_bit_map);
+    inside && !occluded
+}
+
+fn common_ancestor(a: &str, b: &str) -> DomainString {
+    let (a, b) = (util::name_labels(a), util::name_labels(b));
+    let common = a.iter().rev().zip(b.iter().rev()).take_while(|(x, y)| x == y).count();
+    labels_name(&a[a.len() - common..])
+}
+
+fn labels_name(labels: &[Vec<u8>]) -> DomainString {
+    let mut name = DomainString::new();
+    for label in labels {
+        name.push_str(&String::from_utf8_lossy(label));
+        name.push('.');
+    }
+    if name.is_empty() {
+        name.push('.');
+    }
+    name
+}
+
+fn wildcard(encloser: &str) -> DomainString {
+    let mut name = DomainString::from("*.");
+    if encloser != "." {
+        name.push_str(encloser);
+    }
+    name
+}
+
+/// Checks NSEC denial of `qname`/`qtype` (RFC 4035 section 5.4).
+pub fn nsec_denial(qname: &str, qtype: u16, nsecs: &[NSEC]) -> Option<Denial> {
+    let qname = full_domain(qname);
+    if let Some(nsec) = nsecs.iter().find(|n| util::equal_names(&n.hdr.name, &qname)) {
+        let bitmap = &nsec.type_bit_map;
+        let ok = if qtype == types::TYPE_DS {
+            !bitmap.contains(&types::TYPE_DS) && !bitmap.contains(&types::TYPE_CNAME)
+        } else {
+            lacks(bitmap, qtype) && !is_delegation(bitmap)
+        };
+        return ok.then_some(Denial::NoData);
+    }
+
+    let cover = nsecs.iter().find(|n| nsec_covers(n, &qname))?;
+    let a = common_ancestor(&qname, &cover.hdr.name);
+    let b = common_ancestor(&qname, &cover.next_domain);
+    let encloser = if util::is_subdomain(&a, &b) { a } else { b };
+    let wildcard = wildcard(&encloser);
+    if nsecs.iter().any(|n| nsec_covers(n, &wildcard)) {
+        return Some(Denial::NxDomain);
+    }
+    let nsec = nsecs.iter().find(|n| util::equal_names(&n.hdr.name, &wildcard))?;
+    lacks(&nsec.type_bit_map, qtype).then_some(Denial::NoData)
+}
+
+/// Proves that no exact match for `qname` exists, as required to accept a
+/// wildcard expansion (RFC 4035 section 5.3.4).
+pub fn nsec_wildcard_proof(qname: &str, nsecs: &[NSEC]) -> bool {
+    let qname = full_domain(qname);
+    nsecs.iter().any(|n| nsec_covers(n, &qname))
+}
+
+/// NSEC3 records with their hashes decoded, hashing names with each
+/// record's own parameters.
+struct Nsec3Set<'a> {
+    records: Vec<(&'a NSEC3, Vec<u8>, Vec<u8>)>,
+}
+
+impl<'a> Nsec3Set<'a> {
+    fn new(nsec3s: &'a [NSEC3]) -> Self {
+        let records = nsec3s
+            .iter()
+            .filter(|n| n.hash == types::nsec3::NSEC3_SHA1)
+            .filter_map(|n| {
+                let label = n.hdr.name.split('.').next()?;
+                Some((n, util::base32hex_decode(label)?, util::base32hex_decode(&n.next_domain)?))
+            })
+            .collect();
+        Self { records }
+    }
+
+    fn hash(n: &NSEC3, name: &str) -> Option<Vec<u8>> {
+        nsec3_digest(name, &hex::decode(&n.salt).ok()?, n.iterations).ok()
+    }
+
+    fn matching(&self, name: &str) -> Option<&'a NSEC3> {
+        self.records
+            .iter()
+            .find(|(n, owner, _)| Self::hash(n, name).as_ref() == Some(owner))
+            .map(|(n, _, _)| *n)
+    }
+
+    fn covering(&self, name: &str) -> Option<&'a NSEC3> {
+        self.records
+            .iter()
+            .find(|(n, owner, next)| Self::hash(n, name).map(|h| in_span(owner, next, &h)).unwrap_or(false))
+            .map(|(n, _, _)| *n)
+    }
+
+    /// Closest encloser proof (RFC 5155 section 8.3): the closest encloser
+    /// and the NSEC3 covering the next closer name.
+    fn closest_encloser(&self, qname: &str, zone: &str) -> Option<(DomainString, &'a NSEC3)> {
+        let labels = util::name_labels(qname);
+        let zone_len = util::name_labels(zone).len();
+        for depth in (zone_len..labels.len()).rev() {
+            let encloser = labels_name(&labels[labels.len() - depth..]);
+            let Some(matched) = self.matching(&encloser) else { continue; };
+            if is_delegation(&matched.type_bit_map) && depth > zone_len {
+                return None;
+            }
+            let next_closer = labels_name(&labels[labels.len() - depth - 1..]);
+            return self.covering(&next_closer).map(|cover| (encloser, cover));
+        }
+        None
+    }
+}
+
+/// Checks NSEC3 denial of `qname`/`qtype` in `zone` (RFC 5155 section 8).
+pub fn nsec3_denial(qname: &str, qtype: u16, zone: &str, nsec3s: &[NSEC3]) -> Option<Denial> {
+    let (qname, zone) = (full_domain(qname), full_domain(zone));
+    if !util::is_subdomain(&qname, &zone) {
+        return None;
+    }
+    let set = Nsec3Set::new(nsec3s);
+    if let Some(nsec3) = set.matching(&qname) {
+        let bitmap = &nsec3.type_bit_map;
+        let ok = if qtype == types::TYPE_DS {
+            !bitmap.contains(&types::TYPE_DS) && !bitmap.contains(&types::TYPE_CNAME)
+        } else {
+            lacks(bitmap, qtype) && !is_delegation(bitmap)
+        };
+        return ok.then_some(Denial::NoData);
+    }
+
+    let (encloser, cover) = set.closest_encloser(&qname, &zone)?;
+    if cover.is_opt_out() {
+        return Some(Denial::OptOut);
+    }
+    let wildcard = wildcard(&encloser);
+    if set.covering(&wildcard).is_some() {
+        return Some(Denial::NxDomain);
+    }
+    let nsec3 = set.matching(&wildcard)?;
+    lacks(&nsec3.type_bit_map, qtype).then_some(Denial::NoData)
+}
+
+/// Proves that the next closer name below the wildcard's closest encloser
+/// does not exist, as required to accept a wildcard expansion (RFC 5155
+/// section 8.8). `encloser` is derived from the RRSIG labels field.
+pub fn nsec3_wildcard_proof(qname: &str, encloser: &str, nsec3s: &[NSEC3]) -> bool {
+    let (qname, encloser) = (full_domain(qname), full_domain(encloser));
+    let labels = util::name_labels(&qname);
+    let depth = util::name_labels(&encloser).len();
+    if depth >= labels.len() || !util::is_subdomain(&qname, &encloser) {
+        return false;
+    }
+    let next_closer = labels_name(&labels[labels.len() - depth - 1..]);
+    Nsec3Set::new(nsec3s).covering(&next_closer).is_some()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::types::{self, RecourseRecord, NSEC, NSEC3};
+    use crate::zone::{parse, Nsec3Params, Zone};
+    use super::{nsec3_denial, nsec3_wildcard_proof, nsec_denial, nsec_wildcard_proof, Denial};
+
+    fn zone() -> Zone {
+        let text = "$TTL 300\n\
+            @ SOA ns hostmaster 1 1 1 1 60\n\
+            @ NS ns\n\
+            ns A 192.0.2.1\n\
+            a A 192.0.2.2\n\
+            x.y.w A 192.0.2.3\n\
+            *.w A 192.0.2.4\n\
+            sub NS ns.other.\n\
+            secure NS ns.other.\n\
+            secure DS \\# 5 0001 0d02 00\n";
+        Zone::from_records("example.", parse(text, "example.").unwrap())
+    }
+
+    #[test]
+    fn test_nsec_denial() {
+        let nsecs: Vec<NSEC> = zone().nsec_chain().unwrap().into_iter()
+            .filter_map(|rr| match rr { RecourseRecord::NSEC(n) => Some(n), _ => None })
+            .collect();
+        assert_eq!(nsec_denial("nope.example.", types::TYPE_A, &nsecs), Some(Denial::NxDomain));
+        assert_eq!(nsec_denial("a.example.", types::TYPE_AAAA, &nsecs), Some(Denial::NoData));
+        assert_eq!(nsec_denial("a.example.", types::TYPE_A, &nsecs), None);
+        assert_eq!(nsec_denial("z.w.example.", types::TYPE_AAAA, &nsecs), Some(Denial::NoData));
+        assert_eq!(nsec_denial("host.sub.example.", types::TYPE_A, &nsecs), None);
+        assert_eq!(nsec_denial("sub.example.", types::TYPE_DS, &nsecs), Some(Denial::NoData));
+        assert!(nsec_wildcard_proof("z.w.example.", &nsecs));
+        assert!(!nsec_wildcard_proof("a.example.", &nsecs));
+    }
+
+    #[test]
+    fn test_nsec3_denial() {
+        let nsec3s = |opt_out| -> Vec<NSEC3> {
+            let params = Nsec3Params { iterations: 1, salt: "beef".into(), opt_out };
+            zone().nsec3_chain(&params).unwrap().into_iter()
+                .filter_map(|rr| match rr { RecourseRecord::NSEC3(n) => Some(n), _ => None })
+                .collect()
+        };
+        let all = nsec3s(false);
+        assert_eq!(nsec3_denial("nope.example.", types::TYPE_A, "example.", &all), Some(Denial::NxDomain));
+        assert_eq!(nsec3_denial("a.example.", types::TYPE_AAAA, "example.", &all), Some(Denial::NoData));
+        assert_eq!(nsec3_denial("y.w.example.", types::TYPE_A, "example.", &all), Some(Denial::NoData));
+        assert_eq!(nsec3_denial("z.w.example.", types::TYPE_AAAA, "example.", &all), Some(Denial::NoData));
+        assert_eq!(nsec3_denial("z.w.example.", types::TYPE_A, "example.", &all), None);
+        assert_eq!(nsec3_denial("sub.example.", types::TYPE_DS, "example.", &all), Some(Denial::NoData));
+        assert!(nsec3_wildcard_proof("z.w.example.", "w.example.", &all));
+        assert!(!nsec3_wildcard_proof("x.y.w.example.", "y.w.example.", &all));
+
+        let opt_out = nsec3s(true);
+        assert_eq!(nsec3_denial("sub.example.", types::TYPE_DS, "example.", &opt_out), Some(Denial::OptOut));
+        assert_eq!(nsec3_denial("secure.example.", types::TYPE_DS, "example.", &opt_out), None);
+    }
+}
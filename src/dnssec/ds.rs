@@ -0,0 +1,81 @@
+use bytes::{BufMut, BytesMut};
+use sha2::{Digest, Sha256, Sha384};
+use crate::types::{DNSKEY, DS};
+use crate::{util, Error, Result};
+
+/// DS digest types (RFC 4034 appendix A.2, RFC 4509, RFC 6605).
+pub const DIGEST_SHA1: u8 = 1;
+pub const DIGEST_SHA256: u8 = 2;
+pub const DIGEST_SHA384: u8 = 4;
+
+/// The DS record a parent publishes for `dnskey` (RFC 4034 section 5.1.4),
+/// with the owner, class and TTL of the key.
+pub fn ds_for(dnskey: &DNSKEY, digest_type: u8) -> Result<DS> {
+    let mut data = BytesMut::new();
+    util::pack_domain_name(&dnskey.hdr.name.to_ascii_lowercase(), &mut data)?;
+    data.put_u16(dnskey.flags);
+    data.put_u8(dnskey.protocol);
+    data.put_u8(dnskey.algorithm);
+    data.put_slice(&dnskey.public_key);
+    let digest = match digest_type {
+        DIGEST_SHA1 => sha1::Sha1::digest(&data).to_vec(),
+        DIGEST_SHA256 => Sha256::digest(&data).to_vec(),
+        DIGEST_SHA384 => Sha384::digest(&data).to_vec(),
+        _ => return Err(Error::new(format!("unsupported DS digest type {}", digest_type))),
+    };
+    Ok(DS::new(
+        dnskey.hdr.name.clone(),
+        dnskey.hdr.class,
+        dnskey.hdr.ttl,
+        dnskey.key_tag(),
+        dnskey.algorithm,
+        digest_type,
+        digest,
+    ))
+}
+
+/// Whether `ds` refers to `dnskey`: same key tag and algorithm, and a
+/// digest that matches.
+pub fn ds_matches(ds: &DS, dnskey: &DNSKEY) -> bool {
+    ds.key_tag == dnskey.key_tag()
+        && ds.algorithm == dnskey.algorithm
+        && util::equal_names(&ds.hdr.name, &dnskey.hdr.name)
+        && ds_for(dnskey, ds.digest_type).map(|own| own.digest == ds.digest).unwrap_or(false)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::types::RecourseRecord;
+    use crate::zone::{parse, Writer};
+    use super::{ds_for, ds_matches};
+
+    #[test]
+    fn test_ds() {
+        // RFC 8080 section 6.1
+        let records = parse(
+            "example.com. 3600 IN DNSKEY 257 3 15 l02Woi0iS8Aa25FQkUd9RMzZHJpBoRQwAQEX1SxZJA4=\n\
+             example.com. 3600 IN DS 3613 15 2 (\n\
+                 3aa5ab37efce57f737fc1627013fee07bdf241bd10f3b1964ab55c78e79a304b )\n",
+            ".",
+        ).unwrap();
+        let (RecourseRecord::DNSKEY(key), RecourseRecord::DS(ds)) = (&records[0], &records[1]) else {
+            panic!("unexpected {:?}", records);
+        };
+        assert!(ds_matches(ds, key));
+        let own = ds_for(key, 2).unwrap();
+        assert_eq!(own.to_string(), ds.to_string());
+        assert!(ds.to_string().ends_with("3613 15 2 3AA5AB37EFCE57F737FC1627013FEE07BDF241BD10F3B1964AB55C78E79A304B"));
+        assert_eq!(ds_for(key, 1).unwrap().digest.len(), 20);
+        assert_eq!(ds_for(key, 4).unwrap().digest.len(), 48);
+        assert!(ds_for(key, 3).is_err());
+
+        let mut other = ds.clone();
+        other.digest[0] ^= 1;
+        assert!(!ds_matches(&other, key));
+
+        let out = Writer::new("example.com.").to_string(&records[1..]);
+        assert!(out.ends_with("@\t\tIN\tDS\t3613 15 2 3AA5AB37EFCE57F737FC1627013FEE07BDF241BD10F3B1964AB55C78E79A304B\n"), "{}", out);
+        let reparsed = parse(&out, ".").unwrap();
+        assert_eq!(reparsed[0].to_string(), records[1].to_string());
+    }
+}
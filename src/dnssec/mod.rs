@@ -0,0 +1,29 @@
+//! DNSSEC (RFC 4033-4035) support.
+
+mod algorithm;
+#[cfg(feature = "dnssec")]
+mod crypto;
+mod denial;
+#[cfg(feature = "dnssec")]
+mod ds;
+mod nsec3;
+mod policy;
+mod sign;
+mod validator;
+
+pub use algorithm::{Algorithm, Registry};
+#[cfg(feature = "dnssec")]
+pub use crypto::{Ecdsa, Ed25519, RsaSha};
+pub use denial::{nsec3_denial, nsec3_wildcard_proof, nsec_denial, nsec_wildcard_proof, Denial};
+#[cfg(feature = "dnssec")]
+pub use ds::{ds_for, ds_matches, DIGEST_SHA1, DIGEST_SHA256, DIGEST_SHA384};
+pub use nsec3::{nsec3_digest, nsec3_hash, nsec3_owner};
+pub use policy::Policy;
+pub use sign::{sign_rrset, signed_data, verify_rrset, KeyPair};
+pub use validator::Validator;
+
+pub const ALG_RSASHA256: u8 = 8;
+pub const ALG_RSASHA512: u8 = 10;
+pub const ALG_ECDSAP256SHA256: u8 = 13;
+pub const ALG_ECDSAP384SHA384: u8 = 14;
+pub const ALG_ED25519: u8 = 15;
@@ -0,0 +1,47 @@
+use bytes::BytesMut;
+use sha1::{Digest, Sha1};
+use crate::{full_domain, util, DomainString, Result};
+
+/// Iterated SHA-1 digest of `name` (RFC 5155 section 5).
+pub fn nsec3_digest(name: &str, salt: &[u8], iterations: u16) -> Result<Vec<u8>> {
+    let mut wire = BytesMut::new();
+    util::pack_domain_name(&name.to_ascii_lowercase(), &mut wire)?;
+    let mut digest = Sha1::new().chain_update(&wire).chain_update(salt).finalize();
+    for _ in 0..iterations {
+        digest = Sha1::new().chain_update(digest).chain_update(salt).finalize();
+    }
+    Ok(digest.to_vec())
+}
+
+/// NSEC3 hash of `name` as the base32hex label used in NSEC3 owner names.
+pub fn nsec3_hash(name: &str, salt: &[u8], iterations: u16) -> Result<String> {
+    Ok(util::base32hex_encode(&nsec3_digest(name, salt, iterations)?))
+}
+
+/// Owner name of the NSEC3 record for `name` in `zone`.
+pub fn nsec3_owner(name: &str, zone: &str, salt: &[u8], iterations: u16) -> Result<DomainString> {
+    let mut owner = DomainString::from(nsec3_hash(name, salt, iterations)?);
+    owner.push('.');
+    let zone = full_domain(zone);
+    if zone.as_str() != "." {
+        owner.push_str(&zone);
+    }
+    Ok(owner)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{nsec3_hash, nsec3_owner};
+
+    #[test]
+    fn test_nsec3_hash() {
+        // RFC 5155 appendix A
+        let salt = [0xaa, 0xbb, 0xcc, 0xdd];
+        assert_eq!(nsec3_hash("example", &salt, 12).unwrap(), "0p9mhaveqvm6t7vbl5lop2u3t2rp3tom");
+        assert_eq!(nsec3_hash("A.EXAMPLE.", &salt, 12).unwrap(), "35mthgpgcu1qg68fab165klnsnk3dpvl");
+        assert_eq!(
+            nsec3_owner("ns1.example.", "example", &salt, 12).unwrap().as_str(),
+            "2t7b4g4vsa5smi47k61mv5bv1a22bojr.example."
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use std::collections::BTreeSet;
+use std::str::FromStr;
+use crate::types::{DNSKEY, NSEC3};
+use crate::{Error, Result};
+use super::{ALG_ECDSAP256SHA256, ALG_ECDSAP384SHA384, ALG_ED25519, ALG_RSASHA256, ALG_RSASHA512};
+
+/// What the validator accepts. Anything outside the policy makes data
+/// insecure rather than bogus.
+///
+/// Read from text with `parse`, one setting per line, `#` starting comments:
+///
+/// ```text
+/// algorithms RSASHA256 13 ED25519
+/// min-rsa-bits 2048
+/// max-nsec3-iterations 0
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Policy {
+    /// DNSKEY/RRSIG algorithm numbers.
+    pub algorithms: BTreeSet<u8>,
+    /// Smallest accepted RSA modulus.
+    pub min_rsa_bits: usize,
+    /// NSEC3 records with more iterations are not used as proof (RFC 9276
+    /// section 3.2).
+    pub max_nsec3_iterations: u16,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self {
+            algorithms: BTreeSet::from([ALG_RSASHA256, ALG_RSASHA512, ALG_ECDSAP256SHA256, ALG_ECDSAP384SHA384, ALG_ED25519]),
+            min_rsa_bits: 1024,
+            max_nsec3_iterations: 100,
+        }
+    }
+}
+
+impl Policy {
+    pub fn accepts_algorithm(&self, number: u8) -> bool {
+        self.algorithms.contains(&number)
+    }
+
+    /// Whether signatures by `dnskey` may be trusted.
+    pub fn accepts_key(&self, dnskey: &DNSKEY) -> bool {
+        if !self.accepts_algorithm(dnskey.algorithm) {
+            return false;
+        }
+        match dnskey.algorithm {
+            ALG_RSASHA256 | ALG_RSASHA512 => rsa_bits(&dnskey.public_key) >= self.min_rsa_bits,
+            _ => true,
+        }
+    }
+
+    pub fn accepts_nsec3(&self, nsec3: &NSEC3) -> bool {
+        nsec3.iterations <= self.max_nsec3_iterations
+    }
+}
+
+impl FromStr for Policy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut policy = Policy::default();
+        for (i, line) in s.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default();
+            let mut fields = line.split_whitespace();
+            let Some(key) = fields.next() else { continue; };
+            let values: Vec<&str> = fields.collect();
+            let err = |msg: String| Error::Syntax(i + 1, msg);
+            let single = || match values[..] {
+                [value] => Ok(value),
+                _ => Err(err(format!("{} takes one value", key))),
+            };
+            match key {
+                "algorithms" => {
+                    policy.algorithms = values.iter()
+                        .map(|v| algorithm_number(v).ok_or_else(|| err(format!("unknown algorithm {:?}", v))))
+                        .collect::<Result<_>>()?;
+                }
+                "min-rsa-bits" => {
+                    let value = single()?;
+                    policy.min_rsa_bits = value.parse().map_err(|_| err(format!("bad key size {:?}", value)))?;
+                }
+                "max-nsec3-iterations" => {
+                    let value = single()?;
+                    policy.max_nsec3_iterations = value.parse().map_err(|_| err(format!("bad iteration count {:?}", value)))?;
+                }
+                _ => return Err(err(format!("unknown setting {:?}", key))),
+            }
+        }
+        Ok(policy)
+    }
+}
+
+fn algorithm_number(s: &str) -> Option<u8> {
+    Some(match s.to_ascii_uppercase().as_str() {
+        "RSASHA256" => ALG_RSASHA256,
+        "RSASHA512" => ALG_RSASHA512,
+        "ECDSAP256SHA256" => ALG_ECDSAP256SHA256,
+        "ECDSAP384SHA384" => ALG_ECDSAP384SHA384,
+        "ED25519" => ALG_ED25519,
+        _ => return s.parse().ok(),
+    })
+}
+
+/// Modulus size of an RFC 3110 public key, 0 if malformed.
+fn rsa_bits(key: &[u8]) -> usize {
+    let (e_len, rest) = match key {
+        [0, hi, lo, rest @ ..] => ((*hi as usize) << 8 | *lo as usize, rest),
+        [len, rest @ ..] => (*len as usize, rest),
+        [] => return 0,
+    };
+    let modulus = rest.get(e_len..).unwrap_or_default();
+    match modulus.iter().position(|b| *b != 0) {
+        Some(i) => (modulus.len() - i) * 8 - modulus[i].leading_zeros() as usize,
+        None => 0,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::dnssec::{ALG_ECDSAP256SHA256, ALG_ED25519, ALG_RSASHA256};
+    use crate::types::{self, DNSKEY};
+    use super::{rsa_bits, Policy};
+
+    #[test]
+    fn test_policy() {
+        let policy: Policy = "# strict\n\
+            algorithms ECDSAP256SHA256 8\n\
+            min-rsa-bits 2048 \n\
+            max-nsec3-iterations 0\n".parse().unwrap();
+        assert!(policy.accepts_algorithm(ALG_RSASHA256));
+        assert!(!policy.accepts_algorithm(ALG_ED25519));
+        assert_eq!(policy.max_nsec3_iterations, 0);
+
+        let rsa = |bits: usize| {
+            let mut key = vec![3, 1, 0, 1, 0x40];
+            key.resize(4 + bits / 8, 0xff);
+            DNSKEY::new("example.".into(), types::CLASS_INET, 60, 257, ALG_RSASHA256, key)
+        };
+        assert_eq!(rsa_bits(&rsa(1024).public_key), 1023);
+        assert!(!policy.accepts_key(&rsa(1024)));
+        assert!(policy.accepts_key(&rsa(2056)));
+        assert!(policy.accepts_key(&DNSKEY::new("example.".into(), types::CLASS_INET, 60, 257, ALG_ECDSAP256SHA256, vec![0; 64])));
+
+        assert!("algorithms DSA".parse::<Policy>().is_err());
+        assert!("min-rsa-bits 1 2".parse::<Policy>().is_err());
+        assert!("max-nsec3-iteration 1".parse::<Policy>().is_err());
+    }
+}
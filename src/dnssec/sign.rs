@@ -0,0 +1,294 @@
+use std::fmt;
+use base64::Engine;
+use bytes::{BufMut, BytesMut};
+use crate::msg::{RecourseRecordHdr, RR};
+use crate::types::{self, RecourseRecord, DNSKEY, RRSIG};
+use crate::{full_domain, util, DomainString, Error, Result};
+use super::{Registry, ALG_RSASHA256, ALG_RSASHA512};
+
+const BIND_FORMAT: &str = "v1.3";
+
+/// A DNSSEC private key together with the owner and flags of its DNSKEY.
+#[derive(Clone)]
+pub struct KeyPair {
+    pub algorithm: u8,
+    /// DNSKEY owner, which is the signer name of RRSIGs made with the key.
+    pub owner: DomainString,
+    pub flags: u16,
+    private_key: Vec<u8>,
+    public_key: Vec<u8>,
+}
+
+impl fmt::Debug for KeyPair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyPair")
+            .field("algorithm", &self.algorithm)
+            .field("owner", &self.owner)
+            .field("flags", &self.flags)
+            .finish_non_exhaustive()
+    }
+}
+
+impl KeyPair {
+    /// Generates a zone signing key for one of the built-in algorithms,
+    /// owned by the root; set the owner with `with_owner`. RSA keys are
+    /// 2048 bits.
+    pub fn generate(algorithm: u8) -> Result<Self> {
+        #[cfg(feature = "dnssec")]
+        return Self::from_private(algorithm, super::crypto::generate(algorithm)?, &Registry::default());
+        #[cfg(not(feature = "dnssec"))]
+        Err(Error::UnsupportedAlgorithm(algorithm))
+    }
+
+    /// Wraps a private key in the form the algorithm's `sign` takes, the
+    /// algorithm looked up in `registry`.
+    pub fn from_private(algorithm: u8, private_key: Vec<u8>, registry: &Registry) -> Result<Self> {
+        let public_key = registry
+            .get(algorithm)
+            .ok_or(Error::UnsupportedAlgorithm(algorithm))?
+            .public_key(&private_key)?;
+        Ok(Self {
+            algorithm,
+            owner: ".".into(),
+            flags: types::dnskey::DNSKEY_ZONE,
+            private_key,
+            public_key,
+        })
+    }
+
+    pub fn with_owner(mut self, owner: &str) -> Self {
+        self.owner = full_domain(owner);
+        self
+    }
+
+    pub fn with_flags(mut self, flags: u16) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    pub fn private_key(&self) -> &[u8] {
+        &self.private_key
+    }
+
+    pub fn public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+
+    pub fn dnskey(&self, class: u16, ttl: u32) -> DNSKEY {
+        DNSKEY::new(self.owner.clone(), class, ttl, self.flags, self.algorithm, self.public_key.clone())
+    }
+
+    pub fn key_tag(&self) -> u16 {
+        self.dnskey(types::CLASS_INET, 0).key_tag()
+    }
+
+    /// Reads a BIND `.private` key file. Owner and flags are not part of it.
+    pub fn from_bind(text: &str, registry: &Registry) -> Result<Self> {
+        let mut fields = Vec::new();
+        for line in text.lines() {
+            let Some((key, value)) = line.split_once(':') else { continue; };
+            fields.push((key.trim(), value.trim()));
+        }
+        let field = |name: &str| {
+            fields.iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| *value)
+                .ok_or_else(|| Error::new(format!("private key has no {} field", name)))
+        };
+        let decode = |name: &str| -> Result<Vec<u8>> {
+            base64::engine::general_purpose::STANDARD
+                .decode(field(name)?)
+                .map_err(|_| Error::new(format!("bad private key {} field", name)))
+        };
+
+        let algorithm = field("Algorithm")?;
+        let algorithm: u8 = algorithm
+            .split_whitespace()
+            .next()
+            .and_then(|n| n.parse().ok())
+            .ok_or_else(|| Error::new(format!("bad private key algorithm {:?}", algorithm)))?;
+        let private_key = if matches!(algorithm, ALG_RSASHA256 | ALG_RSASHA512) {
+            #[cfg(feature = "dnssec")]
+            {
+                let values = super::crypto::RSA_FIELDS.iter().map(|name| decode(name)).collect::<Result<Vec<_>>>()?;
+                super::crypto::rsa_from_fields(&values)?
+            }
+            #[cfg(not(feature = "dnssec"))]
+            return Err(Error::UnsupportedAlgorithm(algorithm));
+        } else {
+            decode("PrivateKey")?
+        };
+        Self::from_private(algorithm, private_key, registry)
+    }
+
+    /// Writes the key in the BIND `.private` key file format.
+    pub fn to_bind(&self, registry: &Registry) -> Result<String> {
+        let alg = registry.get(self.algorithm).ok_or(Error::UnsupportedAlgorithm(self.algorithm))?;
+        let mut s = format!("Private-key-format: {}\nAlgorithm: {} ({})\n", BIND_FORMAT, self.algorithm, alg.name());
+        let mut field = |name: &str, value: &[u8]| {
+            s.push_str(name);
+            s.push_str(": ");
+            s.push_str(&base64::engine::general_purpose::STANDARD.encode(value));
+            s.push('\n');
+        };
+        if matches!(self.algorithm, ALG_RSASHA256 | ALG_RSASHA512) {
+            #[cfg(feature = "dnssec")]
+            for (name, value) in super::crypto::RSA_FIELDS.iter().zip(super::crypto::rsa_to_fields(&self.private_key)?) {
+                field(name, &value);
+            }
+            #[cfg(not(feature = "dnssec"))]
+            return Err(Error::UnsupportedAlgorithm(self.algorithm));
+        } else {
+            field("PrivateKey", &self.private_key);
+        }
+        Ok(s)
+    }
+}
+
+/// Signs `rrset` with `key`; `inception` and `expiration` are seconds since
+/// the epoch. The records must share owner, class and type.
+pub fn sign_rrset(rrset: &[RecourseRecord], key: &KeyPair, registry: &Registry, inception: u32, expiration: u32) -> Result<RRSIG> {
+    let hdr = rrset_header(rrset)?;
+    let mut rrsig = RRSIG::new(
+        hdr.name.clone(),
+        hdr.class,
+        hdr.ttl,
+        hdr.typ,
+        key.algorithm,
+        label_count(&hdr.name),
+        hdr.ttl,
+        expiration,
+        inception,
+        key.key_tag(),
+        key.owner.clone(),
+        Vec::new(),
+    );
+    let data = signed_data(&rrsig, rrset)?;
+    rrsig.signature = registry.sign(key.algorithm, &key.private_key, &data)?;
+    rrsig.hdr.rd_length += rrsig.signature.len() as u16;
+    Ok(rrsig)
+}
+
+/// Checks `rrsig` over `rrset` against `dnskey`, including its validity
+/// period at `now` (seconds since the epoch).
+pub fn verify_rrset(rrset: &[RecourseRecord], rrsig: &RRSIG, dnskey: &DNSKEY, registry: &Registry, now: u32) -> Result<()> {
+    let hdr = rrset_header(rrset)?;
+    if hdr.typ != rrsig.type_covered || !util::equal_names(&hdr.name, &rrsig.hdr.name) {
+        return Err(Error::new("RRSIG does not cover the RRset"));
+    }
+    if dnskey.algorithm != rrsig.algorithm
+        || dnskey.key_tag() != rrsig.key_tag
+        || dnskey.flags & types::dnskey::DNSKEY_ZONE == 0
+        || !util::equal_names(&dnskey.hdr.name, &rrsig.signer_name)
+    {
+        return Err(Error::new("RRSIG was not made by the DNSKEY"));
+    }
+    // RFC 4034 section 3.1.5, in serial number arithmetic
+    if (now.wrapping_sub(rrsig.inception) as i32) < 0 || (rrsig.expiration.wrapping_sub(now) as i32) < 0 {
+        return Err(Error::new("RRSIG is outside its validity period"));
+    }
+    registry.verify(rrsig.algorithm, &dnskey.public_key, &signed_data(rrsig, rrset)?, &rrsig.signature)
+}
+
+/// The data an RRSIG signs (RFC 4034 section 3.1.8.1): its own rdata
+/// without the signature, then the RRset in canonical form and order.
+pub fn signed_data(rrsig: &RRSIG, rrset: &[RecourseRecord]) -> Result<Vec<u8>> {
+    let hdr = rrset_header(rrset)?;
+    let mut labels = util::name_labels(&hdr.name.to_ascii_lowercase());
+    if labels.len() > rrsig.labels as usize {
+        // wildcard expansion, RFC 4035 section 5.3.2
+        labels.drain(..labels.len() - rrsig.labels as usize);
+        labels.insert(0, b"*".to_vec());
+    }
+    let mut owner = BytesMut::new();
+    for label in &labels {
+        owner.put_u8(label.len() as u8);
+        owner.put_slice(label);
+    }
+    owner.put_u8(0);
+
+    let mut rdatas = rrset.iter().map(RecourseRecord::canonical_rdata).collect::<Result<Vec<_>>>()?;
+    rdatas.sort();
+    rdatas.dedup();
+
+    let mut buf = BytesMut::new();
+    rrsig.pack_unsigned(&mut buf)?;
+    for rdata in rdatas {
+        buf.put_slice(&owner);
+        buf.put_u16(hdr.typ);
+        buf.put_u16(hdr.class);
+        buf.put_u32(rrsig.orig_ttl);
+        buf.put_u16(rdata.len() as u16);
+        buf.put_slice(&rdata);
+    }
+    Ok(buf.to_vec())
+}
+
+fn rrset_header(rrset: &[RecourseRecord]) -> Result<&RecourseRecordHdr> {
+    let hdr = rrset.first().ok_or_else(|| Error::new("empty RRset"))?.header();
+    let same = |h: &RecourseRecordHdr| h.typ == hdr.typ && h.class == hdr.class && util::equal_names(&h.name, &hdr.name);
+    if !rrset.iter().all(|rr| same(rr.header())) {
+        return Err(Error::new("records are not one RRset"));
+    }
+    Ok(hdr)
+}
+
+/// RRSIG labels field: owner labels not counting the root or a leading `*`.
+fn label_count(name: &str) -> u8 {
+    let labels = util::name_labels(name);
+    let wildcard = labels.first().map(|l| l == b"*").unwrap_or(false);
+    (labels.len() - wildcard as usize) as u8
+}
+
+#[cfg(all(test, feature = "dnssec"))]
+mod test {
+    use base64::Engine;
+    use rsa::pkcs1::EncodeRsaPrivateKey;
+    use crate::dnssec::{Registry, ALG_ECDSAP256SHA256, ALG_ED25519, ALG_RSASHA256};
+    use crate::types::{self, RecourseRecord};
+    use crate::zone::parse;
+    use super::{sign_rrset, verify_rrset, KeyPair};
+
+    #[test]
+    fn test_sign_rrset() {
+        let registry = Registry::default();
+        // RFC 8080 section 6.1
+        let key = KeyPair::from_bind(
+            "Private-key-format: v1.2\n\
+             Algorithm: 15 (ED25519)\n\
+             PrivateKey: ODIyNjAzODQ2MjgwODAxMjI2NDUxOTAyMDQxNDIyNjI=\n",
+            &registry,
+        ).unwrap().with_owner("example.com").with_flags(257);
+        assert_eq!(key.key_tag(), 3613);
+        assert!(KeyPair::from_private(key.algorithm, key.private_key().to_vec(), &Registry::empty()).is_err());
+        let mx = parse("example.com. 3600 IN MX \\# 20 000a046d61696c076578616d706c6503636f6d00\n", ".").unwrap();
+        let rrsig = sign_rrset(&mx, &key, &registry, 1438207200, 1440021600).unwrap();
+        assert_eq!(
+            base64::engine::general_purpose::STANDARD.encode(&rrsig.signature),
+            "oL9krJun7xfBOIWcGHi7mag5/hdZrKWw15jPGrHpjQeRAvTdszaPD+QLs3fx8A4M3e23mRZ9VrbpMngwcrqNAg=="
+        );
+        assert_eq!(rrsig.labels, 2);
+
+        let dnskey = key.dnskey(types::CLASS_INET, 3600);
+        assert!(verify_rrset(&mx, &rrsig, &dnskey, &registry, 1439000000).is_ok());
+        assert!(verify_rrset(&mx, &rrsig, &dnskey, &registry, 1440021601).is_err());
+
+        let key = KeyPair::generate(ALG_ECDSAP256SHA256).unwrap().with_owner("Example.");
+        let a = |ip: [u8; 4]| RecourseRecord::new_ip("WWW.example.".into(), types::CLASS_INET, 60, ip.into());
+        let rrset = [a([192, 0, 2, 2]), a([192, 0, 2, 1])];
+        let rrsig = sign_rrset(&rrset, &key, &registry, 0, u32::MAX / 2).unwrap();
+        let dnskey = key.dnskey(types::CLASS_INET, 60);
+        let reordered = [a([192, 0, 2, 1]), a([192, 0, 2, 2]), a([192, 0, 2, 1])];
+        assert!(verify_rrset(&reordered, &rrsig, &dnskey, &registry, 1).is_ok());
+        assert!(verify_rrset(&rrset[..1], &rrsig, &dnskey, &registry, 1).is_err());
+
+        let rsa = rsa::RsaPrivateKey::new(&mut rand::thread_rng(), 1024).unwrap();
+        let rsa = KeyPair::from_private(ALG_RSASHA256, rsa.to_pkcs1_der().unwrap().as_bytes().to_vec(), &registry).unwrap();
+        for key in [rsa, KeyPair::generate(ALG_ED25519).unwrap()] {
+            let text = key.to_bind(&registry).unwrap();
+            let back = KeyPair::from_bind(&text, &registry).unwrap();
+            assert_eq!(back.public_key(), key.public_key());
+            assert_eq!(back.to_bind(&registry).unwrap(), text);
+        }
+    }
+}
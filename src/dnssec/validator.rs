@@ -0,0 +1,35 @@
+use crate::types::{RecourseRecord, DNSKEY, NSEC3, RRSIG};
+use crate::{Error, Result};
+use super::{nsec3_denial, verify_rrset, Denial, Policy, Registry};
+
+/// Checks signatures and denial proofs with the algorithms of a `Registry`,
+/// within the limits of a `Policy`.
+#[derive(Debug, Clone, Default)]
+pub struct Validator {
+    pub registry: Registry,
+    pub policy: Policy,
+}
+
+impl Validator {
+    pub fn new(registry: Registry, policy: Policy) -> Self {
+        Self { registry, policy }
+    }
+
+    /// Like `verify_rrset`, but keys and algorithms the policy rejects fail
+    /// with `Error::UnsupportedAlgorithm`, which makes the RRset insecure.
+    pub fn verify_rrset(&self, rrset: &[RecourseRecord], rrsig: &RRSIG, dnskey: &DNSKEY, now: u32) -> Result<()> {
+        if !self.policy.accepts_algorithm(rrsig.algorithm) || !self.policy.accepts_key(dnskey) {
+            return Err(Error::UnsupportedAlgorithm(rrsig.algorithm));
+        }
+        verify_rrset(rrset, rrsig, dnskey, &self.registry, now)
+    }
+
+    /// Like `nsec3_denial`, but NSEC3 records over the iteration limit make
+    /// the answer insecure (RFC 9276 section 3.2).
+    pub fn nsec3_denial(&self, qname: &str, qtype: u16, zone: &str, nsec3s: &[NSEC3]) -> Option<Denial> {
+        if !nsec3s.iter().all(|n| self.policy.accepts_nsec3(n)) {
+            return Some(Denial::Insecure);
+        }
+        nsec3_denial(qname, qtype, zone, nsec3s)
+    }
+}
@@ -0,0 +1,266 @@
+//! C ABI over the codec, for embedding the parser in C/C++ programs.
+//!
+//! Messages are opaque `DnsMsg` handles owned by the caller and released
+//! with `dns_msg_free`. Functions filling a caller buffer return the number
+//! of bytes the full value needs: nothing is written when that is larger
+//! than `len`, so callers can size the buffer and retry. Failures return -1.
+//! Types and constants are plain C so cbindgen can generate the header.
+//!
+//! Build a C library with
+//! `cargo rustc --release --features ffi --crate-type staticlib` (or `cdylib`).
+
+use std::ffi::{c_char, CStr};
+use std::ptr;
+use bytes::BytesMut;
+use crate::msg::{Msg, RR};
+use crate::types::RecourseRecord;
+
+pub const DNS_SECTION_QUESTION: u32 = 0;
+pub const DNS_SECTION_ANSWER: u32 = 1;
+pub const DNS_SECTION_AUTHORITY: u32 = 2;
+pub const DNS_SECTION_ADDITIONAL: u32 = 3;
+
+/// Opaque message handle.
+pub struct DnsMsg(Msg);
+
+/// Header fields of a message.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct DnsHeader {
+    pub id: u16,
+    /// Flag bits of the second header word, without opcode and RCODE.
+    pub flags: u16,
+    pub opcode: u16,
+    /// Extended RCODE when the message has an OPT record.
+    pub rcode: u16,
+}
+
+/// Fixed fields of a question or record; TTL is 0 for questions.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct DnsRecordInfo {
+    pub rtype: u16,
+    pub class: u16,
+    pub ttl: u32,
+}
+
+/// An empty message with a random ID.
+#[no_mangle]
+pub extern "C" fn dns_msg_new() -> *mut DnsMsg {
+    Box::into_raw(Box::new(DnsMsg(Msg::new())))
+}
+
+/// Parses `len` bytes of wire data; NULL if malformed.
+///
+/// # Safety
+/// `data` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn dns_msg_unpack(data: *const u8, len: usize) -> *mut DnsMsg {
+    if data.is_null() {
+        return ptr::null_mut();
+    }
+    match Msg::unpack(std::slice::from_raw_parts(data, len)) {
+        Ok(msg) => Box::into_raw(Box::new(DnsMsg(msg))),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// # Safety
+/// `msg` must come from this library and not be used afterwards. NULL is ignored.
+#[no_mangle]
+pub unsafe extern "C" fn dns_msg_free(msg: *mut DnsMsg) {
+    if !msg.is_null() {
+        drop(Box::from_raw(msg));
+    }
+}
+
+/// Packs `msg` into `buf`.
+///
+/// # Safety
+/// `msg` must be a live handle and `buf` must point to `len` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn dns_msg_pack(msg: *const DnsMsg, buf: *mut u8, len: usize) -> isize {
+    let Some(msg) = msg.as_ref() else { return -1; };
+    match msg.0.to_buf() {
+        Ok(data) => copy_out(&data, buf, len),
+        Err(_) => -1,
+    }
+}
+
+/// # Safety
+/// `msg` must be a live handle and `hdr` must point to a `DnsHeader`.
+#[no_mangle]
+pub unsafe extern "C" fn dns_msg_header(msg: *const DnsMsg, hdr: *mut DnsHeader) -> i32 {
+    let (Some(msg), Some(hdr)) = (msg.as_ref(), hdr.as_mut()) else { return -1; };
+    let h = msg.0.hdr;
+    let bits = crate::msg::PktMsgHeader::from(h).bits;
+    *hdr = DnsHeader {
+        id: h.id,
+        flags: bits & 0x87f0,
+        opcode: h.op_code,
+        rcode: h.response_code,
+    };
+    0
+}
+
+/// Sets the message ID.
+///
+/// # Safety
+/// `msg` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn dns_msg_set_id(msg: *mut DnsMsg, id: u16) -> i32 {
+    let Some(msg) = msg.as_mut() else { return -1; };
+    msg.0.hdr.id = id;
+    0
+}
+
+/// Replaces the question with `name` (NUL-terminated) of type `qtype`,
+/// class IN, sets RD and picks a new ID.
+///
+/// # Safety
+/// `msg` must be a live handle and `name` a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn dns_msg_set_question(msg: *mut DnsMsg, name: *const c_char, qtype: u16) -> i32 {
+    let Some(msg) = msg.as_mut() else { return -1; };
+    if name.is_null() {
+        return -1;
+    }
+    let Ok(name) = CStr::from_ptr(name).to_str() else { return -1; };
+    msg.0.set_question(crate::full_domain(name), qtype);
+    0
+}
+
+/// Number of entries in `section`, 0 for an unknown section.
+///
+/// # Safety
+/// `msg` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn dns_msg_count(msg: *const DnsMsg, section: u32) -> usize {
+    let Some(msg) = msg.as_ref() else { return 0; };
+    match section {
+        DNS_SECTION_QUESTION => msg.0.question.len(),
+        _ => records(&msg.0, section).map(|v| v.len()).unwrap_or(0),
+    }
+}
+
+/// Owner name of entry `index` of `section`, in presentation format without
+/// a terminating NUL.
+///
+/// # Safety
+/// `msg` must be a live handle and `buf` must point to `len` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn dns_msg_name(msg: *const DnsMsg, section: u32, index: usize, buf: *mut u8, len: usize) -> isize {
+    let Some(msg) = msg.as_ref() else { return -1; };
+    let name = match section {
+        DNS_SECTION_QUESTION => msg.0.question.get(index).map(|q| &q.name),
+        _ => record(&msg.0, section, index).map(|rr| &rr.header().name),
+    };
+    match name {
+        Some(name) => copy_out(name.as_bytes(), buf, len),
+        None => -1,
+    }
+}
+
+/// Type, class and TTL of entry `index` of `section`.
+///
+/// # Safety
+/// `msg` must be a live handle and `info` must point to a `DnsRecordInfo`.
+#[no_mangle]
+pub unsafe extern "C" fn dns_msg_info(msg: *const DnsMsg, section: u32, index: usize, info: *mut DnsRecordInfo) -> i32 {
+    let (Some(msg), Some(info)) = (msg.as_ref(), info.as_mut()) else { return -1; };
+    *info = match section {
+        DNS_SECTION_QUESTION => match msg.0.question.get(index) {
+            Some(q) => DnsRecordInfo { rtype: q.q_type, class: q.q_class, ttl: 0 },
+            None => return -1,
+        },
+        _ => match record(&msg.0, section, index) {
+            Some(rr) => DnsRecordInfo { rtype: rr.header().typ, class: rr.header().class, ttl: rr.header().ttl },
+            None => return -1,
+        },
+    };
+    0
+}
+
+/// Uncompressed wire-format rdata of record `index` of `section`.
+///
+/// # Safety
+/// `msg` must be a live handle and `buf` must point to `len` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn dns_msg_rdata(msg: *const DnsMsg, section: u32, index: usize, buf: *mut u8, len: usize) -> isize {
+    let Some(msg) = msg.as_ref() else { return -1; };
+    let Some(rr) = record(&msg.0, section, index) else { return -1; };
+    let mut data = BytesMut::new();
+    if rr.header().pack(&mut data).is_err() {
+        return -1;
+    }
+    let start = data.len();
+    if rr.pack(&mut data).is_err() {
+        return -1;
+    }
+    copy_out(&data[start..], buf, len)
+}
+
+fn records(msg: &Msg, section: u32) -> Option<&[RecourseRecord]> {
+    match section {
+        DNS_SECTION_ANSWER => Some(&msg.answer),
+        DNS_SECTION_AUTHORITY => Some(&msg.authority),
+        DNS_SECTION_ADDITIONAL => Some(&msg.additional),
+        _ => None,
+    }
+}
+
+fn record(msg: &Msg, section: u32, index: usize) -> Option<&RecourseRecord> {
+    records(msg, section)?.get(index)
+}
+
+unsafe fn copy_out(data: &[u8], buf: *mut u8, len: usize) -> isize {
+    if data.len() <= len && !buf.is_null() {
+        ptr::copy_nonoverlapping(data.as_ptr(), buf, data.len());
+    }
+    data.len() as isize
+}
+
+#[cfg(test)]
+mod test {
+    use std::ptr;
+    use crate::types;
+    use super::*;
+
+    #[test]
+    fn test_ffi() {
+        unsafe {
+            let msg = dns_msg_new();
+            assert_eq!(dns_msg_set_question(msg, c"example.com".as_ptr(), types::TYPE_AAAA), 0);
+            assert_eq!(dns_msg_set_id(msg, 0x1234), 0);
+            let need = dns_msg_pack(msg, ptr::null_mut(), 0);
+            let mut wire = vec![0u8; need as usize];
+            assert_eq!(dns_msg_pack(msg, wire.as_mut_ptr(), wire.len()), need);
+            dns_msg_free(msg);
+
+            let msg = dns_msg_unpack(wire.as_ptr(), wire.len());
+            assert!(!msg.is_null());
+            let mut hdr = DnsHeader { id: 0, flags: 0, opcode: 0, rcode: 0 };
+            assert_eq!(dns_msg_header(msg, &mut hdr), 0);
+            assert_eq!(hdr.id, 0x1234);
+            assert_eq!(hdr.flags, 0x0100);
+            assert_eq!(dns_msg_count(msg, DNS_SECTION_QUESTION), 1);
+            assert_eq!(dns_msg_count(msg, DNS_SECTION_ANSWER), 0);
+
+            let mut name = [0u8; 8];
+            assert_eq!(dns_msg_name(msg, DNS_SECTION_QUESTION, 0, name.as_mut_ptr(), name.len()), 12);
+            assert_eq!(name, [0; 8]);
+            let mut name = [0u8; 12];
+            assert_eq!(dns_msg_name(msg, DNS_SECTION_QUESTION, 0, name.as_mut_ptr(), name.len()), 12);
+            assert_eq!(&name, b"example.com.");
+
+            let mut info = DnsRecordInfo { rtype: 0, class: 0, ttl: 0 };
+            assert_eq!(dns_msg_info(msg, DNS_SECTION_QUESTION, 0, &mut info), 0);
+            assert_eq!((info.rtype, info.class), (types::TYPE_AAAA, types::CLASS_INET));
+            assert_eq!(dns_msg_info(msg, DNS_SECTION_ANSWER, 0, &mut info), -1);
+            assert_eq!(dns_msg_rdata(msg, DNS_SECTION_ANSWER, 0, ptr::null_mut(), 0), -1);
+            dns_msg_free(msg);
+
+            assert!(dns_msg_unpack(wire.as_ptr(), 5).is_null());
+        }
+    }
+}
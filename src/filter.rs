@@ -0,0 +1,335 @@
+//! Domain blocklists: names on a list are blocked along with everything
+//! below them, unless an allowlist entry covers them, and
+//! [`Filter::answer_for`] answers blocked queries locally.
+
+use std::cmp::Ordering;
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::Path;
+use crate::{types, Msg};
+
+/// Names hosts files map to loopback for their own sake, not to block them.
+const HOSTS_LOCAL_NAMES: [&str; 8] = [
+    "localhost",
+    "localhost.localdomain",
+    "local",
+    "broadcasthost",
+    "ip6-localhost",
+    "ip6-loopback",
+    "ip6-allnodes",
+    "ip6-allrouters",
+];
+
+/// How blocked names are answered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockMode {
+    NxDomain,
+    /// `0.0.0.0` for A and `::` for AAAA, NODATA for other types.
+    Null,
+    /// These addresses, NODATA for a family without one and other types.
+    Address(Option<Ipv4Addr>, Option<Ipv6Addr>),
+}
+
+/// Set of names, each covering itself and its subdomains.
+///
+/// Built for lists of millions: names are kept with their labels reversed
+/// (`com.example.ads`) in one sorted buffer, and a lookup is one binary
+/// search per label of the query.
+#[derive(Debug, Default, Clone)]
+pub struct SuffixSet {
+    data: String,
+    /// End offsets of the entries in `data`.
+    ends: Vec<u32>,
+    /// Entries added since the last build, not yet searchable.
+    pending: Vec<String>,
+}
+
+impl SuffixSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues `name`; call [`build`](Self::build) before looking up.
+    pub fn insert(&mut self, name: &str) {
+        if let Some(key) = reversed(name) {
+            self.pending.push(key);
+        }
+    }
+
+    /// Merges queued names into the searchable set.
+    pub fn build(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let mut all: Vec<String> = self.entries().map(String::from).collect();
+        all.append(&mut self.pending);
+        all.sort_unstable();
+        all.dedup();
+
+        self.data = String::with_capacity(all.iter().map(String::len).sum());
+        self.ends = Vec::with_capacity(all.len());
+        // an entry covers everything below it, so entries under a kept one
+        // are redundant. They need not follow it directly (`com.example-a`
+        // sorts between `com.example` and `com.example.ads`), so the kept
+        // entries that are still prefixes of the current key stay on a stack.
+        let mut open: Vec<usize> = Vec::new();
+        for key in all {
+            while let Some(&i) = open.last() {
+                if key.starts_with(self.entry(i)) {
+                    break;
+                }
+                open.pop();
+            }
+            if open.iter().any(|&i| key.as_bytes().get(self.entry(i).len()) == Some(&b'.')) {
+                continue;
+            }
+            open.push(self.ends.len());
+            self.data.push_str(&key);
+            self.ends.push(self.data.len() as u32);
+        }
+        self.data.shrink_to_fit();
+        self.pending.shrink_to_fit();
+    }
+
+    pub fn len(&self) -> usize {
+        self.ends.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ends.is_empty()
+    }
+
+    /// Whether `name` or one of its parents is in the set.
+    pub fn contains(&self, name: &str) -> bool {
+        let Some(key) = reversed(name) else { return false; };
+        let mut end = 0;
+        loop {
+            end = match key[end..].find('.') {
+                Some(i) => end + i,
+                None => key.len(),
+            };
+            if self.search(&key[..end]) {
+                return true;
+            }
+            if end == key.len() {
+                return false;
+            }
+            end += 1;
+        }
+    }
+
+    fn search(&self, key: &str) -> bool {
+        let (mut lo, mut hi) = (0, self.ends.len());
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            match self.entry(mid).cmp(key) {
+                Ordering::Less => lo = mid + 1,
+                Ordering::Greater => hi = mid,
+                Ordering::Equal => return true,
+            }
+        }
+        false
+    }
+
+    fn entry(&self, i: usize) -> &str {
+        let start = if i == 0 { 0 } else { self.ends[i - 1] as usize };
+        &self.data[start..self.ends[i] as usize]
+    }
+
+    fn entries(&self) -> impl Iterator<Item = &str> {
+        (0..self.ends.len()).map(|i| self.entry(i))
+    }
+}
+
+/// Blocklist plus allowlist.
+#[derive(Debug, Clone)]
+pub struct Filter {
+    blocked: SuffixSet,
+    allowed: SuffixSet,
+    mode: BlockMode,
+    /// TTL of synthesized answers.
+    ttl: u32,
+}
+
+impl Filter {
+    pub fn new(mode: BlockMode, ttl: u32) -> Self {
+        Self {
+            blocked: SuffixSet::new(),
+            allowed: SuffixSet::new(),
+            mode,
+            ttl,
+        }
+    }
+
+    /// Adds the names of a list in hosts format (`0.0.0.0 ads.example`) or
+    /// domain-list format (one name per line, `*.` prefixes allowed);
+    /// lines may mix both. `#` and `!` start comments.
+    pub fn block_list<R: BufRead>(&mut self, list: R) -> io::Result<()> {
+        read_list(list, &mut self.blocked)
+    }
+
+    pub fn block_list_path<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        self.block_list(BufReader::new(File::open(path)?))
+    }
+
+    /// Like [`block_list`](Self::block_list), for names never to block.
+    pub fn allow_list<R: BufRead>(&mut self, list: R) -> io::Result<()> {
+        read_list(list, &mut self.allowed)
+    }
+
+    /// Queues `name` for blocking; it takes effect with the next
+    /// [`build`](Self::build) or list.
+    pub fn block(&mut self, name: &str) {
+        self.blocked.insert(name);
+    }
+
+    /// Like [`block`](Self::block), for a name never to block.
+    pub fn allow(&mut self, name: &str) {
+        self.allowed.insert(name);
+    }
+
+    /// Makes the names queued by [`block`](Self::block) and
+    /// [`allow`](Self::allow) searchable, rebuilding each list once.
+    pub fn build(&mut self) {
+        self.blocked.build();
+        self.allowed.build();
+    }
+
+    /// Number of blocking entries, after folding subdomains of other entries.
+    pub fn len(&self) -> usize {
+        self.blocked.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocked.is_empty()
+    }
+
+    pub fn is_blocked(&self, name: &str) -> bool {
+        self.blocked.contains(name) && !self.allowed.contains(name)
+    }
+
+    /// The local answer to `request` when its name is blocked, `None` when
+    /// it should be resolved as usual.
+    pub fn answer_for(&self, request: &Msg) -> Option<Msg> {
+        if request.hdr.response || request.hdr.op_code != types::OPCODE_QUERY || request.question.len() != 1 {
+            return None;
+        }
+        let q = &request.question[0];
+        if !self.is_blocked(&q.name) {
+            return None;
+        }
+
+        let mut msg = Msg::new();
+        msg.set_reply(request);
+        msg.hdr.recursion_available = true;
+        let (v4, v6) = match self.mode {
+            BlockMode::NxDomain => {
+                msg.hdr.response_code = types::RCODE_NAME_ERROR;
+                return Some(msg);
+            }
+            BlockMode::Null => (Some(Ipv4Addr::UNSPECIFIED), Some(Ipv6Addr::UNSPECIFIED)),
+            BlockMode::Address(v4, v6) => (v4, v6),
+        };
+        let ip = match q.q_type {
+            types::TYPE_A => v4.map(IpAddr::V4),
+            types::TYPE_AAAA => v6.map(IpAddr::V6),
+            _ => None,
+        };
+        if let Some(ip) = ip {
+            msg.answer.push(types::RecourseRecord::new_ip(q.name.clone(), q.q_class, self.ttl, ip));
+        }
+        Some(msg)
+    }
+}
+
+fn read_list<R: BufRead>(list: R, set: &mut SuffixSet) -> io::Result<()> {
+    for line in list.lines() {
+        let line = line?;
+        let line = line.split(['#', '!']).next().unwrap_or_default();
+        let mut fields = line.split_whitespace();
+        let Some(first) = fields.next() else { continue; };
+        if first.parse::<IpAddr>().is_ok() {
+            for name in fields {
+                if !HOSTS_LOCAL_NAMES.iter().any(|n| n.eq_ignore_ascii_case(name)) {
+                    set.insert(name);
+                }
+            }
+        } else {
+            set.insert(first.strip_prefix("*.").unwrap_or(first));
+        }
+    }
+    set.build();
+    Ok(())
+}
+
+/// `ads.Example.com.` as `com.example.ads`; `None` for the root.
+fn reversed(name: &str) -> Option<String> {
+    let name = name.strip_suffix('.').unwrap_or(name);
+    if name.is_empty() {
+        return None;
+    }
+    let mut key = String::with_capacity(name.len());
+    for (i, label) in name.rsplit('.').enumerate() {
+        if i > 0 {
+            key.push('.');
+        }
+        key.extend(label.chars().map(|c| c.to_ascii_lowercase()));
+    }
+    Some(key)
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::Ipv4Addr;
+    use crate::msg::RR;
+    use crate::{full_domain, types, Msg};
+    use super::{BlockMode, Filter};
+
+    #[test]
+    fn test_filter() {
+        let mut filter = Filter::new(BlockMode::NxDomain, 60);
+        let list = "# hosts format\n\
+            127.0.0.1 localhost\n\
+            0.0.0.0 ads.example.com tracker.example.net # inline\n\
+            ! domain list\n\
+            doubleclick.example\n\
+            *.metrics.example\n\
+            x.ads.example.com\n\
+            ads-cdn.example.com\n\
+            y.ads.example.com\n";
+        filter.block_list(list.as_bytes()).unwrap();
+        filter.allow_list("ok.doubleclick.example\n".as_bytes()).unwrap();
+        assert_eq!(filter.len(), 5);
+
+        assert!(filter.is_blocked("ads.example.com"));
+        assert!(filter.is_blocked("A.Ads.Example.Com."));
+        assert!(filter.is_blocked("metrics.example"));
+        assert!(filter.is_blocked("cdn.doubleclick.example"));
+        assert!(!filter.is_blocked("ok.doubleclick.example"));
+        assert!(!filter.is_blocked("www.ok.doubleclick.example"));
+        assert!(!filter.is_blocked("localhost"));
+        assert!(!filter.is_blocked("example.com"));
+        assert!(!filter.is_blocked("bads.example.com"));
+        assert!(filter.is_blocked("ads-cdn.example.com"));
+        filter.block("example.org");
+        assert!(!filter.is_blocked("www.example.org"));
+        filter.build();
+        assert!(filter.is_blocked("www.example.org") && filter.is_blocked("ads.example.com"));
+
+        let mut request = Msg::new();
+        request.set_question(full_domain("ads.example.com"), types::TYPE_A);
+        assert_eq!(filter.answer_for(&request).unwrap().hdr.response_code, types::RCODE_NAME_ERROR);
+
+        let filter = Filter { mode: BlockMode::Address(Some(Ipv4Addr::new(192, 0, 2, 1)), None), ..filter };
+        let reply = filter.answer_for(&request).unwrap();
+        assert_eq!(reply.answer.len(), 1);
+        assert_eq!(reply.answer[0].header().ttl, 60);
+        request.set_question(full_domain("ads.example.com"), types::TYPE_AAAA);
+        let reply = filter.answer_for(&request).unwrap();
+        assert_eq!((reply.hdr.response_code, reply.answer.len()), (types::RCODE_SUCCESS, 0));
+        request.set_question(full_domain("example.com"), types::TYPE_A);
+        assert!(filter.answer_for(&request).is_none());
+    }
+}
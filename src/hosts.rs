@@ -1,72 +1,330 @@
-use crate::DomainString;
+use crate::{types, DomainString, Msg};
 use log::*;
 use once_cell::sync::Lazy;
 use parking_lot::Mutex;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::io;
-use std::io::BufRead;
-use std::net::IpAddr;
+use std::io::{BufRead, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::ops::Add;
-use std::path::Path;
-use std::time::{Duration, Instant};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
 
 /// Global instance
-static HOSTS: Lazy<Mutex<Hosts>> = Lazy::new(|| Mutex::new(Hosts::new()));
-/// Cache system hosts age
+static HOSTS: Lazy<Mutex<Hosts>> = Lazy::new(|| Mutex::new(Hosts::system()));
+/// How often the hosts file is checked for changes
 const CACHE_MAX_AGE: Duration = Duration::from_secs(5);
+/// TTL of records synthesized by [`answer_for`]
+pub const DEFAULT_TTL: u32 = 60;
 
 /// Query system hosts
 ///
 /// * name      domain
 pub fn get(name: &str) -> Option<IpAddr> {
     let mut hosts = HOSTS.lock();
-    if Instant::now() > hosts.expire {
-        hosts.reload();
-    }
-    hosts.inner.get(name).map(|e| *e)
+    hosts.refresh();
+    hosts.get(name)
+}
+
+/// Query all addresses of a name in system hosts, in file order
+pub fn get_all(name: &str) -> Vec<IpAddr> {
+    let mut hosts = HOSTS.lock();
+    hosts.refresh();
+    hosts.get_all(name).to_vec()
+}
+
+/// Query the first IPv4 address of a name, for answering A queries
+pub fn get_v4(name: &str) -> Option<Ipv4Addr> {
+    let mut hosts = HOSTS.lock();
+    hosts.refresh();
+    hosts.get_v4(name)
+}
+
+/// Query the first IPv6 address of a name, for answering AAAA queries
+pub fn get_v6(name: &str) -> Option<Ipv6Addr> {
+    let mut hosts = HOSTS.lock();
+    hosts.refresh();
+    hosts.get_v6(name)
+}
+
+/// Answer an A or AAAA query from system hosts, see [`Hosts::answer_for`]
+pub fn answer_for(request: &Msg) -> Option<Msg> {
+    let mut hosts = HOSTS.lock();
+    hosts.refresh();
+    hosts.answer_for(request, DEFAULT_TTL)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Edit {
+    Insert(DomainString, IpAddr),
+    Remove(DomainString),
+    Clear,
 }
 
-struct Hosts {
-    inner: HashMap<DomainString, IpAddr>,
+/// A hosts table, optionally backed by a file.
+///
+/// Programmatic edits are kept and replayed when the file is re-read, so
+/// they keep overriding it.
+#[derive(Debug, Clone)]
+pub struct Hosts {
+    inner: HashMap<DomainString, Vec<IpAddr>>,
+    /// first hostname of the line a name was listed on, as spelled there
+    canonical: HashMap<DomainString, DomainString>,
+    /// interface of scoped IPv6 entries such as `fe80::1%eth0`, by name
+    /// and address
+    scopes: HashMap<(DomainString, Ipv6Addr), DomainString>,
+    path: Option<PathBuf>,
+    edits: Vec<Edit>,
     expire: Instant,
+    /// mtime and size of the file when it was last parsed
+    stamp: Option<(SystemTime, u64)>,
+}
+
+impl Default for Hosts {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[cfg(unix)]
-fn hosts_path() -> Option<&'static str> {
-    Some("/etc/hosts")
+fn hosts_path() -> Option<PathBuf> {
+    Some("/etc/hosts".into())
 }
 
 #[cfg(windows)]
-fn hosts_path() -> Option<std::path::PathBuf> {
+fn hosts_path() -> Option<PathBuf> {
     let system_root = std::env::var_os("SystemRoot")?;
     let system_root = Path::new(&system_root);
     Some(system_root.join("System32\\drivers\\etc\\hosts"))
 }
 
+#[cfg(not(any(unix, windows)))]
+fn hosts_path() -> Option<PathBuf> {
+    None
+}
+
+/// Lowercase without the trailing dot, the way hosts files spell names.
+fn key(name: &str) -> DomainString {
+    DomainString::from(name.strip_suffix('.').unwrap_or(name).to_lowercase())
+}
+
 impl Hosts {
-    fn new() -> Self {
-        let mut hosts = Self {
+    /// Empty table without a backing file
+    pub fn new() -> Self {
+        Self {
             inner: Default::default(),
+            canonical: Default::default(),
+            scopes: Default::default(),
+            path: None,
+            edits: Vec::new(),
             expire: Instant::now(),
-        };
+            stamp: None,
+        }
+    }
+
+    /// The system hosts file; load errors are logged and leave the table empty
+    pub fn system() -> Self {
+        let mut hosts = Self::new();
+        hosts.path = hosts_path();
         hosts.reload();
         hosts
     }
 
-    fn reload(&mut self) {
-        #[cfg(any(unix, windows))]
-        if let Some(path) = hosts_path() {
-            self.inner.clear();
-            if let Err(err) = self.read_system_hosts(path) {
-                error!("load system hosts failed {:?}", err)
-            } else {
-                self.expire = Instant::now().add(CACHE_MAX_AGE);
+    pub fn from_path<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut hosts = Self::new();
+        hosts.path = Some(path.as_ref().to_path_buf());
+        hosts.load()?;
+        Ok(hosts)
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn get(&self, name: &str) -> Option<IpAddr> {
+        self.get_all(name).first().copied()
+    }
+
+    pub fn get_all(&self, name: &str) -> &[IpAddr] {
+        self.inner.get(&key(name)).map(|ips| ips.as_slice()).unwrap_or_default()
+    }
+
+    pub fn get_v4(&self, name: &str) -> Option<Ipv4Addr> {
+        self.get_all(name).iter().find_map(|ip| match ip {
+            IpAddr::V4(ip) => Some(*ip),
+            IpAddr::V6(_) => None,
+        })
+    }
+
+    pub fn get_v6(&self, name: &str) -> Option<Ipv6Addr> {
+        self.get_all(name).iter().find_map(|ip| match ip {
+            IpAddr::V4(_) => None,
+            IpAddr::V6(ip) => Some(*ip),
+        })
+    }
+
+    /// Canonical name of a host or alias, like `getaddrinfo` with `AI_CANONNAME`
+    pub fn canonical_name(&self, name: &str) -> Option<&str> {
+        self.canonical.get(&key(name)).map(|name| name.as_str())
+    }
+
+    /// Scope (interface) an IPv6 address of `name` was listed with
+    pub fn scope_id(&self, name: &str, ip: &Ipv6Addr) -> Option<&str> {
+        self.scopes.get(&(key(name), *ip)).map(|scope| scope.as_str())
+    }
+
+    /// Builds the reply to an A or AAAA query for a name in the table.
+    ///
+    /// The answer holds every address of the queried family, or is empty
+    /// (NODATA) if the name only has addresses of the other family. Returns
+    /// `None` for other queries and unknown names.
+    pub fn answer_for(&self, request: &Msg, ttl: u32) -> Option<Msg> {
+        if request.hdr.response || request.hdr.op_code != types::OPCODE_QUERY || request.question.len() != 1 {
+            return None;
+        }
+        let q = &request.question[0];
+        if !(q.is_a() || q.is_aaaa()) || q.q_class != types::CLASS_INET {
+            return None;
+        }
+        let ips = self.get_all(&q.name);
+        if ips.is_empty() {
+            return None;
+        }
+
+        let mut msg = Msg::new();
+        msg.set_reply(request);
+        msg.hdr.authoritative = true;
+        msg.hdr.recursion_available = true;
+        for ip in ips {
+            if ip.is_ipv4() == q.is_a() {
+                msg.answer.push(types::RecourseRecord::new_ip(q.name.clone(), types::CLASS_INET, ttl, *ip));
+            }
+        }
+        Some(msg)
+    }
+
+    /// Adds an address to a name
+    pub fn insert(&mut self, name: &str, ip: IpAddr) {
+        let edit = Edit::Insert(name.into(), ip);
+        self.apply(&edit);
+        if !self.edits.contains(&edit) {
+            self.edits.push(edit);
+        }
+    }
+
+    /// Removes a name and returns its addresses
+    pub fn remove(&mut self, name: &str) -> Vec<IpAddr> {
+        let name = key(name);
+        let ret = self.inner.get(&name).cloned().unwrap_or_default();
+        // a removal overrides every earlier edit of the name
+        self.edits.retain(|e| !matches!(e, Edit::Insert(n, _) | Edit::Remove(n) if key(n) == name));
+        let edit = Edit::Remove(name);
+        self.apply(&edit);
+        self.edits.push(edit);
+        ret
+    }
+
+    pub fn clear(&mut self) {
+        self.apply(&Edit::Clear);
+        self.edits = vec![Edit::Clear];
+    }
+
+    /// Writes the table, edits included, in hosts file syntax: one line per
+    /// address, scope and canonical name, aliases following the canonical name.
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let mut lines: BTreeMap<(DomainString, IpAddr, Option<&str>), Vec<&str>> = BTreeMap::new();
+        for (name, ips) in &self.inner {
+            for ip in ips {
+                let scope = |name: &str| match ip {
+                    IpAddr::V6(v6) => self.scope_id(name, v6),
+                    IpAddr::V4(_) => None,
+                };
+                let canonical = self
+                    .canonical_name(name)
+                    .filter(|c| self.get_all(c).contains(ip) && scope(c) == scope(name))
+                    .unwrap_or(name);
+                let names = lines.entry((key(canonical), *ip, scope(name))).or_insert_with(|| vec![canonical]);
+                if key(canonical) != *name {
+                    names.push(name);
+                }
+            }
+        }
+        for ((_, ip, scope), mut names) in lines {
+            names[1..].sort_unstable();
+            match scope {
+                Some(scope) => write!(w, "{}%{}", ip, scope)?,
+                None => write!(w, "{}", ip)?,
+            }
+            writeln!(w, "\t{}", names.join(" "))?;
+        }
+        Ok(())
+    }
+
+    /// Re-reads the file if it changed, at most once per check interval
+    pub fn refresh(&mut self) {
+        if Instant::now() > self.expire {
+            self.reload();
+        }
+    }
+
+    /// Re-reads the file if its mtime or size changed; errors are logged
+    pub fn reload(&mut self) {
+        if let Err(err) = self.load() {
+            error!("load hosts {:?} failed {:?}", self.path, err)
+        }
+    }
+
+    fn load(&mut self) -> io::Result<()> {
+        let Some(path) = self.path.clone() else { return Ok(()); };
+        let stamp = std::fs::metadata(&path)
+            .and_then(|m| Ok((m.modified()?, m.len())))
+            .ok();
+        if stamp.is_some() && stamp == self.stamp {
+            self.expire = Instant::now().add(CACHE_MAX_AGE);
+            return Ok(());
+        }
+        self.apply(&Edit::Clear);
+        let ret = self.read_hosts(&path);
+        // the edits stand even when the file is gone, and a failing file
+        // is retried no sooner than a changed one would be
+        for edit in self.edits.clone() {
+            self.apply(&edit);
+        }
+        self.stamp = if ret.is_ok() { stamp } else { None };
+        self.expire = Instant::now().add(CACHE_MAX_AGE);
+        ret
+    }
+
+    fn apply(&mut self, edit: &Edit) {
+        match edit {
+            Edit::Insert(name, ip) => self.add(name, *ip, name, None),
+            Edit::Remove(name) => {
+                self.inner.remove(name);
+                self.canonical.remove(name);
+                self.scopes.retain(|(n, _), _| n != name);
+            }
+            Edit::Clear => {
+                self.inner.clear();
+                self.canonical.clear();
+                self.scopes.clear();
             }
         }
     }
 
-    #[cfg(any(unix, windows))]
-    fn read_system_hosts<S: AsRef<Path>>(&mut self, path: S) -> io::Result<()> {
+    fn add(&mut self, name: &str, ip: IpAddr, canonical: &str, scope: Option<&str>) {
+        let name = key(name);
+        self.canonical.entry(name.clone()).or_insert_with(|| canonical.into());
+        let ips = self.inner.entry(name.clone()).or_default();
+        if ips.contains(&ip) {
+            return;
+        }
+        ips.push(ip);
+        if let (IpAddr::V6(ip), Some(scope)) = (ip, scope) {
+            self.scopes.insert((name, ip), scope.into());
+        }
+    }
+
+    fn read_hosts(&mut self, path: &Path) -> io::Result<()> {
         use std::fs::File;
         use std::io::BufReader;
 
@@ -84,22 +342,227 @@ impl Hosts {
                 continue;
             }
 
-            let ip = match fields[0].parse::<IpAddr>() {
-                Ok(ip) => ip,
-                Err(_) => {
+            let (addr, scope) = match fields[0].split_once('%') {
+                Some((addr, scope)) => (addr, Some(scope)),
+                None => (fields[0], None),
+            };
+            let (ip, scope) = match (addr.parse::<IpAddr>(), scope) {
+                (Ok(ip), None) => (ip, None),
+                (Ok(ip @ IpAddr::V6(_)), Some(scope)) if !scope.is_empty() => (ip, Some(scope)),
+                _ => {
                     warn!("could not parse on ip from hosts file");
                     continue;
                 }
             };
 
-            for domain in fields.iter().skip(1).map(|domain| domain.to_lowercase()) {
-                if crate::msg::Labels::verify(&domain) {
-                    debug!("load system dns domain: {:?}, ip: {:?}", domain, ip,);
-                    self.inner.insert(DomainString::from(domain), ip.clone());
-                }
+            let mut names = fields.iter().skip(1).filter(|domain| crate::msg::Labels::verify(&domain.to_lowercase()));
+            let Some(canonical) = names.next() else { continue; };
+            for domain in std::iter::once(canonical).chain(names) {
+                debug!("load system dns domain: {:?}, ip: {:?}", domain, ip,);
+                self.add(domain, ip, canonical, scope);
             }
         }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{Edit, Hosts};
+    use crate::msg::RR;
+    use crate::{types, Msg};
+    use std::net::IpAddr;
+    use std::time::Instant;
+
+    #[test]
+    fn test_hosts() {
+        let path = std::env::temp_dir().join(format!("dns-hosts-test-{}", std::process::id()));
+        std::fs::write(&path, "127.0.0.1 localhost\n::1 localhost\n192.0.2.1 a.example # comment\n").unwrap();
+
+        let mut hosts = Hosts::from_path(&path).unwrap();
+        assert_eq!(hosts.get_all("LocalHost."), ["127.0.0.1".parse::<IpAddr>().unwrap(), "::1".parse().unwrap()]);
+        assert_eq!(hosts.get_v6("localhost"), Some("::1".parse().unwrap()));
+        assert_eq!(hosts.get_v6("a.example"), None);
+
+        hosts.insert("b.example", "192.0.2.2".parse().unwrap());
+        hosts.remove("a.example");
+        std::fs::write(&path, "192.0.2.1 a.example\n192.0.2.3 c.example\n").unwrap();
+        hosts.reload();
+        assert_eq!(hosts.get("c.example"), Some("192.0.2.3".parse().unwrap()));
+        assert_eq!(hosts.get("b.example"), Some("192.0.2.2".parse().unwrap()));
+        assert_eq!(hosts.get("a.example"), None);
+
+        let mut request = Msg::new();
+        request.set_question("b.example.", types::TYPE_A);
+        let reply = hosts.answer_for(&request, 30).unwrap();
+        assert_eq!(reply.hdr.id, request.hdr.id);
+        assert_eq!(reply.answer.len(), 1);
+        assert_eq!(reply.answer[0].header().ttl, 30);
+        request.set_question("b.example.", types::TYPE_AAAA);
+        let reply = hosts.answer_for(&request, 30).unwrap();
+        assert!(reply.answer.is_empty() && reply.hdr.response_code == types::RCODE_SUCCESS);
+        request.set_question("d.example.", types::TYPE_A);
+        assert!(hosts.answer_for(&request, 30).is_none());
+
+        hosts.clear();
+        assert_eq!(hosts.get("c.example"), None);
+
+        std::fs::write(&path, "fe80::1%eth0 Router.example router gw\nfe80::1%eth1 other\nfe80::2% bad\n").unwrap();
+        let mut hosts = Hosts::from_path(&path).unwrap();
+        let link_local: std::net::Ipv6Addr = "fe80::1".parse().unwrap();
+        assert_eq!(hosts.get("gw"), Some(link_local.into()));
+        assert_eq!(hosts.scope_id("gw", &link_local), Some("eth0"));
+        assert_eq!(hosts.canonical_name("GW"), Some("Router.example"));
+        assert_eq!(hosts.get("bad"), None);
+
+        // the same address on another interface is another entry
+        assert_eq!(hosts.scope_id("other", &link_local), Some("eth1"));
+        hosts.remove("gw");
+        assert_eq!(hosts.scope_id("gw", &link_local), None);
+        assert_eq!(hosts.scope_id("router", &link_local), Some("eth0"));
+        assert_eq!(hosts.scope_id("other", &link_local), Some("eth1"));
+
+        hosts.insert("ns.example", "192.0.2.53".parse().unwrap());
+        hosts.insert("ns.example", "2001:db8::53".parse().unwrap());
+        let mut out = Vec::new();
+        hosts.write_to(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "192.0.2.53\tns.example\n2001:db8::53\tns.example\nfe80::1%eth1\tother\nfe80::1%eth0\tRouter.example router\n"
+        );
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_hosts_multiple_addresses() {
+        let path = std::env::temp_dir().join(format!("dns-hosts-multi-test-{}", std::process::id()));
+        std::fs::write(
+            &path,
+            "192.0.2.1 multi.example\n2001:db8::1 multi.example\n192.0.2.2 multi.example alias.example\n192.0.2.1 multi.example\n",
+        )
+        .unwrap();
+        let mut hosts = Hosts::from_path(&path).unwrap();
+
+        // file order, a repeated line listed once
+        let all: [IpAddr; 3] = ["192.0.2.1".parse().unwrap(), "2001:db8::1".parse().unwrap(), "192.0.2.2".parse().unwrap()];
+        assert_eq!(hosts.get_all("multi.example"), all);
+        assert_eq!(hosts.get("multi.example"), Some(all[0]));
+        assert_eq!(hosts.get_all("alias.example"), [all[2]]);
+
+        // inserts append after the file's addresses, also without duplicates
+        hosts.insert("multi.example", all[1]);
+        hosts.insert("multi.example", "2001:db8::2".parse().unwrap());
+        assert_eq!(hosts.get_all("multi.example").len(), 4);
+        assert_eq!(hosts.get_all("multi.example")[3], "2001:db8::2".parse::<IpAddr>().unwrap());
+
+        assert_eq!(hosts.remove("multi.example").len(), 4);
+        assert!(hosts.get_all("multi.example").is_empty());
+        assert_eq!(hosts.get_all("alias.example"), [all[2]]);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_hosts_family() {
+        let mut hosts = Hosts::new();
+        hosts.insert("v4.example", "192.0.2.1".parse().unwrap());
+        hosts.insert("v6.example", "2001:db8::1".parse().unwrap());
+        hosts.insert("both.example", "2001:db8::2".parse().unwrap());
+        hosts.insert("both.example", "192.0.2.2".parse().unwrap());
+        hosts.insert("both.example", "192.0.2.3".parse().unwrap());
+
+        assert_eq!(hosts.get_v4("v4.example"), Some("192.0.2.1".parse().unwrap()));
+        assert_eq!(hosts.get_v6("v4.example"), None);
+        assert_eq!(hosts.get_v4("v6.example"), None);
+        assert_eq!(hosts.get_v6("v6.example"), Some("2001:db8::1".parse().unwrap()));
+        // the first of each family, whatever order they came in
+        assert_eq!(hosts.get_v4("both.example"), Some("192.0.2.2".parse().unwrap()));
+        assert_eq!(hosts.get_v6("both.example"), Some("2001:db8::2".parse().unwrap()));
+
+        // AAAA for an IPv4-only name is NODATA, not an A record
+        let mut request = Msg::new();
+        request.set_question("v4.example.", types::TYPE_AAAA);
+        let reply = hosts.answer_for(&request, 60).unwrap();
+        assert!(reply.answer.is_empty());
+        assert_eq!(reply.hdr.response_code, types::RCODE_SUCCESS);
+        request.set_question("v6.example.", types::TYPE_A);
+        assert!(hosts.answer_for(&request, 60).unwrap().answer.is_empty());
+
+        request.set_question("both.example.", types::TYPE_A);
+        let reply = hosts.answer_for(&request, 60).unwrap();
+        assert_eq!(reply.answer.len(), 2);
+        assert!(reply.answer.iter().all(|rr| rr.header().typ == types::TYPE_A));
+        request.set_question("both.example.", types::TYPE_AAAA);
+        let reply = hosts.answer_for(&request, 60).unwrap();
+        assert_eq!(reply.answer.len(), 1);
+        assert_eq!(reply.answer[0].header().typ, types::TYPE_AAAA);
+    }
+
+    #[test]
+    fn test_hosts_reload_stamp() {
+        let path = std::env::temp_dir().join(format!("dns-hosts-stamp-test-{}", std::process::id()));
+        std::fs::write(&path, "192.0.2.1 a.example\n").unwrap();
+        let mtime = std::fs::metadata(&path).unwrap().modified().unwrap();
+        let mut hosts = Hosts::from_path(&path).unwrap();
+
+        // same size and mtime: taken as unchanged, not parsed again
+        std::fs::write(&path, "192.0.2.9 a.example\n").unwrap();
+        std::fs::File::options().write(true).open(&path).unwrap().set_modified(mtime).unwrap();
+        hosts.reload();
+        assert_eq!(hosts.get("a.example"), Some("192.0.2.1".parse().unwrap()));
+
+        // a new size is a change even under the same mtime
+        std::fs::write(&path, "192.0.2.10 a.example\n").unwrap();
+        std::fs::File::options().write(true).open(&path).unwrap().set_modified(mtime).unwrap();
+        hosts.reload();
+        assert_eq!(hosts.get("a.example"), Some("192.0.2.10".parse().unwrap()));
+
+        // and so is a new mtime under the same size
+        std::fs::write(&path, "192.0.2.11 a.example\n").unwrap();
+        let later = mtime + std::time::Duration::from_secs(10);
+        std::fs::File::options().write(true).open(&path).unwrap().set_modified(later).unwrap();
+        hosts.reload();
+        assert_eq!(hosts.get("a.example"), Some("192.0.2.11".parse().unwrap()));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_hosts_load_failure() {
+        let path = std::env::temp_dir().join(format!("dns-hosts-fail-test-{}", std::process::id()));
+        std::fs::write(&path, "192.0.2.1 a.example\n").unwrap();
+        let mut hosts = Hosts::from_path(&path).unwrap();
+        hosts.insert("b.example", "192.0.2.2".parse().unwrap());
+
+        // the edits outlive the file, which is not retried until the interval is up
+        std::fs::remove_file(&path).unwrap();
+        hosts.expire = Instant::now();
+        assert!(hosts.load().is_err());
+        assert_eq!(hosts.get("a.example"), None);
+        assert_eq!(hosts.get("b.example"), Some("192.0.2.2".parse().unwrap()));
+        assert!(hosts.expire > Instant::now());
+    }
+
+    #[test]
+    fn test_hosts_edits_collapse() {
+        let mut hosts = Hosts::new();
+        for _ in 0..3 {
+            hosts.insert("a.example", "192.0.2.1".parse().unwrap());
+            hosts.insert("a.example", "192.0.2.2".parse().unwrap());
+            hosts.remove("b.example");
+        }
+        assert_eq!(hosts.edits.len(), 3);
+
+        // the last edit of a name wins
+        hosts.remove("a.example");
+        hosts.insert("a.example", "192.0.2.3".parse().unwrap());
+        assert_eq!(
+            hosts.edits,
+            [
+                Edit::Remove("b.example".into()),
+                Edit::Remove("a.example".into()),
+                Edit::Insert("a.example".into(), "192.0.2.3".parse().unwrap()),
+            ]
+        );
+        assert_eq!(hosts.get_all("a.example"), ["192.0.2.3".parse::<IpAddr>().unwrap()]);
+    }
+}
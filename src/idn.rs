@@ -0,0 +1,83 @@
+//! Internationalized domain names, UTS #46 processing (`with_idna` feature).
+//!
+//! Names are processed whole rather than label by label, so the bidi rule
+//! (RFC 5893) sees every label of the domain, and the CONTEXTJ rules for
+//! joiners (RFC 5892 appendix A) are checked along with hyphen placement.
+
+use std::borrow::Cow;
+use idna::uts46::{AsciiDenyList, DnsLength, Hyphens, Uts46};
+use crate::{full_domain, DomainString, Error, Result};
+
+/// Conversion of a whole name between its A-label and U-label forms.
+pub trait Name {
+    /// ToASCII with the DNS length checks, the form sent on the wire.
+    fn to_ascii(&self) -> Result<DomainString>;
+
+    /// ToUnicode for display. Fails if any label is invalid.
+    fn to_unicode(&self) -> Result<DomainString>;
+}
+
+impl Name for str {
+    fn to_ascii(&self) -> Result<DomainString> {
+        if self == "." {
+            return Ok(full_domain(""));
+        }
+        let ascii = Uts46::new()
+            .to_ascii(self.as_bytes(), AsciiDenyList::EMPTY, Hyphens::CheckFirstLast, DnsLength::VerifyAllowRootDot)
+            .map_err(|_| invalid(self))?;
+        Ok(full_domain(ascii))
+    }
+
+    fn to_unicode(&self) -> Result<DomainString> {
+        let (unicode, ret) = Uts46::new().to_unicode(self.as_bytes(), AsciiDenyList::EMPTY, Hyphens::CheckFirstLast);
+        ret.map_err(|_| invalid(self))?;
+        Ok(full_domain(unicode))
+    }
+}
+
+/// ToASCII as done when packing: no length checks, the codec does those.
+/// ASCII names without A-labels are left alone so their case survives.
+pub(crate) fn name_to_ascii(name: &str) -> Result<Cow<'_, str>> {
+    if name.is_ascii() && !name.to_ascii_lowercase().contains("xn--") {
+        return Ok(Cow::Borrowed(name));
+    }
+    Uts46::new()
+        .to_ascii(name.as_bytes(), AsciiDenyList::EMPTY, Hyphens::CheckFirstLast, DnsLength::Ignore)
+        .map_err(|_| invalid(name))
+}
+
+fn invalid(name: &str) -> Error {
+    Error::new(format!("invalid internationalized name {:?}", name))
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::BytesMut;
+    use crate::util;
+    use super::Name;
+
+    #[test]
+    fn test_idn() {
+        assert_eq!("Bücher.example".to_ascii().unwrap().as_str(), "xn--bcher-kva.example.");
+        assert_eq!("xn--bcher-kva.example.".to_unicode().unwrap().as_str(), "bücher.example.");
+        assert_eq!(".".to_ascii().unwrap().as_str(), ".");
+
+        // bidi rule: an RTL domain may not have a label starting with a digit
+        assert!("0a.\u{05D0}.example".to_ascii().is_err());
+        assert!("a.\u{05D0}.example".to_ascii().is_ok());
+        // CONTEXTJ: ZWJ only after a virama
+        assert!("a\u{200D}b.example".to_ascii().is_err());
+        assert!("\u{0915}\u{094D}\u{200D}\u{0937}.example".to_ascii().is_ok());
+        assert!("-a.example".to_ascii().is_err());
+        assert!("xn--bcher-kva-.example".to_unicode().is_err());
+
+        let mut buf = BytesMut::new();
+        util::pack_domain_name("bücher.example.", &mut buf).unwrap();
+        assert_eq!(&buf[..], b"\x0dxn--bcher-kva\x07example\x00");
+        assert_eq!(util::cal_domain_name_len("bücher.example."), buf.len());
+        assert!(util::pack_domain_name("a\u{200D}b.example.", &mut buf).is_err());
+        buf.clear();
+        util::pack_domain_name("WWW.Example.", &mut buf).unwrap();
+        assert_eq!(&buf[..], b"\x03WWW\x07Example\x00");
+    }
+}
@@ -0,0 +1,88 @@
+//! Shared storage for repeated names and labels.
+//!
+//! Loading a large zone or an AXFR stream sees the same owner names and the
+//! same labels over and over. An [`Interner`] keeps one copy of each and hands
+//! out reference-counted handles to it.
+
+use alloc::collections::BTreeSet;
+use alloc::sync::Arc;
+
+/// Arena of interned names and labels. Entries live as long as the interner
+/// or a handle to them does; [`Interner::purge`] drops the unused ones.
+#[derive(Debug, Clone, Default)]
+pub struct Interner {
+    names: BTreeSet<Arc<str>>,
+    labels: BTreeSet<Arc<[u8]>>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Shared copy of `name`, compared exactly (case included).
+    pub fn name(&mut self, name: &str) -> Arc<str> {
+        if let Some(val) = self.names.get(name) {
+            return val.clone();
+        }
+        let val: Arc<str> = Arc::from(name);
+        self.names.insert(val.clone());
+        val
+    }
+
+    /// Shared copy of a wire-format label.
+    pub fn label(&mut self, label: &[u8]) -> Arc<[u8]> {
+        if let Some(val) = self.labels.get(label) {
+            return val.clone();
+        }
+        let val: Arc<[u8]> = Arc::from(label);
+        self.labels.insert(val.clone());
+        val
+    }
+
+    /// Number of distinct names and labels held.
+    pub fn len(&self) -> usize {
+        self.names.len() + self.labels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty() && self.labels.is_empty()
+    }
+
+    /// Drops entries no handle refers to anymore.
+    pub fn purge(&mut self) {
+        self.names.retain(|val| Arc::strong_count(val) > 1);
+        self.labels.retain(|val| Arc::strong_count(val) > 1);
+    }
+
+    pub fn clear(&mut self) {
+        self.names.clear();
+        self.labels.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::sync::Arc;
+    use super::Interner;
+
+    #[test]
+    fn test_interner() {
+        let mut interner = Interner::new();
+        let a = interner.name("www.example.com.");
+        let b = interner.name("www.example.com.");
+        assert!(Arc::ptr_eq(&a, &b));
+        assert!(!Arc::ptr_eq(&a, &interner.name("WWW.example.com.")));
+
+        let com = interner.label(b"com");
+        assert!(Arc::ptr_eq(&com, &interner.label(b"com")));
+        assert_eq!(interner.len(), 3);
+
+        drop((a, b));
+        interner.purge();
+        assert_eq!(interner.len(), 1);
+        drop(com);
+        interner.purge();
+        assert!(interner.is_empty());
+    }
+}
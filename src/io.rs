@@ -0,0 +1,84 @@
+//! Byte cursor the wire codec reads from.
+//!
+//! With the `std` feature this is `std::io::Cursor` with byteorder's
+//! `ReadBytesExt`; without it, a minimal stand-in with the same method names
+//! that fails with `Error::BufTooSmall` at the end of input.
+
+#[cfg(feature = "std")]
+pub use byteorder::ReadBytesExt;
+#[cfg(feature = "std")]
+pub use std::io::{Cursor, Read};
+
+#[cfg(not(feature = "std"))]
+pub use self::cursor::{Cursor, Read, ReadBytesExt};
+
+#[cfg(not(feature = "std"))]
+mod cursor {
+    use byteorder::ByteOrder;
+    use crate::{Error, Result};
+
+    #[derive(Debug, Clone, Default)]
+    pub struct Cursor<T> {
+        inner: T,
+        pos: u64,
+    }
+
+    impl<T> Cursor<T> {
+        pub fn new(inner: T) -> Self {
+            Self { inner, pos: 0 }
+        }
+
+        pub fn into_inner(self) -> T {
+            self.inner
+        }
+
+        pub fn get_ref(&self) -> &T {
+            &self.inner
+        }
+
+        pub fn position(&self) -> u64 {
+            self.pos
+        }
+
+        pub fn set_position(&mut self, pos: u64) {
+            self.pos = pos;
+        }
+    }
+
+    pub trait Read {
+        fn read_exact(&mut self, buf: &mut [u8]) -> Result<()>;
+    }
+
+    impl<T: AsRef<[u8]>> Read for Cursor<T> {
+        fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+            let data = self.inner.as_ref();
+            let start = usize::try_from(self.pos).unwrap_or(usize::MAX).min(data.len());
+            let src = data[start..].get(..buf.len()).ok_or(Error::BufTooSmall)?;
+            buf.copy_from_slice(src);
+            self.pos += buf.len() as u64;
+            Ok(())
+        }
+    }
+
+    pub trait ReadBytesExt: Read {
+        fn read_u8(&mut self) -> Result<u8> {
+            let mut buf = [0; 1];
+            self.read_exact(&mut buf)?;
+            Ok(buf[0])
+        }
+
+        fn read_u16<B: ByteOrder>(&mut self) -> Result<u16> {
+            let mut buf = [0; 2];
+            self.read_exact(&mut buf)?;
+            Ok(B::read_u16(&buf))
+        }
+
+        fn read_u32<B: ByteOrder>(&mut self) -> Result<u32> {
+            let mut buf = [0; 4];
+            self.read_exact(&mut buf)?;
+            Ok(B::read_u32(&buf))
+        }
+    }
+
+    impl<R: Read + ?Sized> ReadBytesExt for R {}
+}
@@ -1,27 +1,85 @@
-use std::io;
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use core::fmt::Write;
+use core::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use crate::msg::{PktMsgHeader, Question};
+use crate::prelude::*;
 pub use crate::msg::Msg;
+pub use crate::util::{punycode_decode, punycode_encode};
 
 mod util;
+#[cfg(feature = "std")]
+pub mod cache;
+#[cfg(feature = "std")]
 pub mod client;
+#[cfg(all(feature = "tokio-codec", not(target_arch = "wasm32")))]
+pub mod codec;
+#[cfg(feature = "std")]
+pub mod cookie;
+#[cfg(feature = "std")]
+pub mod dnssec;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "std")]
+pub mod filter;
+#[cfg(feature = "with_idna")]
+pub mod idn;
+pub mod intern;
+pub mod io;
+#[cfg(feature = "std")]
+pub mod mdns;
+#[cfg(feature = "metrics")]
+pub mod metrics;
 pub mod msg;
+#[cfg(feature = "psl")]
+pub mod psl;
+#[cfg(feature = "std")]
+pub mod route;
+#[cfg(feature = "std")]
+pub mod stamp;
+#[cfg(feature = "testutil")]
+pub mod testutil;
 pub mod types;
+#[cfg(feature = "std")]
 pub mod hosts;
+#[cfg(feature = "std")]
+pub mod zone;
+
+/// What the std prelude provides, for the `no_std` build of the codec.
+mod prelude {
+    pub use alloc::boxed::Box;
+    pub use alloc::format;
+    pub use alloc::string::{String, ToString};
+    pub use alloc::vec;
+    pub use alloc::vec::Vec;
+}
 
 pub type DomainString = smallstr::SmallString<[u8; 24]>;
 
-pub type Result<T> = std::result::Result<T, Error>;
+pub type Result<T> = core::result::Result<T, Error>;
 
 #[derive(Debug)]
 pub enum Error {
     BufTooSmall,
+    #[cfg(feature = "std")]
     InvalidSubnet(ipnetwork::IpNetworkError),
     BadExtendedResponseCode,
     BadResponseCode,
     InvalidRdLength,
     HexError(hex::FromHexError),
     UnpackOverflow(String),
-    Io(io::Error),
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+    /// Presentation-format syntax error at the given line.
+    Syntax(usize, String),
+    /// No implementation registered for the DNSSEC algorithm number.
+    UnsupportedAlgorithm(u8),
+    /// A DNSSEC signature did not verify.
+    BadSignature,
+    /// `Msg::unpack` failed, with where in the packet it did.
+    Malformed(Box<msg::Malformed>),
     Error(String),
 }
 
@@ -37,23 +95,26 @@ impl From<hex::FromHexError> for Error {
     }
 }
 
+#[cfg(feature = "std")]
 impl From<ipnetwork::IpNetworkError> for Error {
     fn from(value: ipnetwork::IpNetworkError) -> Self {
         Self::InvalidSubnet(value)
     }
 }
 
-impl From<io::Error> for Error {
-    fn from(value: io::Error) -> Self {
+#[cfg(feature = "std")]
+impl From<std::io::Error> for Error {
+    fn from(value: std::io::Error) -> Self {
         Self::Io(value)
     }
 }
 
-impl Into<io::Error> for Error {
-    fn into(self) -> io::Error {
-        match self {
+#[cfg(feature = "std")]
+impl From<Error> for std::io::Error {
+    fn from(value: Error) -> Self {
+        match value {
             Error::Io(val) => val,
-            _ => io::Error::new(io::ErrorKind::Other, format!("{:?}", self))
+            _ => std::io::Error::other(format!("{:?}", value))
         }
     }
 }
@@ -67,11 +128,60 @@ pub fn full_domain<S: Into<String>>(s: S) -> DomainString {
 }
 
 pub fn clear_full_domain(s: &str) -> &str {
-    if s.ends_with('.') {
-        &s[..s.len() - 1]
-    } else {
-        s
+    s.strip_suffix('.').unwrap_or(s)
+}
+
+/// The name a reverse lookup of `ip` asks for: `4.3.2.1.in-addr.arpa.`
+/// for 1.2.3.4, and 32 nibbles, lowest first, under `ip6.arpa.` for IPv6
+/// (RFC 3596 section 2.5).
+pub fn ptr_name(ip: IpAddr) -> DomainString {
+    let mut name = DomainString::new();
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, c, d] = ip.octets();
+            let _ = write!(name, "{}.{}.{}.{}.in-addr.arpa.", d, c, b, a);
+        }
+        IpAddr::V6(ip) => {
+            for octet in ip.octets().iter().rev() {
+                let _ = write!(name, "{:x}.{:x}.", octet & 0xF, octet >> 4);
+            }
+            name.push_str("ip6.arpa.");
+        }
+    }
+    name
+}
+
+/// The address a [`ptr_name`] is for, case-insensitively and with or
+/// without the trailing dot. None for names that are not a whole address,
+/// like the `2.0.192.in-addr.arpa.` of a delegation.
+pub fn parse_ptr_name(name: &str) -> Option<IpAddr> {
+    let name = clear_full_domain(name).to_ascii_lowercase();
+    if let Some(labels) = name.strip_suffix(".in-addr.arpa") {
+        let mut octets = [0u8; 4];
+        let mut labels = labels.split('.');
+        for octet in octets.iter_mut().rev() {
+            let label = labels.next()?;
+            if label.is_empty() || label.len() > 3 || !label.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            *octet = label.parse().ok()?;
+        }
+        return labels.next().is_none().then(|| Ipv4Addr::from(octets).into());
     }
+    let labels = name.strip_suffix(".ip6.arpa")?;
+    let mut octets = [0u8; 16];
+    let mut nibbles = labels.split('.');
+    for octet in octets.iter_mut().rev() {
+        let mut nibble = || {
+            let label = nibbles.next()?;
+            if label.len() != 1 {
+                return None;
+            }
+            u8::from_str_radix(label, 16).ok()
+        };
+        *octet = nibble()? | nibble()? << 4;
+    }
+    nibbles.next().is_none().then(|| Ipv6Addr::from(octets).into())
 }
 
 pub fn is_dns(data: &[u8]) -> bool {
@@ -84,7 +194,7 @@ pub fn is_dns(data: &[u8]) -> bool {
         return false;
     }
     for _ in 0..hdr.question_count {
-        if let Err(_) = Question::skip(&mut cur) {
+        if Question::skip(&mut cur).is_err() {
             return false;
         }
     }
@@ -93,15 +203,16 @@ pub fn is_dns(data: &[u8]) -> bool {
 
 #[cfg(test)]
 mod test {
-    use std::net::Ipv4Addr;
-    use std::time::Instant;
+    use core::net::Ipv4Addr;
     use bytes::BytesMut;
-    use crate::{clear_full_domain, full_domain, is_dns, types};
-    use crate::types::{EDNS0, RecourseRecord};
+    use crate::prelude::*;
+    use crate::{full_domain, types};
+    use crate::types::EDNS0;
     use crate::types::edns::edns0;
     use super::msg::*;
 
     #[test]
+    #[cfg(feature = "std")]
     pub fn test_pp() {
         let x = ipnetwork::Ipv4Network::new(Ipv4Addr::new(114, 114, 114, 114), 24).unwrap();
         eprintln!("{:?}", x);
@@ -109,8 +220,9 @@ mod test {
     }
 
     #[test]
+    #[cfg(feature = "std")]
     pub fn test_pack() {
-        let now = Instant::now();
+        let now = std::time::Instant::now();
         let mut msg = Msg::new();
         msg.set_question(full_domain("www.google.com"), types::TYPE_A);
         msg.answer.push(types::A::new(
@@ -126,33 +238,22 @@ mod test {
             "www.google.com.abc.".into(),
         ).into());
 
-        let mut opt = types::Opt {
-            hdr: RecourseRecordHdr {
-                name: ".".into(),
-                typ: types::TYPE_OPT,
-                class: 0,
-                ttl: 0,
-                rd_length: 0,
-            },
-            option: vec![],
-        };
-        opt.set_udp_size(1350);
+        msg.set_edns0(1350, true);
+        let opt = msg.edns_mut();
         opt.set_extended_r_code(0xfe00);
-        opt.set_do(&[]);
         opt.option.push(EDNS0::SubNet(edns0::SubNet::new(
             Ipv4Addr::new(114, 114, 114, 114).into(),
             24,
             0,
         )));
-        msg.additional.push(opt.into());
 
-        let mut buf = BytesMut::new();
+        let mut buf = bytes::BytesMut::new();
         msg.pack(&mut buf).unwrap();
         eprintln!("{:?}", buf.as_ref());
 
         {
             let data = buf.as_ref();
-            let msg = Msg::unpack(&data[..]).unwrap();
+            let msg = Msg::unpack(data).unwrap();
             eprintln!("{}", msg);
         }
 
@@ -160,6 +261,437 @@ mod test {
     }
 
     #[test]
+    #[cfg(feature = "std")]
+    pub fn test_custom_option() {
+        use core::fmt;
+        use crate::types::edns::edns0::{register_option, unregister_option, IEdns0};
+
+        /// Hop count, in the local use range.
+        #[derive(Debug)]
+        struct Hops(u8);
+        const CODE: u16 = 0xFDEA;
+
+        impl fmt::Display for Hops {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "hops={}", self.0)
+            }
+        }
+
+        impl IEdns0 for Hops {
+            type Item = Hops;
+
+            fn option(&self) -> u16 {
+                CODE
+            }
+
+            fn pack(&self, buf: &mut BytesMut) -> crate::Result<()> {
+                buf.extend_from_slice(&[self.0]);
+                Ok(())
+            }
+
+            fn unpack(_code: u16, bs: &[u8]) -> crate::Result<Self> {
+                match bs {
+                    [hops] => Ok(Hops(*hops)),
+                    _ => Err(crate::Error::new("bad hop count")),
+                }
+            }
+        }
+
+        let mut msg = Msg::new();
+        msg.set_question(full_domain("example.com"), types::TYPE_A);
+        let mut opt = types::Opt::new(1232);
+        opt.option.push(EDNS0::Custom(std::sync::Arc::new(Hops(3))));
+        msg.additional.push(opt.into());
+        let buf = msg.to_buf().unwrap();
+
+        let opt = |msg: &Msg| msg.is_edns0().unwrap().option[0].clone();
+        assert!(matches!(opt(&Msg::unpack(&buf).unwrap()), EDNS0::Local(val) if val.code == CODE && val.data == [3]));
+        register_option::<Hops>(CODE);
+        let back = Msg::unpack(&buf).unwrap();
+        assert_eq!(back.is_edns0().unwrap().custom::<Hops>().map(|h| h.0), Some(3));
+        assert!(back.to_string().contains("hops=3"));
+        assert!(unregister_option(CODE));
+        assert!(matches!(opt(&Msg::unpack(&buf).unwrap()), EDNS0::Local(_)));
+    }
+
+    #[test]
+    pub fn test_ede() {
+        let mut msg = Msg::new();
+        msg.set_question(full_domain("ads.example.com"), types::TYPE_A);
+        let mut opt = types::Opt::new(1232);
+        opt.add_ede(edns0::EDE_BLOCKED, "listed by policy");
+        opt.add_ede(edns0::EDE_STALE_ANSWER, "");
+        msg.additional.push(opt.into());
+        let buf = msg.to_buf().unwrap();
+
+        let back = Msg::unpack(&buf).unwrap();
+        let ede: Vec<(u16, String)> = back.is_edns0().unwrap().ede().map(|e| (e.info_code, e.extra_text.clone())).collect();
+        assert_eq!(ede, vec![(15, "listed by policy".to_string()), (3, String::new())]);
+        let text = back.to_string();
+        assert!(text.contains("; EDE: 15 (Blocked): (listed by policy)"), "{}", text);
+        assert!(text.contains("; EDE: 3 (Stale Answer)\n") || text.ends_with("; EDE: 3 (Stale Answer)"), "{}", text);
+
+        let ede = <edns0::EDE as edns0::IEdns0>::unpack(edns0::EDNS0EDE, b"\x01\x2cnew\x00").unwrap();
+        assert_eq!((ede.info_code, ede.extra_text.as_str(), ede.name()), (300, "new", None));
+        assert!(<edns0::EDE as edns0::IEdns0>::unpack(edns0::EDNS0EDE, b"\x00").is_err());
+    }
+
+    #[test]
+    pub fn test_set_edns0() {
+        let mut msg = Msg::new();
+        msg.set_question(full_domain("example.com"), types::TYPE_A);
+        assert!(msg.is_edns0().is_none());
+        msg.set_edns0(4096, true);
+        msg.edns_mut().set_version(1);
+        msg.edns_mut().set_option(EDNS0::Expire(edns0::Expire { expire: None }));
+        msg.set_edns0(1232, false);
+        assert_eq!(msg.additional.len(), 1);
+
+        let back = Msg::unpack(&msg.to_buf().unwrap()).unwrap();
+        let opt = back.is_edns0().unwrap();
+        assert_eq!((opt.udp_size(), opt.is_do(), opt.version()), (1232, false, 1));
+        assert!(opt.expire().is_some());
+
+        let opt = msg.edns_mut();
+        opt.set_do(&[true]);
+        opt.set_option(EDNS0::Expire(edns0::Expire { expire: Some(60) }));
+        assert_eq!(opt.option.len(), 1);
+        assert!(opt.is_do() && opt.version() == 1);
+        assert!(opt.remove_option(edns0::EDNS0EXPIRE));
+        assert!(!opt.remove_option(edns0::EDNS0EXPIRE));
+        assert!(msg.clear_edns0());
+        assert!(msg.is_edns0().is_none());
+    }
+
+    #[test]
+    pub fn test_is_answer_for() {
+        let mut query = Msg::new();
+        query.set_question(full_domain("www.example.com"), types::TYPE_A);
+        let mut response = Msg::new();
+        response.set_reply(&query);
+        response.question[0].name = full_domain("WWW.Example.COM");
+        let buf = response.to_buf().unwrap();
+        assert!(Msg::unpack(&buf).unwrap().is_answer_for(&query));
+        assert!(!query.is_answer_for(&query));
+
+        let mut other = response.clone();
+        other.hdr.id = other.hdr.id.wrapping_add(1);
+        assert!(!other.is_answer_for(&query));
+        let mut other = response.clone();
+        other.question[0].q_class = types::CLASS_CHAOS;
+        assert!(!other.is_answer_for(&query));
+        let mut other = response.clone();
+        other.question.clear();
+        assert!(!other.is_answer_for(&query));
+        other.hdr.response_code = types::RCODE_NOT_IMPLEMENTED;
+        assert!(other.is_answer_for(&query));
+    }
+
+    #[test]
+    pub fn test_ttls() {
+        let mut msg = Msg::new();
+        assert_eq!(msg.min_ttl(), None);
+        msg.answer.push(types::A::new(full_domain("example.com"), types::CLASS_INET, 300, Ipv4Addr::new(192, 0, 2, 1)).into());
+        msg.authority.push(types::NS::new(full_domain("example.com"), types::CLASS_INET, 86400, full_domain("ns.example.com")).into());
+        msg.additional.push(types::A::new(full_domain("ns.example.com"), types::CLASS_INET, 30, Ipv4Addr::new(192, 0, 2, 53)).into());
+        msg.set_edns0(1232, true);
+        assert_eq!(msg.min_ttl(), Some(30));
+
+        msg.decrement_ttls(60);
+        let ttls = |msg: &Msg| msg.answer.iter().chain(&msg.authority).chain(&msg.additional).map(|rr| rr.header().ttl).collect::<Vec<_>>();
+        assert_eq!(ttls(&msg)[..3], [240, 86340, 0]);
+        assert!(msg.is_edns0().unwrap().is_do());
+        msg.clamp_ttls(10, 3600);
+        assert_eq!(ttls(&msg)[..3], [240, 3600, 10]);
+        assert_eq!(msg.min_ttl(), Some(10));
+    }
+
+    #[test]
+    pub fn test_dedup() {
+        let a = |name: &str, ttl, last| -> types::RecourseRecord {
+            types::A::new(full_domain(name), types::CLASS_INET, ttl, Ipv4Addr::new(192, 0, 2, last)).into()
+        };
+        let mut msg = Msg::new();
+        msg.answer.push(a("www.example.com", 60, 1));
+        msg.answer.push(a("WWW.EXAMPLE.COM", 30, 1));
+        msg.answer.push(a("www.example.com", 60, 2));
+        msg.answer.push(a("www.example.com", 60, 1));
+        msg.authority.push(types::NS::new(full_domain("example.com"), types::CLASS_INET, 60, full_domain("ns.example.com")).into());
+        msg.authority.push(types::NS::new(full_domain("example.com"), types::CLASS_INET, 60, full_domain("ns.example.com")).into());
+        msg.additional.push(a("www.example.com", 60, 1));
+        msg.set_edns0(1232, false);
+
+        msg.dedup();
+        assert_eq!((msg.answer.len(), msg.authority.len(), msg.additional.len()), (2, 1, 2));
+        assert_eq!(msg.answer[0].header().ttl, 60);
+        assert!(msg.answer[1].to_string().ends_with("192.0.2.2"));
+        assert_eq!(msg.answer[0].rdata().unwrap(), [192, 0, 2, 1]);
+    }
+
+    #[test]
+    pub fn test_resolve_chain() {
+        let cname = |name: &str, target: &str| -> types::RecourseRecord {
+            types::CNAME::new(full_domain(name), types::CLASS_INET, 60, full_domain(target)).into()
+        };
+        let a = |name: &str, last| -> types::RecourseRecord {
+            types::A::new(full_domain(name), types::CLASS_INET, 60, Ipv4Addr::new(192, 0, 2, last)).into()
+        };
+        let mut msg = Msg::new();
+        msg.answer.push(a("cdn.example.net", 1));
+        msg.answer.push(cname("WWW.example.com", "web.example.org"));
+        msg.answer.push(types::DNAME::new(full_domain("example.org"), types::CLASS_INET, 60, full_domain("example.net")).into());
+        msg.answer.push(cname("web.example.NET", "cdn.example.net"));
+        msg.answer.push(a("CDN.example.net", 2));
+        msg.answer.push(a("www.example.com", 3));
+        msg.answer.push(types::AAAA::new(full_domain("cdn.example.net"), types::CLASS_INET, 60, "2001:db8::1".parse().unwrap()).into());
+
+        let chain = msg.resolve_chain("www.example.com.");
+        assert_eq!(chain.target, "cdn.example.net.");
+        assert_eq!(chain.records.len(), 3);
+        assert_eq!(chain.ips().len(), 3);
+        assert!(!chain.ips().contains(&Ipv4Addr::new(192, 0, 2, 3).into()));
+        assert_eq!(msg.resolve_chain("other.example.").target, "other.example.");
+        assert!(msg.resolve_chain("other.example.").records.is_empty());
+
+        let buf = msg.to_buf().unwrap();
+        let answer = Msg::unpack_answer(&buf).unwrap();
+        assert_eq!(answer.ips().len(), 4);
+        assert_eq!(answer.ips_for("www.example.com."), chain.ips());
+
+        // a loop ends
+        let mut msg = Msg::new();
+        msg.answer.push(cname("a.example", "b.example"));
+        msg.answer.push(cname("b.example", "a.example"));
+        assert!(msg.resolve_chain("a.example.").records.is_empty());
+    }
+
+    #[test]
+    pub fn test_ptr_name() {
+        use core::net::IpAddr;
+        use super::{parse_ptr_name, ptr_name};
+        let v4: IpAddr = "192.0.2.10".parse().unwrap();
+        let v6: IpAddr = "2001:db8::567:89ab".parse().unwrap();
+        assert_eq!(ptr_name(v4), "10.2.0.192.in-addr.arpa.");
+        assert_eq!(ptr_name(v6), "b.a.9.8.7.6.5.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.8.b.d.0.1.0.0.2.ip6.arpa.");
+        assert_eq!(parse_ptr_name(&ptr_name(v4)), Some(v4));
+        assert_eq!(parse_ptr_name(&ptr_name(v6).to_ascii_uppercase()), Some(v6));
+        assert_eq!(parse_ptr_name("10.2.0.192.IN-ADDR.ARPA"), Some(v4));
+
+        assert_eq!(parse_ptr_name("2.0.192.in-addr.arpa."), None);
+        assert_eq!(parse_ptr_name("1.10.2.0.192.in-addr.arpa."), None);
+        assert_eq!(parse_ptr_name("256.2.0.192.in-addr.arpa."), None);
+        assert_eq!(parse_ptr_name("+1.2.0.192.in-addr.arpa."), None);
+        assert_eq!(parse_ptr_name("8.b.d.0.1.0.0.2.ip6.arpa."), None);
+        assert_eq!(parse_ptr_name("ba.9.8.7.6.5.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.8.b.d.0.1.0.0.2.ip6.arpa."), None);
+        assert_eq!(parse_ptr_name("www.example.com."), None);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    pub fn test_eq() {
+        use std::collections::HashSet;
+        let mut msg = Msg::new();
+        msg.set_question(full_domain("www.example.com"), types::TYPE_A);
+        msg.answer.push(types::CNAME::new(full_domain("www.example.com"), types::CLASS_INET, 60, full_domain("web.example.com")).into());
+        msg.answer.push(types::A::new(full_domain("web.example.com"), types::CLASS_INET, 60, Ipv4Addr::new(192, 0, 2, 1)).into());
+        msg.authority.push(types::SOA::new(full_domain("example.com"), types::CLASS_INET, 60, full_domain("ns.example.com"), full_domain("admin.example.com"), 1, 2, 3, 4, 5).into());
+        msg.set_edns0(1232, true);
+        msg.edns_mut().option.push(EDNS0::Expire(edns0::Expire { expire: Some(60) }));
+
+        let back = Msg::unpack(&msg.to_buf().unwrap()).unwrap();
+        assert_eq!(back, msg);
+        let mut upper = back.clone();
+        upper.question[0].name = full_domain("WWW.example.com");
+        if let types::RecourseRecord::CNAME(val) = &mut upper.answer[0] {
+            val.target = full_domain("Web.Example.com");
+        }
+        assert_eq!(upper, msg);
+        let set: HashSet<_> = [msg.clone(), upper, back].into_iter().collect();
+        assert_eq!(set.len(), 1);
+
+        let mut other = msg.clone();
+        other.answer[1].header_mut().ttl = 30;
+        assert_ne!(other, msg);
+        let mut other = msg.clone();
+        other.edns_mut().option.clear();
+        assert_ne!(other, msg);
+        assert_ne!(msg.answer[0], msg.answer[1]);
+        let records: HashSet<_> = msg.answer.iter().chain(&msg.answer).collect();
+        assert_eq!(records.len(), 2);
+    }
+
+    #[test]
+    pub fn test_unpack_into() {
+        let mut response = Msg::new();
+        response.set_question(full_domain("www.example.com"), types::TYPE_A);
+        response.hdr.response = true;
+        for last in 1..=8 {
+            response.answer.push(types::A::new(full_domain("www.example.com"), types::CLASS_INET, 60, Ipv4Addr::new(192, 0, 2, last)).into());
+        }
+        response.set_edns0(1232, false);
+        let mut query = Msg::new();
+        query.set_question(full_domain("example.com"), types::TYPE_NS);
+
+        let mut msg = Msg::new();
+        msg.unpack_into(&response.to_buf().unwrap()).unwrap();
+        assert_eq!(msg, response);
+        let capacity = msg.answer.capacity();
+        msg.unpack_into(&query.to_buf().unwrap()).unwrap();
+        assert_eq!(msg, query);
+        assert_eq!(msg.answer.capacity(), capacity);
+        assert!(msg.unpack_into(&[0; 5]).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    pub fn test_serde() {
+        let mut msg = Msg::new();
+        msg.set_question(full_domain("example.com"), types::TYPE_DNSKEY);
+        msg.answer.push(types::DNSKEY::new(full_domain("example.com"), types::CLASS_INET, 3600, 257, 15, vec![1, 2, 3, 255]).into());
+        msg.answer.push(types::A::new(full_domain("example.com"), types::CLASS_INET, 60, Ipv4Addr::new(192, 0, 2, 1)).into());
+        msg.set_edns0(1232, true);
+        msg.edns_mut().add_ede(edns0::EDE_BLOCKED, "ads");
+
+        let json = serde_json::to_string(&msg).unwrap();
+        assert!(json.contains(r#""a":"192.0.2.1""#), "{}", json);
+        assert!(json.contains(r#""public_key":"AQID/w==""#), "{}", json);
+        assert!(json.contains(r#"{"code":15,"data":"AA9hZHM="}"#), "{}", json);
+        let back: Msg = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, msg);
+        assert_eq!(back.is_edns0().unwrap().ede().next().unwrap().extra_text, "ads");
+    }
+
+    #[test]
+    pub fn test_pack_with_limit() {
+        let mut request = Msg::new();
+        request.set_question(full_domain("example.com"), types::TYPE_A);
+        assert_eq!(request.udp_payload_size(), 512);
+        let mut msg = Msg::new();
+        msg.set_reply(&request);
+        for i in 0..40 {
+            msg.answer.push(types::A::new(full_domain("example.com"), types::CLASS_INET, 60, Ipv4Addr::new(192, 0, 2, i)).into());
+        }
+        msg.additional.push(types::Opt::new(1232).into());
+        msg.additional.push(types::A::new(full_domain("ns.example.com"), types::CLASS_INET, 60, Ipv4Addr::new(192, 0, 2, 53)).into());
+
+        let mut buf = BytesMut::new();
+        assert!(!msg.pack_with_limit(&mut buf, 1232).unwrap());
+        let full = buf.len();
+        assert_eq!(Msg::unpack(&buf).unwrap().additional.len(), 2);
+
+        // only the additional address has to go: no TC
+        let mut buf = BytesMut::new();
+        assert!(msg.pack_with_limit(&mut buf, full - 1).unwrap());
+        let back = Msg::unpack(&buf).unwrap();
+        assert!(!back.hdr.truncated);
+        assert_eq!((back.answer.len(), back.additional.len()), (40, 1));
+        assert!(back.is_edns0().is_some());
+
+        let mut buf = BytesMut::new();
+        assert!(msg.pack_with_limit(&mut buf, 512).unwrap());
+        assert!(buf.len() <= 512);
+        let back = Msg::unpack(&buf).unwrap();
+        assert!(back.hdr.truncated);
+        assert!(back.answer.len() < 40 && !back.answer.is_empty());
+        assert_eq!(back.additional.len(), 1);
+        assert_eq!(back.is_edns0().unwrap().udp_size(), 1232);
+        assert_eq!(back.question[0].name, "example.com.");
+
+        let mut buf = BytesMut::new();
+        assert!(msg.pack_with_limit(&mut buf, 12).is_err());
+        request.set_edns0(4096, false);
+        assert_eq!(request.udp_payload_size(), 4096);
+        request.set_edns0(100, false);
+        assert_eq!(request.udp_payload_size(), 512);
+    }
+
+    #[test]
+    pub fn test_padding() {
+        let mut msg = Msg::new();
+        msg.set_question(full_domain("www.example.com"), types::TYPE_AAAA);
+        assert!(msg.pad_to(edns0::PADDING_QUERY_BLOCK).is_err());
+        msg.additional.push(types::Opt::new(1232).into());
+        msg.pad_to(edns0::PADDING_QUERY_BLOCK).unwrap();
+        assert_eq!(msg.to_buf().unwrap().len(), 128);
+        // padding again replaces the option
+        msg.pad_to(edns0::PADDING_RESPONSE_BLOCK).unwrap();
+        let buf = msg.to_buf().unwrap();
+        assert_eq!(buf.len(), 468);
+        let back = Msg::unpack(&buf).unwrap();
+        let opt = back.is_edns0().unwrap();
+        assert_eq!(opt.option.len(), 1);
+        assert!(matches!(&opt.option[0], EDNS0::Padding(p) if p.length == 468 - 44 - 4));
+        assert!(back.to_string().contains("; PADDING: "));
+        msg.pad_to(0).unwrap();
+        assert!(msg.is_edns0().unwrap().option.is_empty());
+    }
+
+    #[test]
+    pub fn test_expire() {
+        let mut query = Msg::new();
+        query.set_question(full_domain("example.com"), types::TYPE_SOA);
+        let mut opt = types::Opt::new(1232);
+        opt.option.push(EDNS0::Expire(edns0::Expire::new(None)));
+        query.additional.push(opt.into());
+        let buf = query.to_buf().unwrap();
+        assert!(buf.ends_with(&[0, 9, 0, 0]));
+        let back = Msg::unpack(&buf).unwrap();
+        assert_eq!(back.is_edns0().unwrap().expire().unwrap().expire, None);
+
+        let mut response = Msg::new();
+        response.set_reply(&back);
+        let mut opt = types::Opt::new(1232);
+        opt.option.push(EDNS0::Expire(edns0::Expire::new(Some(1209600))));
+        response.additional.push(opt.into());
+        let back = Msg::unpack(&response.to_buf().unwrap()).unwrap();
+        assert_eq!(back.is_edns0().unwrap().expire().unwrap().expire, Some(1209600));
+        assert!(back.to_string().contains("; EXPIRE: 1209600"));
+        assert!(<edns0::Expire as edns0::IEdns0>::unpack(edns0::EDNS0EXPIRE, &[0, 1]).is_err());
+    }
+
+    #[test]
+    pub fn test_chain() {
+        let mut msg = Msg::new();
+        msg.set_question(full_domain("www.example.com"), types::TYPE_A);
+        let mut opt = types::Opt::new(1232);
+        opt.set_do(&[true]);
+        opt.option.push(EDNS0::Chain(edns0::Chain::new(full_domain("com"))));
+        msg.additional.push(opt.into());
+        let buf = msg.to_buf().unwrap();
+        assert!(buf.ends_with(&[0, 13, 0, 5, 3, b'c', b'o', b'm', 0]));
+        let back = Msg::unpack(&buf).unwrap();
+        assert_eq!(back.is_edns0().unwrap().chain().unwrap().trust_point.as_str(), "com.");
+        assert!(back.to_string().contains("; CHAIN: com."));
+
+        let unpack = |bs: &[u8]| <edns0::Chain as edns0::IEdns0>::unpack(edns0::EDNS0CHAIN, bs);
+        assert_eq!(unpack(&[0]).unwrap().trust_point.as_str(), ".");
+        assert!(unpack(&[3, b'c', b'o', b'm', 0, 0]).is_err());
+        assert!(unpack(&[1, b'a', 0xC0, 0]).is_err());
+        assert!(unpack(&[]).is_err());
+    }
+
+    #[test]
+    pub fn test_key_tag() {
+        let mut msg = Msg::new();
+        msg.set_question(full_domain("."), types::TYPE_DNSKEY);
+        let mut opt = types::Opt::new(1232);
+        opt.option.push(EDNS0::KeyTag(edns0::KeyTag::new(vec![20326, 19036])));
+        msg.additional.push(opt.into());
+        let buf = msg.to_buf().unwrap();
+        assert!(buf.ends_with(&[0, 14, 0, 4, 0x4f, 0x66, 0x4a, 0x5c]));
+        let back = Msg::unpack(&buf).unwrap();
+        let key_tag = back.is_edns0().unwrap().key_tag().unwrap();
+        assert_eq!(key_tag.tags, vec![20326, 19036]);
+        assert_eq!(key_tag.signal_label(), "_ta-4a5c-4f66");
+        assert!(back.to_string().contains("; KEY-TAG: 20326 19036"));
+
+        let unpack = |bs: &[u8]| <edns0::KeyTag as edns0::IEdns0>::unpack(edns0::EDNS0KEYTAG, bs);
+        assert!(unpack(&[]).is_err());
+        assert!(unpack(&[0x4f, 0x66, 0]).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
     pub fn test_unpack() {
         let data = [
             // 0x43, 0x1c, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00,
@@ -188,6 +720,124 @@ mod test {
 
         println!("msg: {}", msg);
         println!("msg2: {:?}", msg2);
-        println!("is_udp_dns: {:?}", is_dns(&data[..14]));
+        println!("is_udp_dns: {:?}", crate::is_dns(&data[..14]));
+    }
+
+    #[test]
+    pub fn test_dname() {
+        let mut data = vec![0x12, 0x34, 0x81, 0x80, 0, 1, 0, 1, 0, 0, 0, 0];
+        data.extend_from_slice(b"\x01a\x07example\x03com\x00\x00\x01\x00\x01");
+        // example.com DNAME net.example.com, the target compressed
+        data.extend_from_slice(&[0xc0, 14, 0, 39, 0, 1, 0, 0, 0, 60, 0, 6, 3, b'n', b'e', b't', 0xc0, 14]);
+        let msg = Msg::unpack(&data).unwrap();
+        let types::RecourseRecord::DNAME(dname) = &msg.answer[0] else { panic!("unexpected {}", msg.answer[0]) };
+        assert_eq!(dname.target.as_str(), "net.example.com.");
+        assert_eq!(dname.substitute("a.Example.com.").unwrap().as_str(), "a.net.example.com.");
+        assert_eq!(dname.substitute("x\\.y.b.example.com.").unwrap().as_str(), "x\\.y.b.net.example.com.");
+        assert!(dname.substitute("example.com.").is_none());
+        assert!(dname.substitute("example.org.").is_none());
+
+        // packed back without the pointer
+        let buf = msg.to_buf().unwrap();
+        assert!(buf.ends_with(b"\x00\x11\x03net\x07example\x03com\x00"), "{:?}", buf);
+        assert!(Msg::unpack(&buf).unwrap().answer[0].to_string().ends_with("DNAME\tnet.example.com."));
+    }
+
+    #[test]
+    pub fn test_punycode() {
+        use crate::{punycode_decode, punycode_encode};
+        for (label, ace) in [
+            ("bücher", "xn--bcher-kva"),
+            ("münchen", "xn--mnchen-3ya"),
+            ("3年B組金八先生", "xn--3B-ww4c5e180e575a65lsy2b"),
+            ("\u{0644}\u{064A}\u{0647}\u{0645}\u{0627}\u{0628}\u{062A}\u{0643}\u{0644}\u{0645}\u{0648}\u{0634}\u{0639}\u{0631}\u{0628}\u{064A}\u{061F}", "xn--egbpdaj6bu4bxfgehfvwxn"),
+            ("example", "example"),
+        ] {
+            assert_eq!(punycode_encode(label).unwrap(), ace);
+            assert_eq!(punycode_decode(ace).unwrap(), label);
+        }
+        assert_eq!(punycode_decode("XN--BCHER-KVA").unwrap(), "BüCHER");
+        assert!(punycode_decode("xn--bcher-kv!").is_none());
+        assert!(punycode_decode("xn--99999999999").is_none());
+    }
+
+    #[test]
+    pub fn test_rotate_answers() {
+        let mut msg = Msg::new();
+        msg.set_question(full_domain("www.example.com"), types::TYPE_A);
+        msg.answer.push(types::CNAME::new(full_domain("www.example.com"), types::CLASS_INET, 60, "cdn.example.net.".into()).into());
+        for last in 1..=3 {
+            msg.answer.push(types::A::new(full_domain("cdn.example.net"), types::CLASS_INET, 60, Ipv4Addr::new(192, 0, 2, last)).into());
+        }
+        msg.answer.insert(2, types::A::new(full_domain("other.example.net"), types::CLASS_INET, 60, Ipv4Addr::new(198, 51, 100, 1)).into());
+        let order = |msg: &Msg| msg.answer.iter().map(|rr| rr.to_string()).collect::<Vec<_>>();
+        let before = order(&msg);
+
+        msg.rotate_answers_by(1);
+        let after = order(&msg);
+        assert_eq!((&after[0], &after[2]), (&before[0], &before[2]));
+        assert_eq!((&after[1], &after[3], &after[4]), (&before[3], &before[4], &before[1]));
+        msg.rotate_answers_by(2);
+        assert_eq!(order(&msg), before);
+    }
+
+    #[test]
+    pub fn test_opt_wire() {
+        use crate::types::edns::IEdns0;
+        let mut msg = Msg::new();
+        msg.set_question(full_domain("www.example.com"), types::TYPE_A);
+        msg.authority.push(types::CNAME::new(full_domain("www.example.com"), types::CLASS_INET, 60, full_domain("web.example.com")).into());
+        let opt = types::Opt {
+            hdr: RecourseRecordHdr { name: ".".into(), typ: types::TYPE_OPT, class: 1232, ttl: 0, rd_length: 0 },
+            option: vec![EDNS0::SubNet(edns0::SubNet::new(Ipv4Addr::new(192, 0, 2, 1).into(), 24, 0))],
+        };
+        msg.additional.push(opt.into());
+        msg.additional.push(types::A::new(full_domain("web.example.com"), types::CLASS_INET, 60, Ipv4Addr::new(192, 0, 2, 1)).into());
+
+        let buf = msg.to_buf().unwrap();
+        // NSCOUNT, ARCOUNT
+        assert_eq!(&buf[8..12], &[0, 1, 0, 2]);
+        let back = Msg::unpack(&buf).unwrap();
+        assert_eq!((back.authority.len(), back.additional.len()), (1, 2));
+        let (types::RecourseRecord::Opt(opt), types::RecourseRecord::A(_)) = (&back.additional[0], &back.additional[1]) else {
+            panic!("unexpected {:?}", back.additional);
+        };
+        let [EDNS0::SubNet(subnet)] = &opt.option[..] else {
+            panic!("unexpected {:?}", opt.option);
+        };
+        assert_eq!((subnet.address, subnet.source_netmask), (Ipv4Addr::new(192, 0, 2, 0).into(), 24));
+
+        // the address has just the octets the prefix covers
+        assert!(edns0::SubNet::unpack(8, &[0, 1, 24, 0, 192, 0, 2]).is_ok());
+        assert!(edns0::SubNet::unpack(8, &[0, 1, 24, 0, 192, 0, 2, 0]).is_err());
+        assert!(edns0::SubNet::unpack(8, &[0, 1, 24, 0, 192, 0]).is_err());
+        assert!(edns0::SubNet::unpack(8, &[0, 2, 0, 0]).is_ok());
+    }
+
+    /// Needs nothing beyond `alloc`, so it also runs with
+    /// `--no-default-features`.
+    #[test]
+    pub fn test_pack_unpack() {
+        use core::net::Ipv6Addr;
+        let mut msg = Msg::new();
+        msg.set_question(full_domain("example.com"), types::TYPE_ANY);
+        msg.hdr.response = true;
+        let name = || full_domain("example.com");
+        msg.answer.push(types::A::new(name(), types::CLASS_INET, 60, Ipv4Addr::new(192, 0, 2, 1)).into());
+        msg.answer.push(types::AAAA::new(name(), types::CLASS_INET, 60, Ipv6Addr::LOCALHOST).into());
+        msg.answer.push(types::MX::new(name(), types::CLASS_INET, 60, 10, full_domain("mail.example.com")).into());
+        msg.answer.push(types::TXT::new(name(), types::CLASS_INET, 60, vec![b"v=spf1 -all".to_vec()]).into());
+        msg.answer.push(types::SRV::new(full_domain("_sip._udp.example.com"), types::CLASS_INET, 60, 1, 2, 5060, full_domain("sip.example.com")).into());
+        let alpn = types::SvcParam { key: types::svcb::SVC_KEY_ALPN, value: b"\x02h2".to_vec() };
+        msg.answer.push(types::SVCB::new_https(name(), types::CLASS_INET, 60, 1, ".".into(), vec![alpn]).into());
+        msg.authority.push(types::SOA::new(name(), types::CLASS_INET, 60, full_domain("ns.example.com"), full_domain("admin.example.com"), 1, 2, 3, 4, 5).into());
+        msg.set_edns0(1232, true);
+
+        let buf = msg.to_buf().unwrap();
+        assert_eq!(Msg::unpack(&buf).unwrap(), msg);
+        let mut compressed = BytesMut::new();
+        msg.pack_compressed(&mut CompressionContext::new(), &mut compressed).unwrap();
+        assert!(compressed.len() < buf.len());
+        assert_eq!(Msg::unpack(&compressed).unwrap(), msg);
     }
 }
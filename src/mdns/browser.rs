@@ -0,0 +1,303 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+use crate::msg::Question;
+use crate::{full_domain, types, DomainString, Msg, Result};
+use super::{parse_response, MdnsCache, MdnsRecord, RecordData};
+
+/// Browse queries start this far apart and back off to once an hour
+/// (RFC 6762 section 5.2).
+const FIRST_INTERVAL: Duration = Duration::from_secs(1);
+const MAX_INTERVAL: Duration = Duration::from_secs(3600);
+/// The same question about an instance is not asked again sooner.
+const REASK: Duration = Duration::from_secs(1);
+
+/// A service instance with everything needed to connect to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceInstance {
+    /// E.g. `Office Printer._ipp._tcp.local.`
+    pub name: DomainString,
+    pub target: DomainString,
+    pub port: u16,
+    /// The TXT strings, usually `key=value` pairs.
+    pub txt: Vec<Vec<u8>>,
+    /// Sorted.
+    pub addresses: Vec<IpAddr>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BrowseEvent {
+    /// An instance became known and resolved.
+    Resolved(ServiceInstance),
+    /// Its target, port, TXT strings or addresses changed.
+    Updated(ServiceInstance),
+    /// It went away or can no longer be resolved.
+    Removed(DomainString),
+}
+
+/// Continuous browsing for the instances of a service type, e.g.
+/// `_ipp._tcp.local.`, resolving each to its SRV, TXT and addresses.
+///
+/// Send what [`next_query`](Self::next_query) returns to
+/// [`MDNS_V4`](super::MDNS_V4) or [`MDNS_V6`](super::MDNS_V6) after creating
+/// the browser, after each call of the other methods and at
+/// [`next_timeout`](Self::next_timeout), calling [`poll`](Self::poll) then
+/// too.
+#[derive(Debug)]
+pub struct ServiceBrowser {
+    service: DomainString,
+    cache: MdnsCache,
+    /// Instances reported so far, by lowercase name.
+    reported: HashMap<DomainString, ServiceInstance>,
+    next_browse: Instant,
+    interval: Duration,
+    /// Questions about unresolved instances, with when they were last asked.
+    pending: Vec<(DomainString, u16, Option<Instant>)>,
+}
+
+impl ServiceBrowser {
+    pub fn new(service: &str, now: Instant) -> Self {
+        Self {
+            service: full_domain(service),
+            cache: MdnsCache::new(),
+            reported: HashMap::new(),
+            next_browse: now,
+            interval: FIRST_INTERVAL,
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn service(&self) -> &str {
+        &self.service
+    }
+
+    /// The instances resolved so far.
+    pub fn instances(&self) -> impl Iterator<Item = &ServiceInstance> {
+        self.reported.values()
+    }
+
+    /// Takes in a response read from the multicast socket, keeping the
+    /// records about this service.
+    pub fn handle_response(&mut self, msg: &[u8], now: Instant) -> Result<Vec<BrowseEvent>> {
+        let records = parse_response(msg)?;
+        // addresses last, as they are only kept for known SRV targets
+        let (addresses, others): (Vec<_>, Vec<_>) =
+            records.into_iter().partition(|r| matches!(r.data, RecordData::A(_) | RecordData::AAAA(_)));
+        for record in others.into_iter().chain(addresses) {
+            if self.is_wanted(&record) {
+                self.cache.insert(record, now);
+            }
+        }
+        Ok(self.update())
+    }
+
+    /// Drops expired records, reporting what that changes.
+    pub fn poll(&mut self, now: Instant) -> Vec<BrowseEvent> {
+        if self.cache.expire(now).is_empty() {
+            return Vec::new();
+        }
+        self.update()
+    }
+
+    /// The query to send now, if any: the browse query when due, with the
+    /// instances known as known answers, questions about instances not
+    /// resolved yet and refreshes of records near expiry.
+    pub fn next_query(&mut self, now: Instant) -> Option<Msg> {
+        let mut msg = Msg::new();
+        let ask = |msg: &mut Msg, name: &DomainString, q_type: u16| {
+            if !msg.question.iter().any(|q| q.q_type == q_type && q.name.eq_ignore_ascii_case(name)) {
+                msg.question.push(Question { name: name.clone(), q_type, q_class: types::CLASS_INET });
+            }
+        };
+        if now >= self.next_browse {
+            ask(&mut msg, &self.service, types::TYPE_PTR);
+            for known in self.cache.known_answers(&self.service, types::TYPE_PTR, now) {
+                if let Ok(rr) = known.to_record() {
+                    msg.answer.push(rr);
+                }
+            }
+            self.next_browse = now + self.interval;
+            self.interval = (self.interval * 2).min(MAX_INTERVAL);
+        }
+        for (name, typ, asked) in self.pending.iter_mut() {
+            if asked.is_none_or(|at| at + REASK <= now) {
+                *asked = Some(now);
+                ask(&mut msg, name, *typ);
+            }
+        }
+        for (name, typ) in self.cache.refresh_due(now, |_| true) {
+            ask(&mut msg, &name, typ);
+        }
+        (!msg.question.is_empty()).then_some(msg)
+    }
+
+    /// When to call [`poll`](Self::poll) and [`next_query`](Self::next_query)
+    /// next.
+    pub fn next_timeout(&self) -> Instant {
+        let reask = self.pending.iter().filter_map(|(_, _, asked)| asked.map(|at| at + REASK));
+        self.cache.next_timeout().into_iter().chain(reask).fold(self.next_browse, Instant::min)
+    }
+
+    /// Pointers to instances of the service, and the records of instances
+    /// and their targets.
+    fn is_wanted(&self, record: &MdnsRecord) -> bool {
+        match &record.data {
+            RecordData::Ptr(_) => record.name.eq_ignore_ascii_case(&self.service),
+            RecordData::Srv { .. } | RecordData::Txt(_) => self.is_instance(&record.name),
+            RecordData::A(_) | RecordData::AAAA(_) => self.instance_names().any(|name| {
+                self.cache.get(&name, types::TYPE_SRV).any(|srv| matches!(&srv.data, RecordData::Srv { target, .. } if target.eq_ignore_ascii_case(&record.name)))
+            }),
+            RecordData::Other(..) => false,
+        }
+    }
+
+    fn is_instance(&self, name: &str) -> bool {
+        let (name, service) = (name.to_ascii_lowercase(), self.service.to_ascii_lowercase());
+        name.len() > service.len() + 1 && name.ends_with(&service) && name.as_bytes()[name.len() - service.len() - 1] == b'.'
+    }
+
+    fn instance_names(&self) -> impl Iterator<Item = DomainString> + '_ {
+        self.cache.get(&self.service, types::TYPE_PTR).filter_map(|ptr| match &ptr.data {
+            RecordData::Ptr(name) if self.is_instance(name) => Some(name.clone()),
+            _ => None,
+        })
+    }
+
+    /// Resolves the instances again from the cache and reports the
+    /// differences from what was reported before.
+    fn update(&mut self) -> Vec<BrowseEvent> {
+        let mut resolved = HashMap::new();
+        let mut pending = Vec::new();
+        for name in self.instance_names().collect::<Vec<_>>() {
+            // the newest records win
+            let Some((target, port)) = self.cache.get(&name, types::TYPE_SRV).filter_map(|r| match &r.data {
+                RecordData::Srv { target, port, .. } => Some((target.clone(), *port)),
+                _ => None,
+            }).last() else {
+                pending.push((name.clone(), types::TYPE_SRV));
+                pending.push((name, types::TYPE_TXT));
+                continue;
+            };
+            let txt = self.cache.get(&name, types::TYPE_TXT).filter_map(|r| match &r.data {
+                RecordData::Txt(strings) => Some(strings.clone()),
+                _ => None,
+            }).last().unwrap_or_default();
+            let mut addresses: Vec<IpAddr> = self.cache.get(&target, types::TYPE_A).chain(self.cache.get(&target, types::TYPE_AAAA)).filter_map(|r| match r.data {
+                RecordData::A(ip) => Some(ip.into()),
+                RecordData::AAAA(ip) => Some(ip.into()),
+                _ => None,
+            }).collect();
+            if addresses.is_empty() {
+                pending.push((target.clone(), types::TYPE_A));
+                pending.push((target, types::TYPE_AAAA));
+                continue;
+            }
+            addresses.sort();
+            addresses.dedup();
+            resolved.insert(name.to_ascii_lowercase().into(), ServiceInstance { name, target, port, txt, addresses });
+        }
+        self.pending = pending
+            .into_iter()
+            .map(|(name, typ)| {
+                let asked = self.pending.iter().find(|(n, t, _)| *t == typ && n.eq_ignore_ascii_case(&name)).and_then(|p| p.2);
+                (name, typ, asked)
+            })
+            .collect();
+
+        let mut events = Vec::new();
+        for (key, instance) in &resolved {
+            match self.reported.get(key) {
+                None => events.push(BrowseEvent::Resolved(instance.clone())),
+                Some(old) if old != instance => events.push(BrowseEvent::Updated(instance.clone())),
+                _ => {}
+            }
+        }
+        for (key, old) in &self.reported {
+            if !resolved.contains_key(key) {
+                events.push(BrowseEvent::Removed(old.name.clone()));
+            }
+        }
+        self.reported = resolved;
+        events
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::time::{Duration, Instant};
+    use crate::msg::RecourseRecordHdr;
+    use crate::types::{RecourseRecord, RFC3597};
+    use crate::{full_domain, types, Msg};
+    use super::{BrowseEvent, ServiceBrowser, ServiceInstance};
+
+    fn rr(name: &str, typ: u16, flush: bool, ttl: u32, data: &str) -> RecourseRecord {
+        let class = types::CLASS_INET | if flush { 0x8000 } else { 0 };
+        RFC3597 { hdr: RecourseRecordHdr { name: full_domain(name), typ, class, ttl, rd_length: 0 }, data: data.into() }.into()
+    }
+
+    fn response(records: Vec<RecourseRecord>) -> Vec<u8> {
+        let mut msg = Msg::new();
+        msg.hdr.response = true;
+        msg.hdr.authoritative = true;
+        msg.answer = records;
+        msg.to_buf().unwrap().to_vec()
+    }
+
+    #[test]
+    fn test_service_browser() {
+        let start = Instant::now();
+        let secs = |s: f64| start + Duration::from_secs_f64(s);
+        let mut browser = ServiceBrowser::new("_ipp._tcp.local", start);
+        let query = browser.next_query(start).unwrap();
+        assert_eq!((query.question[0].name.as_str(), query.question[0].q_type), ("_ipp._tcp.local.", types::TYPE_PTR));
+        assert!(browser.next_query(start).is_none());
+
+        // PTR data compressed against the question: "Printer" and a pointer to offset 12
+        let mut msg = Msg::new();
+        msg.hdr.response = true;
+        msg.question.push(query.question[0].clone());
+        msg.answer.push(rr("_ipp._tcp.local", types::TYPE_PTR, false, 4500, "075072696e746572c00c"));
+        msg.answer.push(rr("_http._tcp.local", types::TYPE_PTR, false, 4500, "075072696e746572c00c"));
+        assert!(browser.handle_response(&msg.to_buf().unwrap(), secs(0.1)).unwrap().is_empty());
+        let query = browser.next_query(secs(0.1)).unwrap();
+        let asked: Vec<_> = query.question.iter().map(|q| (q.name.as_str(), q.q_type)).collect();
+        assert_eq!(asked, vec![("Printer._ipp._tcp.local.", types::TYPE_SRV), ("Printer._ipp._tcp.local.", types::TYPE_TXT)]);
+
+        // SRV 0 0 631 printer.local., TXT rp=ipp/print
+        let events = browser.handle_response(&response(vec![
+            rr("Printer._ipp._tcp.local", types::TYPE_SRV, true, 120, "000000000277077072696e746572056c6f63616c00"),
+            rr("Printer._ipp._tcp.local", types::TYPE_TXT, true, 4500, "0c72703d6970702f7072696e74"),
+            RecourseRecord::new_ip(full_domain("printer.local"), types::CLASS_INET | 0x8000, 120, Ipv4Addr::new(192, 168, 1, 5).into()),
+            RecourseRecord::new_ip(full_domain("other.local"), types::CLASS_INET, 120, Ipv4Addr::new(192, 168, 1, 9).into()),
+        ]), secs(0.2)).unwrap();
+        let mut instance = ServiceInstance {
+            name: full_domain("Printer._ipp._tcp.local"),
+            target: full_domain("printer.local"),
+            port: 631,
+            txt: vec![b"rp=ipp/print".to_vec()],
+            addresses: vec![IpAddr::from(Ipv4Addr::new(192, 168, 1, 5))],
+        };
+        assert_eq!(events, vec![BrowseEvent::Resolved(instance.clone())]);
+        assert!(browser.next_timeout() <= secs(1.0));
+
+        // the next browse query lists the instance as a known answer
+        let query = browser.next_query(secs(1.0)).unwrap();
+        assert_eq!((query.question.len(), query.answer.len()), (1, 1));
+
+        // TXT rp=ipp/color
+        let events = browser.handle_response(&response(vec![rr("Printer._ipp._tcp.local", types::TYPE_TXT, true, 4500, "0c72703d6970702f636f6c6f72")]), secs(3.0)).unwrap();
+        instance.txt = vec![b"rp=ipp/color".to_vec()];
+        assert_eq!(events, vec![BrowseEvent::Updated(instance.clone())]);
+        assert!(browser.poll(secs(4.0)).is_empty());
+
+        // the SRV is queried again at 80% of its TTL
+        let query = browser.next_query(secs(0.2 + 120.0 * 0.82)).unwrap();
+        assert!(query.question.iter().any(|q| q.q_type == types::TYPE_SRV));
+
+        // goodbye
+        assert!(browser.handle_response(&response(vec![rr("_ipp._tcp.local", types::TYPE_PTR, false, 0, "075072696e746572045f697070045f746370056c6f63616c00")]), secs(100.0)).unwrap().is_empty());
+        assert_eq!(browser.poll(secs(101.0)), vec![BrowseEvent::Removed(instance.name)]);
+        assert_eq!(browser.instances().count(), 0);
+    }
+}
@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use rand::Rng;
+use crate::DomainString;
+use super::MdnsRecord;
+
+/// Percentages of the TTL at which a record still of interest is queried
+/// again (RFC 6762 section 5.2), each plus up to 2% at random.
+const REFRESH_AT: [u32; 4] = [80, 85, 90, 95];
+/// Records flushed or said goodbye to stay this long (RFC 6762 section 10).
+const GRACE: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone)]
+struct Cached {
+    record: MdnsRecord,
+    received: Instant,
+    expires: Instant,
+    /// Next entry of [`REFRESH_AT`] to query at, past the end when done.
+    refresh: usize,
+    /// Extra share of the TTL added to each refresh time, 0 to 0.02.
+    jitter: f64,
+}
+
+impl Cached {
+    fn refresh_at(&self) -> Option<Instant> {
+        let percent = *REFRESH_AT.get(self.refresh)?;
+        let share = percent as f64 / 100.0 + self.jitter;
+        Some(self.received + Duration::from_secs(self.record.ttl as u64).mul_f64(share))
+    }
+}
+
+/// Records learned from mDNS responses, expired by their TTLs.
+#[derive(Debug, Default)]
+pub struct MdnsCache {
+    /// By lowercase name and type.
+    records: HashMap<(DomainString, u16), Vec<Cached>>,
+}
+
+impl MdnsCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of records, including ones about to expire.
+    pub fn len(&self) -> usize {
+        self.records.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Takes in a record of a response. A cache-flush record outdates the
+    /// others of its name and type received over a second ago, and a
+    /// goodbye (TTL 0) the same record; both go after a second. Returns
+    /// whether the record is new.
+    pub fn insert(&mut self, record: MdnsRecord, now: Instant) -> bool {
+        let key = (key_name(&record.name), record.data.typ());
+        let cached = self.records.entry(key).or_default();
+        if record.cache_flush {
+            for other in cached.iter_mut() {
+                if other.record.data != record.data && other.received + GRACE <= now {
+                    other.expires = other.expires.min(now + GRACE);
+                    other.refresh = REFRESH_AT.len();
+                }
+            }
+        }
+        if let Some(same) = cached.iter_mut().find(|c| c.record.class == record.class && c.record.data == record.data) {
+            if record.ttl == 0 {
+                same.expires = same.expires.min(now + GRACE);
+                same.refresh = REFRESH_AT.len();
+            } else {
+                *same = new_entry(record, now);
+            }
+            return false;
+        }
+        if record.ttl == 0 {
+            return false;
+        }
+        cached.push(new_entry(record, now));
+        true
+    }
+
+    /// The records of `name` and `typ`.
+    pub fn get(&self, name: &str, typ: u16) -> impl Iterator<Item = &MdnsRecord> {
+        self.records.get(&(key_name(name), typ)).into_iter().flatten().map(|c| &c.record)
+    }
+
+    /// Records of `name` and `typ` with over half their TTL left, which
+    /// queries list so responders do not send them again (RFC 6762
+    /// section 7.1). The TTLs are the remaining ones.
+    pub fn known_answers(&self, name: &str, typ: u16, now: Instant) -> Vec<MdnsRecord> {
+        self.records
+            .get(&(key_name(name), typ))
+            .into_iter()
+            .flatten()
+            .filter_map(|c| {
+                let left = c.expires.saturating_duration_since(now).as_secs() as u32;
+                (left * 2 > c.record.ttl).then(|| MdnsRecord { ttl: left, ..c.record.clone() })
+            })
+            .collect()
+    }
+
+    /// Drops expired records and returns them.
+    pub fn expire(&mut self, now: Instant) -> Vec<MdnsRecord> {
+        let mut expired = Vec::new();
+        self.records.retain(|_, cached| {
+            cached.retain(|c| {
+                if c.expires <= now {
+                    expired.push(c.record.clone());
+                    return false;
+                }
+                true
+            });
+            !cached.is_empty()
+        });
+        expired
+    }
+
+    /// The names and types to query again as records of them near expiry,
+    /// limited to those `interested` says are still wanted.
+    pub fn refresh_due<F: Fn(&MdnsRecord) -> bool>(&mut self, now: Instant, interested: F) -> Vec<(DomainString, u16)> {
+        let mut due = Vec::new();
+        for ((_, typ), cached) in self.records.iter_mut() {
+            for c in cached.iter_mut() {
+                let mut hit = false;
+                while c.refresh_at().is_some_and(|at| at <= now) {
+                    c.refresh += 1;
+                    hit = true;
+                }
+                if hit && interested(&c.record) && !due.iter().any(|(n, t): &(DomainString, u16)| t == typ && n.eq_ignore_ascii_case(&c.record.name)) {
+                    due.push((c.record.name.clone(), *typ));
+                }
+            }
+        }
+        due
+    }
+
+    /// When a record next expires or is due for a refresh.
+    pub fn next_timeout(&self) -> Option<Instant> {
+        self.records.values().flatten().map(|c| c.refresh_at().map_or(c.expires, |at| at.min(c.expires))).min()
+    }
+}
+
+fn new_entry(record: MdnsRecord, now: Instant) -> Cached {
+    Cached {
+        expires: now + Duration::from_secs(record.ttl as u64),
+        received: now,
+        record,
+        refresh: 0,
+        jitter: rand::thread_rng().gen_range(0.0..0.02),
+    }
+}
+
+fn key_name(name: &str) -> DomainString {
+    let mut name = DomainString::from(name);
+    name.make_ascii_lowercase();
+    name
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::Ipv4Addr;
+    use std::time::{Duration, Instant};
+    use crate::mdns::{MdnsRecord, RecordData};
+    use crate::{full_domain, types};
+    use super::MdnsCache;
+
+    fn a(last: u8, ttl: u32, cache_flush: bool) -> MdnsRecord {
+        MdnsRecord {
+            name: full_domain("printer.local"),
+            class: types::CLASS_INET,
+            cache_flush,
+            ttl,
+            data: RecordData::A(Ipv4Addr::new(192, 168, 1, last)),
+        }
+    }
+
+    #[test]
+    fn test_mdns_cache() {
+        let start = Instant::now();
+        let secs = |s: f64| start + Duration::from_secs_f64(s);
+        let mut cache = MdnsCache::new();
+        assert!(cache.insert(a(1, 100, false), start));
+        assert!(!cache.insert(a(1, 100, false), start));
+        assert_eq!(cache.known_answers("Printer.local.", types::TYPE_A, secs(40.0)).len(), 1);
+        assert!(cache.known_answers("printer.local.", types::TYPE_A, secs(60.0)).is_empty());
+
+        // queried again at 80 to 82, 85 to 87, ... percent, once each
+        assert!(cache.refresh_due(secs(79.9), |_| true).is_empty());
+        assert_eq!(cache.refresh_due(secs(82.0), |_| true).len(), 1);
+        assert!(cache.refresh_due(secs(84.9), |_| true).is_empty());
+        assert!(cache.next_timeout().unwrap() <= secs(87.0));
+        assert!(cache.refresh_due(secs(99.0), |_| false).is_empty());
+        assert_eq!(cache.next_timeout(), Some(secs(100.0)));
+        assert!(cache.expire(secs(99.0)).is_empty());
+        assert_eq!(cache.expire(secs(100.0)), vec![a(1, 100, false)]);
+        assert!(cache.is_empty());
+
+        // a cache-flush record outdates older records, but not ones from the same second
+        cache.insert(a(1, 120, false), start);
+        cache.insert(a(2, 120, false), secs(9.5));
+        cache.insert(a(3, 120, true), secs(10.0));
+        assert_eq!(cache.expire(secs(11.0)), vec![a(1, 120, false)]);
+        assert_eq!(cache.len(), 2);
+
+        // goodbye
+        assert!(!cache.insert(a(2, 0, false), secs(20.0)));
+        assert!(!cache.insert(a(9, 0, false), secs(20.0)));
+        assert_eq!(cache.get("printer.local.", types::TYPE_A).count(), 2);
+        assert_eq!(cache.expire(secs(21.0)), vec![a(2, 120, false)]);
+        assert_eq!(cache.get("printer.local.", types::TYPE_A).collect::<Vec<_>>(), vec![&a(3, 120, true)]);
+    }
+}
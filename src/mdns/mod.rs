@@ -0,0 +1,151 @@
+//! Multicast DNS (RFC 6762) and DNS-based service discovery (RFC 6763),
+//! querier side. The types here do no I/O: feed them the responses read
+//! from the multicast socket and send the queries they produce, calling
+//! back at [`ServiceBrowser::next_timeout`].
+
+mod browser;
+mod cache;
+
+use std::io::Cursor;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+use byteorder::{BigEndian, ReadBytesExt};
+use bytes::BytesMut;
+use crate::msg::{PktMsgHeader, Question, RecourseRecordHdr};
+use crate::types::{RecourseRecord, RFC3597};
+use crate::{types, util, DomainString, Error, Result};
+pub use browser::{BrowseEvent, ServiceBrowser, ServiceInstance};
+pub use cache::MdnsCache;
+
+pub const MDNS_PORT: u16 = 5353;
+pub const MDNS_V4: SocketAddr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(224, 0, 0, 251), MDNS_PORT));
+pub const MDNS_V6: SocketAddr = SocketAddr::V6(SocketAddrV6::new(Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 0xfb), MDNS_PORT, 0, 0));
+
+/// Top bit of the class of a record: other records of its name and type
+/// are outdated (RFC 6762 section 10.2).
+const CACHE_FLUSH: u16 = 0x8000;
+
+/// The data of the record types service discovery reads.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordData {
+    A(Ipv4Addr),
+    AAAA(Ipv6Addr),
+    Ptr(DomainString),
+    Srv { priority: u16, weight: u16, port: u16, target: DomainString },
+    /// The character strings, usually `key=value` pairs.
+    Txt(Vec<Vec<u8>>),
+    Other(u16, Vec<u8>),
+}
+
+impl RecordData {
+    pub fn typ(&self) -> u16 {
+        match self {
+            RecordData::A(_) => types::TYPE_A,
+            RecordData::AAAA(_) => types::TYPE_AAAA,
+            RecordData::Ptr(_) => types::TYPE_PTR,
+            RecordData::Srv { .. } => types::TYPE_SRV,
+            RecordData::Txt(_) => types::TYPE_TXT,
+            RecordData::Other(typ, _) => *typ,
+        }
+    }
+}
+
+/// A record of an mDNS response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MdnsRecord {
+    pub name: DomainString,
+    /// The class without the cache-flush bit.
+    pub class: u16,
+    pub cache_flush: bool,
+    /// Zero in goodbye announcements of records going away.
+    pub ttl: u32,
+    pub data: RecordData,
+}
+
+impl MdnsRecord {
+    /// Packed the unicast DNS way, for known answers in queries.
+    fn to_record(&self) -> Result<RecourseRecord> {
+        let mut data = BytesMut::new();
+        match &self.data {
+            RecordData::A(ip) => return Ok(RecourseRecord::new_ip(self.name.clone(), self.class, self.ttl, (*ip).into())),
+            RecordData::AAAA(ip) => return Ok(RecourseRecord::new_ip(self.name.clone(), self.class, self.ttl, (*ip).into())),
+            RecordData::Ptr(name) => util::pack_domain_name(name, &mut data)?,
+            RecordData::Srv { priority, weight, port, target } => {
+                data.extend_from_slice(&priority.to_be_bytes());
+                data.extend_from_slice(&weight.to_be_bytes());
+                data.extend_from_slice(&port.to_be_bytes());
+                util::pack_domain_name(target, &mut data)?;
+            }
+            RecordData::Txt(strings) => {
+                for s in strings {
+                    data.extend_from_slice(&[s.len() as u8]);
+                    data.extend_from_slice(s);
+                }
+            }
+            RecordData::Other(_, raw) => data.extend_from_slice(raw),
+        }
+        let hdr = RecourseRecordHdr { name: self.name.clone(), typ: self.data.typ(), class: self.class, ttl: self.ttl, rd_length: 0 };
+        Ok(RFC3597 { hdr, data: hex::encode(data) }.into())
+    }
+}
+
+/// The answer, authority and additional records of an mDNS response.
+///
+/// Read off the wire rather than through [`Msg`](crate::Msg), as responders
+/// compress the names inside PTR and SRV data, which unknown types keep as
+/// raw bytes.
+pub fn parse_response(msg: &[u8]) -> Result<Vec<MdnsRecord>> {
+    let mut cur = Cursor::new(msg);
+    let hdr = PktMsgHeader::unpack(&mut cur)?;
+    if hdr.bits & 0x8000 == 0 {
+        return Err(Error::new("not a response"));
+    }
+    for _ in 0..hdr.question_count {
+        Question::unpack(&mut cur)?;
+    }
+    let count = hdr.answer_count as usize + hdr.authority_count as usize + hdr.additional_count as usize;
+    let mut records = Vec::with_capacity(count);
+    for _ in 0..count {
+        let name = util::unpack_domain_name_cur(&mut cur)?;
+        let typ = cur.read_u16::<BigEndian>()?;
+        let class = cur.read_u16::<BigEndian>()?;
+        let ttl = cur.read_u32::<BigEndian>()?;
+        let rd_length = cur.read_u16::<BigEndian>()? as usize;
+        let start = cur.position() as usize;
+        let end = start + rd_length;
+        let Some(raw) = msg.get(start..end) else { return Err(Error::InvalidRdLength); };
+        let data = match typ {
+            types::TYPE_A if rd_length == 4 => RecordData::A(Ipv4Addr::from(<[u8; 4]>::try_from(raw).unwrap())),
+            types::TYPE_AAAA if rd_length == 16 => RecordData::AAAA(Ipv6Addr::from(<[u8; 16]>::try_from(raw).unwrap())),
+            types::TYPE_PTR => RecordData::Ptr(util::unpack_domain_name_cur(&mut cur)?),
+            types::TYPE_SRV => RecordData::Srv {
+                priority: cur.read_u16::<BigEndian>()?,
+                weight: cur.read_u16::<BigEndian>()?,
+                port: cur.read_u16::<BigEndian>()?,
+                target: util::unpack_domain_name_cur(&mut cur)?,
+            },
+            types::TYPE_TXT => {
+                let mut strings = Vec::new();
+                let mut rest = raw;
+                while let Some((&len, tail)) = rest.split_first() {
+                    let Some(s) = tail.get(..len as usize) else { return Err(Error::InvalidRdLength); };
+                    if !s.is_empty() {
+                        strings.push(s.to_vec());
+                    }
+                    rest = &tail[len as usize..];
+                }
+                RecordData::Txt(strings)
+            }
+            types::TYPE_OPT => {
+                cur.set_position(end as u64);
+                continue;
+            }
+            _ => RecordData::Other(typ, raw.to_vec()),
+        };
+        if cur.position() as usize > end {
+            return Err(Error::InvalidRdLength);
+        }
+        cur.set_position(end as u64);
+        records.push(MdnsRecord { name, class: class & !CACHE_FLUSH, cache_flush: class & CACHE_FLUSH != 0, ttl, data });
+    }
+    Ok(records)
+}
@@ -0,0 +1,192 @@
+//! Counters for operators, rendered in the Prometheus text format.
+//!
+//! [`global`] is fed by `client::lookup_host`; servers and resolvers built on
+//! the crate record into it (or their own [`Metrics`]) the same way. Caches
+//! keep their own [`CacheStats`] and are passed in at scrape time, as are
+//! the per-upstream averages of a `client::Health`.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::time::Duration;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use crate::cache::CacheStats;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::client::UpstreamStats;
+use crate::util;
+
+/// Upper bounds of the latency buckets, in seconds.
+const LATENCY_BUCKETS: [f64; 11] = [0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5];
+
+type CacheCounter = fn(&CacheStats) -> u64;
+
+static GLOBAL: Lazy<Metrics> = Lazy::new(Metrics::new);
+
+/// Process-wide metrics.
+pub fn global() -> &'static Metrics {
+    &GLOBAL
+}
+
+#[derive(Debug, Default)]
+pub struct Metrics {
+    /// Completed queries by (type, rcode).
+    queries: Mutex<BTreeMap<(u16, u16), u64>>,
+    in_flight: AtomicI64,
+    upstream: Histogram,
+}
+
+#[derive(Debug, Default)]
+struct Histogram {
+    buckets: [AtomicU64; LATENCY_BUCKETS.len()],
+    count: AtomicU64,
+    sum_micros: AtomicU64,
+}
+
+/// Counts a query in flight until dropped, see [`Metrics::start`].
+#[derive(Debug)]
+pub struct InFlight<'a>(&'a Metrics);
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts a completed query of type `qtype` answered with `rcode`.
+    pub fn query(&self, qtype: u16, rcode: u16) {
+        *self.queries.lock().entry((qtype, rcode)).or_default() += 1;
+    }
+
+    pub fn start(&self) -> InFlight<'_> {
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+        InFlight(self)
+    }
+
+    /// Records the round trip of one upstream query.
+    pub fn upstream_latency(&self, elapsed: Duration) {
+        let h = &self.upstream;
+        let secs = elapsed.as_secs_f64();
+        for (bucket, le) in h.buckets.iter().zip(LATENCY_BUCKETS) {
+            if secs <= le {
+                bucket.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        h.count.fetch_add(1, Ordering::Relaxed);
+        h.sum_micros.fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    /// Text exposition of everything, with the given caches labelled by name.
+    pub fn render(&self, caches: &[(&str, &CacheStats)]) -> String {
+        let mut out = String::new();
+        header(&mut out, "dns_queries_total", "counter", "Queries completed, by type and response code.");
+        for ((qtype, rcode), n) in self.queries.lock().iter() {
+            let typ = util::type_to_string(*qtype).map(String::from).unwrap_or_else(|| format!("TYPE{}", qtype));
+            let _ = writeln!(out, "dns_queries_total{{type=\"{}\",rcode=\"{}\"}} {}", typ, util::rcode_string(*rcode), n);
+        }
+
+        header(&mut out, "dns_in_flight", "gauge", "Queries in flight.");
+        let _ = writeln!(out, "dns_in_flight {}", self.in_flight.load(Ordering::Relaxed));
+
+        let h = &self.upstream;
+        let name = "dns_upstream_duration_seconds";
+        header(&mut out, name, "histogram", "Round trip time of upstream queries.");
+        for (bucket, le) in h.buckets.iter().zip(LATENCY_BUCKETS) {
+            let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, le, bucket.load(Ordering::Relaxed));
+        }
+        let count = h.count.load(Ordering::Relaxed);
+        let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, count);
+        let _ = writeln!(out, "{}_sum {}", name, h.sum_micros.load(Ordering::Relaxed) as f64 / 1e6);
+        let _ = writeln!(out, "{}_count {}", name, count);
+
+        if caches.is_empty() {
+            return out;
+        }
+        let counters: [(&str, &str, CacheCounter); 4] = [
+            ("dns_cache_hits_total", "Cache lookups answered from fresh data.", CacheStats::hits),
+            ("dns_cache_misses_total", "Cache lookups that found nothing usable.", CacheStats::misses),
+            ("dns_cache_stale_hits_total", "Cache lookups answered from expired data.", CacheStats::stale_hits),
+            ("dns_cache_evictions_total", "Entries evicted to stay within limits.", CacheStats::evictions),
+        ];
+        for (name, help, get) in counters {
+            header(&mut out, name, "counter", help);
+            for (cache, stats) in caches {
+                let _ = writeln!(out, "{}{{cache=\"{}\"}} {}", name, cache, get(stats));
+            }
+        }
+        header(&mut out, "dns_cache_hit_ratio", "gauge", "Hits over all lookups.");
+        for (cache, stats) in caches {
+            let lookups = stats.hits() + stats.misses();
+            let ratio = if lookups == 0 { 0.0 } else { stats.hits() as f64 / lookups as f64 };
+            let _ = writeln!(out, "dns_cache_hit_ratio{{cache=\"{}\"}} {}", cache, ratio);
+        }
+        out
+    }
+
+    /// Gauges of each upstream's averages, from `Health::stats`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn render_upstreams(&self, upstreams: &[UpstreamStats]) -> String {
+        let mut out = String::new();
+        header(&mut out, "dns_upstream_rtt_seconds", "gauge", "Moving average of the round trip, per upstream.");
+        for u in upstreams {
+            if let Some(rtt) = u.rtt {
+                let _ = writeln!(out, "dns_upstream_rtt_seconds{{upstream=\"{}\"}} {}", u.addr, rtt.as_secs_f64());
+            }
+        }
+        header(&mut out, "dns_upstream_error_ratio", "gauge", "Moving average of failed queries, per upstream.");
+        for u in upstreams {
+            let _ = writeln!(out, "dns_upstream_error_ratio{{upstream=\"{}\"}} {}", u.addr, u.error_rate);
+        }
+        header(&mut out, "dns_upstream_up", "gauge", "Whether the upstream is in use, 0 while marked down.");
+        for u in upstreams {
+            let _ = writeln!(out, "dns_upstream_up{{upstream=\"{}\"}} {}", u.addr, u8::from(!u.down));
+        }
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, typ: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, typ);
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+    use crate::cache::CacheStats;
+    use crate::types;
+    use super::Metrics;
+
+    #[test]
+    fn test_render() {
+        let metrics = Metrics::new();
+        metrics.query(types::TYPE_A, 0);
+        metrics.query(types::TYPE_A, 0);
+        metrics.query(65280, 3);
+        let guard = metrics.start();
+        metrics.upstream_latency(Duration::from_millis(20));
+        let text = metrics.render(&[("msg", &CacheStats::default())]);
+        drop(guard);
+
+        assert!(text.contains("dns_queries_total{type=\"A\",rcode=\"NOERROR\"} 2\n"));
+        assert!(text.contains("dns_queries_total{type=\"TYPE65280\",rcode=\"NXDOMAIN\"} 1\n"));
+        assert!(text.contains("dns_in_flight 1\n"));
+        assert!(text.contains("dns_upstream_duration_seconds_bucket{le=\"0.01\"} 0\n"));
+        assert!(text.contains("dns_upstream_duration_seconds_bucket{le=\"0.025\"} 1\n"));
+        assert!(text.contains("dns_upstream_duration_seconds_count 1\n"));
+        assert!(text.contains("dns_cache_hit_ratio{cache=\"msg\"} 0\n"));
+        assert!(metrics.render(&[]).contains("dns_in_flight 0\n"));
+
+        let health = crate::client::Health::default();
+        let upstream = "192.0.2.53:53".parse().unwrap();
+        health.success(upstream, Duration::from_millis(25));
+        let text = metrics.render_upstreams(&health.stats());
+        assert!(text.contains("dns_upstream_rtt_seconds{upstream=\"192.0.2.53:53\"} 0.025\n"));
+        assert!(text.contains("dns_upstream_up{upstream=\"192.0.2.53:53\"} 1\n"));
+    }
+}
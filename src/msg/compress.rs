@@ -0,0 +1,128 @@
+use bytes::{BufMut, BytesMut};
+use crate::prelude::*;
+use crate::{util, Error, Result};
+
+#[cfg(feature = "std")]
+type Offsets = std::collections::HashMap<String, u16>;
+#[cfg(not(feature = "std"))]
+type Offsets = alloc::collections::BTreeMap<String, u16>;
+
+/// Pointers can only reach this far into a message.
+const MAX_POINTER_OFFSET: usize = 0x3FFF;
+const POINTER: u16 = 0xC000;
+const MAX_LABEL_LEN: usize = 63;
+
+/// Where each name written to a message so far starts (RFC 1035 section
+/// 4.1.4), for [`Msg::pack_compressed`](super::Msg::pack_compressed).
+///
+/// Keep one per worker: packing a message clears it but keeps the
+/// allocations, so a busy server does not rebuild the map per response.
+#[derive(Debug, Default, Clone)]
+pub struct CompressionContext {
+    /// Lowercase name suffixes to their offsets from the message start.
+    offsets: Offsets,
+    /// Where the message starts in the buffer.
+    base: usize,
+    lower: String,
+}
+
+impl CompressionContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forgets every name, for a message starting at `base` in the buffer.
+    pub fn reset(&mut self, base: usize) {
+        self.offsets.clear();
+        self.base = base;
+    }
+
+    /// Number of name suffixes that later names can point to.
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// Writes `name`, pointing to the longest suffix written before, and
+    /// remembers the suffixes it writes out.
+    pub fn pack_name(&mut self, name: &str, buf: &mut BytesMut) -> Result<()> {
+        let ascii = util::name_to_ascii(name)?;
+        let name = ascii.strip_suffix('.').unwrap_or(&ascii);
+        if name.is_empty() {
+            buf.put_u8(0);
+            return Ok(());
+        }
+        if name.split('.').any(str::is_empty) {
+            return util::pack_domain_name(name, buf);
+        }
+        self.lower.clear();
+        self.lower.push_str(name);
+        self.lower.make_ascii_lowercase();
+
+        let mut start = 0;
+        loop {
+            let suffix = &self.lower[start..];
+            if let Some(offset) = self.offsets.get(suffix) {
+                buf.put_u16(POINTER | offset);
+                return Ok(());
+            }
+            let offset = buf.len() - self.base;
+            if offset <= MAX_POINTER_OFFSET {
+                self.offsets.insert(String::from(suffix), offset as u16);
+            }
+            let end = name[start..].find('.').map_or(name.len(), |i| start + i);
+            let label = &name[start..end];
+            if label.len() > MAX_LABEL_LEN {
+                return Err(Error::new(format!("label too long {:?}", label)));
+            }
+            buf.put_u8(label.len() as u8);
+            buf.put_slice(label.as_bytes());
+            if end == name.len() {
+                buf.put_u8(0);
+                return Ok(());
+            }
+            start = end + 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use core::net::Ipv4Addr;
+    use bytes::{BufMut, BytesMut};
+    use crate::prelude::*;
+    use crate::{full_domain, types, Msg};
+    use super::CompressionContext;
+
+    #[test]
+    fn test_compression() {
+        let mut msg = Msg::new();
+        msg.set_question(full_domain("www.Example.com"), types::TYPE_A);
+        msg.answer.push(types::CNAME::new(full_domain("www.example.com"), types::CLASS_INET, 60, "cdn.example.com.".into()).into());
+        for last in 1..=3 {
+            msg.answer.push(types::A::new(full_domain("cdn.example.com"), types::CLASS_INET, 60, Ipv4Addr::new(192, 0, 2, last)).into());
+        }
+        msg.authority.push(types::NS::new(full_domain("example.com"), types::CLASS_INET, 60, "ns1.example.com.".into()).into());
+        let plain = msg.to_buf().unwrap();
+
+        let mut ctx = CompressionContext::new();
+        let mut buf = BytesMut::new();
+        // a TCP length prefix before the message must not shift the pointers
+        buf.put_u16(0);
+        msg.pack_compressed(&mut ctx, &mut buf).unwrap();
+        assert!(buf.len() - 2 < plain.len() - 60, "{} {}", buf.len(), plain.len());
+        let back = Msg::unpack(&buf[2..]).unwrap();
+        let text = |m: &Msg| m.answer.iter().chain(&m.authority).map(|rr| rr.to_string().to_ascii_lowercase()).collect::<Vec<_>>();
+        assert_eq!(text(&back), text(&msg));
+        assert_eq!(back.question[0].name, "www.Example.com.");
+
+        // reuse starts over
+        let suffixes = ctx.len();
+        let mut again = BytesMut::new();
+        msg.pack_compressed(&mut ctx, &mut again).unwrap();
+        assert_eq!((&again[..], ctx.len()), (&buf[2..], suffixes));
+    }
+}
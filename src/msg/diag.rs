@@ -0,0 +1,198 @@
+//! Diagnostics for packets that do not unpack.
+
+use core::fmt;
+use core::fmt::{Display, Formatter, Write};
+use crate::io::Cursor;
+use crate::msg::{unpack_rr, MsgHdr, PktMsgHeader, Question};
+use crate::prelude::*;
+use crate::{util, Error};
+
+/// Bytes of context kept in a [`Malformed`] error.
+const EXCERPT_LEN: usize = 16;
+/// Bytes dumped by `Msg::explain` where parsing stopped.
+const EXPLAIN_DUMP_LEN: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Section {
+    Header,
+    Question,
+    Answer,
+    Authority,
+    Additional,
+}
+
+impl Display for Section {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Section::Header => "header",
+            Section::Question => "question",
+            Section::Answer => "answer",
+            Section::Authority => "authority",
+            Section::Additional => "additional",
+        })
+    }
+}
+
+/// Where `Msg::unpack` gave up. The alternate form (`{:#}`) adds a hexdump
+/// of the bytes at `offset`.
+#[derive(Debug)]
+pub struct Malformed {
+    /// Offset of the question or record that failed, 0 for the header.
+    pub offset: usize,
+    pub section: Section,
+    /// Index of the question or record within its section.
+    pub index: usize,
+    pub error: Error,
+    /// The bytes from `offset` on, at most 16.
+    pub excerpt: Vec<u8>,
+}
+
+impl Display for Malformed {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.section {
+            Section::Header => write!(f, "header")?,
+            section => write!(f, "{} {}", section, self.index)?,
+        }
+        write!(f, " at offset {}: {:?}", self.offset, self.error)?;
+        if f.alternate() {
+            f.write_char('\n')?;
+            hexdump(f, &self.excerpt, self.offset)?;
+        }
+        Ok(())
+    }
+}
+
+pub(super) fn malformed(data: &[u8], offset: usize, section: Section, index: usize, error: Error) -> Error {
+    let excerpt = data.get(offset..).unwrap_or_default();
+    Error::Malformed(Box::new(Malformed {
+        offset,
+        section,
+        index,
+        error,
+        excerpt: excerpt[..excerpt.len().min(EXCERPT_LEN)].to_vec(),
+    }))
+}
+
+/// Annotated dump of `data`, one line per header, question and record, up to
+/// the first thing that does not parse.
+pub(super) fn explain(data: &[u8]) -> String {
+    let mut out = String::new();
+    let _ = explain_to(&mut out, data);
+    out
+}
+
+fn explain_to(out: &mut String, data: &[u8]) -> fmt::Result {
+    let mut cur = Cursor::new(data);
+    let hdr = match PktMsgHeader::unpack(&mut cur) {
+        Ok(hdr) => hdr,
+        Err(err) => return stop(out, data, 0, "header", err),
+    };
+    let flags = MsgHdr::from(hdr);
+    writeln!(
+        out,
+        "{:04x}  header: id {}, opcode {}, rcode {}, flags {:#06x}, {} questions, {} answers, {} authority, {} additional",
+        0,
+        hdr.id,
+        util::opcode_string(flags.op_code),
+        util::rcode_string(flags.response_code),
+        hdr.bits,
+        hdr.question_count,
+        hdr.answer_count,
+        hdr.authority_count,
+        hdr.additional_count
+    )?;
+
+    for index in 0..hdr.question_count {
+        let start = cur.position() as usize;
+        match Question::unpack(&mut cur) {
+            Ok(q) => writeln!(out, "{:04x}  question {}: {}", start, index, q)?,
+            Err(err) => return stop(out, data, start, &format!("question {}", index), err),
+        }
+    }
+
+    let sections = [
+        (Section::Answer, hdr.answer_count),
+        (Section::Authority, hdr.authority_count),
+        (Section::Additional, hdr.additional_count),
+    ];
+    for (section, count) in sections {
+        for index in 0..count {
+            let start = cur.position() as usize;
+            match unpack_rr(&mut cur) {
+                Ok(rr) => writeln!(out, "{:04x}  {} {}: {}", start, section, index, rr)?,
+                Err(err) => return stop(out, data, start, &format!("{} {}", section, index), err),
+            }
+        }
+    }
+
+    let end = cur.position() as usize;
+    if end < data.len() {
+        writeln!(out, "{:04x}  {} trailing bytes", end, data.len() - end)?;
+    }
+    Ok(())
+}
+
+fn stop(out: &mut String, data: &[u8], offset: usize, what: &str, err: Error) -> fmt::Result {
+    writeln!(out, "{:04x}  {}: {:?}", offset, what, err)?;
+    let rest = data.get(offset..).unwrap_or_default();
+    hexdump(out, &rest[..rest.len().min(EXPLAIN_DUMP_LEN)], offset)
+}
+
+/// `offset  hex bytes  |ascii|` lines of 16 bytes.
+fn hexdump<W: Write>(out: &mut W, data: &[u8], offset: usize) -> fmt::Result {
+    for (i, chunk) in data.chunks(16).enumerate() {
+        write!(out, "{:04x} ", offset + i * 16)?;
+        for b in chunk {
+            write!(out, " {:02x}", b)?;
+        }
+        for _ in chunk.len()..16 {
+            out.write_str("   ")?;
+        }
+        out.write_str("  |")?;
+        for b in chunk {
+            out.write_char(if b.is_ascii_graphic() { *b as char } else { '.' })?;
+        }
+        out.write_str("|\n")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::msg::{Msg, Section};
+    use crate::prelude::*;
+    use crate::types::{self, RecourseRecord};
+    use crate::{full_domain, Error};
+
+    #[test]
+    fn test_malformed() {
+        let mut msg = Msg::new();
+        msg.set_question(full_domain("example.com"), types::TYPE_A);
+        let a = types::A::new(full_domain("example.com"), types::CLASS_INET, 60, [192, 0, 2, 1].into());
+        msg.answer.push(RecourseRecord::from(a.clone()));
+        msg.answer.push(RecourseRecord::from(a));
+        let wire = msg.to_buf().unwrap();
+        let second = wire.len() - 27;
+
+        let text = Msg::explain(&wire);
+        assert!(text.contains("000c  question 0: ;example.com.\tIN\t A\n"), "{}", text);
+        assert!(text.contains("answer 1: example.com."), "{}", text);
+
+        let cut = &wire[..wire.len() - 2];
+        match Msg::unpack(cut) {
+            Err(Error::Malformed(m)) => {
+                assert_eq!((m.section, m.index, m.offset), (Section::Answer, 1, second));
+                assert_eq!(m.excerpt, &cut[second..second + 16]);
+                assert!(format!("{}", m).starts_with("answer 1 at offset"));
+                assert!(format!("{:#}", m).contains("|.example.com"), "{:#}", m);
+            }
+            other => panic!("{:?}", other),
+        }
+        let text = Msg::explain(cut);
+        assert!(text.contains(&format!("{:04x}  answer 1: ", second)), "{}", text);
+        assert!(!text.contains("trailing"));
+
+        let err = Msg::unpack(&wire[..5]).unwrap_err();
+        assert!(matches!(err, Error::Malformed(m) if m.section == Section::Header));
+    }
+}
@@ -0,0 +1,350 @@
+//! Messages read back from their dig-like text, as [`Msg`] displays them
+//! and as dig prints them.
+
+use std::net::IpAddr;
+use crate::types::edns::edns0::{self, EDNS0};
+use crate::types::Opt;
+use crate::msg::Question;
+use crate::{full_domain, util, Error, Msg, Result};
+use crate::zone::Parser;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Part {
+    Preamble,
+    Opt,
+    Question,
+    Answer,
+    Authority,
+    Additional,
+}
+
+/// Record lines of one section, kept at their line numbers so parse
+/// errors point into the whole text.
+struct Chunk {
+    part: Part,
+    text: String,
+}
+
+/// Reads `s` for [`Msg::from_str`](core::str::FromStr::from_str).
+pub(super) fn parse(s: &str) -> Result<Msg> {
+    let mut msg = Msg::new();
+    msg.hdr.recursion_desired = false;
+    let mut part = Part::Preamble;
+    let mut chunks: Vec<Chunk> = Vec::new();
+    let mut opt: Option<(Opt, usize)> = None;
+
+    for (index, raw) in s.lines().enumerate() {
+        let line = index + 1;
+        let text = raw.trim();
+        if let Some(comment) = text.strip_prefix(";;") {
+            let comment = comment.trim();
+            if comment.contains("opcode:") {
+                header(&mut msg, comment).map_err(|err| Error::Syntax(line, err))?;
+            } else if let Some(flags) = comment.strip_prefix("flags:") {
+                set_flags(&mut msg, flags.split(';').next().unwrap_or(""));
+            }
+            part = match comment {
+                "QUESTION SECTION:" => Part::Question,
+                "ANSWER SECTION:" => Part::Answer,
+                "AUTHORITY SECTION:" => Part::Authority,
+                "ADDITIONAL SECTION:" => Part::Additional,
+                "OPT PSEUDOSECTION:" => {
+                    opt = Some((Opt::new(0), chunks.len()));
+                    Part::Opt
+                }
+                _ => part,
+            };
+            continue;
+        }
+        if text.is_empty() {
+            continue;
+        }
+        match part {
+            Part::Question => {
+                let Some(question) = text.strip_prefix(';') else {
+                    return Err(Error::Syntax(line, "question line without ';'".into()));
+                };
+                self::question(&mut msg, question).map_err(|err| Error::Syntax(line, err))?;
+                continue;
+            }
+            Part::Opt => {
+                if let Some(pseudo) = text.strip_prefix(';') {
+                    if let Some((opt, _)) = &mut opt {
+                        edns(opt, pseudo.trim()).map_err(|err| Error::Syntax(line, err))?;
+                    }
+                    continue;
+                }
+                // records of the additional section go on after the OPT record
+                part = Part::Additional;
+            }
+            Part::Preamble => continue,
+            _ => {}
+        }
+        let after_opt = opt.as_ref().is_some_and(|(_, at)| *at == chunks.len());
+        if after_opt || chunks.last().is_none_or(|c| c.part != part) {
+            chunks.push(Chunk { part, text: "\n".repeat(index) });
+        }
+        let chunk = chunks.last_mut().unwrap();
+        chunk.text.push_str(raw);
+        chunk.text.push('\n');
+    }
+
+    for (i, chunk) in chunks.into_iter().enumerate() {
+        // the OPT record goes before the additional records printed after it
+        if chunk.part == Part::Additional {
+            if let Some((val, _)) = opt.take_if(|(_, at)| i >= *at) {
+                msg.additional.push(val.into());
+            }
+        }
+        let section = match chunk.part {
+            Part::Answer => &mut msg.answer,
+            Part::Authority => &mut msg.authority,
+            _ => &mut msg.additional,
+        };
+        for rr in Parser::new(&chunk.text, ".") {
+            section.push(rr?);
+        }
+    }
+    if let Some((val, _)) = opt {
+        msg.additional.push(val.into());
+    }
+    Ok(msg)
+}
+
+/// `opcode: QUERY, status: NOERROR, id: 4242`, after dig's `->>HEADER<<-`.
+fn header(msg: &mut Msg, text: &str) -> core::result::Result<(), String> {
+    for field in text.split(',') {
+        let Some((key, value)) = field.split_once(':') else { continue; };
+        let value = value.trim();
+        match key.trim().trim_start_matches("->>HEADER<<-").trim() {
+            "opcode" => {
+                msg.hdr.op_code = (0..16).find(|c| util::opcode_string(*c) == value)
+                    .ok_or_else(|| format!("unknown opcode {}", value))?;
+            }
+            "status" => {
+                msg.hdr.response_code = (0..0x1000).find(|c| util::rcode_string(*c) == value)
+                    .ok_or_else(|| format!("unknown status {}", value))?;
+            }
+            "id" => msg.hdr.id = value.parse().map_err(|_| format!("bad id {}", value))?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn set_flags(msg: &mut Msg, flags: &str) {
+    for flag in flags.split_whitespace() {
+        match flag {
+            "qr" => msg.hdr.response = true,
+            "aa" => msg.hdr.authoritative = true,
+            "tc" => msg.hdr.truncated = true,
+            "rd" => msg.hdr.recursion_desired = true,
+            "ra" => msg.hdr.recursion_available = true,
+            "z" => msg.hdr.zero = true,
+            "ad" => msg.hdr.authenticated_data = true,
+            "cd" => msg.hdr.checking_disabled = true,
+            _ => {}
+        }
+    }
+}
+
+/// `example.com. IN A`, the leading ';' gone.
+fn question(msg: &mut Msg, text: &str) -> core::result::Result<(), String> {
+    let fields: Vec<&str> = text.split_whitespace().collect();
+    let [name, class, typ] = fields[..] else {
+        return Err(format!("bad question {}", text));
+    };
+    let q_class = util::string_to_class(class).ok_or_else(|| format!("unknown class {}", class))?;
+    let q_type = util::string_to_type(typ).ok_or_else(|| format!("unknown type {}", typ))?;
+    msg.question.push(Question { name: full_domain(name), q_type, q_class });
+    Ok(())
+}
+
+/// One line of the OPT pseudosection, the leading ';' gone.
+fn edns(opt: &mut Opt, text: &str) -> core::result::Result<(), String> {
+    let Some((name, value)) = text.split_once(':') else { return Ok(()); };
+    let value = value.trim();
+    let number = |s: &str| s.trim_matches(|c: char| !c.is_ascii_digit()).parse::<u32>().ok();
+    let option = match name.trim() {
+        "EDNS" => {
+            // ours: `version 0; flags: do; udp: 1232`, dig: `version: 0, flags: do; udp: 1232`
+            for field in value.split([';', ',']) {
+                let field = field.trim();
+                if let Some(version) = field.strip_prefix("version") {
+                    let version = number(version).ok_or("bad EDNS version")?;
+                    opt.set_version(version as u8);
+                } else if let Some(flags) = field.strip_prefix("flags:") {
+                    opt.set_do(&[flags.split_whitespace().any(|f| f == "do")]);
+                } else if let Some(udp) = field.strip_prefix("udp:") {
+                    opt.set_udp_size(number(udp).ok_or("bad EDNS udp size")? as u16);
+                }
+            }
+            return Ok(());
+        }
+        "SUBNET" | "CLIENT-SUBNET" => {
+            let mut fields = value.split('/');
+            let address: IpAddr = fields.next().and_then(|s| s.parse().ok()).ok_or("bad subnet address")?;
+            let source = fields.next().and_then(|s| s.parse().ok()).ok_or("bad subnet prefix")?;
+            let scope = fields.next().map_or(Some(0), |s| s.parse().ok()).ok_or("bad subnet scope")?;
+            EDNS0::SubNet(edns0::SubNet::new(address, source, scope))
+        }
+        "EDE" => {
+            let code = value.split_whitespace().next().and_then(|s| s.parse().ok()).ok_or("bad EDE code")?;
+            let text = value.split_once("): (").or_else(|| value.split_once(": ("))
+                .map_or("", |(_, text)| text.strip_suffix(')').unwrap_or(text));
+            EDNS0::Ede(edns0::EDE::new(code, text))
+        }
+        "PADDING" if value.ends_with("octets") => {
+            EDNS0::Padding(edns0::Padding::new(number(value).ok_or("bad padding length")? as u16))
+        }
+        "EXPIRE" => EDNS0::Expire(edns0::Expire::new(number(value))),
+        "CHAIN" => EDNS0::Chain(edns0::Chain::new(full_domain(value))),
+        "KEY-TAG" => {
+            let tags = value.split([' ', ',']).filter(|s| !s.is_empty()).map(str::parse).collect::<core::result::Result<_, _>>();
+            EDNS0::KeyTag(edns0::KeyTag::new(tags.map_err(|_| "bad key tag")?))
+        }
+        "COOKIE" => {
+            let cookie = value.split_whitespace().next().unwrap_or("");
+            let data = hex::decode(cookie).map_err(|_| "bad cookie")?;
+            EDNS0::Local(edns0::LOCAL { code: edns0::EDNS0COOKIE, data })
+        }
+        _ => return Ok(()),
+    };
+    opt.option.push(option);
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::Ipv4Addr;
+    use crate::types::edns::edns0;
+    use crate::types::RecourseRecord;
+    use crate::{full_domain, types, Error, Msg};
+
+    #[test]
+    fn test_dig_round_trip() {
+        let mut msg = Msg::new();
+        msg.set_question(full_domain("www.example.com"), types::TYPE_A);
+        msg.hdr.response = true;
+        msg.hdr.recursion_available = true;
+        msg.hdr.response_code = types::RCODE_NAME_ERROR;
+        msg.answer.push(types::CNAME::new(full_domain("www.example.com"), types::CLASS_INET, 60, full_domain("web.example.com")).into());
+        msg.answer.push(types::A::new(full_domain("web.example.com"), types::CLASS_INET, 60, Ipv4Addr::new(192, 0, 2, 1)).into());
+        msg.authority.push(types::SOA::new(full_domain("example.com"), types::CLASS_INET, 300, full_domain("ns.example.com"), full_domain("admin.example.com"), 1, 7200, 900, 1209600, 300).into());
+        msg.additional.push(types::A::new(full_domain("ns.example.com"), types::CLASS_INET, 60, Ipv4Addr::new(192, 0, 2, 53)).into());
+        msg.set_edns0(1232, true);
+        msg.edns_mut().add_ede(edns0::EDE_BLOCKED, "ads");
+        msg.edns_mut().option.push(edns0::EDNS0::Expire(edns0::Expire::new(Some(60))));
+        msg.additional.push(types::A::new(full_domain("ns2.example.com"), types::CLASS_INET, 60, Ipv4Addr::new(192, 0, 2, 54)).into());
+
+        let text = msg.to_string();
+        let back: Msg = text.parse().unwrap();
+        assert_eq!(back, msg, "{}", text);
+    }
+
+    #[test]
+    fn test_dig_round_trip_types() {
+        let zone = "$ORIGIN example.com.\n$TTL 300\n\
+            @ A 192.0.2.1\n\
+            @ AAAA 2001:db8::1\n\
+            www CNAME @\n\
+            sub DNAME example.net.\n\
+            @ NS ns1\n\
+            @ SOA ns1 hostmaster 1 7200 900 1209600 300\n\
+            @ NSEC www A NS SOA MX TXT RRSIG NSEC\n\
+            0p9mhaveqvm6t7vbl5lop2u3t2rp3tom NSEC3 1 1 12 aabbccdd 2t7b4g4vsa5smi47k61mv5bv1a22bojr NS SOA\n\
+            @ NSEC3PARAM 1 0 12 aabbccdd\n\
+            @ DNSKEY 257 3 15 l02Woi0iS8Aa25FQkUd9RMzZHJpBoRQwAQEX1SxZJA4=\n\
+            sub DS 2371 13 2 1F987CC6583E92DF0890718C42A3D4D2C3D5F6A7B8C9D0E1F2A3B4C5D6E7F8A9\n\
+            @ HINFO \"x86\" \"Linux\"\n\
+            @ AFSDB 1 afs1\n\
+            @ RRSIG A 15 2 300 20300101000000 20250101000000 2371 example.com. AAEC\n\
+            @ MX 10 mail\n\
+            1.2.0.192.in-addr.arpa. PTR www\n\
+            @ TXT \"v=spf1 -all\" \"second \\\"string\\\"\"\n\
+            _sip._udp SRV 10 60 5060 sip\n\
+            @ CAA 0 issue \"ca.example.net\"\n\
+            @ HTTPS 1 . alpn=\"h2,h3\" ech=AEX+DQBB\n\
+            @ SVCB 0 svc.example.net.\n\
+            @ TYPE65280 \\# 3 010203\n\
+            @ CLASS255 TYPE65281 \\# 0\n";
+        let mut msg = Msg::new();
+        msg.set_question(full_domain("example.com"), types::TYPE_ANY);
+        msg.hdr.response = true;
+        msg.answer = crate::zone::parse(zone, ".").unwrap();
+        msg.set_edns0(1232, false);
+
+        let text = msg.to_string();
+        assert!(text.contains("TYPE65280\t\\# 3 010203\n"), "{}", text);
+        let back: Msg = text.parse().unwrap();
+        assert_eq!(back, msg, "{}", text);
+    }
+
+    #[test]
+    fn test_dig_output_mx_txt() {
+        let text = "\
+; <<>> DiG 9.18.28 <<>> example.org ANY @192.0.2.53
+;; global options: +cmd
+;; Got answer:
+;; ->>HEADER<<- opcode: QUERY, status: NOERROR, id: 60357
+;; flags: qr rd ra; QUERY: 1, ANSWER: 4, AUTHORITY: 0, ADDITIONAL: 1
+
+;; OPT PSEUDOSECTION:
+; EDNS: version: 0, flags:; udp: 1232
+;; QUESTION SECTION:
+;example.org.\t\t\tIN\tANY
+
+;; ANSWER SECTION:
+example.org.\t\t3600\tIN\tMX\t10 mail.example.org.
+example.org.\t\t3600\tIN\tMX\t20 mx2.example.net.
+example.org.\t\t3600\tIN\tTXT\t\"v=spf1 ip4:192.0.2.0/24 -all\"
+example.org.\t\t3600\tIN\tTXT\t\"google-site-verification=abc\" \"part two\"
+
+;; Query time: 20 msec
+;; SERVER: 192.0.2.53#53(192.0.2.53) (UDP)
+;; WHEN: Thu Oct 15 10:00:00 UTC 2026
+;; MSG SIZE  rcvd: 190
+";
+        let msg: Msg = text.parse().unwrap();
+        assert_eq!(msg.answer.len(), 4);
+        let RecourseRecord::MX(mx) = &msg.answer[1] else { panic!("unexpected {}", msg.answer[1]) };
+        assert_eq!((mx.preference, mx.mx.as_str()), (20, "mx2.example.net."));
+        let RecourseRecord::TXT(txt) = &msg.answer[3] else { panic!("unexpected {}", msg.answer[3]) };
+        assert_eq!(txt.txt, [&b"google-site-verification=abc"[..], b"part two"]);
+        assert_eq!(msg.to_string().parse::<Msg>().unwrap(), msg);
+    }
+
+    #[test]
+    fn test_dig_output() {
+        let text = "\
+; <<>> DiG 9.18.24 <<>> example.com A +dnssec
+;; global options: +cmd
+;; Got answer:
+;; ->>HEADER<<- opcode: QUERY, status: NOERROR, id: 4242
+;; flags: qr rd ra ad; QUERY: 1, ANSWER: 1, AUTHORITY: 0, ADDITIONAL: 1
+
+;; OPT PSEUDOSECTION:
+; EDNS: version: 0, flags: do; udp: 1232
+; COOKIE: 0102030405060708 (good)
+;; QUESTION SECTION:
+;example.com.\t\t\tIN\tA
+
+;; ANSWER SECTION:
+example.com.\t\t3600\tIN\tA\t192.0.2.1
+
+;; Query time: 12 msec
+;; SERVER: 192.0.2.53#53(192.0.2.53) (UDP)
+;; MSG SIZE  rcvd: 82
+";
+        let msg: Msg = text.parse().unwrap();
+        assert_eq!((msg.hdr.id, msg.hdr.response, msg.hdr.authenticated_data, msg.hdr.checking_disabled), (4242, true, true, false));
+        assert_eq!((msg.question.len(), msg.answer.len(), msg.additional.len()), (1, 1, 1));
+        assert_eq!(msg.question[0].q_type, types::TYPE_A);
+        let opt = msg.is_edns0().unwrap();
+        assert_eq!((opt.udp_size(), opt.is_do()), (1232, true));
+        assert_eq!(opt.cookie(), Some(&[1, 2, 3, 4, 5, 6, 7, 8][..]));
+
+        let bad = text.replace("IN\tA\t192.0.2.1", "IN\tA\t192.0.2.256");
+        assert!(matches!(bad.parse::<Msg>(), Err(Error::Syntax(14, _))));
+    }
+}
@@ -0,0 +1,136 @@
+//! Records read one at a time from a packed message.
+
+use crate::io::Cursor;
+use crate::types::RecourseRecord;
+use crate::Result;
+use super::{diag, unpack_rr, MsgHdr, PktMsgHeader, Question, Section};
+
+/// Iterator over the answer, authority and additional records of a packed
+/// message, unpacking each only when it is asked for. See
+/// [`Msg::records_iter`](super::Msg::records_iter).
+///
+/// After an error the iterator ends, as the records that follow cannot be
+/// found.
+pub struct RecordIter<'a> {
+    cur: Cursor<&'a [u8]>,
+    hdr: PktMsgHeader,
+    section: Section,
+    index: usize,
+    offset: usize,
+    done: bool,
+}
+
+impl<'a> RecordIter<'a> {
+    pub(super) fn new(msg: &'a [u8]) -> Result<Self> {
+        let mut cur = Cursor::new(msg);
+        let hdr = PktMsgHeader::unpack(&mut cur).map_err(|err| diag::malformed(msg, 0, Section::Header, 0, err))?;
+        for index in 0..hdr.question_count as usize {
+            let start = cur.position() as usize;
+            Question::skip(&mut cur).map_err(|err| diag::malformed(msg, start, Section::Question, index, err))?;
+        }
+        let mut iter = Self {
+            offset: cur.position() as usize,
+            cur,
+            hdr,
+            section: Section::Answer,
+            index: 0,
+            done: false,
+        };
+        iter.skip_empty();
+        Ok(iter)
+    }
+
+    /// The header of the message.
+    pub fn header(&self) -> MsgHdr {
+        self.hdr.into()
+    }
+
+    /// Offset in the message of the record last returned, so a caller can
+    /// patch it in place, e.g. its TTL.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    fn section_len(&self) -> usize {
+        let count = match self.section {
+            Section::Answer => self.hdr.answer_count,
+            Section::Authority => self.hdr.authority_count,
+            Section::Additional => self.hdr.additional_count,
+            Section::Header | Section::Question => 0,
+        };
+        count as usize
+    }
+
+    /// Moves on to the next section with records left, or ends.
+    fn skip_empty(&mut self) {
+        while !self.done && self.index >= self.section_len() {
+            self.index = 0;
+            match self.section {
+                Section::Answer => self.section = Section::Authority,
+                Section::Authority => self.section = Section::Additional,
+                _ => self.done = true,
+            }
+        }
+    }
+}
+
+impl Iterator for RecordIter<'_> {
+    type Item = Result<(Section, RecourseRecord)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let section = self.section;
+        self.offset = self.cur.position() as usize;
+        match unpack_rr(&mut self.cur) {
+            Ok(rr) => {
+                self.index += 1;
+                self.skip_empty();
+                Some(Ok((section, rr)))
+            }
+            Err(err) => {
+                self.done = true;
+                Some(Err(diag::malformed(self.cur.get_ref(), self.offset, section, self.index, err)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use core::net::Ipv4Addr;
+    use crate::msg::{Section, RR};
+    use crate::prelude::*;
+    use crate::{full_domain, types, Msg};
+
+    #[test]
+    fn test_records_iter() {
+        let mut msg = Msg::new();
+        msg.set_question(full_domain("www.example.com"), types::TYPE_A);
+        msg.hdr.response = true;
+        msg.answer.push(types::CNAME::new(full_domain("www.example.com"), types::CLASS_INET, 60, full_domain("web.example.com")).into());
+        msg.answer.push(types::A::new(full_domain("web.example.com"), types::CLASS_INET, 60, Ipv4Addr::new(192, 0, 2, 1)).into());
+        msg.set_edns0(1232, false);
+        let mut buf = msg.to_buf().unwrap();
+
+        let mut iter = Msg::records_iter(&buf).unwrap();
+        assert_eq!(iter.header().id, msg.hdr.id);
+        let (section, first) = iter.next().unwrap().unwrap();
+        assert_eq!((section, first.header().typ), (Section::Answer, types::TYPE_CNAME));
+
+        // the TTL follows the name, type and class
+        let offset = iter.offset() + "www.example.com.".len() + 1 + 4;
+        buf[offset..offset + 4].copy_from_slice(&30u32.to_be_bytes());
+        let records: Vec<_> = Msg::records_iter(&buf).unwrap().map(Result::unwrap).collect();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].1.header().ttl, 30);
+        assert_eq!(records[1].1.to_string(), msg.answer[1].to_string());
+        assert_eq!(records[2].0, Section::Additional);
+
+        let mut iter = Msg::records_iter(&buf[..buf.len() - 3]).unwrap();
+        assert!(iter.nth(2).unwrap().is_err());
+        assert!(iter.next().is_none());
+        assert!(Msg::records_iter(&buf[..5]).is_err());
+    }
+}
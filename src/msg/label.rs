@@ -1,13 +1,13 @@
 //! Reading strings from the DNS wire protocol.
 #![allow(dead_code)]
 
-use byteorder::ReadBytesExt;
+use alloc::borrow::Cow;
 use bytes::{BufMut, BytesMut};
-use crate::DomainString;
+use crate::io::{Cursor, ReadBytesExt};
+use crate::prelude::*;
+use crate::{DomainString, Error, Result};
 use tracing::*;
-use std::convert::TryFrom;
-use std::fmt;
-use std::io::{self, Cursor, ErrorKind};
+use core::fmt;
 
 /// Domain names in the DNS protocol are encoded as **Labels**, which are
 /// segments of ASCII characters prefixed by their length. When written out,
@@ -20,19 +20,13 @@ pub struct Labels {
 }
 
 #[cfg(feature = "with_idna")]
-fn label_to_ascii(label: &str) -> Result<DomainString, unic_idna::Errors> {
-    let flags = unic_idna::Flags {
-        use_std3_ascii_rules: false,
-        transitional_processing: false,
-        verify_dns_length: true,
-    };
-    let s = unic_idna::to_ascii(label, flags)?;
-    Ok(s.into())
+fn name_to_ascii(name: &str) -> Result<Cow<'_, str>> {
+    crate::idn::name_to_ascii(name)
 }
 
 #[cfg(not(feature = "with_idna"))]
-fn label_to_ascii(label: &str) -> Result<DomainString, ()> {
-    Ok(DomainString::from(label))
+fn name_to_ascii(name: &str) -> Result<Cow<'_, str>> {
+    Ok(Cow::Borrowed(name))
 }
 
 impl Labels {
@@ -49,38 +43,37 @@ impl Labels {
             if label.is_empty() {
                 continue;
             }
-            if !u8::try_from(label.len()).is_ok() {
+            if u8::try_from(label.len()).is_err() {
                 return false;
             }
         }
         true
     }
 
-    pub fn encode_with_io(input: &str) -> io::Result<Self> {
+    pub fn encode_with_io(input: &str) -> Result<Self> {
         let mut segments = Vec::new();
-        Self::encode(input, &mut segments).map_err(|e| io::Error::new(ErrorKind::Other, e))?;
+        Self::encode(input, &mut segments).map_err(Error::new)?;
         Ok(Self { segments })
     }
 
-    pub fn encode_to_buf(input: &str, buf: &mut BytesMut) -> io::Result<()> {
-        for label in input.split('.') {
+    pub fn encode_to_buf(input: &str, buf: &mut BytesMut) -> Result<()> {
+        let ascii = name_to_ascii(input).map_err(|e| {
+            warn!("Could not encode name {:?}: {:?}", input, e);
+            e
+        })?;
+        for label in ascii.split('.') {
             if label.is_empty() {
                 continue;
             }
 
-            let label_idn = label_to_ascii(label).map_err(|e| {
-                warn!("Could not encode label {:?}: {:?}", label, e);
-                io::Error::new(ErrorKind::Other, label)
-            })?;
-
-            match u8::try_from(label_idn.len()) {
+            match u8::try_from(label.len()) {
                 Ok(length) => {
                     buf.put_u8(length);
-                    buf.put_slice(label_idn.as_bytes());
+                    buf.put_slice(label.as_bytes());
                 }
                 Err(e) => {
                     warn!("Could not encode label {:?}: {}", label, e);
-                    return Err(io::Error::new(ErrorKind::Other, label));
+                    return Err(Error::new(label));
                 }
             }
         }
@@ -89,25 +82,25 @@ impl Labels {
     }
 
     /// Encodes the given input string as labels. If any segment is too long,
-    /// returns that segment as an error.
-    pub fn encode<'a>(input: &'a str, segments: &'a mut Vec<(u8, DomainString)>) -> Result<(), &'a str> {
-        for label in input.split('.') {
+    /// returns that segment as an error; the whole input if it is not a
+    /// valid internationalized name.
+    pub fn encode<'a>(input: &'a str, segments: &'a mut Vec<(u8, DomainString)>) -> core::result::Result<(), &'a str> {
+        let ascii = name_to_ascii(input).map_err(|e| {
+            warn!("Could not encode name {:?}: {:?}", input, e);
+            input
+        })?;
+        for (i, label) in ascii.split('.').enumerate() {
             if label.is_empty() {
                 continue;
             }
 
-            let label_idn = label_to_ascii(label).map_err(|e| {
-                warn!("Could not encode label {:?}: {:?}", label, e);
-                label
-            })?;
-
-            match u8::try_from(label_idn.len()) {
+            match u8::try_from(label.len()) {
                 Ok(length) => {
-                    segments.push((length, label_idn));
+                    segments.push((length, label.into()));
                 }
                 Err(e) => {
                     warn!("Could not encode label {:?}: {}", label, e);
-                    return Err(label);
+                    return Err(input.split('.').nth(i).unwrap_or(input));
                 }
             }
         }
@@ -120,6 +113,11 @@ impl Labels {
         self.segments.len()
     }
 
+    /// Returns true if this is the root name.
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
     /// Returns a new set of labels concatenating two names.
     pub fn extend(&self, other: &Self) -> Self {
         let mut segments = self.segments.clone();
@@ -127,7 +125,7 @@ impl Labels {
         Self { segments }
     }
 
-    pub fn unpack(buf: &[u8]) -> io::Result<(Labels, u16)> {
+    pub fn unpack(buf: &[u8]) -> Result<(Labels, u16)> {
         let mut labels = Labels {
             segments: Vec::new(),
         };
@@ -144,7 +142,7 @@ impl Labels {
     ///
     /// So “dns.lookup.dog” would be encoded as:
     /// “3, dns, 6, lookup, 3, dog, 0”.
-    pub fn pack(&self, buf: &mut BytesMut) -> io::Result<()> {
+    pub fn pack(&self, buf: &mut BytesMut) -> Result<()> {
         for (length, label) in &self.segments {
             buf.put_u8(*length);
             buf.put_slice(label.as_bytes());
@@ -171,12 +169,11 @@ const RECURSION_LIMIT: usize = 8;
 /// recursions to track backtracking positions. Returns the count of bytes
 /// that had to be read to produce the string, including the bytes to signify
 /// backtracking, but not including the bytes read _during_ backtracking.
-#[cfg_attr(feature = "with_mutagen", ::mutagen::mutate)]
 fn read_string_recursive(
     labels: &mut Labels,
     c: &mut Cursor<&[u8]>,
     recursions: &mut Vec<u16>,
-) -> io::Result<u16> {
+) -> Result<u16> {
     let mut bytes_read = 0;
 
     loop {
@@ -193,14 +190,14 @@ fn read_string_recursive(
 
             if recursions.contains(&offset) {
                 warn!("Hit previous offset ({}) decoding string", offset);
-                return Err(io::Error::new(ErrorKind::Other, "TooMuchRecursion"));
+                return Err(Error::new("TooMuchRecursion"));
             }
 
             recursions.push(offset);
 
             if recursions.len() >= RECURSION_LIMIT {
                 warn!("Hit recursion limit ({}) decoding string", RECURSION_LIMIT);
-                return Err(io::Error::new(ErrorKind::Other, "TooMuchRecursion"));
+                return Err(Error::new("TooMuchRecursion"));
             }
 
             trace!("Backtracking to offset {}", offset);
@@ -1,28 +1,52 @@
+mod compress;
+mod diag;
+#[cfg(feature = "std")]
+mod dig;
+mod iter;
 mod label;
+mod rrset;
+mod view;
+pub use compress::CompressionContext;
+pub use diag::{Malformed, Section};
+pub use iter::RecordIter;
 pub use label::Labels;
-
-use std::{fmt, io};
-use std::fmt::{Display, Formatter, Write};
-use std::io::Cursor;
-use std::net::IpAddr;
-use byteorder::{BigEndian, ReadBytesExt};
+pub use rrset::RrSet;
+pub use view::{MsgView, NameView, QuestionView, RecordView, RecordViews};
+
+use alloc::borrow::Cow;
+use alloc::collections::BTreeSet;
+use core::fmt;
+use core::fmt::{Display, Formatter, Write};
+use core::hash::{Hash, Hasher};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use crate::io::{Cursor, ReadBytesExt};
+use core::net::IpAddr;
+use byteorder::BigEndian;
 use bytes::{BufMut, BytesMut};
-use rand::Rng;
 use crate::{DomainString, util};
+use crate::prelude::*;
 use crate::{Result, Error};
 use crate::types;
 use crate::types::RecourseRecord;
 
+/// Places [`Msg::rotate_answers`] has rotated by, one more per call.
+static ROTATION: AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(feature = "std")]
 fn id() -> u16 {
+    use rand::Rng;
     rand::thread_rng().gen()
 }
 
+/// Without `std` there is no random source; callers set `hdr.id` themselves.
+#[cfg(not(feature = "std"))]
+fn id() -> u16 {
+    0
+}
+
 #[inline]
-fn error<E>(msg: E) -> io::Error
-    where
-        E: Into<Box<dyn std::error::Error + Send + Sync>>,
-{
-    io::Error::new(io::ErrorKind::Other, msg)
+fn error<S: Into<String>>(msg: S) -> Error {
+    Error::new(msg)
 }
 
 
@@ -47,15 +71,15 @@ impl PktMsgHeader {
         Ok(())
     }
 
-    pub fn unpack(cur: &mut Cursor<&[u8]>) -> io::Result<PktMsgHeader> {
-        let mut ret: PktMsgHeader = PktMsgHeader::default();
-        ret.id = cur.read_u16::<BigEndian>()?;
-        ret.bits = cur.read_u16::<BigEndian>()?;
-        ret.question_count = cur.read_u16::<BigEndian>()?;
-        ret.answer_count = cur.read_u16::<BigEndian>()?;
-        ret.authority_count = cur.read_u16::<BigEndian>()?;
-        ret.additional_count = cur.read_u16::<BigEndian>()?;
-        Ok(ret)
+    pub fn unpack(cur: &mut Cursor<&[u8]>) -> Result<PktMsgHeader> {
+        Ok(PktMsgHeader {
+            id: cur.read_u16::<BigEndian>()?,
+            bits: cur.read_u16::<BigEndian>()?,
+            question_count: cur.read_u16::<BigEndian>()?,
+            answer_count: cur.read_u16::<BigEndian>()?,
+            authority_count: cur.read_u16::<BigEndian>()?,
+            additional_count: cur.read_u16::<BigEndian>()?,
+        })
     }
 }
 
@@ -67,7 +91,8 @@ pub trait RR: Display {
 }
 
 /// DNS Message Header
-#[derive(Copy, Clone, Default)]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MsgHdr {
     pub id: u16,
     pub response: bool,
@@ -122,8 +147,8 @@ impl Display for MsgHdr {
     }
 }
 
-impl Into<PktMsgHeader> for MsgHdr {
-    fn into(self) -> PktMsgHeader {
+impl From<MsgHdr> for PktMsgHeader {
+    fn from(val: MsgHdr) -> Self {
         // Header.Bits
         const _QR: u16 = 1 << 15; // query/response (response=1)
         const _AA: u16 = 1 << 10; // authoritative
@@ -134,31 +159,33 @@ impl Into<PktMsgHeader> for MsgHdr {
         const _AD: u16 = 1 << 5; // authenticated data
         const _CD: u16 = 1 << 4; // checking disabled
 
-        let mut ret = PktMsgHeader::default();
-        ret.id = self.id;
-        ret.bits = self.op_code << 11 | (self.response_code & 0xF) as u16;
-        if self.response {
+        let mut ret = PktMsgHeader {
+            id: val.id,
+            bits: val.op_code << 11 | (val.response_code & 0xF),
+            ..Default::default()
+        };
+        if val.response {
             ret.bits |= _QR;
         }
-        if self.authoritative {
+        if val.authoritative {
             ret.bits |= _AA;
         }
-        if self.truncated {
+        if val.truncated {
             ret.bits |= _TC;
         }
-        if self.recursion_desired {
+        if val.recursion_desired {
             ret.bits |= _RD;
         }
-        if self.recursion_available {
+        if val.recursion_available {
             ret.bits |= _RA;
         }
-        if self.zero {
+        if val.zero {
             ret.bits |= _Z;
         }
-        if self.authenticated_data {
+        if val.authenticated_data {
             ret.bits |= _AD;
         }
-        if self.checking_disabled {
+        if val.checking_disabled {
             ret.bits |= _CD;
         }
         ret
@@ -178,29 +205,47 @@ impl From<PktMsgHeader> for MsgHdr {
         const _AD: u16 = 1 << 5; // authenticated data
         const _CD: u16 = 1 << 4; // checking disabled
 
-        let mut msg = MsgHdr::default();
-        msg.id = value.id;
-        msg.response = value.bits & _QR != 0;
-        msg.op_code = (value.bits >> 11) & 0xF;
-        msg.authoritative = value.bits & _AA != 0;
-        msg.truncated = value.bits & _TC != 0;
-        msg.recursion_desired = value.bits & _RD != 0;
-        msg.recursion_available = value.bits & _RA != 0;
-        msg.zero = value.bits & _Z != 0;
-        msg.authenticated_data = value.bits & _AD != 0;
-        msg.checking_disabled = value.bits & _CD != 0;
-        msg.response_code = value.bits & 0xF;
-        msg
+        MsgHdr {
+            id: value.id,
+            response: value.bits & _QR != 0,
+            op_code: (value.bits >> 11) & 0xF,
+            authoritative: value.bits & _AA != 0,
+            truncated: value.bits & _TC != 0,
+            recursion_desired: value.bits & _RD != 0,
+            recursion_available: value.bits & _RA != 0,
+            zero: value.bits & _Z != 0,
+            authenticated_data: value.bits & _AD != 0,
+            checking_disabled: value.bits & _CD != 0,
+            response_code: value.bits & 0xF,
+        }
     }
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Question {
     pub name: DomainString,
     pub q_type: u16,
     pub q_class: u16,
 }
 
+/// The name compares ignoring ASCII case.
+impl PartialEq for Question {
+    fn eq(&self, other: &Self) -> bool {
+        self.q_type == other.q_type && self.q_class == other.q_class && self.name.eq_ignore_ascii_case(&other.name)
+    }
+}
+
+impl Eq for Question {}
+
+impl Hash for Question {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        util::hash_name(&self.name, state);
+        self.q_type.hash(state);
+        self.q_class.hash(state);
+    }
+}
+
 impl Display for Question {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         f.write_str(";")?;
@@ -233,7 +278,7 @@ impl Question {
         Ok(())
     }
 
-    pub fn unpack(cur: &mut Cursor<&[u8]>) -> io::Result<Self> {
+    pub fn unpack(cur: &mut Cursor<&[u8]>) -> Result<Self> {
         let name = util::unpack_domain_name_cur(cur)?;
         let q_type = cur.read_u16::<BigEndian>()?;
         let q_class = cur.read_u16::<BigEndian>()?;
@@ -244,18 +289,19 @@ impl Question {
         })
     }
 
-    pub fn skip(cur: &mut Cursor<&[u8]>) -> io::Result<()> {
+    pub fn skip(cur: &mut Cursor<&[u8]>) -> Result<()> {
         if util::skip_domain_name(cur) {
             let _ = cur.read_u16::<BigEndian>()?;
             let _ = cur.read_u16::<BigEndian>()?;
             Ok(())
         } else {
-            Err(io::Error::new(io::ErrorKind::Other, " skip failed"))
+            Err(error(" skip failed"))
         }
     }
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RecourseRecordHdr {
     pub name: DomainString,
     pub typ: u16,
@@ -274,7 +320,7 @@ impl RecourseRecordHdr {
         Ok(())
     }
 
-    pub fn unpack(cur: &mut Cursor<&[u8]>) -> io::Result<Self> {
+    pub fn unpack(cur: &mut Cursor<&[u8]>) -> Result<Self> {
         let name = util::unpack_domain_name_cur(cur)?;
         let r_type = cur.read_u16::<BigEndian>()?;
         let class = cur.read_u16::<BigEndian>()?;
@@ -290,6 +336,29 @@ impl RecourseRecordHdr {
     }
 }
 
+/// The owner name compares ignoring ASCII case, and RDLENGTH not at all,
+/// as it depends on how the record was packed. Records compare the same
+/// way, names in their data too.
+impl PartialEq for RecourseRecordHdr {
+    fn eq(&self, other: &Self) -> bool {
+        self.typ == other.typ
+            && self.class == other.class
+            && self.ttl == other.ttl
+            && self.name.eq_ignore_ascii_case(&other.name)
+    }
+}
+
+impl Eq for RecourseRecordHdr {}
+
+impl Hash for RecourseRecordHdr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        util::hash_name(&self.name, state);
+        self.typ.hash(state);
+        self.class.hash(state);
+        self.ttl.hash(state);
+    }
+}
+
 impl Display for RecourseRecordHdr {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         if self.typ == types::TYPE_OPT {
@@ -344,6 +413,12 @@ impl Display for RecourseRecordHdr {
 #[derive(Debug, Clone)]
 pub struct RRs(Vec<RecourseRecord>);
 
+impl Default for RRs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl RRs {
     pub fn new() -> Self {
         Self(Vec::new())
@@ -368,10 +443,61 @@ impl RRs {
         }
         ret
     }
+
+    /// The addresses `name` has, following CNAME and DNAME records,
+    /// where [`ips`](Self::ips) takes every address record there is.
+    pub fn ips_for(&self, name: &str) -> Vec<IpAddr> {
+        resolve_chain(&self.0, name).ips()
+    }
+}
+
+/// Where the CNAME and DNAME records of an answer lead from a name; see
+/// [`Msg::resolve_chain`].
+#[derive(Debug, Clone)]
+pub struct ResolvedChain<'a> {
+    /// The name at the end of the chain, the name itself without any.
+    pub target: DomainString,
+    /// The A and AAAA records of `target`.
+    pub records: Vec<&'a RecourseRecord>,
+}
+
+impl ResolvedChain<'_> {
+    pub fn ips(&self) -> Vec<IpAddr> {
+        self.records.iter().filter_map(|rr| match rr {
+            RecourseRecord::A(val) => Some(IpAddr::V4(val.a)),
+            RecourseRecord::AAAA(val) => Some(IpAddr::V6(val.aaaa)),
+            _ => None,
+        }).collect()
+    }
+}
+
+/// Follows CNAMEs, and DNAMEs without their synthesized CNAME, from
+/// `name`; a loop stops once every record could have been taken.
+fn resolve_chain<'a>(records: &'a [RecourseRecord], name: &str) -> ResolvedChain<'a> {
+    let mut target = DomainString::from(name);
+    for _ in 0..records.len() {
+        let next = records.iter().find_map(|rr| match rr {
+            RecourseRecord::CNAME(val) if util::equal_names(&val.hdr.name, &target) => Some(val.target.clone()),
+            _ => None,
+        }).or_else(|| records.iter().find_map(|rr| match rr {
+            RecourseRecord::DNAME(val) => val.substitute(&target),
+            _ => None,
+        }));
+        match next {
+            Some(next) => target = next,
+            None => break,
+        }
+    }
+    let records = records.iter()
+        .filter(|rr| matches!(rr, RecourseRecord::A(_) | RecourseRecord::AAAA(_)))
+        .filter(|rr| util::equal_names(&rr.header().name, &target))
+        .collect();
+    ResolvedChain { target, records }
 }
 
 /// DNS Message
-#[derive(Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Msg {
     pub hdr: MsgHdr,
     pub question: Vec<Question>,
@@ -421,13 +547,100 @@ impl Msg {
             self.hdr.checking_disabled = request.hdr.checking_disabled;
         }
         self.hdr.response_code = types::RCODE_SUCCESS;
-        if request.question.len() > 0 {
+        if !request.question.is_empty() {
             self.question.clear();
             self.question.push(request.question[0].clone());
         }
         self
     }
 
+    /// Whether this is a response to `query`: same ID, the QR bit set and
+    /// the same question, names compared case-insensitively. A FORMERR or
+    /// NOTIMP without a question section also counts, as servers that
+    /// cannot parse a query send those.
+    pub fn is_answer_for(&self, query: &Msg) -> bool {
+        if self.hdr.id != query.hdr.id || !self.hdr.response {
+            return false;
+        }
+        if self.question.is_empty()
+            && matches!(self.hdr.response_code, types::RCODE_FORMAT_ERROR | types::RCODE_NOT_IMPLEMENTED)
+        {
+            return true;
+        }
+        self.question.len() == query.question.len()
+            && query.question.iter().zip(&self.question).all(|(q, r)| {
+                q.q_type == r.q_type && q.q_class == r.q_class && q.name.eq_ignore_ascii_case(&r.name)
+            })
+    }
+
+    /// Follows the CNAME and DNAME records of the answer section from
+    /// `name`, names compared case-insensitively, to the name they end at
+    /// and its A and AAAA records. Records off the chain are left out.
+    pub fn resolve_chain(&self, name: &str) -> ResolvedChain<'_> {
+        resolve_chain(&self.answer, name)
+    }
+
+    /// The answer section grouped into RRsets.
+    pub fn answer_rrsets(&self) -> Vec<RrSet> {
+        RrSet::group(&self.answer)
+    }
+
+    /// The authority section grouped into RRsets.
+    pub fn authority_rrsets(&self) -> Vec<RrSet> {
+        RrSet::group(&self.authority)
+    }
+
+    /// The additional section grouped into RRsets, without the OPT record.
+    pub fn additional_rrsets(&self) -> Vec<RrSet> {
+        RrSet::group(&self.additional)
+    }
+
+    /// Takes `elapsed` seconds off the TTL of every record, OPT aside,
+    /// stopping at zero, as a cached reply ages.
+    pub fn decrement_ttls(&mut self, elapsed: u32) -> &mut Self {
+        for rr in self.records_mut() {
+            let hdr = rr.header_mut();
+            hdr.ttl = hdr.ttl.saturating_sub(elapsed);
+        }
+        self
+    }
+
+    /// Brings the TTL of every record, OPT aside, into `min..=max`.
+    pub fn clamp_ttls(&mut self, min: u32, max: u32) -> &mut Self {
+        for rr in self.records_mut() {
+            let hdr = rr.header_mut();
+            hdr.ttl = hdr.ttl.max(min).min(max);
+        }
+        self
+    }
+
+    /// The smallest TTL of the records, OPT aside, or `None` without any.
+    pub fn min_ttl(&self) -> Option<u32> {
+        self.answer.iter().chain(&self.authority).chain(&self.additional)
+            .filter(|rr| !matches!(rr, RecourseRecord::Opt(_)))
+            .map(|rr| rr.header().ttl)
+            .min()
+    }
+
+    /// Removes records repeating an earlier one of the same section:
+    /// same owner name, ignoring case, type, class and data.
+    pub fn dedup(&mut self) -> &mut Self {
+        for section in [&mut self.answer, &mut self.authority, &mut self.additional] {
+            let mut seen = BTreeSet::new();
+            section.retain(|rr| {
+                let hdr = rr.header();
+                let Ok(rdata) = rr.rdata() else { return true; };
+                seen.insert((hdr.name.to_ascii_lowercase(), hdr.typ, hdr.class, rdata))
+            });
+        }
+        self
+    }
+
+    fn records_mut(&mut self) -> impl Iterator<Item = &mut RecourseRecord> {
+        self.answer.iter_mut().chain(&mut self.authority).chain(&mut self.additional)
+            .filter(|rr| !matches!(rr, RecourseRecord::Opt(_)))
+    }
+
     pub fn is_edns0(&self) -> Option<&types::Opt> {
         for extra in &self.additional {
             if let RecourseRecord::Opt(val) = &extra {
@@ -446,13 +659,95 @@ impl Msg {
         None
     }
 
+    /// Adds an OPT record advertising `udp_size`, or updates the one there
+    /// is, keeping its options, and sets or clears its DO bit.
+    pub fn set_edns0(&mut self, udp_size: u16, do_bit: bool) -> &mut Self {
+        let opt = self.edns_mut();
+        opt.set_udp_size(udp_size);
+        opt.set_do(&[do_bit]);
+        self
+    }
+
+    /// The OPT record, added with
+    /// [`DEFAULT_UDP_SIZE`](types::edns::edns0::DEFAULT_UDP_SIZE) when
+    /// there is none.
+    pub fn edns_mut(&mut self) -> &mut types::Opt {
+        if self.is_edns0().is_none() {
+            self.additional.push(types::Opt::new(types::edns::edns0::DEFAULT_UDP_SIZE).into());
+        }
+        self.get_edns0_mut().unwrap()
+    }
+
+    /// Drops the OPT record, telling whether there was one.
+    pub fn clear_edns0(&mut self) -> bool {
+        let len = self.additional.len();
+        self.additional.retain(|rr| !matches!(rr, RecourseRecord::Opt(_)));
+        self.additional.len() != len
+    }
+
+    /// Adds an EDNS0 padding option (RFC 7830), replacing any there was,
+    /// so the message packs to a multiple of `block_size` octets; see
+    /// [`PADDING_QUERY_BLOCK`](types::edns::edns0::PADDING_QUERY_BLOCK) and
+    /// [`PADDING_RESPONSE_BLOCK`](types::edns::edns0::PADDING_RESPONSE_BLOCK).
+    /// The size is that of [`to_buf`](Msg::to_buf), so pad last, and
+    /// send without compression. Needs an OPT record.
+    pub fn pad_to(&mut self, block_size: usize) -> Result<&mut Self> {
+        use types::edns::edns0::{Padding, EDNS0};
+        let Some(opt) = self.get_edns0_mut() else {
+            return Err(error("padding needs an OPT record"));
+        };
+        opt.option.retain(|o| !matches!(o, EDNS0::Padding(_)));
+        if block_size == 0 {
+            return Ok(self);
+        }
+        let len = self.to_buf()?.len() + 4;
+        let padding = (block_size - len % block_size) % block_size;
+        let padding = u16::try_from(padding).map_err(|_| error("padding block too large"))?;
+        if let Some(opt) = self.get_edns0_mut() {
+            opt.option.push(EDNS0::Padding(Padding::new(padding)));
+        }
+        Ok(self)
+    }
+
+    /// Rotates each A and AAAA RRset of the answer section one place
+    /// further than the previous call did, so clients taking the first
+    /// address spread over all of them. Records of a set only trade places
+    /// with each other, keeping CNAMEs ahead of their targets.
+    pub fn rotate_answers(&mut self) -> &mut Self {
+        self.rotate_answers_by(ROTATION.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// Rotates each A and AAAA RRset of the answer section `n` places left.
+    pub fn rotate_answers_by(&mut self, n: usize) -> &mut Self {
+        let mut done = vec![false; self.answer.len()];
+        for i in 0..self.answer.len() {
+            let h = self.answer[i].header();
+            if done[i] || !(h.typ == types::TYPE_A || h.typ == types::TYPE_AAAA) {
+                continue;
+            }
+            let set: Vec<usize> = (i..self.answer.len())
+                .filter(|j| {
+                    let other = self.answer[*j].header();
+                    other.typ == h.typ && other.class == h.class && util::equal_names(&other.name, &h.name)
+                })
+                .collect();
+            let mut records: Vec<_> = set.iter().map(|j| self.answer[*j].clone()).collect();
+            records.rotate_left(n % set.len());
+            for (j, rr) in set.into_iter().zip(records) {
+                self.answer[j] = rr;
+                done[j] = true;
+            }
+        }
+        self
+    }
+
     pub fn set_hdr(&mut self, h: PktMsgHeader) -> &mut Self {
         self.hdr = h.into();
         self
     }
 
     pub fn is_compressible(&self) -> bool {
-        self.question.len() > 1 || self.answer.len() > 0 || self.authority.len() > 0 || self.additional.len() > 0
+        self.question.len() > 1 || !self.answer.is_empty() || !self.authority.is_empty() || !self.additional.is_empty()
     }
 
     pub fn has_ipv6_question(&self) -> bool {
@@ -465,56 +760,170 @@ impl Msg {
     }
 
     pub fn pack(&self, buf: &mut BytesMut) -> Result<()> {
-        // if self.compress && self.is_compressible() {
-        //     // todo: compress
-        // }
+        self.pack_with(buf, None, None)
+    }
+
+    /// Like [`pack`](Self::pack), with question and owner names, and the
+    /// names in CNAME, MX, NS, PTR and SOA data, compressed. `ctx` is reset
+    /// first, so one can be reused for every message.
+    pub fn pack_compressed(&self, ctx: &mut CompressionContext, buf: &mut BytesMut) -> Result<()> {
+        self.pack_with(buf, Some(ctx), None)
+    }
+
+    /// Packs compressed, like [`pack_compressed`](Self::pack_compressed),
+    /// into at most `max_size` octets, as a UDP reply must fit in; see
+    /// [`udp_payload_size`](Self::udp_payload_size). Whole records are
+    /// dropped from the end until it fits, the OPT record excepted, and
+    /// the TC bit is set when any of them came from the answer or
+    /// authority section (RFC 2181 section 9). Tells whether any records
+    /// were dropped. Fails when even the question does not fit.
+    pub fn pack_with_limit(&self, buf: &mut BytesMut, max_size: usize) -> Result<bool> {
+        let start = buf.len();
+        let mut ends = Vec::with_capacity(1 + self.answer.len() + self.authority.len() + self.additional.len());
+        self.pack_with(buf, Some(&mut CompressionContext::new()), Some(&mut ends))?;
+        let limit = start + max_size;
+        if buf.len() <= limit {
+            return Ok(false);
+        }
+        if ends[0] > limit {
+            return Err(error("question does not fit the size limit"));
+        }
+
+        // The OPT record has the root as owner, so its octets are free of
+        // compression pointers and can move.
+        let (answers, authorities) = (self.answer.len(), self.authority.len());
+        let opt_at = self.additional.iter()
+            .position(|rr| matches!(rr, RecourseRecord::Opt(_)))
+            .map(|i| answers + authorities + i);
+        let opt = opt_at.map(|i| buf[ends[i]..ends[i + 1]].to_vec()).unwrap_or_default();
+        let opt = if ends[0] + opt.len() <= limit { opt } else { Vec::new() };
+        let mut kept = ends.len() - 1;
+        while kept > 0 {
+            let moved = if opt_at.is_some_and(|i| i >= kept) { opt.len() } else { 0 };
+            if ends[kept] + moved <= limit {
+                break;
+            }
+            kept -= 1;
+        }
+        buf.truncate(ends[kept]);
+        let mut additionals = kept.saturating_sub(answers + authorities);
+        if opt_at.is_some_and(|i| i >= kept) && !opt.is_empty() {
+            buf.put_slice(&opt);
+            additionals += 1;
+        }
+        util::set_value_offset(buf.as_mut(), start + 6, kept.min(answers) as u16);
+        util::set_value_offset(buf.as_mut(), start + 8, kept.saturating_sub(answers).min(authorities) as u16);
+        util::set_value_offset(buf.as_mut(), start + 10, additionals as u16);
+        if kept < answers + authorities {
+            buf[start + 2] |= 0x02; // TC
+        }
+        Ok(true)
+    }
+
+    /// The largest reply a client takes over UDP, by the OPT record of
+    /// this query: its advertised size, never below 512, or 512 without
+    /// one.
+    pub fn udp_payload_size(&self) -> usize {
+        self.is_edns0().map_or(512, |opt| opt.udp_size().max(512) as usize)
+    }
+
+    fn pack_with(
+        &self,
+        buf: &mut BytesMut,
+        mut ctx: Option<&mut CompressionContext>,
+        mut ends: Option<&mut Vec<usize>>,
+    ) -> Result<()> {
         if self.hdr.response_code > 0xFFF {
             return Err(Error::BadResponseCode);
         }
 
         let r_code = self.hdr.response_code;
-        if let Some(_) = self.is_edns0() {} else if r_code > 0xF {
+        if self.is_edns0().is_some() {} else if r_code > 0xF {
             return Err(Error::BadExtendedResponseCode);
         }
+        if let Some(ctx) = ctx.as_deref_mut() {
+            ctx.reset(buf.len());
+        }
 
         // Header
         {
             let mut hdr: PktMsgHeader = self.hdr.into();
             hdr.question_count = self.question.len() as u16;
             hdr.answer_count = self.answer.len() as u16;
-            hdr.additional_count = self.authority.len() as u16;
-            hdr.authority_count = self.additional.len() as u16;
+            hdr.authority_count = self.authority.len() as u16;
+            hdr.additional_count = self.additional.len() as u16;
             hdr.pack(buf)?;
         }
 
         for item in &self.question {
-            item.pack(buf)?;
-        }
-        for item in &self.answer {
-            item.header().pack(buf)?;
-            item.pack(buf)?;
+            match ctx.as_deref_mut() {
+                Some(ctx) => {
+                    ctx.pack_name(&item.name, buf)?;
+                    buf.put_u16(item.q_type);
+                    buf.put_u16(item.q_class);
+                }
+                None => item.pack(buf)?,
+            }
         }
-        for item in &self.authority {
-            item.header().pack(buf)?;
-            item.pack(buf)?;
+        if let Some(ends) = ends.as_deref_mut() {
+            ends.push(buf.len());
         }
-        for item in &self.additional {
-            if let RecourseRecord::Opt(opt) = &item {
-                let mut new_opt = opt.hdr.clone();
-                new_opt.ttl = opt.op_extended_r_code(r_code);
-                new_opt.pack(buf)?;
-            } else {
-                item.header().pack(buf)?;
+        for item in self.answer.iter().chain(&self.authority).chain(&self.additional) {
+            Self::pack_record(item, r_code, buf, ctx.as_deref_mut())?;
+            if let Some(ends) = ends.as_deref_mut() {
+                ends.push(buf.len());
             }
-            item.pack(buf)?;
         }
 
         Ok(())
     }
 
+    fn pack_record(
+        item: &RecourseRecord,
+        r_code: u16,
+        buf: &mut BytesMut,
+        ctx: Option<&mut CompressionContext>,
+    ) -> Result<()> {
+        let mut hdr = Cow::Borrowed(item.header());
+        if let RecourseRecord::Opt(opt) = &item {
+            hdr.to_mut().ttl = opt.op_extended_r_code(r_code);
+        }
+        let Some(ctx) = ctx else {
+            hdr.pack(buf)?;
+            return item.pack(buf);
+        };
+        ctx.pack_name(&hdr.name, buf)?;
+        buf.put_u16(hdr.typ);
+        buf.put_u16(hdr.class);
+        buf.put_u32(hdr.ttl);
+        buf.put_u16(hdr.rd_length);
+        let start = buf.len();
+        match item {
+            RecourseRecord::CNAME(val) => ctx.pack_name(&val.target, buf)?,
+            RecourseRecord::NS(val) => ctx.pack_name(&val.ns, buf)?,
+            RecourseRecord::PTR(val) => ctx.pack_name(&val.ptr, buf)?,
+            RecourseRecord::MX(val) => {
+                buf.put_u16(val.preference);
+                ctx.pack_name(&val.mx, buf)?;
+            }
+            RecourseRecord::SOA(val) => {
+                ctx.pack_name(&val.ns, buf)?;
+                ctx.pack_name(&val.mbox, buf)?;
+                for v in [val.serial, val.refresh, val.retry, val.expire, val.min_ttl] {
+                    buf.put_u32(v);
+                }
+            }
+            _ => return item.pack(buf),
+        }
+        let count = buf.len() - start;
+        util::set_value_offset(buf.as_mut(), start - 2, count as u16);
+        Ok(())
+    }
+
     pub fn unpack(msg: &[u8]) -> Result<Self> {
         let mut cur = Cursor::new(msg);
-        let pkt_msg_hdr = PktMsgHeader::unpack(&mut cur)?;
+        let pkt_msg_hdr = PktMsgHeader::unpack(&mut cur)
+            .map_err(|err| diag::malformed(msg, 0, Section::Header, 0, err))?;
         let mut msg = Msg {
             hdr: pkt_msg_hdr.into(),
             ..Default::default()
@@ -523,19 +932,43 @@ impl Msg {
         Ok(msg)
     }
 
+    /// Unpacks `msg` into `self`, like [`Msg::unpack`] but reusing the
+    /// capacity of the sections. On error `self` holds whatever had been
+    /// unpacked.
+    pub fn unpack_into(&mut self, msg: &[u8]) -> Result<()> {
+        let mut cur = Cursor::new(msg);
+        let pkt_msg_hdr = PktMsgHeader::unpack(&mut cur)
+            .map_err(|err| diag::malformed(msg, 0, Section::Header, 0, err))?;
+        self.hdr = pkt_msg_hdr.into();
+        self.__unpack(pkt_msg_hdr, &mut cur)
+    }
+
+    /// Annotated structure of `msg` for debugging, one line per header,
+    /// question and record, ending with a hexdump where parsing stopped.
+    pub fn explain(msg: &[u8]) -> String {
+        diag::explain(msg)
+    }
+
+    /// The records of `msg`, unpacked one at a time as the iterator is
+    /// advanced, for callers that look at a few of them without building
+    /// the whole `Msg`. The header and questions are checked up front.
+    pub fn records_iter(msg: &[u8]) -> Result<RecordIter<'_>> {
+        RecordIter::new(msg)
+    }
+
     pub fn unpack_answer(msg: &[u8]) -> Option<RRs> {
         let mut cur = Cursor::new(msg);
         if let Some(hdr) = Self::skip_questions(&mut cur) {
             let mut ret = RRs::new();
             if hdr.answer_count > 0 {
-                if let Ok(_) = unpack_slice(hdr.answer_count as usize, &mut ret.0, &mut cur) {
+                if unpack_slice(hdr.answer_count as usize, &mut ret.0, Section::Answer, &mut cur).is_ok() {
                     return Some(ret);
                 }
             } else {
                 return Some(ret);
             }
         }
-        return None;
+        None
     }
 
     pub fn pick_question(msg: &[u8]) -> Option<DomainString> {
@@ -563,21 +996,21 @@ impl Msg {
             }
             return Some(question);
         }
-        return None;
+        None
     }
 
     pub fn skip_questions(cur: &mut Cursor<&[u8]>) -> Option<PktMsgHeader> {
         if let Ok(val) = PktMsgHeader::unpack(cur) {
             if val.question_count > 0 {
                 for _ in 0..val.question_count {
-                    if let Err(_) = Question::skip(cur) {
+                    if Question::skip(cur).is_err() {
                         return None;
                     }
                 }
             }
             return Some(val);
         }
-        return None;
+        None
     }
 
     pub fn to_buf(&self) -> Result<BytesMut> {
@@ -593,25 +1026,27 @@ impl Msg {
 
     fn __unpack(&mut self, hdr: PktMsgHeader, cur: &mut Cursor<&[u8]>) -> Result<()> {
         if cur.get_ref().len() == cur.position() as usize {
-            self.question = vec![];
-            self.answer = vec![];
-            self.authority = vec![];
-            self.additional = vec![];
+            self.question.clear();
+            self.answer.clear();
+            self.authority.clear();
+            self.additional.clear();
             return Ok(());
         }
         self.question.clear();
-        for _ in 0..hdr.question_count {
-            self.question.push(Question::unpack(cur)?);
+        for index in 0..hdr.question_count as usize {
+            let start = cur.position() as usize;
+            let q = Question::unpack(cur).map_err(|err| diag::malformed(cur.get_ref(), start, Section::Question, index, err))?;
+            self.question.push(q);
         }
-        unpack_slice(hdr.answer_count as usize, self.answer.as_mut(), cur)?;
-        unpack_slice(hdr.authority_count as usize, self.authority.as_mut(), cur)?;
-        unpack_slice(hdr.additional_count as usize, self.additional.as_mut(), cur)?;
+        unpack_slice(hdr.answer_count as usize, self.answer.as_mut(), Section::Answer, cur)?;
+        unpack_slice(hdr.authority_count as usize, self.authority.as_mut(), Section::Authority, cur)?;
+        unpack_slice(hdr.additional_count as usize, self.additional.as_mut(), Section::Additional, cur)?;
 
         if let Some(opt) = self.is_edns0() {
             self.hdr.response_code |= opt.extended_r_code();
         }
 
-        return Ok(());
+        Ok(())
     }
 }
 
@@ -665,18 +1100,42 @@ impl Display for Msg {
     }
 }
 
-fn unpack_slice(l: usize, slice: &mut Vec<RecourseRecord>, cur: &mut Cursor<&[u8]>) -> Result<()> {
+/// Parses the text a [`Msg`] displays as, or the output of dig:
+/// the header and flags lines, the question, the record sections and the OPT
+/// pseudosection, wherever it stands. Records are read as in a master
+/// file, so every one needs its TTL; all but
+/// [`Extension`](crate::types::RecourseRecord::Extension) records, which
+/// display as their type chooses, read back as they display. Other `;;`
+/// lines, like dig's timing and server, are skipped, as are EDNS options
+/// without a text form here: only SUBNET/CLIENT-SUBNET, EDE, PADDING,
+/// EXPIRE, CHAIN, KEY-TAG and COOKIE come back.
+#[cfg(feature = "std")]
+impl core::str::FromStr for Msg {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        dig::parse(s)
+    }
+}
+
+fn unpack_slice(l: usize, slice: &mut Vec<RecourseRecord>, section: Section, cur: &mut Cursor<&[u8]>) -> Result<()> {
     slice.clear();
-    for _ in 0..l {
-        let h = RecourseRecordHdr::unpack(cur)?;
-        let l = cur.get_ref().len();
-        if h.rd_length as usize + cur.position() as usize > l {
-            return Err(error("overflow header").into());
-        }
-        if cur.position() as usize + h.rd_length as usize > l {
-            return Err(error("bad rdlength").into());
-        }
-        slice.push(RecourseRecord::unpack(h, cur)?);
+    for index in 0..l {
+        let start = cur.position() as usize;
+        let rr = unpack_rr(cur).map_err(|err| diag::malformed(cur.get_ref(), start, section, index, err))?;
+        slice.push(rr);
     }
     Ok(())
+}
+
+pub(super) fn unpack_rr(cur: &mut Cursor<&[u8]>) -> Result<RecourseRecord> {
+    let h = RecourseRecordHdr::unpack(cur)?;
+    let l = cur.get_ref().len();
+    if h.rd_length as usize + cur.position() as usize > l {
+        return Err(error("overflow header"));
+    }
+    if cur.position() as usize + h.rd_length as usize > l {
+        return Err(error("bad rdlength"));
+    }
+    RecourseRecord::unpack(h, cur)
 }
\ No newline at end of file
@@ -0,0 +1,195 @@
+//! Grouping records into RRsets (RFC 2181 section 5).
+
+use crate::prelude::*;
+use crate::msg::RR;
+use crate::types::RecourseRecord;
+use crate::{util, DomainString, Error, Result};
+
+/// The records of one owner name, type and class, which share a TTL.
+#[derive(Debug, Clone)]
+pub struct RrSet {
+    pub name: DomainString,
+    pub typ: u16,
+    pub class: u16,
+    /// The smallest TTL of the records, which RFC 2181 section 5.2 has
+    /// stand for the whole set.
+    pub ttl: u32,
+    pub records: Vec<RecourseRecord>,
+}
+
+impl RrSet {
+    /// A set of `first` alone.
+    pub fn new(first: RecourseRecord) -> Self {
+        let hdr = first.header();
+        Self {
+            name: hdr.name.clone(),
+            typ: hdr.typ,
+            class: hdr.class,
+            ttl: hdr.ttl,
+            records: vec![first],
+        }
+    }
+
+    /// Groups `records` into sets, in the order each set first appears.
+    /// OPT records, which belong to no set, are left out.
+    pub fn group<'a, I: IntoIterator<Item = &'a RecourseRecord>>(records: I) -> Vec<RrSet> {
+        let mut sets: Vec<RrSet> = Vec::new();
+        for rr in records {
+            if matches!(rr, RecourseRecord::Opt(_)) {
+                continue;
+            }
+            match sets.iter_mut().find(|set| set.contains(rr)) {
+                Some(set) => set.add(rr.clone()),
+                None => sets.push(RrSet::new(rr.clone())),
+            }
+        }
+        sets
+    }
+
+    /// Whether `rr` belongs in this set; names compare case-insensitively.
+    pub fn contains(&self, rr: &RecourseRecord) -> bool {
+        let hdr = rr.header();
+        hdr.typ == self.typ && hdr.class == self.class && util::equal_names(&hdr.name, &self.name)
+    }
+
+    /// Adds `rr`, which must belong in the set.
+    pub fn push(&mut self, rr: RecourseRecord) -> Result<()> {
+        if !self.contains(&rr) {
+            return Err(Error::new(format!("{} does not belong to the {} RRset", rr.header().name, self.name)));
+        }
+        self.add(rr);
+        Ok(())
+    }
+
+    fn add(&mut self, rr: RecourseRecord) {
+        self.ttl = self.ttl.min(rr.header().ttl);
+        self.records.push(rr);
+    }
+
+    /// Puts the records in canonical order (RFC 4034 section 6.3), by
+    /// their canonical data, dropping any that repeat.
+    pub fn sort_canonical(&mut self) -> Result<()> {
+        let mut keyed = self.records.drain(..)
+            .map(|rr| Ok((rr.canonical_rdata()?, rr)))
+            .collect::<Result<Vec<_>>>()?;
+        keyed.sort_by(|a, b| a.0.cmp(&b.0));
+        keyed.dedup_by(|a, b| a.0 == b.0);
+        self.records = keyed.into_iter().map(|(_, rr)| rr).collect();
+        Ok(())
+    }
+
+    /// The set in canonical form, as signed: names lowercased, records in
+    /// canonical order, and each TTL `orig_ttl` when given, as the
+    /// original TTL of an RRSIG would be.
+    pub fn to_canonical(&self, orig_ttl: Option<u32>) -> Result<RrSet> {
+        let ttl = orig_ttl.unwrap_or(self.ttl);
+        let mut set = RrSet {
+            name: self.name.to_ascii_lowercase().into(),
+            typ: self.typ,
+            class: self.class,
+            ttl,
+            records: self.records.iter().map(RecourseRecord::to_canonical).collect(),
+        };
+        for rr in &mut set.records {
+            rr.header_mut().ttl = ttl;
+        }
+        set.sort_canonical()?;
+        Ok(set)
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    pub fn iter(&self) -> core::slice::Iter<'_, RecourseRecord> {
+        self.records.iter()
+    }
+
+    /// The records, each with the TTL of the set.
+    pub fn into_records(self) -> Vec<RecourseRecord> {
+        let ttl = self.ttl;
+        self.records
+            .into_iter()
+            .map(|mut rr| {
+                rr.header_mut().ttl = ttl;
+                rr
+            })
+            .collect()
+    }
+}
+
+impl<'a> IntoIterator for &'a RrSet {
+    type Item = &'a RecourseRecord;
+    type IntoIter = core::slice::Iter<'a, RecourseRecord>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.records.iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use core::net::Ipv4Addr;
+    use crate::msg::{RecourseRecordHdr, RR};
+    use crate::prelude::*;
+    use crate::{full_domain, types, Msg};
+    use super::RrSet;
+
+    #[test]
+    fn test_rrsets() {
+        let a = |name: &str, ttl, last| types::A::new(full_domain(name), types::CLASS_INET, ttl, Ipv4Addr::new(192, 0, 2, last));
+        let mut msg = Msg::new();
+        msg.answer.push(a("www.example.com", 300, 1).into());
+        msg.answer.push(types::CNAME::new(full_domain("mail.example.com"), types::CLASS_INET, 60, full_domain("www.example.com")).into());
+        msg.answer.push(a("WWW.example.com", 120, 2).into());
+        msg.additional.push(types::Opt::new(1232).into());
+        msg.additional.push(a("ns.example.com", 60, 53).into());
+
+        let sets = msg.answer_rrsets();
+        assert_eq!(sets.len(), 2);
+        assert_eq!((sets[0].typ, sets[0].len(), sets[0].ttl), (types::TYPE_A, 2, 120));
+        assert_eq!(sets[1].typ, types::TYPE_CNAME);
+        assert!(msg.authority_rrsets().is_empty());
+        assert_eq!(msg.additional_rrsets().len(), 1);
+
+        let mut set = sets[0].clone();
+        assert!(set.push(msg.answer[1].clone()).is_err());
+        assert!(set.push(a("www.example.com.", 30, 3).into()).is_ok());
+        assert_eq!(set.iter().count(), 3);
+        assert!(set.into_records().iter().all(|rr| rr.header().ttl == 30));
+        assert_eq!(RrSet::new(msg.answer[1].clone()).ttl, 60);
+    }
+
+    #[test]
+    fn test_canonical() {
+        let ns = |target: &str, ttl| types::NS::new(full_domain("Example.COM"), types::CLASS_INET, ttl, full_domain(target)).into();
+        let mut set = RrSet::new(ns("NS2.example.com", 300));
+        set.push(ns("ns1.Example.com", 60)).unwrap();
+        set.push(ns("ns10.example.com", 300)).unwrap();
+        set.push(ns("ns1.example.com", 300)).unwrap();
+
+        let canonical = set.to_canonical(Some(3600)).unwrap();
+        assert_eq!(canonical.name, "example.com.");
+        let records: Vec<_> = canonical.iter().map(|rr| rr.to_string()).collect();
+        assert_eq!(records, [
+            "example.com.\t3600\tIN\tNS\tns1.example.com.",
+            "example.com.\t3600\tIN\tNS\tns2.example.com.",
+            "example.com.\t3600\tIN\tNS\tns10.example.com.",
+        ]);
+        assert_eq!(set.to_canonical(None).unwrap().iter().next().unwrap().header().ttl, 60);
+        let mx = types::MX::new(full_domain("example.com"), types::CLASS_INET, 60, 10, full_domain("Mail.Example.com")).into();
+        assert_eq!(RrSet::new(mx).to_canonical(None).unwrap().records[0].to_string(), "example.com.\t60\tIN\tMX\t10 mail.example.com.");
+
+        // by octets, and a prefix before what it starts
+        let a = types::A::new(full_domain("a.example"), types::CLASS_INET, 60, Ipv4Addr::new(192, 0, 2, 1)).into();
+        let hdr = RecourseRecordHdr { name: full_domain("a.example"), typ: types::TYPE_A, class: types::CLASS_INET, ttl: 60, rd_length: 5 };
+        let mut set = RrSet::new(a);
+        set.records.insert(0, types::RFC3597 { hdr, data: "c0000201ff".into() }.into());
+        set.sort_canonical().unwrap();
+        assert_eq!(set.records[0].rdata().unwrap(), [192, 0, 2, 1]);
+    }
+}
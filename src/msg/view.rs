@@ -0,0 +1,318 @@
+//! A packed message read in place, without unpacking it into a [`Msg`].
+
+use core::fmt;
+use core::fmt::{Debug, Formatter};
+use crate::io::{Cursor, ReadBytesExt};
+use byteorder::BigEndian;
+use crate::types::RecourseRecord;
+use crate::{util, DomainString, Result};
+use super::{diag, error, unpack_rr, Msg, MsgHdr, PktMsgHeader, Question, Section};
+
+/// Borrowed view of a packed message. Its layout is checked once by
+/// [`MsgView::new`]; after that questions and records are read from the
+/// buffer as they are iterated, with names decoded only on request and
+/// record data handed out as slices, so nothing is allocated.
+#[derive(Clone, Copy)]
+pub struct MsgView<'a> {
+    msg: &'a [u8],
+    hdr: PktMsgHeader,
+    records: usize,
+}
+
+impl<'a> MsgView<'a> {
+    /// Checks that every name and record of `msg` is where its header
+    /// says, failing as [`Msg::unpack`] would on a malformed layout.
+    /// Record data is not looked into until [`RecordView::to_record`].
+    pub fn new(msg: &'a [u8]) -> Result<Self> {
+        let mut cur = Cursor::new(msg);
+        let hdr = PktMsgHeader::unpack(&mut cur).map_err(|err| diag::malformed(msg, 0, Section::Header, 0, err))?;
+        for index in 0..hdr.question_count as usize {
+            let start = cur.position() as usize;
+            Question::skip(&mut cur).map_err(|err| diag::malformed(msg, start, Section::Question, index, err))?;
+        }
+        let view = Self {
+            msg,
+            hdr,
+            records: cur.position() as usize,
+        };
+        let mut records = view.records();
+        while records.pending.is_some() {
+            let start = records.pos;
+            let (section, index) = records.pending.unwrap();
+            if let Err(err) = skip_record(&mut cur) {
+                return Err(diag::malformed(msg, start, section, index, err));
+            }
+            records.pos = cur.position() as usize;
+            records.advance();
+        }
+        Ok(view)
+    }
+
+    pub fn header(&self) -> MsgHdr {
+        self.hdr.into()
+    }
+
+    /// The whole packed message.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.msg
+    }
+
+    pub fn questions(&self) -> impl Iterator<Item = QuestionView<'a>> + 'a {
+        let msg = self.msg;
+        let mut pos = 12;
+        (0..self.hdr.question_count).map(move |_| {
+            let name = NameView { msg, offset: pos };
+            pos = name.end();
+            let q_type = u16::from_be_bytes([msg[pos], msg[pos + 1]]);
+            let q_class = u16::from_be_bytes([msg[pos + 2], msg[pos + 3]]);
+            pos += 4;
+            QuestionView { name, q_type, q_class }
+        })
+    }
+
+    /// The answer, authority and additional records, in order.
+    pub fn records(&self) -> RecordViews<'a> {
+        let mut records = RecordViews {
+            msg: self.msg,
+            hdr: self.hdr,
+            pos: self.records,
+            pending: Some((Section::Answer, 0)),
+        };
+        records.skip_empty();
+        records
+    }
+
+    pub fn answers(&self) -> impl Iterator<Item = RecordView<'a>> + 'a {
+        self.records().filter(|rr| rr.section == Section::Answer)
+    }
+
+    /// Unpacks the whole message.
+    pub fn to_msg(&self) -> Result<Msg> {
+        Msg::unpack(self.msg)
+    }
+}
+
+impl Debug for MsgView<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MsgView")
+            .field("hdr", &self.header())
+            .field("len", &self.msg.len())
+            .finish()
+    }
+}
+
+fn skip_record(cur: &mut Cursor<&[u8]>) -> Result<()> {
+    if !util::skip_domain_name(cur) {
+        return Err(error("bad owner name"));
+    }
+    cur.set_position(cur.position() + 8);
+    let rd_length = cur.read_u16::<BigEndian>()? as u64;
+    if cur.position() + rd_length > cur.get_ref().len() as u64 {
+        return Err(error("bad rdlength"));
+    }
+    cur.set_position(cur.position() + rd_length);
+    Ok(())
+}
+
+/// A name in a packed message, compression pointers and all.
+#[derive(Clone, Copy)]
+pub struct NameView<'a> {
+    msg: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> NameView<'a> {
+    /// The labels, root label left out, following compression pointers.
+    pub fn labels(&self) -> impl Iterator<Item = &'a [u8]> + 'a {
+        let msg = self.msg;
+        let mut pos = self.offset;
+        core::iter::from_fn(move || loop {
+            let len = *msg.get(pos)? as usize;
+            if len & 0xC0 == 0xC0 {
+                pos = (len & 0x3F) << 8 | *msg.get(pos + 1)? as usize;
+                continue;
+            }
+            if len == 0 {
+                return None;
+            }
+            let label = msg.get(pos + 1..pos + 1 + len)?;
+            pos += 1 + len;
+            return Some(label);
+        })
+    }
+
+    /// Whether this is `name`, ignoring ASCII case.
+    pub fn eq_name(&self, name: &str) -> bool {
+        let labels = util::name_labels(name);
+        self.labels().count() == labels.len() && self.labels().zip(&labels).all(|(a, b)| a.eq_ignore_ascii_case(b))
+    }
+
+    /// Decodes the name, as `Msg::unpack` would.
+    pub fn to_name(&self) -> Result<DomainString> {
+        let mut cur = Cursor::new(self.msg);
+        cur.set_position(self.offset as u64);
+        util::unpack_domain_name_cur(&mut cur)
+    }
+
+    /// Offset just past the name where it is written.
+    fn end(&self) -> usize {
+        let mut cur = Cursor::new(self.msg);
+        cur.set_position(self.offset as u64);
+        util::skip_domain_name(&mut cur);
+        cur.position() as usize
+    }
+}
+
+impl Debug for NameView<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.to_name() {
+            Ok(name) => Debug::fmt(&name, f),
+            Err(_) => f.write_str("<bad name>"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct QuestionView<'a> {
+    pub name: NameView<'a>,
+    pub q_type: u16,
+    pub q_class: u16,
+}
+
+impl QuestionView<'_> {
+    pub fn to_question(&self) -> Result<Question> {
+        Ok(Question {
+            name: self.name.to_name()?,
+            q_type: self.q_type,
+            q_class: self.q_class,
+        })
+    }
+}
+
+/// A record in a packed message, its data left as it is on the wire.
+#[derive(Debug, Clone, Copy)]
+pub struct RecordView<'a> {
+    pub section: Section,
+    /// Offset of the record in the message.
+    pub offset: usize,
+    pub name: NameView<'a>,
+    pub typ: u16,
+    pub class: u16,
+    pub ttl: u32,
+    /// The record data; names in it may be compression pointers into the
+    /// rest of the message.
+    pub rdata: &'a [u8],
+}
+
+impl RecordView<'_> {
+    /// Unpacks the record.
+    pub fn to_record(&self) -> Result<RecourseRecord> {
+        let mut cur = Cursor::new(self.name.msg);
+        cur.set_position(self.offset as u64);
+        unpack_rr(&mut cur)
+    }
+}
+
+/// Iterator over the records of a [`MsgView`].
+pub struct RecordViews<'a> {
+    msg: &'a [u8],
+    hdr: PktMsgHeader,
+    pos: usize,
+    /// Section and index of the next record, `None` past the last.
+    pending: Option<(Section, usize)>,
+}
+
+impl RecordViews<'_> {
+    fn advance(&mut self) {
+        if let Some((_, index)) = &mut self.pending {
+            *index += 1;
+        }
+        self.skip_empty();
+    }
+
+    fn skip_empty(&mut self) {
+        while let Some((section, index)) = self.pending {
+            let len = match section {
+                Section::Answer => self.hdr.answer_count,
+                Section::Authority => self.hdr.authority_count,
+                _ => self.hdr.additional_count,
+            };
+            if index < len as usize {
+                return;
+            }
+            self.pending = match section {
+                Section::Answer => Some((Section::Authority, 0)),
+                Section::Authority => Some((Section::Additional, 0)),
+                _ => None,
+            };
+        }
+    }
+}
+
+impl<'a> Iterator for RecordViews<'a> {
+    type Item = RecordView<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (section, _) = self.pending?;
+        let msg = self.msg;
+        let name = NameView { msg, offset: self.pos };
+        let pos = name.end();
+        let field = |at: usize, len: usize| msg[pos + at..pos + at + len].iter().fold(0u32, |v, b| v << 8 | *b as u32);
+        let rd_length = field(8, 2) as usize;
+        let view = RecordView {
+            section,
+            offset: self.pos,
+            name,
+            typ: field(0, 2) as u16,
+            class: field(2, 2) as u16,
+            ttl: field(4, 4),
+            rdata: &msg[pos + 10..pos + 10 + rd_length],
+        };
+        self.pos = pos + 10 + rd_length;
+        self.advance();
+        Some(view)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use core::net::Ipv4Addr;
+    use crate::msg::Section;
+    use crate::prelude::*;
+    use crate::{full_domain, types, Msg};
+    use super::MsgView;
+
+    #[test]
+    fn test_msg_view() {
+        let mut msg = Msg::new();
+        msg.set_question(full_domain("www.Example.com"), types::TYPE_A);
+        msg.hdr.response = true;
+        msg.answer.push(types::CNAME::new(full_domain("www.example.com"), types::CLASS_INET, 60, full_domain("web.example.com")).into());
+        msg.answer.push(types::A::new(full_domain("web.example.com"), types::CLASS_INET, 30, Ipv4Addr::new(192, 0, 2, 1)).into());
+        msg.authority.push(types::NS::new(full_domain("example.com"), types::CLASS_INET, 300, full_domain("ns.example.com")).into());
+        msg.set_edns0(1232, false);
+        let buf = msg.to_buf().unwrap();
+
+        let view = MsgView::new(&buf).unwrap();
+        assert_eq!(view.header(), msg.hdr);
+        let question = view.questions().next().unwrap();
+        assert!(question.name.eq_name("WWW.example.com."));
+        assert!(!question.name.eq_name("example.com."));
+        assert_eq!(question.to_question().unwrap(), msg.question[0]);
+
+        let records: Vec<_> = view.records().collect();
+        assert_eq!(records.len(), 4);
+        assert_eq!(view.answers().count(), 2);
+        assert_eq!((records[1].typ, records[1].ttl, records[1].rdata), (types::TYPE_A, 30, &[192, 0, 2, 1][..]));
+        assert_eq!(records[1].name.to_name().unwrap(), "web.example.com.");
+        assert_eq!(records[1].name.labels().collect::<Vec<_>>(), [&b"web"[..], b"example", b"com"]);
+        assert_eq!((records[2].section, records[3].section, records[3].typ), (Section::Authority, Section::Additional, types::TYPE_OPT));
+        for (view, rr) in records.iter().zip(msg.answer.iter().chain(&msg.authority).chain(&msg.additional)) {
+            assert_eq!(&view.to_record().unwrap(), rr);
+        }
+        assert_eq!(view.to_msg().unwrap(), msg);
+
+        assert!(MsgView::new(&buf[..buf.len() - 1]).is_err());
+        assert!(MsgView::new(&buf[..20]).is_err());
+    }
+}
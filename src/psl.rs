@@ -0,0 +1,235 @@
+//! Public Suffix List (<https://publicsuffix.org/list/>) lookups.
+//!
+//! The module-level functions use the system copy of the list (the
+//! `publicsuffix` package on Linux distributions) unless [`set_global`] is
+//! called first. Without any list only the implicit `*` rule applies, so
+//! every TLD is a public suffix.
+
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use log::*;
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use crate::punycode_encode;
+
+/// Where distributions install the list.
+const SYSTEM_PATHS: [&str; 2] = ["/usr/share/publicsuffix/public_suffix_list.dat", "/etc/publicsuffix/public_suffix_list.dat"];
+
+static GLOBAL: Lazy<RwLock<Arc<PublicSuffixList>>> = Lazy::new(|| RwLock::new(Arc::new(PublicSuffixList::system())));
+
+/// Replaces the list used by the module-level functions.
+pub fn set_global(list: PublicSuffixList) {
+    *GLOBAL.write() = Arc::new(list);
+}
+
+/// The list used by the module-level functions.
+pub fn global() -> Arc<PublicSuffixList> {
+    GLOBAL.read().clone()
+}
+
+/// See [`PublicSuffixList::public_suffix`].
+pub fn public_suffix(name: &str) -> Option<String> {
+    global().public_suffix(name)
+}
+
+/// See [`PublicSuffixList::is_public_suffix`].
+pub fn is_public_suffix(name: &str) -> bool {
+    global().is_public_suffix(name)
+}
+
+/// See [`PublicSuffixList::registrable_domain`].
+pub fn registrable_domain(name: &str) -> Option<String> {
+    global().registrable_domain(name)
+}
+
+/// The public suffix plus one label, the same as [`registrable_domain`].
+pub fn effective_tld_plus_one(name: &str) -> Option<String> {
+    registrable_domain(name)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Rule {
+    Normal,
+    /// `!` rules, which end the suffix one label earlier.
+    Exception,
+}
+
+/// A trie of rules keyed by label, TLD first. `*` labels are wildcards.
+#[derive(Debug, Default, Clone)]
+struct Node {
+    rule: Option<Rule>,
+    children: HashMap<Box<str>, Node>,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct PublicSuffixList {
+    root: Node,
+    rules: usize,
+}
+
+impl PublicSuffixList {
+    /// A list with no rules but the implicit `*`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The first system copy found; missing files are logged and leave the list empty.
+    pub fn system() -> Self {
+        for path in SYSTEM_PATHS {
+            if Path::new(path).exists() {
+                match Self::from_path(path) {
+                    Ok(list) => return list,
+                    Err(err) => error!("load public suffix list {:?} failed {:?}", path, err),
+                }
+            }
+        }
+        debug!("no public suffix list found");
+        Self::new()
+    }
+
+    pub fn from_path<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self::parse(&std::fs::read_to_string(path)?))
+    }
+
+    /// Parses the `.dat` format: one rule per line, `//` comments. Both the
+    /// ICANN and the private sections are kept. Unicode rules are stored as
+    /// A-labels to match names off the wire.
+    pub fn parse(text: &str) -> Self {
+        let mut list = Self::new();
+        for line in text.lines() {
+            let Some(rule) = line.split_whitespace().next() else { continue; };
+            if rule.starts_with("//") {
+                continue;
+            }
+            list.insert(rule);
+        }
+        list
+    }
+
+    /// Adds one rule, e.g. `co.uk`, `*.ck` or `!www.ck`.
+    pub fn insert(&mut self, rule: &str) {
+        let (kind, rule) = match rule.strip_prefix('!') {
+            Some(rule) => (Rule::Exception, rule),
+            None => (Rule::Normal, rule),
+        };
+        let Some(labels) = labels(rule) else { return; };
+        let mut node = &mut self.root;
+        for label in labels.iter().rev() {
+            node = node.children.entry(label.as_str().into()).or_default();
+        }
+        if node.rule.replace(kind).is_none() {
+            self.rules += 1;
+        }
+    }
+
+    /// Number of rules loaded.
+    pub fn len(&self) -> usize {
+        self.rules
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules == 0
+    }
+
+    /// The public suffix of `name`, lowercase and without the trailing dot.
+    pub fn public_suffix(&self, name: &str) -> Option<String> {
+        let labels = labels(name)?;
+        let n = self.suffix_labels(&labels);
+        Some(labels[labels.len() - n..].join("."))
+    }
+
+    pub fn is_public_suffix(&self, name: &str) -> bool {
+        labels(name).is_some_and(|labels| self.suffix_labels(&labels) == labels.len())
+    }
+
+    /// The public suffix plus one label, or `None` when `name` is itself a
+    /// public suffix.
+    pub fn registrable_domain(&self, name: &str) -> Option<String> {
+        let labels = labels(name)?;
+        let n = self.suffix_labels(&labels);
+        if labels.len() <= n {
+            return None;
+        }
+        Some(labels[labels.len() - n - 1..].join("."))
+    }
+
+    /// Labels in the public suffix: exception rules win, then the longest
+    /// matching rule, then `*`.
+    fn suffix_labels(&self, labels: &[String]) -> usize {
+        let mut longest = 1;
+        let mut exception = None;
+        let mut frontier = vec![&self.root];
+        for (depth, label) in labels.iter().rev().enumerate() {
+            let mut next = Vec::new();
+            for node in frontier {
+                for key in [label.as_str(), "*"] {
+                    let Some(child) = node.children.get(key) else { continue; };
+                    match child.rule {
+                        Some(Rule::Normal) => longest = longest.max(depth + 1),
+                        Some(Rule::Exception) => exception = Some(depth),
+                        None => {}
+                    }
+                    next.push(child);
+                }
+            }
+            if next.is_empty() {
+                break;
+            }
+            frontier = next;
+        }
+        exception.unwrap_or(longest)
+    }
+}
+
+/// Lowercase A-labels of `name`; `None` for the root or empty labels.
+fn labels(name: &str) -> Option<Vec<String>> {
+    let name = name.strip_suffix('.').unwrap_or(name);
+    if name.is_empty() {
+        return None;
+    }
+    name.split('.')
+        .map(|label| match label {
+            "" => None,
+            label if label.is_ascii() => Some(label.to_ascii_lowercase()),
+            label => punycode_encode(&label.to_lowercase()),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::PublicSuffixList;
+
+    #[test]
+    fn test_psl() {
+        let list = PublicSuffixList::parse(
+            "// ===BEGIN ICANN DOMAINS===\n\
+             com\nuk\nco.uk\njp\nkobe.jp\n*.kobe.jp\n!city.kobe.jp\nck\n*.ck\n!www.ck\n\
+             公司.cn\n\n// ===BEGIN PRIVATE DOMAINS===\ngithub.io\n",
+        );
+        assert_eq!(list.len(), 12);
+        let reg = |name| list.registrable_domain(name);
+
+        assert_eq!(reg("WWW.Example.CO.UK.").as_deref(), Some("example.co.uk"));
+        assert_eq!(reg("example.com").as_deref(), Some("example.com"));
+        assert_eq!(reg("co.uk"), None);
+        assert!(list.is_public_suffix("co.uk") && !list.is_public_suffix("a.co.uk"));
+        // the implicit * rule
+        assert_eq!(reg("a.b.example").as_deref(), Some("b.example"));
+        assert!(list.is_public_suffix("example"));
+        // wildcards and exceptions
+        assert_eq!(list.public_suffix("a.b.kobe.jp").as_deref(), Some("b.kobe.jp"));
+        assert_eq!(reg("a.city.kobe.jp").as_deref(), Some("city.kobe.jp"));
+        assert_eq!(reg("www.ck").as_deref(), Some("www.ck"));
+        assert!(list.is_public_suffix("other.ck"));
+        // private section and IDN rules
+        assert_eq!(reg("user.github.io").as_deref(), Some("user.github.io"));
+        assert_eq!(reg("食狮.公司.cn").as_deref(), Some("xn--85x722f.xn--55qx5d.cn"));
+        assert_eq!(reg("a.xn--85x722f.xn--55qx5d.cn").as_deref(), Some("xn--85x722f.xn--55qx5d.cn"));
+
+        assert_eq!(list.public_suffix("."), None);
+        assert_eq!(reg("a..com"), None);
+    }
+}
@@ -0,0 +1,182 @@
+#[cfg(feature = "geoip")]
+use std::collections::HashMap;
+use std::net::IpAddr;
+#[cfg(feature = "geoip")]
+use std::path::Path;
+#[cfg(feature = "geoip")]
+use std::sync::Arc;
+use ipnetwork::IpNetwork;
+use crate::types::edns::edns0::SubNet;
+use crate::types::{EDNS0, Opt};
+use crate::{types, Msg};
+#[cfg(feature = "geoip")]
+use crate::{Error, Result};
+
+/// The address to select answers for: the client subnet of the request if
+/// it carries one (RFC 7871), otherwise `peer`.
+pub fn client_address(request: &Msg, peer: IpAddr) -> IpAddr {
+    match request.is_edns0().and_then(Opt::subnet) {
+        Some(subnet) if subnet.source_netmask > 0 => subnet.address,
+        _ => peer,
+    }
+}
+
+/// Picks one of several candidate values by where the client is: the most
+/// specific configured network containing it, then its country in a
+/// MaxMind database (`geoip` feature), then the default.
+#[derive(Debug, Clone)]
+pub struct GeoSelector<T> {
+    /// Most specific first.
+    networks: Vec<(IpNetwork, T)>,
+    #[cfg(feature = "geoip")]
+    database: Option<Arc<maxminddb::Reader<Vec<u8>>>>,
+    /// ISO 3166 country codes, uppercase.
+    #[cfg(feature = "geoip")]
+    countries: HashMap<String, T>,
+    default: Option<T>,
+}
+
+impl<T> Default for GeoSelector<T> {
+    fn default() -> Self {
+        Self {
+            networks: Vec::new(),
+            #[cfg(feature = "geoip")]
+            database: None,
+            #[cfg(feature = "geoip")]
+            countries: HashMap::new(),
+            default: None,
+        }
+    }
+}
+
+impl<T> GeoSelector<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Clients in `net` get `value`, unless a more specific network matches.
+    pub fn insert_network(&mut self, net: IpNetwork, value: T) {
+        let at = self.networks.partition_point(|(n, _)| n.prefix() >= net.prefix());
+        self.networks.insert(at, (net, value));
+    }
+
+    /// Clients nothing else matches get `value`.
+    pub fn set_default(&mut self, value: T) {
+        self.default = Some(value);
+    }
+
+    /// Loads a GeoIP2 or GeoLite2 Country (or City) database.
+    #[cfg(feature = "geoip")]
+    pub fn open_database<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let reader = maxminddb::Reader::open_readfile(path).map_err(|e| Error::new(format!("open GeoIP database: {}", e)))?;
+        self.database = Some(Arc::new(reader));
+        Ok(())
+    }
+
+    /// Clients the database places in `country` (e.g. `DE`) get `value`.
+    #[cfg(feature = "geoip")]
+    pub fn insert_country(&mut self, country: &str, value: T) {
+        self.countries.insert(country.to_ascii_uppercase(), value);
+    }
+
+    /// The value for `client`, with the prefix length the choice holds for,
+    /// to be returned as the ECS scope.
+    pub fn select(&self, client: IpAddr) -> Option<(&T, u8)> {
+        let client = match client {
+            IpAddr::V6(ip) => ip.to_ipv4_mapped().map_or(client, IpAddr::V4),
+            ip => ip,
+        };
+        if let Some((net, value)) = self.networks.iter().find(|(net, _)| net.contains(client)) {
+            return Some((value, net.prefix()));
+        }
+        #[cfg(feature = "geoip")]
+        if let Some(found) = self.select_country(client) {
+            return Some(found);
+        }
+        self.default.as_ref().map(|value| (value, 0))
+    }
+
+    #[cfg(feature = "geoip")]
+    fn select_country(&self, client: IpAddr) -> Option<(&T, u8)> {
+        let database = self.database.as_ref()?;
+        let (record, prefix) = database.lookup_prefix::<maxminddb::geoip2::Country>(client).ok()?;
+        let code = record.country?.iso_code?;
+        self.countries.get(code).map(|value| (value, prefix as u8))
+    }
+}
+
+impl GeoSelector<Vec<IpAddr>> {
+    /// Answers an A or AAAA query with the addresses selected for its
+    /// client, whatever the name: one selector serves one name, so put it
+    /// behind a [`DomainMatcher`](super::DomainMatcher) for several.
+    ///
+    /// A client subnet in the request is echoed with the scope of the
+    /// choice. Returns `None` for other queries and unmatched clients.
+    pub fn answer_for(&self, request: &Msg, peer: IpAddr, ttl: u32) -> Option<Msg> {
+        if request.hdr.response || request.hdr.op_code != types::OPCODE_QUERY || request.question.len() != 1 {
+            return None;
+        }
+        let q = &request.question[0];
+        if !(q.is_a() || q.is_aaaa()) || q.q_class != types::CLASS_INET {
+            return None;
+        }
+        let (ips, scope) = self.select(client_address(request, peer))?;
+
+        let mut msg = Msg::new();
+        msg.set_reply(request);
+        msg.hdr.authoritative = true;
+        for ip in ips {
+            if ip.is_ipv4() == q.is_a() {
+                msg.answer.push(types::RecourseRecord::new_ip(q.name.clone(), types::CLASS_INET, ttl, *ip));
+            }
+        }
+        if let Some(opt) = request.is_edns0() {
+            let mut reply_opt = Opt::new(opt.udp_size());
+            if let Some(subnet) = opt.subnet() {
+                reply_opt.option.push(EDNS0::SubNet(SubNet { source_scope: scope, ..subnet.clone() }));
+            }
+            msg.additional.push(reply_opt.into());
+        }
+        Some(msg)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::{IpAddr, Ipv4Addr};
+    use crate::types::edns::edns0::SubNet;
+    use crate::types::{EDNS0, Opt};
+    use crate::{full_domain, types, Msg};
+    use super::{client_address, GeoSelector};
+
+    #[test]
+    fn test_geo() {
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+        let mut geo = GeoSelector::new();
+        geo.insert_network("10.0.0.0/8".parse().unwrap(), vec![ip("192.0.2.1")]);
+        geo.insert_network("10.1.0.0/16".parse().unwrap(), vec![ip("192.0.2.2"), ip("2001:db8::2")]);
+        geo.set_default(vec![ip("192.0.2.9")]);
+
+        assert_eq!(geo.select(ip("10.2.3.4")), Some((&vec![ip("192.0.2.1")], 8)));
+        assert_eq!(geo.select(ip("::ffff:10.1.3.4")).map(|(_, scope)| scope), Some(16));
+        assert_eq!(geo.select(ip("203.0.113.1")), Some((&vec![ip("192.0.2.9")], 0)));
+
+        let mut request = Msg::new();
+        request.set_question(full_domain("www.example.com"), types::TYPE_A);
+        let reply = geo.answer_for(&request, ip("10.9.9.9"), 30).unwrap();
+        assert!(matches!(&reply.answer[..], [types::RecourseRecord::A(a)] if a.a == Ipv4Addr::new(192, 0, 2, 1)));
+        assert!(reply.is_edns0().is_none());
+
+        // the client subnet wins over the address the query came from
+        let mut opt = Opt::new(1232);
+        opt.option.push(EDNS0::SubNet(SubNet::new(Ipv4Addr::new(10, 1, 2, 0).into(), 24, 0)));
+        request.additional.push(opt.into());
+        assert_eq!(client_address(&request, ip("10.9.9.9")), ip("10.1.2.0"));
+        let reply = geo.answer_for(&request, ip("10.9.9.9"), 30).unwrap();
+        assert!(matches!(&reply.answer[..], [types::RecourseRecord::A(a)] if a.a == Ipv4Addr::new(192, 0, 2, 2)));
+        assert_eq!(reply.is_edns0().and_then(Opt::subnet).map(|s| s.source_scope), Some(16));
+
+        request.set_question(full_domain("www.example.com"), types::TYPE_MX);
+        assert!(geo.answer_for(&request, ip("10.9.9.9"), 30).is_none());
+    }
+}
@@ -0,0 +1,209 @@
+//! Per-domain routing: which upstream group answers which part of the
+//! namespace, with dnsmasq's `server=/corp.example/10.0.0.1` semantics,
+//! [`Rules`] for decisions that also depend on type, client and time, and
+//! [`GeoSelector`] for answers that depend on where the client is.
+
+mod geo;
+mod rules;
+
+pub use geo::{client_address, GeoSelector};
+pub use rules::{Action, Query, Rule, Rules};
+
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use crate::{Error, Result};
+
+/// Port used by `server=` entries without `#port`.
+const DNS_PORT: u16 = 53;
+
+/// Maps domain suffixes to values, the longest matching suffix wins.
+///
+/// A pattern `corp.example` matches the name itself and everything below
+/// it; `*.corp.example` only what is below. At the same depth the plain
+/// pattern is more specific than the wildcard. `.` (or `#`) matches every
+/// name, as a default route.
+#[derive(Debug, Clone)]
+pub struct DomainMatcher<T> {
+    root: Node<T>,
+    len: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Node<T> {
+    value: Option<T>,
+    children: HashMap<Box<str>, Node<T>>,
+}
+
+impl<T> Default for Node<T> {
+    fn default() -> Self {
+        Self { value: None, children: HashMap::new() }
+    }
+}
+
+impl<T> Default for DomainMatcher<T> {
+    fn default() -> Self {
+        Self { root: Node::default(), len: 0 }
+    }
+}
+
+impl<T> DomainMatcher<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the value of `pattern`, returning the one it replaces.
+    pub fn insert(&mut self, pattern: &str, value: T) -> Option<T> {
+        let old = self.node_mut(pattern).value.replace(value);
+        if old.is_none() {
+            self.len += 1;
+        }
+        old
+    }
+
+    /// The value of `pattern` itself, not of a match.
+    pub fn get_mut(&mut self, pattern: &str) -> Option<&mut T> {
+        let mut node = &mut self.root;
+        for label in labels(pattern) {
+            node = node.children.get_mut(label.as_str())?;
+        }
+        node.value.as_mut()
+    }
+
+    /// The value routing `name`, or `None` without a default route.
+    pub fn get(&self, name: &str) -> Option<&T> {
+        self.lookup(name).map(|(_, value)| value)
+    }
+
+    /// Like [`get`](Self::get), with the number of labels the winning
+    /// pattern covered (0 for the default route).
+    pub fn lookup(&self, name: &str) -> Option<(usize, &T)> {
+        let labels = labels(name);
+        let mut node = &self.root;
+        let mut found = node.value.as_ref().map(|v| (0, v));
+        for (depth, label) in labels.iter().enumerate() {
+            if let Some(value) = node.children.get("*").and_then(|n| n.value.as_ref()) {
+                found = Some((depth + 1, value));
+            }
+            match node.children.get(label.as_str()) {
+                Some(child) => node = child,
+                None => break,
+            }
+            if let Some(value) = &node.value {
+                found = Some((depth + 1, value));
+            }
+        }
+        found
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn node_mut(&mut self, pattern: &str) -> &mut Node<T> {
+        let mut node = &mut self.root;
+        for label in labels(pattern) {
+            node = node.children.entry(label.into()).or_default();
+        }
+        node
+    }
+}
+
+impl DomainMatcher<Vec<SocketAddr>> {
+    /// Reads `server=/domain/.../addr[#port]` lines as dnsmasq does: every
+    /// domain listed gets the address added to its group, and an empty
+    /// address (`server=/lan/`) gives an empty group, meaning the domain is
+    /// answered locally and never forwarded. Other lines, comments included,
+    /// are skipped; `server=addr` without domains adds to the default route.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut matcher = Self::new();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            let Some(spec) = line.strip_prefix("server=") else { continue; };
+            let err = |msg: String| Error::Syntax(n + 1, msg);
+
+            let (domains, addr) = match spec.strip_prefix('/') {
+                Some(spec) => spec.rsplit_once('/').ok_or_else(|| err(format!("unterminated domain list {:?}", spec)))?,
+                None => ("#", spec),
+            };
+            let addr = match addr {
+                "" => None,
+                addr => Some(parse_addr(addr).ok_or_else(|| err(format!("bad server address {:?}", addr)))?),
+            };
+            for domain in domains.split('/') {
+                let node = matcher.node_mut(domain);
+                let new = node.value.is_none();
+                node.value.get_or_insert_with(Vec::new).extend(addr);
+                if new {
+                    matcher.len += 1;
+                }
+            }
+        }
+        Ok(matcher)
+    }
+}
+
+/// `10.0.0.1`, `10.0.0.1#5353`, `fd00::1` or `fd00::1#5353`.
+fn parse_addr(s: &str) -> Option<SocketAddr> {
+    let (ip, port) = match s.split_once('#') {
+        Some((ip, port)) => (ip, port.parse().ok()?),
+        None => (s, DNS_PORT),
+    };
+    Some(SocketAddr::new(ip.parse::<IpAddr>().ok()?, port))
+}
+
+/// Lowercase labels of `name`, TLD first; `.`, `#` and `` are the root.
+fn labels(name: &str) -> Vec<String> {
+    let name = name.strip_suffix('.').unwrap_or(name);
+    if name.is_empty() || name == "#" {
+        return Vec::new();
+    }
+    name.rsplit('.').map(|label| label.to_ascii_lowercase()).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::SocketAddr;
+    use crate::Error;
+    use super::DomainMatcher;
+
+    #[test]
+    fn test_domain_matcher() {
+        let mut m = DomainMatcher::new();
+        m.insert("corp.example", "corp");
+        m.insert("*.dev.corp.example", "dev");
+        m.insert("db.dev.corp.example.", "db");
+        assert_eq!(m.get("corp.example"), Some(&"corp"));
+        assert_eq!(m.get("WWW.Corp.Example."), Some(&"corp"));
+        assert_eq!(m.get("dev.corp.example"), Some(&"corp"));
+        assert_eq!(m.lookup("a.dev.corp.example"), Some((4, &"dev")));
+        assert_eq!(m.get("db.dev.corp.example"), Some(&"db"));
+        assert_eq!(m.get("x.db.dev.corp.example"), Some(&"db"));
+        assert_eq!(m.get("example"), None);
+        assert_eq!(m.get("notcorp.example"), None);
+        m.insert(".", "default");
+        assert_eq!(m.lookup("example.com"), Some((0, &"default")));
+        assert_eq!(m.len(), 4);
+
+        let conf = "# routes\n\
+            server=/corp.example/10.0.0.1\n\
+            server=/corp.example/lab.example/10.0.0.2#5353\n\
+            server=/*.internal/fd00::1\n\
+            server=/lan/\n\
+            server=1.1.1.1\n\
+            no-resolv\n";
+        let m = DomainMatcher::parse(conf).unwrap();
+        let addrs = |v: &[&str]| v.iter().map(|a| a.parse::<SocketAddr>().unwrap()).collect::<Vec<_>>();
+        assert_eq!(m.get("a.corp.example").unwrap(), &addrs(&["10.0.0.1:53", "10.0.0.2:5353"]));
+        assert_eq!(m.get("lab.example").unwrap(), &addrs(&["10.0.0.2:5353"]));
+        assert_eq!(m.get("x.internal").unwrap(), &addrs(&["[fd00::1]:53"]));
+        assert_eq!(m.get("internal").unwrap(), &addrs(&["1.1.1.1:53"]));
+        assert!(m.get("printer.lan").unwrap().is_empty());
+
+        assert!(matches!(DomainMatcher::parse("\nserver=/a/10.0.0.300\n"), Err(Error::Syntax(2, _))));
+        assert!(matches!(DomainMatcher::parse("server=/a\n"), Err(Error::Syntax(1, _))));
+    }
+}
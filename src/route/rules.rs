@@ -0,0 +1,244 @@
+use std::net::IpAddr;
+use std::time::{SystemTime, UNIX_EPOCH};
+use ipnetwork::IpNetwork;
+use log::*;
+use crate::{full_domain, util, DomainString, Error, Result};
+use super::DomainMatcher;
+
+const MINUTES_PER_DAY: u16 = 24 * 60;
+
+/// What a matching rule does with the query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action<G> {
+    /// Send to an upstream group.
+    Forward(G),
+    /// Resolve this name instead, answering under the original one.
+    Rewrite(DomainString),
+    /// Refuse to resolve.
+    Block,
+    /// Log the query with this tag and keep evaluating.
+    Log(String),
+}
+
+/// One rule: every condition that is set must hold.
+#[derive(Debug, Clone)]
+pub struct Rule<G> {
+    pub domains: Option<DomainMatcher<()>>,
+    /// Query types, ascending; empty matches any.
+    pub qtypes: Vec<u16>,
+    /// Client networks; empty matches any.
+    pub clients: Vec<IpNetwork>,
+    /// Minutes since midnight, `[start, end)`; wraps past midnight when
+    /// `end < start`.
+    pub time: Option<(u16, u16)>,
+    pub action: Action<G>,
+}
+
+/// The facts a rule can match on.
+#[derive(Debug, Clone, Copy)]
+pub struct Query<'a> {
+    pub name: &'a str,
+    pub qtype: u16,
+    pub client: IpAddr,
+    /// Minutes since midnight in whatever zone the rules were written for.
+    pub minute: u16,
+}
+
+impl<'a> Query<'a> {
+    /// A query at the current UTC time.
+    pub fn now(name: &'a str, qtype: u16, client: IpAddr) -> Self {
+        let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        Self { name, qtype, client, minute: ((secs / 60) % MINUTES_PER_DAY as u64) as u16 }
+    }
+}
+
+impl<G> Rule<G> {
+    fn matches(&self, q: &Query) -> bool {
+        if !self.qtypes.is_empty() && self.qtypes.binary_search(&q.qtype).is_err() {
+            return false;
+        }
+        if let Some((start, end)) = self.time {
+            let inside = if start <= end { (start..end).contains(&q.minute) } else { q.minute >= start || q.minute < end };
+            if !inside {
+                return false;
+            }
+        }
+        if !self.clients.is_empty() && !self.clients.iter().any(|net| contains(net, q.client)) {
+            return false;
+        }
+        self.domains.as_ref().is_none_or(|d| d.get(q.name).is_some())
+    }
+}
+
+/// An ordered rule list, evaluated per query; the first matching rule with
+/// a terminal action decides.
+#[derive(Debug, Clone)]
+pub struct Rules<G> {
+    rules: Vec<Rule<G>>,
+}
+
+impl<G> Default for Rules<G> {
+    fn default() -> Self {
+        Self { rules: Vec::new() }
+    }
+}
+
+impl<G> Rules<G> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, mut rule: Rule<G>) {
+        rule.qtypes.sort_unstable();
+        rule.qtypes.dedup();
+        self.rules.push(rule);
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// The action for `q`, `None` when no rule decides. `Log` rules that
+    /// match on the way are logged at info level.
+    pub fn evaluate(&self, q: &Query) -> Option<&Action<G>> {
+        for rule in &self.rules {
+            if !rule.matches(q) {
+                continue;
+            }
+            match &rule.action {
+                Action::Log(tag) => info!("[{}] {} {} from {}", tag, q.name, q.qtype, q.client),
+                action => return Some(action),
+            }
+        }
+        None
+    }
+}
+
+impl Rules<String> {
+    /// Reads one rule per line: `key=value` conditions followed by an
+    /// action, `#` starts a comment.
+    ///
+    /// ```text
+    /// domain=corp.example,*.lab.example client=10.0.0.0/8 forward corp
+    /// qtype=ANY block
+    /// domain=old.example rewrite new.example
+    /// time=22:00-06:00 client=192.168.7.0/24 log night
+    /// ```
+    ///
+    /// Values are comma-separated lists; `time` is `HH:MM-HH:MM`.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut rules = Self::new();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default();
+            let mut tokens = line.split_whitespace().peekable();
+            if tokens.peek().is_none() {
+                continue;
+            }
+            let err = |msg: String| Error::Syntax(n + 1, msg);
+            let mut rule = Rule { domains: None, qtypes: Vec::new(), clients: Vec::new(), time: None, action: Action::Block };
+
+            let action = loop {
+                let token = tokens.next().ok_or_else(|| err("missing action".into()))?;
+                let Some((key, value)) = token.split_once('=') else { break token; };
+                match key {
+                    "domain" => {
+                        let domains = rule.domains.get_or_insert_with(DomainMatcher::new);
+                        for d in value.split(',') {
+                            domains.insert(d, ());
+                        }
+                    }
+                    "qtype" => {
+                        for t in value.split(',') {
+                            rule.qtypes.push(util::string_to_type(t).ok_or_else(|| err(format!("unknown type {:?}", t)))?);
+                        }
+                    }
+                    "client" => {
+                        for net in value.split(',') {
+                            rule.clients.push(net.parse().map_err(|_| err(format!("bad subnet {:?}", net)))?);
+                        }
+                    }
+                    "time" => rule.time = Some(parse_window(value).ok_or_else(|| err(format!("bad time window {:?}", value)))?),
+                    _ => return Err(err(format!("unknown condition {:?}", key))),
+                }
+            };
+            let mut arg = || tokens.next().ok_or_else(|| err(format!("{} needs an argument", action)));
+            rule.action = match action {
+                "forward" => Action::Forward(arg()?.to_string()),
+                "rewrite" => Action::Rewrite(full_domain(arg()?)),
+                "log" => Action::Log(arg()?.to_string()),
+                "block" => Action::Block,
+                _ => return Err(err(format!("unknown action {:?}", action))),
+            };
+            if let Some(extra) = tokens.next() {
+                return Err(err(format!("unexpected {:?} after action", extra)));
+            }
+            rules.push(rule);
+        }
+        Ok(rules)
+    }
+}
+
+/// `HH:MM-HH:MM` as minutes since midnight.
+fn parse_window(s: &str) -> Option<(u16, u16)> {
+    let minute = |t: &str| {
+        let (h, m) = t.split_once(':')?;
+        let (h, m): (u16, u16) = (h.parse().ok()?, m.parse().ok()?);
+        (h < 24 && m < 60).then_some(h * 60 + m)
+    };
+    let (start, end) = s.split_once('-')?;
+    Some((minute(start)?, minute(end)?))
+}
+
+/// IPv4 clients also match IPv4 networks when seen as mapped IPv6 addresses.
+fn contains(net: &IpNetwork, ip: IpAddr) -> bool {
+    match (net, ip) {
+        (IpNetwork::V4(net), IpAddr::V6(ip)) => ip.to_ipv4_mapped().is_some_and(|ip| net.contains(ip)),
+        _ => net.contains(ip),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::types;
+    use super::{Action, Query, Rules};
+
+    #[test]
+    fn test_rules() {
+        let rules = Rules::parse(
+            "# corp traffic\n\
+             domain=corp.example,*.lab.example client=10.0.0.0/8 forward corp\n\
+             qtype=ANY block\n\
+             domain=old.example rewrite new.example\n\
+             time=22:00-06:00 client=192.168.7.0/24 log night\n\
+             time=22:00-06:00 client=192.168.7.0/24 qtype=A,AAAA block\n",
+        )
+        .unwrap();
+        assert_eq!(rules.len(), 5);
+
+        let q = |name, qtype, client: &str, minute| Query { name, qtype, client: client.parse().unwrap(), minute };
+        let corp = Action::Forward("corp".to_string());
+        assert_eq!(rules.evaluate(&q("www.corp.example.", types::TYPE_A, "10.1.2.3", 0)), Some(&corp));
+        assert_eq!(rules.evaluate(&q("www.corp.example.", types::TYPE_A, "::ffff:10.1.2.3", 0)), Some(&corp));
+        assert_eq!(rules.evaluate(&q("www.corp.example.", types::TYPE_A, "192.0.2.1", 0)), None);
+        assert_eq!(rules.evaluate(&q("lab.example.", types::TYPE_A, "10.1.2.3", 0)), None);
+        assert_eq!(rules.evaluate(&q("example.com.", types::TYPE_ANY, "10.1.2.3", 0)), Some(&Action::Block));
+        assert!(matches!(rules.evaluate(&q("a.old.example.", 1, "10.1.2.3", 0)), Some(Action::Rewrite(n)) if n == "new.example."));
+
+        // 23:30 and 05:59 are inside the wrapping window, 06:00 is not
+        assert_eq!(rules.evaluate(&q("example.com.", types::TYPE_AAAA, "192.168.7.9", 23 * 60 + 30)), Some(&Action::Block));
+        assert_eq!(rules.evaluate(&q("example.com.", types::TYPE_A, "192.168.7.9", 5 * 60 + 59)), Some(&Action::Block));
+        assert_eq!(rules.evaluate(&q("example.com.", types::TYPE_A, "192.168.7.9", 6 * 60)), None);
+        assert_eq!(rules.evaluate(&q("example.com.", types::TYPE_MX, "192.168.7.9", 0)), None);
+
+        for (n, bad) in ["block extra", "qtype=NOPE block", "client=10.0.0.0/33 block", "time=25:00-01:00 block", "forward", "domain=a.", "color=red block"]
+            .iter()
+            .enumerate()
+        {
+            assert!(Rules::parse(bad).is_err(), "{} {}", n, bad);
+        }
+    }
+}
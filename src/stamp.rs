@@ -0,0 +1,345 @@
+//! DNS stamps: `sdns://` strings holding everything needed to reach a
+//! resolver, base64url over the fields of one protocol. Plain DNS,
+//! DNSCrypt, DoH, DoT, DoQ and ODoH targets and relays are supported;
+//! anonymized DNSCrypt relays are not.
+
+use core::fmt;
+use core::str::FromStr;
+use std::net::{IpAddr, SocketAddr};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use crate::{Error, Result};
+
+const SCHEME: &str = "sdns://";
+
+/// The resolver does DNSSEC validation.
+pub const PROP_DNSSEC: u64 = 1;
+/// The resolver does not keep logs.
+pub const PROP_NO_LOG: u64 = 2;
+/// The resolver does not block names of its own accord.
+pub const PROP_NO_FILTER: u64 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    Plain,
+    DnsCrypt,
+    DoH,
+    DoT,
+    DoQ,
+    OdohTarget,
+    OdohRelay,
+}
+
+impl Protocol {
+    fn id(self) -> u8 {
+        match self {
+            Protocol::Plain => 0x00,
+            Protocol::DnsCrypt => 0x01,
+            Protocol::DoH => 0x02,
+            Protocol::DoT => 0x03,
+            Protocol::DoQ => 0x04,
+            Protocol::OdohTarget => 0x05,
+            Protocol::OdohRelay => 0x85,
+        }
+    }
+
+    fn from_id(id: u8) -> Option<Self> {
+        Some(match id {
+            0x00 => Protocol::Plain,
+            0x01 => Protocol::DnsCrypt,
+            0x02 => Protocol::DoH,
+            0x03 => Protocol::DoT,
+            0x04 => Protocol::DoQ,
+            0x05 => Protocol::OdohTarget,
+            0x85 => Protocol::OdohRelay,
+            _ => return None,
+        })
+    }
+}
+
+/// A parsed stamp. Fields the protocol has no use for are empty.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stamp {
+    pub protocol: Protocol,
+    /// `PROP_*` bits.
+    pub props: u64,
+    /// IP address, optionally with a port: `9.9.9.9`, `[2620:fe::fe]:853`.
+    /// May be empty for encrypted protocols, to resolve the host name.
+    pub addr: String,
+    /// Provider public key of DNSCrypt, signing the certificates.
+    pub public_key: Vec<u8>,
+    /// SHA-256 digests of the to-be-signed part of certificates in the
+    /// chain, one of which must match.
+    pub hashes: Vec<Vec<u8>>,
+    /// Host name, optionally with a port; the provider name for DNSCrypt.
+    pub host: String,
+    /// HTTP path of DoH and ODoH.
+    pub path: String,
+    /// Resolvers to look up `host` with.
+    pub bootstrap: Vec<String>,
+}
+
+impl Stamp {
+    fn new(protocol: Protocol) -> Self {
+        Self { protocol, props: 0, addr: String::new(), public_key: Vec::new(), hashes: Vec::new(), host: String::new(), path: String::new(), bootstrap: Vec::new() }
+    }
+
+    pub fn plain(addr: &str) -> Self {
+        Self { addr: addr.into(), ..Self::new(Protocol::Plain) }
+    }
+
+    pub fn dnscrypt(addr: &str, public_key: &[u8], provider_name: &str) -> Self {
+        Self { addr: addr.into(), public_key: public_key.into(), host: provider_name.into(), ..Self::new(Protocol::DnsCrypt) }
+    }
+
+    pub fn doh(addr: &str, host: &str, path: &str) -> Self {
+        Self { addr: addr.into(), host: host.into(), path: path.into(), ..Self::new(Protocol::DoH) }
+    }
+
+    pub fn dot(addr: &str, host: &str) -> Self {
+        Self { addr: addr.into(), host: host.into(), ..Self::new(Protocol::DoT) }
+    }
+
+    pub fn doq(addr: &str, host: &str) -> Self {
+        Self { addr: addr.into(), host: host.into(), ..Self::new(Protocol::DoQ) }
+    }
+
+    pub fn odoh_target(host: &str, path: &str) -> Self {
+        Self { host: host.into(), path: path.into(), ..Self::new(Protocol::OdohTarget) }
+    }
+
+    pub fn odoh_relay(addr: &str, host: &str, path: &str) -> Self {
+        Self { addr: addr.into(), host: host.into(), path: path.into(), ..Self::new(Protocol::OdohRelay) }
+    }
+
+    pub fn with_props(mut self, props: u64) -> Self {
+        self.props = props;
+        self
+    }
+
+    /// How to send queries to the resolver of the stamp.
+    pub fn transport(&self) -> Result<Transport> {
+        let server_name = || self.host.rsplit_once(':').filter(|(_, port)| port.parse::<u16>().is_ok()).map_or(&self.host[..], |(host, _)| host).to_string();
+        let url = || format!("https://{}{}", self.host, self.path);
+        Ok(match self.protocol {
+            Protocol::Plain => Transport::Udp(socket_addr(&self.addr, 53)?),
+            Protocol::DnsCrypt => Transport::DnsCrypt {
+                addr: socket_addr(&self.addr, 443)?,
+                provider_name: self.host.clone(),
+                public_key: self.public_key[..].try_into().map_err(|_| Error::new("DNSCrypt stamp key is not 32 octets"))?,
+            },
+            Protocol::DoH => Transport::Https { url: url(), addr: optional_addr(&self.addr, 443)? },
+            Protocol::DoT => Transport::Tls { addr: optional_addr(&self.addr, 853)?, server_name: server_name() },
+            Protocol::DoQ => Transport::Quic { addr: optional_addr(&self.addr, 853)?, server_name: server_name() },
+            Protocol::OdohTarget => Transport::OdohTarget { host: self.host.clone(), path: self.path.clone() },
+            Protocol::OdohRelay => Transport::OdohRelay { url: url(), addr: optional_addr(&self.addr, 443)? },
+        })
+    }
+}
+
+impl FromStr for Stamp {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let encoded = s.strip_prefix(SCHEME).ok_or_else(|| Error::new(format!("not a DNS stamp {:?}", s)))?;
+        let data = URL_SAFE_NO_PAD.decode(encoded).map_err(|e| Error::new(format!("bad stamp encoding: {}", e)))?;
+        let mut r = Reader(&data);
+        let id = r.take(1)?[0];
+        let protocol = Protocol::from_id(id).ok_or_else(|| Error::new(format!("unsupported stamp protocol {:#04x}", id)))?;
+        let mut stamp = Stamp::new(protocol);
+        stamp.props = u64::from_le_bytes(r.take(8)?.try_into().unwrap());
+        if protocol != Protocol::OdohTarget {
+            stamp.addr = r.string()?;
+        }
+        if protocol == Protocol::DnsCrypt {
+            let len = r.take(1)?[0];
+            stamp.public_key = r.bytes(len)?;
+        }
+        if matches!(protocol, Protocol::DoH | Protocol::DoT | Protocol::DoQ | Protocol::OdohRelay) {
+            stamp.hashes = r.set()?.into_iter().filter(|h| !h.is_empty()).collect();
+        }
+        if protocol != Protocol::Plain {
+            stamp.host = r.string()?;
+        }
+        if matches!(protocol, Protocol::DoH | Protocol::OdohTarget | Protocol::OdohRelay) {
+            stamp.path = r.string()?;
+        }
+        if !r.0.is_empty() && matches!(protocol, Protocol::DoH | Protocol::DoT | Protocol::DoQ | Protocol::OdohRelay) {
+            stamp.bootstrap = r.set()?.into_iter().map(utf8).collect::<Result<_>>()?;
+        }
+        if !r.0.is_empty() {
+            return Err(Error::new("trailing data in stamp"));
+        }
+        Ok(stamp)
+    }
+}
+
+impl fmt::Display for Stamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut data = vec![self.protocol.id()];
+        data.extend_from_slice(&self.props.to_le_bytes());
+        let protocol = self.protocol;
+        if protocol != Protocol::OdohTarget {
+            put(&mut data, self.addr.as_bytes());
+        }
+        if protocol == Protocol::DnsCrypt {
+            put(&mut data, &self.public_key);
+        }
+        let encrypted = matches!(protocol, Protocol::DoH | Protocol::DoT | Protocol::DoQ | Protocol::OdohRelay);
+        if encrypted {
+            put_set(&mut data, self.hashes.iter().map(Vec::as_slice));
+        }
+        if protocol != Protocol::Plain {
+            put(&mut data, self.host.as_bytes());
+        }
+        if matches!(protocol, Protocol::DoH | Protocol::OdohTarget | Protocol::OdohRelay) {
+            put(&mut data, self.path.as_bytes());
+        }
+        if encrypted && !self.bootstrap.is_empty() {
+            put_set(&mut data, self.bootstrap.iter().map(String::as_bytes));
+        }
+        f.write_str(SCHEME)?;
+        f.write_str(&URL_SAFE_NO_PAD.encode(data))
+    }
+}
+
+/// Where and how to send queries, from [`Stamp::transport`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Transport {
+    /// Plain DNS, e.g. with [`lookup_host`](crate::client::lookup_host).
+    Udp(SocketAddr),
+    /// DNSCrypt v2, with `client::DnsCryptCert` (`dnscrypt` feature).
+    DnsCrypt { addr: SocketAddr, provider_name: String, public_key: [u8; 32] },
+    /// RFC 8484, e.g. with `fetch_doh`. `addr` saves resolving the host.
+    Https { url: String, addr: Option<SocketAddr> },
+    /// RFC 7858, `server_name` being the name to verify the certificate for.
+    Tls { addr: Option<SocketAddr>, server_name: String },
+    /// RFC 9250.
+    Quic { addr: Option<SocketAddr>, server_name: String },
+    /// An ODoH target, to reach through a relay with `fetch_odoh`.
+    OdohTarget { host: String, path: String },
+    /// An ODoH relay, the proxy URL of `fetch_odoh`.
+    OdohRelay { url: String, addr: Option<SocketAddr> },
+}
+
+/// `addr` with `port` if it has none.
+fn socket_addr(addr: &str, port: u16) -> Result<SocketAddr> {
+    if let Ok(addr) = addr.parse() {
+        return Ok(addr);
+    }
+    let ip = addr.strip_prefix('[').and_then(|a| a.strip_suffix(']')).unwrap_or(addr);
+    ip.parse::<IpAddr>().map(|ip| SocketAddr::new(ip, port)).map_err(|_| Error::new(format!("bad stamp address {:?}", addr)))
+}
+
+fn optional_addr(addr: &str, port: u16) -> Result<Option<SocketAddr>> {
+    if addr.is_empty() {
+        return Ok(None);
+    }
+    socket_addr(addr, port).map(Some)
+}
+
+fn utf8(data: Vec<u8>) -> Result<String> {
+    String::from_utf8(data).map_err(|_| Error::new("stamp field is not UTF-8"))
+}
+
+/// Length-prefixed field.
+fn put(data: &mut Vec<u8>, value: &[u8]) {
+    data.push(value.len() as u8);
+    data.extend_from_slice(value);
+}
+
+/// Fields whose length bytes have the top bit set but on the last one.
+fn put_set<'a, I: ExactSizeIterator<Item = &'a [u8]>>(data: &mut Vec<u8>, values: I) {
+    let count = values.len();
+    if count == 0 {
+        data.push(0);
+    }
+    for (i, value) in values.enumerate() {
+        data.push(value.len() as u8 | if i + 1 < count { 0x80 } else { 0 });
+        data.extend_from_slice(value);
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn take(&mut self, n: usize) -> Result<&[u8]> {
+        if self.0.len() < n {
+            return Err(Error::new("stamp too short"));
+        }
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn bytes(&mut self, len: u8) -> Result<Vec<u8>> {
+        self.take(len as usize).map(<[u8]>::to_vec)
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let len = self.take(1)?[0];
+        self.bytes(len).and_then(utf8)
+    }
+
+    fn set(&mut self) -> Result<Vec<Vec<u8>>> {
+        let mut values = Vec::new();
+        loop {
+            let len = self.take(1)?[0];
+            values.push(self.bytes(len & 0x7f)?);
+            if len & 0x80 == 0 {
+                return Ok(values);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Protocol, Stamp, Transport, PROP_DNSSEC, PROP_NO_FILTER, PROP_NO_LOG};
+
+    #[test]
+    fn test_stamps() {
+        let plain: Stamp = "sdns://AAEAAAAAAAAABzkuOS45Ljk".parse().unwrap();
+        assert_eq!(plain, Stamp::plain("9.9.9.9").with_props(PROP_DNSSEC));
+        assert_eq!(plain.transport().unwrap(), Transport::Udp("9.9.9.9:53".parse().unwrap()));
+
+        let text = "sdns://AgcAAAAAAAAABzkuOS45LjkgPhoaD2xT8-l6SS1XCEtbmAcFnuBXqxUFh2_YP9o9uDgNZG5zLnF1YWQ5Lm5ldAovZG5zLXF1ZXJ5";
+        let doh: Stamp = text.parse().unwrap();
+        assert_eq!((doh.protocol, doh.props), (Protocol::DoH, PROP_DNSSEC | PROP_NO_LOG | PROP_NO_FILTER));
+        assert_eq!((doh.host.as_str(), doh.path.as_str()), ("dns.quad9.net", "/dns-query"));
+        assert_eq!(hex::encode(&doh.hashes[0]), "3e1a1a0f6c53f3e97a492d57084b5b9807059ee057ab1505876fd83fda3db838");
+        assert_eq!(doh.to_string(), text);
+        assert_eq!(doh.transport().unwrap(), Transport::Https {
+            url: "https://dns.quad9.net/dns-query".into(),
+            addr: Some("9.9.9.9:443".parse().unwrap()),
+        });
+
+        let dot: Stamp = "sdns://AwAAAAAAAAAAEVsyNjIwOmZlOjpmZV06ODUzAA1kbnMucXVhZDkubmV0".parse().unwrap();
+        assert_eq!(dot, Stamp::dot("[2620:fe::fe]:853", "dns.quad9.net"));
+        assert_eq!(dot.transport().unwrap(), Transport::Tls {
+            addr: Some("[2620:fe::fe]:853".parse().unwrap()),
+            server_name: "dns.quad9.net".into(),
+        });
+
+        let mut doq = Stamp::doq("", "dns.example:8853");
+        doq.hashes = vec![vec![1; 32], vec![2; 32]];
+        doq.bootstrap = vec!["1.1.1.1".into(), "8.8.8.8".into()];
+        for stamp in [doq, Stamp::odoh_target("odoh.example", "/dns-query"), Stamp::odoh_relay("", "relay.example", "/proxy")] {
+            assert_eq!(stamp.to_string().parse::<Stamp>().unwrap(), stamp);
+        }
+        assert_eq!(Stamp::doq("", "dns.example:8853").transport().unwrap(), Transport::Quic { addr: None, server_name: "dns.example".into() });
+
+        assert!("https://dns.example".parse::<Stamp>().is_err());
+        let dnscrypt = Stamp::dnscrypt("192.0.2.53", &[3; 32], "2.dnscrypt-cert.example.com");
+        assert_eq!(dnscrypt.to_string().parse::<Stamp>().unwrap(), dnscrypt);
+        assert_eq!(dnscrypt.transport().unwrap(), Transport::DnsCrypt {
+            addr: "192.0.2.53:443".parse().unwrap(),
+            provider_name: "2.dnscrypt-cert.example.com".into(),
+            public_key: [3; 32],
+        });
+        // anonymized DNSCrypt relay
+        assert!("sdns://gQ".parse::<Stamp>().is_err());
+        assert!("sdns://AAEAAAAAAAAABzkuOS45".parse::<Stamp>().is_err());
+        assert!(Stamp::plain("dns.example").transport().is_err());
+    }
+}
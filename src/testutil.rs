@@ -0,0 +1,175 @@
+//! proptest strategies for property tests of the codec.
+//!
+//! The valid strategies only produce data that must survive
+//! `pack`/`unpack`; the adversarial ones produce input the codec has to
+//! reject or truncate without panicking.
+
+use std::net::{Ipv4Addr, Ipv6Addr};
+use bytes::BytesMut;
+use proptest::collection::vec;
+use proptest::prelude::*;
+use crate::msg::{Msg, MsgHdr, Question};
+use crate::types::{self, RecourseRecord};
+use crate::DomainString;
+
+/// Longest name in wire format, root label included.
+const MAX_NAME_OCTETS: usize = 255;
+
+/// A valid LDH label, mixed case. Hyphens in the third and fourth position
+/// are left out, they are reserved for A-labels (RFC 5891 section 4.2.3.1).
+pub fn label() -> impl Strategy<Value = String> {
+    "[a-zA-Z0-9]([a-zA-Z0-9-]{0,61}[a-zA-Z0-9])?".prop_filter("reserved label", |label| label.get(2..4) != Some("--"))
+}
+
+/// A valid absolute name, at most 255 octets in wire format.
+pub fn domain_name() -> impl Strategy<Value = DomainString> {
+    vec(label(), 0..8).prop_map(|labels| {
+        let mut name = DomainString::new();
+        let mut octets = 1;
+        for label in labels {
+            octets += label.len() + 1;
+            if octets > MAX_NAME_OCTETS {
+                break;
+            }
+            name.push_str(&label);
+            name.push('.');
+        }
+        if name.is_empty() {
+            name.push('.');
+        }
+        name
+    })
+}
+
+/// Names a well-behaved encoder must reject or escape: overlong labels and
+/// names, empty labels, `\DDD` escapes out of range, arbitrary characters.
+pub fn adversarial_name() -> impl Strategy<Value = String> {
+    prop_oneof![
+        "[a-z]{64,80}\\.",
+        vec("[a-z]{63}", 4..6).prop_map(|labels| labels.join(".") + "."),
+        "([a-z]{0,3}\\.){1,4}\\.[a-z]{0,3}",
+        "([a-z]{0,3}\\\\[0-9]{3}){1,4}\\.",
+        "\\PC{0,40}",
+        ".{0,40}",
+    ]
+}
+
+/// Any header a 4-bit RCODE can express.
+pub fn header() -> impl Strategy<Value = MsgHdr> {
+    (any::<u16>(), 0u16..16, 0u16..16, any::<[bool; 8]>()).prop_map(|(id, op_code, response_code, bits)| MsgHdr {
+        id,
+        response: bits[0],
+        op_code,
+        authoritative: bits[1],
+        truncated: bits[2],
+        recursion_desired: bits[3],
+        recursion_available: bits[4],
+        zero: bits[5],
+        authenticated_data: bits[6],
+        checking_disabled: bits[7],
+        response_code,
+    })
+}
+
+pub fn question() -> impl Strategy<Value = Question> {
+    (domain_name(), any::<u16>(), prop_oneof![Just(types::CLASS_INET), any::<u16>()])
+        .prop_map(|(name, q_type, q_class)| Question { name, q_type, q_class })
+}
+
+/// A, AAAA, CNAME, NS or SOA record of class IN.
+pub fn record() -> impl Strategy<Value = RecourseRecord> {
+    let class = types::CLASS_INET;
+    prop_oneof![
+        (domain_name(), any::<u32>(), any::<[u8; 4]>())
+            .prop_map(move |(name, ttl, ip)| types::A::new(name, class, ttl, Ipv4Addr::from(ip)).into()),
+        (domain_name(), any::<u32>(), any::<[u8; 16]>())
+            .prop_map(move |(name, ttl, ip)| types::AAAA::new(name, class, ttl, Ipv6Addr::from(ip)).into()),
+        (domain_name(), any::<u32>(), domain_name())
+            .prop_map(move |(name, ttl, target)| types::CNAME::new(name, class, ttl, target).into()),
+        (domain_name(), any::<u32>(), domain_name())
+            .prop_map(move |(name, ttl, ns)| types::NS::new(name, class, ttl, ns).into()),
+        (domain_name(), any::<u32>(), domain_name(), domain_name(), any::<[u32; 5]>())
+            .prop_map(move |(name, ttl, ns, mbox, [serial, refresh, retry, expire, min_ttl])| {
+                types::SOA::new(name, class, ttl, ns, mbox, serial, refresh, retry, expire, min_ttl).into()
+            }),
+    ]
+}
+
+/// A message without EDNS, so it always packs.
+pub fn message() -> impl Strategy<Value = Msg> {
+    (header(), vec(question(), 0..3), vec(record(), 0..6), vec(record(), 0..4), vec(record(), 0..4))
+        .prop_map(|(hdr, question, answer, authority, additional)| Msg {
+            hdr,
+            question,
+            answer,
+            authority,
+            additional,
+        })
+}
+
+/// Wire format of [`message`].
+pub fn wire() -> impl Strategy<Value = Vec<u8>> {
+    message().prop_map(|msg| {
+        let mut buf = BytesMut::new();
+        msg.pack(&mut buf).expect("generated message packs");
+        buf.to_vec()
+    })
+}
+
+/// Wire data cut short, overwritten or padded with garbage.
+pub fn mangled_wire() -> impl Strategy<Value = Vec<u8>> {
+    prop_oneof![
+        (wire(), any::<prop::sample::Index>()).prop_map(|(mut buf, at)| {
+            buf.truncate(at.index(buf.len() + 1));
+            buf
+        }),
+        (wire(), vec((any::<prop::sample::Index>(), any::<u8>()), 1..8)).prop_map(|(mut buf, edits)| {
+            for (at, byte) in edits {
+                let i = at.index(buf.len());
+                buf[i] = byte;
+            }
+            buf
+        }),
+        (wire(), vec(any::<u8>(), 1..32)).prop_map(|(mut buf, tail)| {
+            buf.extend(tail);
+            buf
+        }),
+        vec(any::<u8>(), 0..512),
+    ]
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::BytesMut;
+    use proptest::prelude::*;
+    use crate::io::Cursor;
+    use crate::msg::{Msg, Question};
+    use super::{adversarial_name, domain_name, mangled_wire, wire};
+
+    proptest! {
+        #[test]
+        fn test_name_round_trip(name in domain_name()) {
+            let q = Question { name: name.clone(), q_type: 1, q_class: 1 };
+            let mut buf = BytesMut::new();
+            q.pack(&mut buf).unwrap();
+            let back = Question::unpack(&mut Cursor::new(&buf[..])).unwrap();
+            prop_assert!(back.name.eq_ignore_ascii_case(&name));
+        }
+
+        #[test]
+        fn test_pack_adversarial(name in adversarial_name()) {
+            let q = Question { name: name.into(), q_type: 1, q_class: 1 };
+            let _ = q.pack(&mut BytesMut::new());
+        }
+
+        #[test]
+        fn test_unpack_wire(buf in wire()) {
+            prop_assert!(Msg::unpack(&buf).is_ok());
+        }
+
+        #[test]
+        fn test_unpack_mangled(buf in mangled_wire()) {
+            let _ = Msg::unpack(&buf);
+        }
+    }
+}
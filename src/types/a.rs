@@ -1,14 +1,15 @@
-use std::fmt;
-use std::fmt::{Display, Formatter};
-use std::io::{Cursor, Read};
-use std::net::Ipv4Addr;
+use core::fmt;
+use core::fmt::{Display, Formatter};
+use crate::io::{Cursor, Read};
+use core::net::Ipv4Addr;
 use bytes::{BytesMut};
 use crate::msg::{RR, RecourseRecordHdr};
 use crate::types::RecourseRecord;
 use crate::{DomainString, Result, types, util};
 
 /// RFC 1035.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct A {
     pub hdr: RecourseRecordHdr,
     pub a: Ipv4Addr,
@@ -29,16 +30,16 @@ impl A {
     }
 }
 
-impl Into<RecourseRecord> for A {
-    fn into(self) -> RecourseRecord {
-        RecourseRecord::A(self)
+impl From<A> for RecourseRecord {
+    fn from(value: A) -> Self {
+        RecourseRecord::A(value)
     }
 }
 
 impl Display for A {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         self.hdr.fmt(f)?;
-        std::fmt::Display::fmt(&self.a, f)
+        fmt::Display::fmt(&self.a, f)
     }
 }
 
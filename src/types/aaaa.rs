@@ -1,14 +1,15 @@
-use std::fmt;
-use std::fmt::{Display, Formatter};
-use std::io::{Cursor, Read};
-use std::net::Ipv6Addr;
+use core::fmt;
+use core::fmt::{Display, Formatter};
+use crate::io::{Cursor, Read};
+use core::net::Ipv6Addr;
 use bytes::{BytesMut};
 use crate::msg::{RR, RecourseRecordHdr};
 use crate::types::{RecourseRecord, TYPE_AAAA};
 use crate::{DomainString, Result, util};
 
 /// RFC 3596.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AAAA {
     pub hdr: RecourseRecordHdr,
     pub aaaa: Ipv6Addr,
@@ -32,13 +33,13 @@ impl AAAA {
 impl Display for AAAA {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         self.hdr.fmt(f)?;
-        std::fmt::Display::fmt(&self.aaaa, f)
+        fmt::Display::fmt(&self.aaaa, f)
     }
 }
 
-impl Into<RecourseRecord> for AAAA {
-    fn into(self) -> RecourseRecord {
-        RecourseRecord::AAAA(self)
+impl From<AAAA> for RecourseRecord {
+    fn from(value: AAAA) -> Self {
+        RecourseRecord::AAAA(value)
     }
 }
 
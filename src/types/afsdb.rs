@@ -0,0 +1,97 @@
+use core::fmt;
+use core::fmt::{Display, Formatter};
+use core::hash::{Hash, Hasher};
+use crate::io::{Cursor, ReadBytesExt};
+use byteorder::BigEndian;
+use bytes::{BufMut, BytesMut};
+use crate::{DomainString, util};
+use crate::msg::{RecourseRecordHdr, RR};
+use crate::types::RecourseRecord;
+use crate::Result;
+use crate::types::TYPE_AFSDB;
+
+/// AFSDB
+/// RFC 1183. The hostname is packed uncompressed (RFC 3597 section 4)
+/// but compression pointers in it are followed on unpack.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AFSDB {
+    pub hdr: RecourseRecordHdr,
+    /// 1 for an AFS cell database server, 2 for a DCE authenticated name server.
+    pub subtype: u16,
+    pub hostname: DomainString,
+}
+
+impl AFSDB {
+    pub fn new(name: DomainString, class: u16, ttl: u32, subtype: u16, hostname: DomainString) -> Self {
+        Self {
+            hdr: RecourseRecordHdr {
+                name,
+                typ: TYPE_AFSDB,
+                class,
+                ttl,
+                rd_length: (2 + util::cal_domain_name_len(&hostname)) as u16,
+            },
+            subtype,
+            hostname,
+        }
+    }
+}
+
+impl From<AFSDB> for RecourseRecord {
+    fn from(value: AFSDB) -> Self {
+        RecourseRecord::AFSDB(value)
+    }
+}
+
+impl PartialEq for AFSDB {
+    fn eq(&self, other: &Self) -> bool {
+        self.hdr == other.hdr
+            && self.subtype == other.subtype
+            && self.hostname.eq_ignore_ascii_case(&other.hostname)
+    }
+}
+
+impl Eq for AFSDB {}
+
+impl Hash for AFSDB {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.hdr.hash(state);
+        self.subtype.hash(state);
+        util::hash_name(&self.hostname, state);
+    }
+}
+
+impl Display for AFSDB {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.hdr, f)?;
+        write!(f, "{} {}", self.subtype, self.hostname)
+    }
+}
+
+impl RR for AFSDB {
+    type Item = AFSDB;
+
+    fn pack(&self, buf: &mut BytesMut) -> Result<()> {
+        let start = buf.len();
+        buf.put_u16(self.subtype);
+        util::pack_domain_name(&self.hostname, buf)?;
+        let count = buf.len() - start;
+        util::set_value_offset(buf.as_mut(), start - 2, count as u16);
+        Ok(())
+    }
+
+    fn unpack(h: RecourseRecordHdr, cur: &mut Cursor<&[u8]>) -> Result<Self::Item> {
+        let subtype = cur.read_u16::<BigEndian>()?;
+        let hostname = util::unpack_domain_name_cur(cur)?;
+        Ok(Self {
+            hdr: h,
+            subtype,
+            hostname,
+        })
+    }
+
+    fn header(&self) -> &RecourseRecordHdr {
+        &self.hdr
+    }
+}
@@ -0,0 +1,105 @@
+use core::fmt;
+use core::fmt::{Display, Formatter};
+use crate::io::{Cursor, Read, ReadBytesExt};
+use bytes::{BufMut, BytesMut};
+use crate::prelude::*;
+use crate::{DomainString, Error, util};
+use crate::msg::{RecourseRecordHdr, RR};
+use crate::types::RecourseRecord;
+use crate::Result;
+use crate::types::TYPE_CAA;
+
+/// CAA
+/// RFC 8659.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CAA {
+    pub hdr: RecourseRecordHdr,
+    /// 128 marks the property critical.
+    pub flags: u8,
+    /// `issue`, `issuewild`, `iodef` and the like: ASCII letters and digits.
+    pub tag: String,
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::base64_serde"))]
+    pub value: Vec<u8>,
+}
+
+impl CAA {
+    pub fn new(name: DomainString, class: u16, ttl: u32, flags: u8, tag: String, value: Vec<u8>) -> Self {
+        Self {
+            hdr: RecourseRecordHdr {
+                name,
+                typ: TYPE_CAA,
+                class,
+                ttl,
+                rd_length: (2 + tag.len() + value.len()) as u16,
+            },
+            flags,
+            tag,
+            value,
+        }
+    }
+
+    pub fn is_critical(&self) -> bool {
+        self.flags & 0x80 != 0
+    }
+}
+
+impl From<CAA> for RecourseRecord {
+    fn from(value: CAA) -> Self {
+        RecourseRecord::CAA(value)
+    }
+}
+
+impl Display for CAA {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.hdr, f)?;
+        write!(f, "{} {} {}", self.flags, self.tag, util::quote_character_string(&self.value))
+    }
+}
+
+impl RR for CAA {
+    type Item = CAA;
+
+    fn pack(&self, buf: &mut BytesMut) -> Result<()> {
+        if self.tag.is_empty() || self.tag.len() > 15 || !self.tag.bytes().all(|b| b.is_ascii_alphanumeric()) {
+            return Err(Error::new("bad CAA tag"));
+        }
+        let start = buf.len();
+        buf.put_u8(self.flags);
+        buf.put_u8(self.tag.len() as u8);
+        buf.put_slice(self.tag.as_bytes());
+        buf.put_slice(&self.value);
+        let count = buf.len() - start;
+        util::set_value_offset(buf.as_mut(), start - 2, count as u16);
+        Ok(())
+    }
+
+    fn unpack(h: RecourseRecordHdr, cur: &mut Cursor<&[u8]>) -> Result<Self::Item> {
+        if h.rd_length < 2 {
+            return Err(Error::InvalidRdLength);
+        }
+        let end = cur.position() + h.rd_length as u64;
+        let flags = cur.read_u8()?;
+        let len = cur.read_u8()? as u64;
+        if cur.position() + len > end {
+            return Err(Error::InvalidRdLength);
+        }
+        let mut tag = vec![0u8; len as usize];
+        cur.read_exact(&mut tag)?;
+        if tag.is_empty() || !tag.iter().all(|b| b.is_ascii_alphanumeric()) {
+            return Err(Error::new("bad CAA tag"));
+        }
+        let mut value = vec![0u8; (end - cur.position()) as usize];
+        cur.read_exact(&mut value)?;
+        Ok(Self {
+            hdr: h,
+            flags,
+            tag: String::from_utf8(tag).unwrap(),
+            value,
+        })
+    }
+
+    fn header(&self) -> &RecourseRecordHdr {
+        &self.hdr
+    }
+}
@@ -1,6 +1,7 @@
-use std::fmt;
-use std::fmt::{Display, Formatter};
-use std::io::{Cursor};
+use core::fmt;
+use core::fmt::{Display, Formatter};
+use core::hash::{Hash, Hasher};
+use crate::io::Cursor;
 use bytes::{BytesMut};
 use crate::{DomainString, util};
 use crate::msg::{RecourseRecordHdr, RR};
@@ -11,6 +12,7 @@ use crate::types::TYPE_CNAME;
 /// CNAME
 /// RFC 6891.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CNAME {
     pub hdr: RecourseRecordHdr,
     pub target: DomainString,
@@ -31,9 +33,24 @@ impl CNAME {
     }
 }
 
-impl Into<RecourseRecord> for CNAME {
-    fn into(self) -> RecourseRecord {
-        RecourseRecord::CNAME(self)
+impl From<CNAME> for RecourseRecord {
+    fn from(value: CNAME) -> Self {
+        RecourseRecord::CNAME(value)
+    }
+}
+
+impl PartialEq for CNAME {
+    fn eq(&self, other: &Self) -> bool {
+        self.hdr == other.hdr && self.target.eq_ignore_ascii_case(&other.target)
+    }
+}
+
+impl Eq for CNAME {}
+
+impl Hash for CNAME {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.hdr.hash(state);
+        util::hash_name(&self.target, state);
     }
 }
 
@@ -0,0 +1,116 @@
+use core::fmt;
+use core::fmt::{Display, Formatter};
+use core::hash::{Hash, Hasher};
+use crate::io::Cursor;
+use bytes::BytesMut;
+use crate::{DomainString, util};
+use crate::msg::{RecourseRecordHdr, RR};
+use crate::types::RecourseRecord;
+use crate::Result;
+use crate::types::TYPE_DNAME;
+
+/// DNAME
+/// RFC 6672. The target is packed uncompressed, as the RFC requires, but
+/// compression pointers in it are followed on unpack.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DNAME {
+    pub hdr: RecourseRecordHdr,
+    pub target: DomainString,
+}
+
+impl DNAME {
+    pub fn new(name: DomainString, class: u16, ttl: u32, target: DomainString) -> Self {
+        Self {
+            hdr: RecourseRecordHdr {
+                name,
+                typ: TYPE_DNAME,
+                class,
+                ttl,
+                rd_length: util::cal_domain_name_len(&target) as u16,
+            },
+            target,
+        }
+    }
+
+    /// `name` moved from below the owner to below the target, as the
+    /// synthesized CNAME points (RFC 6672 section 2.2). None when `name`
+    /// is not strictly below the owner or the result is too long.
+    pub fn substitute(&self, name: &str) -> Option<DomainString> {
+        let keep = util::name_labels(name).len().checked_sub(util::name_labels(&self.hdr.name).len())?;
+        if keep == 0 || !util::is_subdomain(name, &self.hdr.name) {
+            return None;
+        }
+        // the leading labels as written, escapes included
+        let bytes = name.as_bytes();
+        let (mut off, mut dots) = (0, 0);
+        while dots < keep {
+            match bytes[off] {
+                b'\\' if bytes.get(off + 1).is_some_and(u8::is_ascii_digit) => off += 4,
+                b'\\' => off += 2,
+                b'.' => {
+                    dots += 1;
+                    off += 1;
+                }
+                _ => off += 1,
+            }
+        }
+        let mut ret = DomainString::from(&name[..off]);
+        if self.target.as_str() != "." {
+            ret.push_str(&self.target);
+        }
+        (util::cal_domain_name_len(&ret) <= 255).then_some(ret)
+    }
+}
+
+impl From<DNAME> for RecourseRecord {
+    fn from(value: DNAME) -> Self {
+        RecourseRecord::DNAME(value)
+    }
+}
+
+impl PartialEq for DNAME {
+    fn eq(&self, other: &Self) -> bool {
+        self.hdr == other.hdr && self.target.eq_ignore_ascii_case(&other.target)
+    }
+}
+
+impl Eq for DNAME {}
+
+impl Hash for DNAME {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.hdr.hash(state);
+        util::hash_name(&self.target, state);
+    }
+}
+
+impl Display for DNAME {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.hdr, f)?;
+        f.write_str(&self.target)
+    }
+}
+
+impl RR for DNAME {
+    type Item = DNAME;
+
+    fn pack(&self, buf: &mut BytesMut) -> Result<()> {
+        let start = buf.len();
+        util::pack_domain_name(&self.target, buf)?;
+        let count = buf.len() - start;
+        util::set_value_offset(buf.as_mut(), start - 2, count as u16);
+        Ok(())
+    }
+
+    fn unpack(h: RecourseRecordHdr, cur: &mut Cursor<&[u8]>) -> Result<Self::Item> {
+        let target = util::unpack_domain_name_cur(cur)?;
+        Ok(Self {
+            hdr: h,
+            target,
+        })
+    }
+
+    fn header(&self) -> &RecourseRecordHdr {
+        &self.hdr
+    }
+}
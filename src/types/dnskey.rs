@@ -0,0 +1,139 @@
+use core::fmt;
+use core::fmt::{Display, Formatter};
+use crate::io::{Cursor, Read, ReadBytesExt};
+use base64::Engine;
+use byteorder::BigEndian;
+use bytes::{BufMut, BytesMut};
+use crate::prelude::*;
+use crate::{DomainString, util};
+use crate::msg::{RecourseRecordHdr, RR};
+use crate::types::RecourseRecord;
+use crate::Result;
+use crate::types::TYPE_DNSKEY;
+
+/// Zone Key flag of DNSKEY.
+pub const DNSKEY_ZONE: u16 = 0x0100;
+/// Secure Entry Point flag of DNSKEY.
+pub const DNSKEY_SEP: u16 = 0x0001;
+/// Revoke flag of DNSKEY (RFC 5011).
+pub const DNSKEY_REVOKE: u16 = 0x0080;
+
+/// DNSKEY
+/// RFC 4034.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DNSKEY {
+    pub hdr: RecourseRecordHdr,
+    pub flags: u16,
+    pub protocol: u8,
+    pub algorithm: u8,
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::base64_serde"))]
+    pub public_key: Vec<u8>,
+}
+
+impl DNSKEY {
+    pub fn new(name: DomainString, class: u16, ttl: u32, flags: u16, algorithm: u8, public_key: Vec<u8>) -> Self {
+        Self {
+            hdr: RecourseRecordHdr {
+                name,
+                typ: TYPE_DNSKEY,
+                class,
+                ttl,
+                rd_length: (4 + public_key.len()) as u16,
+            },
+            flags,
+            protocol: 3,
+            algorithm,
+            public_key,
+        }
+    }
+
+    /// Whether the key may sign zone data; others must not validate RRSIGs.
+    pub fn is_zone_key(&self) -> bool {
+        self.flags & DNSKEY_ZONE != 0
+    }
+
+    /// Whether the key is marked as a secure entry point, usually a KSK.
+    pub fn is_sep(&self) -> bool {
+        self.flags & DNSKEY_SEP != 0
+    }
+
+    /// Whether the key has been revoked (RFC 5011) and must not be trusted.
+    pub fn is_revoked(&self) -> bool {
+        self.flags & DNSKEY_REVOKE != 0
+    }
+
+    /// Key tag (RFC 4034 appendix B). RSA/MD5 keys (algorithm 1) use the
+    /// older definition of appendix B.1.
+    pub fn key_tag(&self) -> u16 {
+        if self.algorithm == 1 {
+            let n = self.public_key.len();
+            return if n >= 3 { u16::from_be_bytes([self.public_key[n - 3], self.public_key[n - 2]]) } else { 0 };
+        }
+        let mut rdata = Vec::with_capacity(4 + self.public_key.len());
+        rdata.extend_from_slice(&self.flags.to_be_bytes());
+        rdata.push(self.protocol);
+        rdata.push(self.algorithm);
+        rdata.extend_from_slice(&self.public_key);
+        let mut ac: u32 = 0;
+        for (i, b) in rdata.iter().enumerate() {
+            ac += if i & 1 == 0 { (*b as u32) << 8 } else { *b as u32 };
+        }
+        ac += (ac >> 16) & 0xffff;
+        (ac & 0xffff) as u16
+    }
+}
+
+impl From<DNSKEY> for RecourseRecord {
+    fn from(value: DNSKEY) -> Self {
+        RecourseRecord::DNSKEY(value)
+    }
+}
+
+impl Display for DNSKEY {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.hdr, f)?;
+        write!(
+            f,
+            "{} {} {} {}",
+            self.flags,
+            self.protocol,
+            self.algorithm,
+            base64::engine::general_purpose::STANDARD.encode(&self.public_key)
+        )
+    }
+}
+
+impl RR for DNSKEY {
+    type Item = DNSKEY;
+
+    fn pack(&self, buf: &mut BytesMut) -> Result<()> {
+        let start = buf.len();
+        buf.put_u16(self.flags);
+        buf.put_u8(self.protocol);
+        buf.put_u8(self.algorithm);
+        buf.put_slice(&self.public_key);
+        let count = buf.len() - start;
+        util::set_value_offset(buf.as_mut(), start - 2, count as u16);
+        Ok(())
+    }
+
+    fn unpack(h: RecourseRecordHdr, cur: &mut Cursor<&[u8]>) -> Result<Self::Item> {
+        let flags = cur.read_u16::<BigEndian>()?;
+        let protocol = cur.read_u8()?;
+        let algorithm = cur.read_u8()?;
+        let mut public_key = vec![0u8; (h.rd_length as usize).saturating_sub(4)];
+        cur.read_exact(&mut public_key)?;
+        Ok(Self {
+            hdr: h,
+            flags,
+            protocol,
+            algorithm,
+            public_key,
+        })
+    }
+
+    fn header(&self) -> &RecourseRecordHdr {
+        &self.hdr
+    }
+}
@@ -0,0 +1,96 @@
+use core::fmt;
+use core::fmt::{Display, Formatter};
+use crate::io::{Cursor, Read, ReadBytesExt};
+use byteorder::BigEndian;
+use bytes::{BufMut, BytesMut};
+use crate::prelude::*;
+use crate::{DomainString, util};
+use crate::msg::{RecourseRecordHdr, RR};
+use crate::types::RecourseRecord;
+use crate::Result;
+use crate::types::TYPE_DS;
+
+/// DS
+/// RFC 4034.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DS {
+    pub hdr: RecourseRecordHdr,
+    pub key_tag: u16,
+    pub algorithm: u8,
+    pub digest_type: u8,
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::base64_serde"))]
+    pub digest: Vec<u8>,
+}
+
+impl DS {
+    pub fn new(name: DomainString, class: u16, ttl: u32, key_tag: u16, algorithm: u8, digest_type: u8, digest: Vec<u8>) -> Self {
+        Self {
+            hdr: RecourseRecordHdr {
+                name,
+                typ: TYPE_DS,
+                class,
+                ttl,
+                rd_length: (4 + digest.len()) as u16,
+            },
+            key_tag,
+            algorithm,
+            digest_type,
+            digest,
+        }
+    }
+}
+
+impl From<DS> for RecourseRecord {
+    fn from(value: DS) -> Self {
+        RecourseRecord::DS(value)
+    }
+}
+
+impl Display for DS {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.hdr, f)?;
+        write!(
+            f,
+            "{} {} {} {}",
+            self.key_tag,
+            self.algorithm,
+            self.digest_type,
+            hex::encode_upper(&self.digest)
+        )
+    }
+}
+
+impl RR for DS {
+    type Item = DS;
+
+    fn pack(&self, buf: &mut BytesMut) -> Result<()> {
+        let start = buf.len();
+        buf.put_u16(self.key_tag);
+        buf.put_u8(self.algorithm);
+        buf.put_u8(self.digest_type);
+        buf.put_slice(&self.digest);
+        let count = buf.len() - start;
+        util::set_value_offset(buf.as_mut(), start - 2, count as u16);
+        Ok(())
+    }
+
+    fn unpack(h: RecourseRecordHdr, cur: &mut Cursor<&[u8]>) -> Result<Self::Item> {
+        let key_tag = cur.read_u16::<BigEndian>()?;
+        let algorithm = cur.read_u8()?;
+        let digest_type = cur.read_u8()?;
+        let mut digest = vec![0u8; (h.rd_length as usize).saturating_sub(4)];
+        cur.read_exact(&mut digest)?;
+        Ok(Self {
+            hdr: h,
+            key_tag,
+            algorithm,
+            digest_type,
+            digest,
+        })
+    }
+
+    fn header(&self) -> &RecourseRecordHdr {
+        &self.hdr
+    }
+}
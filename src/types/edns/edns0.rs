@@ -1,8 +1,19 @@
-use std::fmt;
-use std::fmt::{Display, Formatter};
-use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use core::any::Any;
+use core::fmt;
+use core::fmt::{Display, Formatter};
+use core::hash::{Hash, Hasher};
+use core::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use alloc::sync::Arc;
+#[cfg(feature = "std")]
+use std::collections::BTreeMap;
+#[cfg(feature = "std")]
+use once_cell::sync::Lazy;
+#[cfg(feature = "std")]
+use parking_lot::RwLock;
 use byteorder::{BigEndian, ByteOrder};
-use crate::{Error, Result};
+use crate::prelude::*;
+use crate::io::Cursor;
+use crate::{util, DomainString, Error, Result};
 use bytes::{BufMut, BytesMut};
 use crate::util::ResizeMut;
 
@@ -29,8 +40,15 @@ pub const EDNS0EXPIRE: u16 = 0x9;
 pub const EDNS0COOKIE: u16 = 0xa;
 // EDNS0 tcp keep alive (See RFC 7828)
 pub const EDNS0TCPKEEPALIVE: u16 = 0xb;
+// EDNS0 CHAIN (See RFC 7901)
+pub const EDNS0CHAIN: u16 = 0xd;
+// EDNS0 key tag (See RFC 8145)
+pub const EDNS0KEYTAG: u16 = 0xe;
 // EDNS0 padding (See RFC 7830)
 pub const EDNS0PADDING: u16 = 0xc;
+// Padding block sizes for queries and responses (See RFC 8467 section 4.1)
+pub const PADDING_QUERY_BLOCK: usize = 128;
+pub const PADDING_RESPONSE_BLOCK: usize = 468;
 // EDNS0 extended DNS errors (See RFC 8914)
 pub const EDNS0EDE: u16 = 0xf;
 // Beginning of range reserved for local/experimental use (See RFC 6891)
@@ -39,6 +57,35 @@ pub const EDNS0LOCALSTART: u16 = 0xFDE9;
 pub const EDNS0LOCALEND: u16 = 0xFFFE;
 // DNSSEC OK
 pub const _DO: u16 = 1 << 15;
+// UDP payload size to advertise when nothing else is known (DNS flag day 2020)
+pub const DEFAULT_UDP_SIZE: u16 = 1232;
+
+// Extended DNS Error info codes (See RFC 8914 section 4)
+pub const EDE_OTHER: u16 = 0;
+pub const EDE_UNSUPPORTED_DNSKEY_ALGORITHM: u16 = 1;
+pub const EDE_UNSUPPORTED_DS_DIGEST_TYPE: u16 = 2;
+pub const EDE_STALE_ANSWER: u16 = 3;
+pub const EDE_FORGED_ANSWER: u16 = 4;
+pub const EDE_DNSSEC_INDETERMINATE: u16 = 5;
+pub const EDE_DNSSEC_BOGUS: u16 = 6;
+pub const EDE_SIGNATURE_EXPIRED: u16 = 7;
+pub const EDE_SIGNATURE_NOT_YET_VALID: u16 = 8;
+pub const EDE_DNSKEY_MISSING: u16 = 9;
+pub const EDE_RRSIGS_MISSING: u16 = 10;
+pub const EDE_NO_ZONE_KEY_BIT_SET: u16 = 11;
+pub const EDE_NSEC_MISSING: u16 = 12;
+pub const EDE_CACHED_ERROR: u16 = 13;
+pub const EDE_NOT_READY: u16 = 14;
+pub const EDE_BLOCKED: u16 = 15;
+pub const EDE_CENSORED: u16 = 16;
+pub const EDE_FILTERED: u16 = 17;
+pub const EDE_PROHIBITED: u16 = 18;
+pub const EDE_STALE_NXDOMAIN_ANSWER: u16 = 19;
+pub const EDE_NOT_AUTHORITATIVE: u16 = 20;
+pub const EDE_NOT_SUPPORTED: u16 = 21;
+pub const EDE_NO_REACHABLE_AUTHORITY: u16 = 22;
+pub const EDE_NETWORK_ERROR: u16 = 23;
+pub const EDE_INVALID_DATA: u16 = 24;
 
 pub trait IEdns0: Display {
     type Item;
@@ -48,17 +95,144 @@ pub trait IEdns0: Display {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(into = "RawOption", try_from = "RawOption"))]
 pub enum EDNS0 {
     Nid(NSID),
     SubNet(SubNet),
+    Ede(EDE),
+    Padding(Padding),
+    Expire(Expire),
+    Chain(Chain),
+    KeyTag(KeyTag),
+    /// An option of a code given to [`register_option`].
+    Custom(Arc<dyn CustomOption>),
     Local(LOCAL),
 }
 
+impl EDNS0 {
+    /// The value of a [`Custom`](EDNS0::Custom) option of type `T`.
+    pub fn downcast_ref<T: 'static>(&self) -> Option<&T> {
+        match self {
+            EDNS0::Custom(val) => val.as_any().downcast_ref(),
+            _ => None,
+        }
+    }
+
+    fn packed(&self) -> Option<BytesMut> {
+        let mut buf = BytesMut::new();
+        self.pack(&mut buf).ok().map(|_| buf)
+    }
+}
+
+/// How options serialize: code and base64 value, so registered custom
+/// options come back as themselves.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct RawOption {
+    code: u16,
+    #[serde(with = "crate::util::base64_serde")]
+    data: Vec<u8>,
+}
+
+#[cfg(feature = "serde")]
+impl From<EDNS0> for RawOption {
+    fn from(value: EDNS0) -> Self {
+        let data = value.packed().map(|buf| buf.to_vec()).unwrap_or_default();
+        Self { code: value.option(), data }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<RawOption> for EDNS0 {
+    type Error = String;
+
+    fn try_from(value: RawOption) -> core::result::Result<Self, Self::Error> {
+        EDNS0::unpack(value.code, &value.data).map_err(|err| format!("{:?}", err))
+    }
+}
+
+/// [`IEdns0`] without its constructor, for options of types outside the
+/// crate. Every `IEdns0` type that is `fmt::Debug + Send + Sync` has it.
+pub trait CustomOption: Display + fmt::Debug + Send + Sync {
+    fn code(&self) -> u16;
+    fn pack_value(&self, buf: &mut BytesMut) -> Result<()>;
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<T: IEdns0<Item = T> + fmt::Debug + Send + Sync + 'static> CustomOption for T {
+    fn code(&self) -> u16 {
+        self.option()
+    }
+
+    fn pack_value(&self, buf: &mut BytesMut) -> Result<()> {
+        self.pack(buf)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+type Unpacker = fn(u16, &[u8]) -> Result<Arc<dyn CustomOption>>;
+
+#[cfg(feature = "std")]
+static REGISTRY: Lazy<RwLock<BTreeMap<u16, Unpacker>>> = Lazy::new(Default::default);
+
+/// Unpacks options of `code` as `T` from now on, as [`EDNS0::Custom`]
+/// rather than [`EDNS0::Local`]. Codes the crate has a type for, like
+/// NSID, keep it.
+#[cfg(feature = "std")]
+pub fn register_option<T: IEdns0<Item = T> + fmt::Debug + Send + Sync + 'static>(code: u16) {
+    fn unpack<T: IEdns0<Item = T> + fmt::Debug + Send + Sync + 'static>(code: u16, bs: &[u8]) -> Result<Arc<dyn CustomOption>> {
+        Ok(Arc::new(T::unpack(code, bs)?))
+    }
+    REGISTRY.write().insert(code, unpack::<T> as Unpacker);
+}
+
+/// Goes back to unpacking `code` as [`EDNS0::Local`].
+#[cfg(feature = "std")]
+pub fn unregister_option(code: u16) -> bool {
+    REGISTRY.write().remove(&code).is_some()
+}
+
+#[cfg(feature = "std")]
+fn registered(code: u16) -> Option<Unpacker> {
+    REGISTRY.read().get(&code).copied()
+}
+
+#[cfg(not(feature = "std"))]
+fn registered(_code: u16) -> Option<Unpacker> {
+    None
+}
+
+/// Options compare by code and packed value, so custom ones can too.
+impl PartialEq for EDNS0 {
+    fn eq(&self, other: &Self) -> bool {
+        self.option() == other.option() && self.packed() == other.packed()
+    }
+}
+
+impl Eq for EDNS0 {}
+
+impl Hash for EDNS0 {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.option().hash(state);
+        self.packed().hash(state);
+    }
+}
+
 impl Display for EDNS0 {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             EDNS0::Nid(val) => val.fmt(f),
             EDNS0::SubNet(val) => val.fmt(f),
+            EDNS0::Ede(val) => val.fmt(f),
+            EDNS0::Padding(val) => val.fmt(f),
+            EDNS0::Expire(val) => val.fmt(f),
+            EDNS0::Chain(val) => val.fmt(f),
+            EDNS0::KeyTag(val) => val.fmt(f),
+            EDNS0::Custom(val) => val.fmt(f),
             EDNS0::Local(val) => val.fmt(f),
         }
     }
@@ -71,6 +245,12 @@ impl IEdns0 for EDNS0 {
         match self {
             EDNS0::Nid(val) => val.option(),
             EDNS0::SubNet(val) => val.option(),
+            EDNS0::Ede(val) => val.option(),
+            EDNS0::Padding(val) => val.option(),
+            EDNS0::Expire(val) => val.option(),
+            EDNS0::Chain(val) => val.option(),
+            EDNS0::KeyTag(val) => val.option(),
+            EDNS0::Custom(val) => val.code(),
             EDNS0::Local(val) => val.option(),
         }
     }
@@ -79,6 +259,12 @@ impl IEdns0 for EDNS0 {
         match self {
             EDNS0::Nid(val) => val.pack(buf),
             EDNS0::SubNet(val) => val.pack(buf),
+            EDNS0::Ede(val) => val.pack(buf),
+            EDNS0::Padding(val) => val.pack(buf),
+            EDNS0::Expire(val) => val.pack(buf),
+            EDNS0::Chain(val) => val.pack(buf),
+            EDNS0::KeyTag(val) => val.pack(buf),
+            EDNS0::Custom(val) => val.pack_value(buf),
             EDNS0::Local(val) => val.pack(buf),
         }
     }
@@ -87,7 +273,15 @@ impl IEdns0 for EDNS0 {
         Ok(match code {
             EDNS0NSID => Self::Nid(NSID::unpack(code, bs)?),
             EDNS0SUBNET => Self::SubNet(SubNet::unpack(code, bs)?),
-            _ => Self::Local(LOCAL::unpack(code, bs)?),
+            EDNS0EDE => Self::Ede(EDE::unpack(code, bs)?),
+            EDNS0PADDING => Self::Padding(Padding::unpack(code, bs)?),
+            EDNS0EXPIRE => Self::Expire(Expire::unpack(code, bs)?),
+            EDNS0CHAIN => Self::Chain(Chain::unpack(code, bs)?),
+            EDNS0KEYTAG => Self::KeyTag(KeyTag::unpack(code, bs)?),
+            _ => match registered(code) {
+                Some(unpack) => Self::Custom(unpack(code, bs)?),
+                None => Self::Local(LOCAL::unpack(code, bs)?),
+            },
         })
     }
 }
@@ -156,8 +350,9 @@ impl IEdns0 for SubNet {
                     IpAddr::V4(val) => Some(val),
                     IpAddr::V6(val) => val.to_ipv4_mapped(),
                 }.ok_or(Error::new("bad address"))?;
-                let network = ipnetwork::Ipv4Network::new(address, self.source_netmask)?.network();
-                buf.put_slice(&network.octets());
+                let mask = u32::MAX.checked_shl(32 - self.source_netmask as u32).unwrap_or(0);
+                // only the bytes the prefix covers (RFC 7871 section 6)
+                buf.put_slice(&(u32::from(address) & mask).to_be_bytes()[..(self.source_netmask as usize).div_ceil(8)]);
             }
             2 => {
                 if self.source_netmask > 16/*ipv6*/ * 8 {
@@ -167,8 +362,8 @@ impl IEdns0 for SubNet {
                     IpAddr::V4(val) => val.to_ipv6_mapped(),
                     IpAddr::V6(val) => val,
                 };
-                let network = ipnetwork::Ipv6Network::new(address, self.source_netmask)?.network();
-                buf.put_slice(&network.octets());
+                let mask = u128::MAX.checked_shl(128 - self.source_netmask as u32).unwrap_or(0);
+                buf.put_slice(&(u128::from(address) & mask).to_be_bytes()[..(self.source_netmask as usize).div_ceil(8)]);
             }
             _ => {
                 return Err(Error::new("bad address family"));
@@ -184,6 +379,10 @@ impl IEdns0 for SubNet {
         let family = BigEndian::read_u16(&bs[0..2]);
         let source_netmask = bs[2];
         let source_scope = bs[3];
+        // the address has just the octets the prefix covers (RFC 7871 section 6)
+        if family != 0 && bs.len() - 4 != (source_netmask as usize).div_ceil(8) {
+            return Err(Error::new("bad address length"));
+        }
         let address: IpAddr = match family {
             0 => {
                 if source_netmask != 0 {
@@ -195,13 +394,17 @@ impl IEdns0 for SubNet {
                 if source_netmask > 4 * 8 || source_scope > 4 * 8 {
                     return Err(Error::new("bad netmask"));
                 }
-                Ipv4Addr::from(BigEndian::read_u32(&bs[4..])).into()
+                let mut octets = [0u8; 4];
+                octets[..bs.len() - 4].copy_from_slice(&bs[4..]);
+                Ipv4Addr::from(octets).into()
             }
             2 => {
                 if source_netmask > 16 * 8 || source_scope > 16 * 8 {
                     return Err(Error::new("bad netmask"));
                 }
-                Ipv6Addr::from(BigEndian::read_u128(&bs[4..])).into()
+                let mut octets = [0u8; 16];
+                octets[..bs.len() - 4].copy_from_slice(&bs[4..]);
+                Ipv6Addr::from(octets).into()
             }
             _ => {
                 return Err(Error::new("bad address family"));
@@ -216,6 +419,20 @@ impl IEdns0 for SubNet {
     }
 }
 
+/// `address` with all but the first `prefix` bits cleared.
+pub fn mask_address(address: IpAddr, prefix: u8) -> IpAddr {
+    match address {
+        IpAddr::V4(ip) => {
+            let mask = u32::MAX.checked_shl(32u32.saturating_sub(prefix as u32)).unwrap_or(0);
+            Ipv4Addr::from(u32::from(ip) & mask).into()
+        }
+        IpAddr::V6(ip) => {
+            let mask = u128::MAX.checked_shl(128u32.saturating_sub(prefix as u32)).unwrap_or(0);
+            Ipv6Addr::from(u128::from(ip) & mask).into()
+        }
+    }
+}
+
 impl SubNet {
     pub fn new(address: IpAddr, source_netmask: u8, source_scope: u8) -> Self {
         Self {
@@ -243,6 +460,300 @@ impl Display for SubNet {
     }
 }
 
+/// Extended DNS Error (RFC 8914).
+#[derive(Debug, Clone)]
+pub struct EDE {
+    pub info_code: u16,
+    /// Free-form text for people, may be empty.
+    pub extra_text: String,
+}
+
+impl EDE {
+    pub fn new<S: Into<String>>(info_code: u16, extra_text: S) -> Self {
+        Self {
+            info_code,
+            extra_text: extra_text.into(),
+        }
+    }
+
+    /// Name of the info code in the IANA registry.
+    pub fn name(&self) -> Option<&'static str> {
+        ede_name(self.info_code)
+    }
+}
+
+/// Name of an Extended DNS Error info code in the IANA registry.
+pub fn ede_name(info_code: u16) -> Option<&'static str> {
+    Some(match info_code {
+        EDE_OTHER => "Other Error",
+        EDE_UNSUPPORTED_DNSKEY_ALGORITHM => "Unsupported DNSKEY Algorithm",
+        EDE_UNSUPPORTED_DS_DIGEST_TYPE => "Unsupported DS Digest Type",
+        EDE_STALE_ANSWER => "Stale Answer",
+        EDE_FORGED_ANSWER => "Forged Answer",
+        EDE_DNSSEC_INDETERMINATE => "DNSSEC Indeterminate",
+        EDE_DNSSEC_BOGUS => "DNSSEC Bogus",
+        EDE_SIGNATURE_EXPIRED => "Signature Expired",
+        EDE_SIGNATURE_NOT_YET_VALID => "Signature Not Yet Valid",
+        EDE_DNSKEY_MISSING => "DNSKEY Missing",
+        EDE_RRSIGS_MISSING => "RRSIGs Missing",
+        EDE_NO_ZONE_KEY_BIT_SET => "No Zone Key Bit Set",
+        EDE_NSEC_MISSING => "NSEC Missing",
+        EDE_CACHED_ERROR => "Cached Error",
+        EDE_NOT_READY => "Not Ready",
+        EDE_BLOCKED => "Blocked",
+        EDE_CENSORED => "Censored",
+        EDE_FILTERED => "Filtered",
+        EDE_PROHIBITED => "Prohibited",
+        EDE_STALE_NXDOMAIN_ANSWER => "Stale NXDomain Answer",
+        EDE_NOT_AUTHORITATIVE => "Not Authoritative",
+        EDE_NOT_SUPPORTED => "Not Supported",
+        EDE_NO_REACHABLE_AUTHORITY => "No Reachable Authority",
+        EDE_NETWORK_ERROR => "Network Error",
+        EDE_INVALID_DATA => "Invalid Data",
+        25 => "Signature Expired before Valid",
+        26 => "Too Early",
+        27 => "Unsupported NSEC3 Iterations Value",
+        28 => "Unable to conform to policy",
+        29 => "Synthesized",
+        30 => "Invalid Query Type",
+        _ => return None,
+    })
+}
+
+impl Display for EDE {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.info_code, f)?;
+        if let Some(name) = self.name() {
+            write!(f, " ({})", name)?;
+        }
+        if !self.extra_text.is_empty() {
+            write!(f, ": ({})", self.extra_text)?;
+        }
+        Ok(())
+    }
+}
+
+impl IEdns0 for EDE {
+    type Item = EDE;
+
+    fn option(&self) -> u16 {
+        EDNS0EDE
+    }
+
+    fn pack(&self, buf: &mut BytesMut) -> Result<()> {
+        buf.put_u16(self.info_code);
+        buf.put_slice(self.extra_text.as_bytes());
+        Ok(())
+    }
+
+    fn unpack(_code: u16, bs: &[u8]) -> Result<Self::Item> {
+        if bs.len() < 2 {
+            return Err(Error::BufTooSmall);
+        }
+        // some senders NUL-terminate the text, which section 2 allows for
+        let text = &bs[2..];
+        let text = text.strip_suffix(&[0]).unwrap_or(text);
+        Ok(Self {
+            info_code: BigEndian::read_u16(&bs[0..2]),
+            extra_text: String::from_utf8_lossy(text).into_owned(),
+        })
+    }
+}
+
+/// Padding (RFC 7830). Sent as zeros; what a sender put in is ignored.
+#[derive(Debug, Clone)]
+pub struct Padding {
+    pub length: u16,
+}
+
+impl Padding {
+    pub fn new(length: u16) -> Self {
+        Self { length }
+    }
+}
+
+impl Display for Padding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} octets", self.length)
+    }
+}
+
+impl IEdns0 for Padding {
+    type Item = Padding;
+
+    fn option(&self) -> u16 {
+        EDNS0PADDING
+    }
+
+    fn pack(&self, buf: &mut BytesMut) -> Result<()> {
+        buf.put_bytes(0, self.length as usize);
+        Ok(())
+    }
+
+    fn unpack(_code: u16, bs: &[u8]) -> Result<Self::Item> {
+        Ok(Self {
+            length: bs.len() as u16,
+        })
+    }
+}
+
+/// Expire (RFC 7314). Queries carry it empty to ask for the expire
+/// timer of the zone, which responses carry in seconds.
+#[derive(Debug, Clone)]
+pub struct Expire {
+    pub expire: Option<u32>,
+}
+
+impl Expire {
+    pub fn new(expire: Option<u32>) -> Self {
+        Self { expire }
+    }
+}
+
+impl Display for Expire {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.expire {
+            Some(val) => fmt::Display::fmt(&val, f),
+            None => Ok(()),
+        }
+    }
+}
+
+impl IEdns0 for Expire {
+    type Item = Expire;
+
+    fn option(&self) -> u16 {
+        EDNS0EXPIRE
+    }
+
+    fn pack(&self, buf: &mut BytesMut) -> Result<()> {
+        if let Some(val) = self.expire {
+            buf.put_u32(val);
+        }
+        Ok(())
+    }
+
+    fn unpack(_code: u16, bs: &[u8]) -> Result<Self::Item> {
+        let expire = match bs.len() {
+            0 => None,
+            4 => Some(BigEndian::read_u32(bs)),
+            _ => return Err(Error::new("bad expire option length")),
+        };
+        Ok(Self { expire })
+    }
+}
+
+/// CHAIN (RFC 7901): the closest trust point the client already holds,
+/// asking for the records to validate from there down in one response.
+#[derive(Debug, Clone)]
+pub struct Chain {
+    pub trust_point: DomainString,
+}
+
+impl Chain {
+    pub fn new(trust_point: DomainString) -> Self {
+        Self { trust_point }
+    }
+}
+
+impl Display for Chain {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.trust_point)
+    }
+}
+
+impl IEdns0 for Chain {
+    type Item = Chain;
+
+    fn option(&self) -> u16 {
+        EDNS0CHAIN
+    }
+
+    fn pack(&self, buf: &mut BytesMut) -> Result<()> {
+        util::pack_domain_name(&self.trust_point, buf)
+    }
+
+    fn unpack(_code: u16, bs: &[u8]) -> Result<Self::Item> {
+        // uncompressed, so the labels must fill the option exactly
+        let mut off = 0;
+        while let Some(&len) = bs.get(off) {
+            if len & 0xC0 != 0 {
+                return Err(Error::new("compressed CHAIN trust point"));
+            }
+            off += 1 + len as usize;
+            if len == 0 {
+                break;
+            }
+        }
+        if off != bs.len() {
+            return Err(Error::new("bad CHAIN option length"));
+        }
+        let trust_point = util::unpack_domain_name_cur(&mut Cursor::new(bs))?;
+        Ok(Self { trust_point })
+    }
+}
+
+/// edns-key-tag (RFC 8145): the key tags of the trust anchors a
+/// validator uses for the zone it queries, for trust anchor telemetry.
+#[derive(Debug, Clone)]
+pub struct KeyTag {
+    pub tags: Vec<u16>,
+}
+
+impl KeyTag {
+    pub fn new(tags: Vec<u16>) -> Self {
+        Self { tags }
+    }
+
+    /// The `_ta-` label that signals the same tags in a query name
+    /// instead (RFC 8145 section 5), sorted as it asks.
+    pub fn signal_label(&self) -> String {
+        let mut tags = self.tags.clone();
+        tags.sort_unstable();
+        let mut label = String::from("_ta");
+        for tag in tags {
+            label.push_str(&format!("-{:04x}", tag));
+        }
+        label
+    }
+}
+
+impl Display for KeyTag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (i, tag) in self.tags.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            fmt::Display::fmt(tag, f)?;
+        }
+        Ok(())
+    }
+}
+
+impl IEdns0 for KeyTag {
+    type Item = KeyTag;
+
+    fn option(&self) -> u16 {
+        EDNS0KEYTAG
+    }
+
+    fn pack(&self, buf: &mut BytesMut) -> Result<()> {
+        for tag in &self.tags {
+            buf.put_u16(*tag);
+        }
+        Ok(())
+    }
+
+    fn unpack(_code: u16, bs: &[u8]) -> Result<Self::Item> {
+        if bs.is_empty() || !bs.len().is_multiple_of(2) {
+            return Err(Error::new("bad key tag option length"));
+        }
+        Ok(Self {
+            tags: bs.chunks_exact(2).map(BigEndian::read_u16).collect(),
+        })
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct LOCAL {
     pub code: u16,
@@ -1,28 +1,30 @@
 pub mod edns0;
 
-use std::fmt;
-use std::fmt::Formatter;
-use std::fmt::Display;
-use std::fmt::Write;
-use std::io::{Cursor};
-use byteorder::{BigEndian, ReadBytesExt};
+use core::fmt;
+use core::fmt::Formatter;
+use core::fmt::Display;
+use core::fmt::Write;
+use crate::io::{Cursor, ReadBytesExt};
+use byteorder::BigEndian;
 use bytes::{BufMut, BytesMut};
+use crate::prelude::*;
 use crate::msg::{RecourseRecordHdr, RR};
 use crate::types::RecourseRecord;
-use crate::{Result, util};
+use crate::{Error, Result, util};
 pub use edns0::{IEdns0, EDNS0};
 
 /// EDNS0
 /// RFC 6891.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Opt {
     pub hdr: RecourseRecordHdr,
     pub option: Vec<EDNS0>,
 }
 
-impl Into<RecourseRecord> for Opt {
-    fn into(self) -> RecourseRecord {
-        RecourseRecord::Opt(self)
+impl From<Opt> for RecourseRecord {
+    fn from(value: Opt) -> Self {
+        RecourseRecord::Opt(value)
     }
 }
 
@@ -30,6 +32,7 @@ impl RR for Opt {
     type Item = Opt;
 
     fn pack(&self, bs: &mut BytesMut) -> Result<()> {
+        let rdata = bs.len();
         for el in &self.option {
             bs.put_u16(el.option());
             bs.put_u16(0);
@@ -38,6 +41,8 @@ impl RR for Opt {
             let count = bs.len() - start;
             util::set_value_offset(bs.as_mut(), start - 2, count as u16);
         }
+        let count = bs.len() - rdata;
+        util::set_value_offset(bs.as_mut(), rdata - 2, count as u16);
         Ok(())
     }
 
@@ -50,18 +55,20 @@ impl RR for Opt {
         }
         let mut options = Vec::new();
         let mut off: usize = cur.position() as usize;
+        let end = off + h.rd_length as usize;
 
-        loop {
+        while off < end {
             let code = cur.read_u16::<BigEndian>()?;
             let opt_len = cur.read_u16::<BigEndian>()?;
             off += 4;
+            if off + opt_len as usize > end {
+                return Err(Error::new("bad option length"));
+            }
             let data = &cur.get_ref()[off..off + opt_len as usize];
             let e0 = EDNS0::unpack(code, data)?;
             options.push(e0);
             off += opt_len as usize;
-            if off >= cur.get_ref().len() {
-                break;
-            }
+            cur.set_position(off as u64);
         }
 
         Ok(Self {
@@ -76,12 +83,85 @@ impl RR for Opt {
 }
 
 impl Opt {
+    /// An empty OPT record advertising `udp_size`.
+    pub fn new(udp_size: u16) -> Self {
+        Self {
+            hdr: RecourseRecordHdr {
+                name: ".".into(),
+                typ: crate::types::TYPE_OPT,
+                class: udp_size,
+                ttl: 0,
+                rd_length: 0,
+            },
+            option: Vec::new(),
+        }
+    }
+
+    /// The data of the DNS cookie option (RFC 7873), if any.
+    pub fn cookie(&self) -> Option<&[u8]> {
+        self.option.iter().find_map(|o| match o {
+            EDNS0::Local(val) if val.code == edns0::EDNS0COOKIE => Some(&val.data[..]),
+            _ => None,
+        })
+    }
+
+    /// The first option of type `T`, of a code given to
+    /// [`edns0::register_option`].
+    pub fn custom<T: 'static>(&self) -> Option<&T> {
+        self.option.iter().find_map(EDNS0::downcast_ref)
+    }
+
+    /// The Extended DNS Errors (RFC 8914), of which there may be several.
+    pub fn ede(&self) -> impl Iterator<Item = &edns0::EDE> {
+        self.option.iter().filter_map(|o| match o {
+            EDNS0::Ede(val) => Some(val),
+            _ => None,
+        })
+    }
+
+    /// Adds an Extended DNS Error; `text` may be empty.
+    pub fn add_ede<S: Into<String>>(&mut self, info_code: u16, text: S) {
+        self.option.push(EDNS0::Ede(edns0::EDE::new(info_code, text)));
+    }
+
+    /// The expire option (RFC 7314), if any.
+    pub fn expire(&self) -> Option<&edns0::Expire> {
+        self.option.iter().find_map(|o| match o {
+            EDNS0::Expire(val) => Some(val),
+            _ => None,
+        })
+    }
+
+    /// The CHAIN option (RFC 7901), if any.
+    pub fn chain(&self) -> Option<&edns0::Chain> {
+        self.option.iter().find_map(|o| match o {
+            EDNS0::Chain(val) => Some(val),
+            _ => None,
+        })
+    }
+
+    /// The key tag option (RFC 8145), if any.
+    pub fn key_tag(&self) -> Option<&edns0::KeyTag> {
+        self.option.iter().find_map(|o| match o {
+            EDNS0::KeyTag(val) => Some(val),
+            _ => None,
+        })
+    }
+
+    /// The client subnet option, if any.
+    pub fn subnet(&self) -> Option<&edns0::SubNet> {
+        self.option.iter().find_map(|o| match o {
+            EDNS0::SubNet(val) => Some(val),
+            _ => None,
+        })
+    }
+
     pub fn is_do(&self) -> bool {
         (self.hdr.ttl & edns0::_DO as u32) == edns0::_DO as u32
     }
 
     pub fn version(&self) -> u8 {
-        (self.hdr.ttl & 0x00FF0000 >> 16) as u8
+        (self.hdr.ttl >> 16) as u8
     }
 
     /// UDP buffer size.
@@ -94,7 +174,7 @@ impl Opt {
     }
 
     pub fn extended_r_code(&self) -> u16 {
-        ((self.hdr.ttl & 0xFF000000 >> 24) << 4) as u16
+        ((self.hdr.ttl >> 24) << 4) as u16
     }
 
     pub fn set_extended_r_code(&mut self, v: u16) {
@@ -102,21 +182,34 @@ impl Opt {
     }
 
     pub fn op_extended_r_code(&self, v: u16) -> u32 {
-        return self.hdr.ttl & 0x00FFFFFF | ((v >> 4) as u32) << 24
+        self.hdr.ttl & 0x00FFFFFF | ((v >> 4) as u32) << 24
     }
 
     pub fn set_do(&mut self, d: &[bool]) {
-        if d.len() == 1 {
-            if d[0] {
-                self.hdr.ttl |= edns0::_DO as u32;
-            } else {
-                self.hdr.ttl ^= edns0::_DO as u32;
-                self.hdr.ttl &= edns0::_DO as u32;
-            }
+        if d.len() == 1 && !d[0] {
+            self.hdr.ttl &= !(edns0::_DO as u32);
         } else {
             self.hdr.ttl |= edns0::_DO as u32
         }
     }
+
+    pub fn set_version(&mut self, version: u8) {
+        self.hdr.ttl = self.hdr.ttl & 0xFF00FFFF | (version as u32) << 16;
+    }
+
+    /// Adds `option`, replacing any others of its code.
+    pub fn set_option(&mut self, option: EDNS0) -> &mut Self {
+        self.remove_option(option.option());
+        self.option.push(option);
+        self
+    }
+
+    /// Removes the options of `code`, telling whether there were any.
+    pub fn remove_option(&mut self, code: u16) -> bool {
+        let len = self.option.len();
+        self.option.retain(|o| o.option() != code);
+        self.option.len() != len
+    }
 }
 
 impl Display for Opt {
@@ -133,7 +226,7 @@ impl Display for Opt {
 
         if self.hdr.ttl & 0x7FFF != 0 {
             f.write_str("MBZ: 0x")?;
-            f.write_str(&hex::encode(&(self.hdr.ttl & 0x7FFF).to_be_bytes()))?;
+            f.write_str(&hex::encode((self.hdr.ttl & 0x7FFF).to_be_bytes()))?;
         }
         f.write_str("udp: ")?;
         fmt::Display::fmt(&self.udp_size(), f)?;
@@ -143,7 +236,7 @@ impl Display for Opt {
                 EDNS0::Nid(val) => {
                     f.write_str("\n; NSID: ")?;
                     let mut buf = BytesMut::new();
-                    if let Ok(_) = val.pack(&mut buf) {
+                    if val.pack(&mut buf).is_ok() {
                         f.write_str("(")?;
                         for a in buf.as_ref() {
                             f.write_char((*a) as char)?;
@@ -155,6 +248,30 @@ impl Display for Opt {
                     f.write_str("\n; SUBNET: ")?;
                     val.fmt(f)?;
                 }
+                EDNS0::Ede(val) => {
+                    f.write_str("\n; EDE: ")?;
+                    val.fmt(f)?;
+                }
+                EDNS0::Padding(val) => {
+                    f.write_str("\n; PADDING: ")?;
+                    val.fmt(f)?;
+                }
+                EDNS0::Expire(val) => {
+                    f.write_str("\n; EXPIRE: ")?;
+                    val.fmt(f)?;
+                }
+                EDNS0::Chain(val) => {
+                    f.write_str("\n; CHAIN: ")?;
+                    val.fmt(f)?;
+                }
+                EDNS0::KeyTag(val) => {
+                    f.write_str("\n; KEY-TAG: ")?;
+                    val.fmt(f)?;
+                }
+                EDNS0::Custom(val) => {
+                    write!(f, "\n; OPT={}: ", val.code())?;
+                    val.fmt(f)?;
+                }
                 EDNS0::Local(val) => {
                     f.write_str("\n; LOCAL OPT: ")?;
                     val.fmt(f)?;
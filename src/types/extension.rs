@@ -0,0 +1,213 @@
+use core::any::Any;
+use core::fmt;
+use core::fmt::Display;
+#[cfg(feature = "std")]
+use std::collections::BTreeMap;
+use bytes::BytesMut;
+#[cfg(feature = "std")]
+use once_cell::sync::Lazy;
+#[cfg(feature = "std")]
+use parking_lot::RwLock;
+use crate::io::Cursor;
+use crate::msg::RecourseRecordHdr;
+use crate::prelude::*;
+use crate::Result;
+use super::RecourseRecord;
+
+/// A record type the crate does not know, given to [`register_type`] so
+/// records of it unpack as [`RecourseRecord::Extension`] rather than as
+/// [`RFC3597`](super::RFC3597) hex.
+///
+/// Display writes the whole record, header first, like the built-in types.
+pub trait RData: Display + fmt::Debug + Send + Sync + AsRData {
+    fn header(&self) -> &RecourseRecordHdr;
+
+    fn header_mut(&mut self) -> &mut RecourseRecordHdr;
+
+    /// Writes the data; the RDLENGTH before it is filled in afterwards.
+    fn pack_data(&self, buf: &mut BytesMut) -> Result<()>;
+
+    /// Reads `hdr.rd_length` octets of data at the cursor, which spans the
+    /// whole message so compressed names can be followed.
+    fn unpack_data(hdr: RecourseRecordHdr, cur: &mut Cursor<&[u8]>) -> Result<Self>
+    where
+        Self: Sized;
+}
+
+/// Cloning and downcasting boxed [`RData`]; every `Clone` type has it.
+pub trait AsRData {
+    fn clone_box(&self) -> Box<dyn RData>;
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<T: RData + Clone + 'static> AsRData for T {
+    fn clone_box(&self) -> Box<dyn RData> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Clone for Box<dyn RData> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+impl<T: RData + 'static> From<T> for RecourseRecord {
+    fn from(value: T) -> Self {
+        RecourseRecord::Extension(Box::new(value))
+    }
+}
+
+impl RecourseRecord {
+    /// The data of an [`Extension`](RecourseRecord::Extension) record of type `T`.
+    pub fn downcast_ref<T: 'static>(&self) -> Option<&T> {
+        match self {
+            RecourseRecord::Extension(val) => val.as_any().downcast_ref(),
+            _ => None,
+        }
+    }
+}
+
+type Unpacker = fn(RecourseRecordHdr, &mut Cursor<&[u8]>) -> Result<Box<dyn RData>>;
+
+#[cfg(feature = "std")]
+static REGISTRY: Lazy<RwLock<BTreeMap<u16, Unpacker>>> = Lazy::new(Default::default);
+
+/// Unpacks records of type `typ` as `T` from now on. Types the crate
+/// implements itself keep their own representation.
+#[cfg(feature = "std")]
+pub fn register_type<T: RData + 'static>(typ: u16) {
+    fn unpack<T: RData + 'static>(hdr: RecourseRecordHdr, cur: &mut Cursor<&[u8]>) -> Result<Box<dyn RData>> {
+        Ok(Box::new(T::unpack_data(hdr, cur)?))
+    }
+    REGISTRY.write().insert(typ, unpack::<T> as Unpacker);
+}
+
+/// Goes back to unpacking `typ` as [`RFC3597`](super::RFC3597).
+#[cfg(feature = "std")]
+pub fn unregister_type(typ: u16) -> bool {
+    REGISTRY.write().remove(&typ).is_some()
+}
+
+#[cfg(feature = "std")]
+pub(super) fn registered(typ: u16) -> Option<Unpacker> {
+    REGISTRY.read().get(&typ).copied()
+}
+
+#[cfg(not(feature = "std"))]
+pub(super) fn registered(_typ: u16) -> Option<Unpacker> {
+    None
+}
+
+/// Extension records as their header and base64 data; they deserialize
+/// only while their type is registered.
+#[cfg(feature = "serde")]
+pub(super) mod serde_rdata {
+    use serde::de::Error as _;
+    use serde::ser::Error as _;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    struct Raw {
+        hdr: RecourseRecordHdr,
+        #[serde(with = "crate::util::base64_serde")]
+        data: Vec<u8>,
+    }
+
+    #[allow(clippy::borrowed_box)]
+    pub fn serialize<S: Serializer>(rr: &Box<dyn RData>, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+        let mut data = BytesMut::new();
+        rr.pack_data(&mut data).map_err(|err| S::Error::custom(format!("{:?}", err)))?;
+        Raw { hdr: rr.header().clone(), data: data.to_vec() }.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> core::result::Result<Box<dyn RData>, D::Error> {
+        let Raw { mut hdr, data } = Raw::deserialize(deserializer)?;
+        let unpack = registered(hdr.typ).ok_or_else(|| D::Error::custom(format!("type {} is not registered", hdr.typ)))?;
+        hdr.rd_length = data.len() as u16;
+        unpack(hdr, &mut Cursor::new(&data[..])).map_err(|err| D::Error::custom(format!("{:?}", err)))
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use core::fmt;
+    use bytes::{BufMut, BytesMut};
+    use byteorder::BigEndian;
+    use crate::io::{Cursor, ReadBytesExt};
+    use crate::msg::{RecourseRecordHdr, RR};
+    use crate::types::RecourseRecord;
+    use crate::{full_domain, types, util, DomainString, Msg, Result};
+    use super::{register_type, unregister_type, RData};
+
+    /// A private-use type: a weight and a target name.
+    #[derive(Debug, Clone)]
+    struct Weight {
+        hdr: RecourseRecordHdr,
+        weight: u16,
+        target: DomainString,
+    }
+    const TYPE_WEIGHT: u16 = 65280;
+
+    impl fmt::Display for Weight {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.hdr.fmt(f)?;
+            write!(f, "{} {}", self.weight, self.target)
+        }
+    }
+
+    impl RData for Weight {
+        fn header(&self) -> &RecourseRecordHdr {
+            &self.hdr
+        }
+
+        fn header_mut(&mut self) -> &mut RecourseRecordHdr {
+            &mut self.hdr
+        }
+
+        fn pack_data(&self, buf: &mut BytesMut) -> Result<()> {
+            buf.put_u16(self.weight);
+            util::pack_domain_name(&self.target, buf)
+        }
+
+        fn unpack_data(hdr: RecourseRecordHdr, cur: &mut Cursor<&[u8]>) -> Result<Self> {
+            let weight = cur.read_u16::<BigEndian>()?;
+            let target = util::unpack_domain_name_cur(cur)?;
+            Ok(Self { hdr, weight, target })
+        }
+    }
+
+    #[test]
+    fn test_extension() {
+        let hdr = RecourseRecordHdr { name: full_domain("example.com"), typ: TYPE_WEIGHT, class: types::CLASS_INET, ttl: 60, rd_length: 0 };
+        let mut msg = Msg::new();
+        msg.set_question(full_domain("example.com"), TYPE_WEIGHT);
+        msg.answer.push(Weight { hdr, weight: 7, target: full_domain("www.example.com") }.into());
+        msg.answer.push(msg.answer[0].clone());
+        let buf = msg.to_buf().unwrap();
+
+        let back = Msg::unpack(&buf).unwrap();
+        assert!(matches!(&back.answer[0], RecourseRecord::Unknown(val) if val.data == "000703777777076578616d706c6503636f6d00"));
+        register_type::<Weight>(TYPE_WEIGHT);
+        let mut back = Msg::unpack(&buf).unwrap();
+        let weight = back.answer[1].downcast_ref::<Weight>().unwrap();
+        assert_eq!((weight.weight, weight.target.as_str()), (7, "www.example.com."));
+        assert!(back.answer[0].to_string().ends_with("7 www.example.com."));
+        back.answer[0].header_mut().ttl = 30;
+        assert_eq!(back.answer[0].header().ttl, 30);
+        assert_eq!(back.to_buf().unwrap().len(), buf.len());
+        #[cfg(feature = "serde")]
+        {
+            let json = serde_json::to_string(&back.answer[1]).unwrap();
+            let rr: RecourseRecord = serde_json::from_str(&json).unwrap();
+            assert_eq!(rr, back.answer[1]);
+        }
+        assert!(unregister_type(TYPE_WEIGHT));
+        assert!(matches!(Msg::unpack(&buf).unwrap().answer[0], RecourseRecord::Unknown(_)));
+    }
+}
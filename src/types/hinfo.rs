@@ -0,0 +1,90 @@
+use core::fmt;
+use core::fmt::{Display, Formatter};
+use crate::io::{Cursor, Read, ReadBytesExt};
+use bytes::{BufMut, BytesMut};
+use crate::prelude::*;
+use crate::{DomainString, Error, util};
+use crate::msg::{RecourseRecordHdr, RR};
+use crate::types::RecourseRecord;
+use crate::Result;
+use crate::types::TYPE_HINFO;
+
+/// HINFO
+/// RFC 1035. Also what resolvers answer ANY queries with (RFC 8482).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HINFO {
+    pub hdr: RecourseRecordHdr,
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::base64_serde"))]
+    pub cpu: Vec<u8>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::base64_serde"))]
+    pub os: Vec<u8>,
+}
+
+impl HINFO {
+    pub fn new(name: DomainString, class: u16, ttl: u32, cpu: Vec<u8>, os: Vec<u8>) -> Self {
+        Self {
+            hdr: RecourseRecordHdr {
+                name,
+                typ: TYPE_HINFO,
+                class,
+                ttl,
+                rd_length: (2 + cpu.len() + os.len()) as u16,
+            },
+            cpu,
+            os,
+        }
+    }
+}
+
+impl From<HINFO> for RecourseRecord {
+    fn from(value: HINFO) -> Self {
+        RecourseRecord::HINFO(value)
+    }
+}
+
+impl Display for HINFO {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.hdr, f)?;
+        write!(f, "{} {}", util::quote_character_string(&self.cpu), util::quote_character_string(&self.os))
+    }
+}
+
+impl RR for HINFO {
+    type Item = HINFO;
+
+    fn pack(&self, buf: &mut BytesMut) -> Result<()> {
+        let start = buf.len();
+        for s in [&self.cpu, &self.os] {
+            let len = u8::try_from(s.len()).map_err(|_| Error::new("HINFO string longer than 255 octets"))?;
+            buf.put_u8(len);
+            buf.put_slice(s);
+        }
+        let count = buf.len() - start;
+        util::set_value_offset(buf.as_mut(), start - 2, count as u16);
+        Ok(())
+    }
+
+    fn unpack(h: RecourseRecordHdr, cur: &mut Cursor<&[u8]>) -> Result<Self::Item> {
+        let end = cur.position() + h.rd_length as u64;
+        let mut strings = [Vec::new(), Vec::new()];
+        for s in strings.iter_mut() {
+            let len = cur.read_u8()? as u64;
+            if cur.position() + len > end {
+                return Err(Error::InvalidRdLength);
+            }
+            s.resize(len as usize, 0);
+            cur.read_exact(s)?;
+        }
+        let [cpu, os] = strings;
+        Ok(Self {
+            hdr: h,
+            cpu,
+            os,
+        })
+    }
+
+    fn header(&self) -> &RecourseRecordHdr {
+        &self.hdr
+    }
+}
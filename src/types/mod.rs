@@ -1,29 +1,86 @@
 pub mod a;
 pub mod aaaa;
+pub mod afsdb;
+pub mod caa;
 pub mod cname;
+pub mod dname;
+pub mod dnskey;
+pub mod ds;
 pub mod edns;
+pub mod extension;
+pub mod hinfo;
+pub mod mx;
+pub mod ns;
+pub mod nsec;
+pub mod nsec3;
+pub mod ptr;
 pub mod rfc3597;
-// pub mod svcb;
+pub mod rrsig;
+pub mod soa;
+pub mod srv;
+pub mod svcb;
+pub mod txt;
 
-use std::fmt;
-use std::fmt::{Display, Formatter};
-use std::io::Cursor;
-use std::net::IpAddr;
-use bytes::BytesMut;
+use core::fmt;
+use core::fmt::{Display, Formatter};
+use core::hash::{Hash, Hasher};
+use crate::io::Cursor;
+use core::net::IpAddr;
+use crate::prelude::*;
+use bytes::{BufMut, BytesMut};
 pub use a::A;
 pub use aaaa::AAAA;
+pub use afsdb::AFSDB;
+pub use caa::CAA;
 pub use cname::CNAME;
+pub use dname::DNAME;
+pub use dnskey::DNSKEY;
+pub use ds::DS;
+pub use hinfo::HINFO;
 pub use edns::{EDNS0, Opt};
+pub use extension::RData;
+pub use mx::MX;
+pub use ns::NS;
+pub use nsec::NSEC;
+pub use nsec3::{NSEC3, NSEC3PARAM};
+pub use ptr::PTR;
 pub use rfc3597::RFC3597;
+pub use rrsig::RRSIG;
+pub use soa::SOA;
+pub use srv::SRV;
+pub use svcb::{SvcParam, SVCB};
+pub use txt::TXT;
 use crate::msg::{RecourseRecordHdr, RR};
-use crate::{DomainString, Result};
+use crate::{util, DomainString, Result};
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum RecourseRecord {
     A(A),
     AAAA(AAAA),
     CNAME(CNAME),
+    DNAME(DNAME),
+    NS(NS),
+    SOA(SOA),
+    NSEC(NSEC),
+    NSEC3(NSEC3),
+    NSEC3PARAM(NSEC3PARAM),
+    MX(MX),
+    PTR(PTR),
+    TXT(TXT),
+    SRV(SRV),
+    CAA(CAA),
+    /// SVCB or HTTPS.
+    SVCB(SVCB),
+    DNSKEY(DNSKEY),
+    DS(DS),
+    HINFO(HINFO),
+    AFSDB(AFSDB),
+    RRSIG(RRSIG),
     Opt(Opt),
+    /// A type given to [`extension::register_type`].
+    #[cfg_attr(feature = "serde", serde(with = "extension::serde_rdata"))]
+    Extension(Box<dyn RData>),
     Unknown(RFC3597),
 }
 
@@ -34,6 +91,149 @@ impl RecourseRecord {
             IpAddr::V6(val) => AAAA::new(name, class, ttl, val).into()
         }
     }
+
+    /// The data of the record, packed without name compression.
+    pub fn rdata(&self) -> Result<Vec<u8>> {
+        let mut buf = BytesMut::new();
+        buf.put_u16(0); // RDLENGTH, which pack fills in
+        self.pack(&mut buf)?;
+        Ok(buf[2..].to_vec())
+    }
+
+    /// The record in canonical form (RFC 4034 section 6.2): the owner and
+    /// the names in its data lowercased, NSEC data aside (RFC 6840
+    /// section 5.1).
+    pub fn to_canonical(&self) -> RecourseRecord {
+        let mut rr = self.clone();
+        rr.header_mut().name.make_ascii_lowercase();
+        match &mut rr {
+            RecourseRecord::NS(val) => val.ns.make_ascii_lowercase(),
+            RecourseRecord::CNAME(val) => val.target.make_ascii_lowercase(),
+            RecourseRecord::DNAME(val) => val.target.make_ascii_lowercase(),
+            RecourseRecord::AFSDB(val) => val.hostname.make_ascii_lowercase(),
+            RecourseRecord::MX(val) => val.mx.make_ascii_lowercase(),
+            RecourseRecord::PTR(val) => val.ptr.make_ascii_lowercase(),
+            RecourseRecord::SRV(val) => val.target.make_ascii_lowercase(),
+            RecourseRecord::SOA(val) => {
+                val.ns.make_ascii_lowercase();
+                val.mbox.make_ascii_lowercase();
+            }
+            RecourseRecord::RRSIG(val) => val.signer_name.make_ascii_lowercase(),
+            _ => {}
+        }
+        rr
+    }
+
+    /// The data of the canonical form, which orders records within an
+    /// RRset (RFC 4034 section 6.3).
+    pub fn canonical_rdata(&self) -> Result<Vec<u8>> {
+        self.to_canonical().rdata()
+    }
+
+    pub fn header_mut(&mut self) -> &mut RecourseRecordHdr {
+        match self {
+            RecourseRecord::A(val) => &mut val.hdr,
+            RecourseRecord::AAAA(val) => &mut val.hdr,
+            RecourseRecord::CNAME(val) => &mut val.hdr,
+            RecourseRecord::DNAME(val) => &mut val.hdr,
+            RecourseRecord::NS(val) => &mut val.hdr,
+            RecourseRecord::SOA(val) => &mut val.hdr,
+            RecourseRecord::NSEC(val) => &mut val.hdr,
+            RecourseRecord::NSEC3(val) => &mut val.hdr,
+            RecourseRecord::NSEC3PARAM(val) => &mut val.hdr,
+            RecourseRecord::MX(val) => &mut val.hdr,
+            RecourseRecord::PTR(val) => &mut val.hdr,
+            RecourseRecord::TXT(val) => &mut val.hdr,
+            RecourseRecord::SRV(val) => &mut val.hdr,
+            RecourseRecord::CAA(val) => &mut val.hdr,
+            RecourseRecord::SVCB(val) => &mut val.hdr,
+            RecourseRecord::DNSKEY(val) => &mut val.hdr,
+            RecourseRecord::DS(val) => &mut val.hdr,
+            RecourseRecord::HINFO(val) => &mut val.hdr,
+            RecourseRecord::AFSDB(val) => &mut val.hdr,
+            RecourseRecord::RRSIG(val) => &mut val.hdr,
+            RecourseRecord::Opt(val) => &mut val.hdr,
+            RecourseRecord::Extension(val) => val.header_mut(),
+            RecourseRecord::Unknown(val) => &mut val.hdr,
+        }
+    }
+}
+
+/// Records of the same type compare field by field, names ignoring
+/// ASCII case; [`Extension`](RecourseRecord::Extension) records by their
+/// packed data.
+impl PartialEq for RecourseRecord {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (RecourseRecord::A(a), RecourseRecord::A(b)) => a == b,
+            (RecourseRecord::AAAA(a), RecourseRecord::AAAA(b)) => a == b,
+            (RecourseRecord::CNAME(a), RecourseRecord::CNAME(b)) => a == b,
+            (RecourseRecord::DNAME(a), RecourseRecord::DNAME(b)) => a == b,
+            (RecourseRecord::NS(a), RecourseRecord::NS(b)) => a == b,
+            (RecourseRecord::SOA(a), RecourseRecord::SOA(b)) => a == b,
+            (RecourseRecord::NSEC(a), RecourseRecord::NSEC(b)) => a == b,
+            (RecourseRecord::NSEC3(a), RecourseRecord::NSEC3(b)) => a == b,
+            (RecourseRecord::NSEC3PARAM(a), RecourseRecord::NSEC3PARAM(b)) => a == b,
+            (RecourseRecord::MX(a), RecourseRecord::MX(b)) => a == b,
+            (RecourseRecord::PTR(a), RecourseRecord::PTR(b)) => a == b,
+            (RecourseRecord::TXT(a), RecourseRecord::TXT(b)) => a == b,
+            (RecourseRecord::SRV(a), RecourseRecord::SRV(b)) => a == b,
+            (RecourseRecord::CAA(a), RecourseRecord::CAA(b)) => a == b,
+            (RecourseRecord::SVCB(a), RecourseRecord::SVCB(b)) => a == b,
+            (RecourseRecord::DNSKEY(a), RecourseRecord::DNSKEY(b)) => a == b,
+            (RecourseRecord::DS(a), RecourseRecord::DS(b)) => a == b,
+            (RecourseRecord::HINFO(a), RecourseRecord::HINFO(b)) => a == b,
+            (RecourseRecord::AFSDB(a), RecourseRecord::AFSDB(b)) => a == b,
+            (RecourseRecord::RRSIG(a), RecourseRecord::RRSIG(b)) => a == b,
+            (RecourseRecord::Opt(a), RecourseRecord::Opt(b)) => a == b,
+            (RecourseRecord::Extension(a), RecourseRecord::Extension(b)) => {
+                a.header() == b.header() && extension_data(a.as_ref()) == extension_data(b.as_ref())
+            }
+            (RecourseRecord::Unknown(a), RecourseRecord::Unknown(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for RecourseRecord {}
+
+impl Hash for RecourseRecord {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        core::mem::discriminant(self).hash(state);
+        match self {
+            RecourseRecord::A(val) => val.hash(state),
+            RecourseRecord::AAAA(val) => val.hash(state),
+            RecourseRecord::CNAME(val) => val.hash(state),
+            RecourseRecord::DNAME(val) => val.hash(state),
+            RecourseRecord::NS(val) => val.hash(state),
+            RecourseRecord::SOA(val) => val.hash(state),
+            RecourseRecord::NSEC(val) => val.hash(state),
+            RecourseRecord::NSEC3(val) => val.hash(state),
+            RecourseRecord::NSEC3PARAM(val) => val.hash(state),
+            RecourseRecord::MX(val) => val.hash(state),
+            RecourseRecord::PTR(val) => val.hash(state),
+            RecourseRecord::TXT(val) => val.hash(state),
+            RecourseRecord::SRV(val) => val.hash(state),
+            RecourseRecord::CAA(val) => val.hash(state),
+            RecourseRecord::SVCB(val) => val.hash(state),
+            RecourseRecord::DNSKEY(val) => val.hash(state),
+            RecourseRecord::DS(val) => val.hash(state),
+            RecourseRecord::HINFO(val) => val.hash(state),
+            RecourseRecord::AFSDB(val) => val.hash(state),
+            RecourseRecord::RRSIG(val) => val.hash(state),
+            RecourseRecord::Opt(val) => val.hash(state),
+            RecourseRecord::Extension(val) => {
+                val.header().hash(state);
+                extension_data(val.as_ref()).hash(state);
+            }
+            RecourseRecord::Unknown(val) => val.hash(state),
+        }
+    }
+}
+
+fn extension_data(rr: &dyn RData) -> Option<BytesMut> {
+    let mut buf = BytesMut::new();
+    rr.pack_data(&mut buf).ok().map(|_| buf)
 }
 
 impl Display for RecourseRecord {
@@ -42,7 +242,25 @@ impl Display for RecourseRecord {
             RecourseRecord::A(val) => val.fmt(f),
             RecourseRecord::AAAA(val) => val.fmt(f),
             RecourseRecord::CNAME(val) => val.fmt(f),
+            RecourseRecord::DNAME(val) => val.fmt(f),
+            RecourseRecord::NS(val) => val.fmt(f),
+            RecourseRecord::SOA(val) => val.fmt(f),
+            RecourseRecord::NSEC(val) => val.fmt(f),
+            RecourseRecord::NSEC3(val) => val.fmt(f),
+            RecourseRecord::NSEC3PARAM(val) => val.fmt(f),
+            RecourseRecord::MX(val) => val.fmt(f),
+            RecourseRecord::PTR(val) => val.fmt(f),
+            RecourseRecord::TXT(val) => val.fmt(f),
+            RecourseRecord::SRV(val) => val.fmt(f),
+            RecourseRecord::CAA(val) => val.fmt(f),
+            RecourseRecord::SVCB(val) => val.fmt(f),
+            RecourseRecord::DNSKEY(val) => val.fmt(f),
+            RecourseRecord::DS(val) => val.fmt(f),
+            RecourseRecord::HINFO(val) => val.fmt(f),
+            RecourseRecord::AFSDB(val) => val.fmt(f),
+            RecourseRecord::RRSIG(val) => val.fmt(f),
             RecourseRecord::Opt(val) => val.fmt(f),
+            RecourseRecord::Extension(val) => val.fmt(f),
             RecourseRecord::Unknown(val) => val.fmt(f),
         }
     }
@@ -56,7 +274,31 @@ impl RR for RecourseRecord {
             RecourseRecord::A(val) => val.pack(buf),
             RecourseRecord::AAAA(val) => val.pack(buf),
             RecourseRecord::CNAME(val) => val.pack(buf),
+            RecourseRecord::DNAME(val) => val.pack(buf),
+            RecourseRecord::NS(val) => val.pack(buf),
+            RecourseRecord::SOA(val) => val.pack(buf),
+            RecourseRecord::NSEC(val) => val.pack(buf),
+            RecourseRecord::NSEC3(val) => val.pack(buf),
+            RecourseRecord::NSEC3PARAM(val) => val.pack(buf),
+            RecourseRecord::MX(val) => val.pack(buf),
+            RecourseRecord::PTR(val) => val.pack(buf),
+            RecourseRecord::TXT(val) => val.pack(buf),
+            RecourseRecord::SRV(val) => val.pack(buf),
+            RecourseRecord::CAA(val) => val.pack(buf),
+            RecourseRecord::SVCB(val) => val.pack(buf),
+            RecourseRecord::DNSKEY(val) => val.pack(buf),
+            RecourseRecord::DS(val) => val.pack(buf),
+            RecourseRecord::HINFO(val) => val.pack(buf),
+            RecourseRecord::AFSDB(val) => val.pack(buf),
+            RecourseRecord::RRSIG(val) => val.pack(buf),
             RecourseRecord::Opt(val) => val.pack(buf),
+            RecourseRecord::Extension(val) => {
+                let start = buf.len();
+                val.pack_data(buf)?;
+                let count = buf.len() - start;
+                util::set_value_offset(buf.as_mut(), start - 2, count as u16);
+                Ok(())
+            }
             RecourseRecord::Unknown(val) => val.pack(buf),
         }
     }
@@ -66,8 +308,28 @@ impl RR for RecourseRecord {
             TYPE_A => A::unpack(h, cur)?.into(),
             TYPE_AAAA => AAAA::unpack(h, cur)?.into(),
             TYPE_CNAME => CNAME::unpack(h, cur)?.into(),
+            TYPE_DNAME => DNAME::unpack(h, cur)?.into(),
+            TYPE_NS => NS::unpack(h, cur)?.into(),
+            TYPE_SOA => SOA::unpack(h, cur)?.into(),
+            TYPE_NSEC => NSEC::unpack(h, cur)?.into(),
+            TYPE_NSEC3 => NSEC3::unpack(h, cur)?.into(),
+            TYPE_NSEC3PARAM => NSEC3PARAM::unpack(h, cur)?.into(),
+            TYPE_MX => MX::unpack(h, cur)?.into(),
+            TYPE_PTR => PTR::unpack(h, cur)?.into(),
+            TYPE_TXT => TXT::unpack(h, cur)?.into(),
+            TYPE_SRV => SRV::unpack(h, cur)?.into(),
+            TYPE_CAA => CAA::unpack(h, cur)?.into(),
+            TYPE_SVCB | TYPE_HTTPS => SVCB::unpack(h, cur)?.into(),
+            TYPE_DNSKEY => DNSKEY::unpack(h, cur)?.into(),
+            TYPE_DS => DS::unpack(h, cur)?.into(),
+            TYPE_HINFO => HINFO::unpack(h, cur)?.into(),
+            TYPE_AFSDB => AFSDB::unpack(h, cur)?.into(),
+            TYPE_RRSIG => RRSIG::unpack(h, cur)?.into(),
             TYPE_OPT => Opt::unpack(h, cur)?.into(),
-            _ => RFC3597::unpack(h, cur)?.into(),
+            _ => match extension::registered(h.typ) {
+                Some(unpack) => RecourseRecord::Extension(unpack(h, cur)?),
+                None => RFC3597::unpack(h, cur)?.into(),
+            },
         })
     }
 
@@ -76,7 +338,25 @@ impl RR for RecourseRecord {
             RecourseRecord::A(val) => val.header(),
             RecourseRecord::AAAA(val) => val.header(),
             RecourseRecord::CNAME(val) => val.header(),
+            RecourseRecord::DNAME(val) => val.header(),
+            RecourseRecord::NS(val) => val.header(),
+            RecourseRecord::SOA(val) => val.header(),
+            RecourseRecord::NSEC(val) => val.header(),
+            RecourseRecord::NSEC3(val) => val.header(),
+            RecourseRecord::NSEC3PARAM(val) => val.header(),
+            RecourseRecord::MX(val) => val.header(),
+            RecourseRecord::PTR(val) => val.header(),
+            RecourseRecord::TXT(val) => val.header(),
+            RecourseRecord::SRV(val) => val.header(),
+            RecourseRecord::CAA(val) => val.header(),
+            RecourseRecord::SVCB(val) => val.header(),
+            RecourseRecord::DNSKEY(val) => val.header(),
+            RecourseRecord::DS(val) => val.header(),
+            RecourseRecord::HINFO(val) => val.header(),
+            RecourseRecord::AFSDB(val) => val.header(),
+            RecourseRecord::RRSIG(val) => val.header(),
             RecourseRecord::Opt(val) => val.header(),
+            RecourseRecord::Extension(val) => val.header(),
             RecourseRecord::Unknown(val) => val.header(),
         }
     }
@@ -0,0 +1,95 @@
+use core::fmt;
+use core::fmt::{Display, Formatter};
+use core::hash::{Hash, Hasher};
+use crate::io::{Cursor, ReadBytesExt};
+use byteorder::BigEndian;
+use bytes::{BufMut, BytesMut};
+use crate::{DomainString, util};
+use crate::msg::{RecourseRecordHdr, RR};
+use crate::types::RecourseRecord;
+use crate::Result;
+use crate::types::TYPE_MX;
+
+/// MX
+/// RFC 1035.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MX {
+    pub hdr: RecourseRecordHdr,
+    pub preference: u16,
+    pub mx: DomainString,
+}
+
+impl MX {
+    pub fn new(name: DomainString, class: u16, ttl: u32, preference: u16, mx: DomainString) -> Self {
+        Self {
+            hdr: RecourseRecordHdr {
+                name,
+                typ: TYPE_MX,
+                class,
+                ttl,
+                rd_length: (2 + util::cal_domain_name_len(&mx)) as u16,
+            },
+            preference,
+            mx,
+        }
+    }
+}
+
+impl From<MX> for RecourseRecord {
+    fn from(value: MX) -> Self {
+        RecourseRecord::MX(value)
+    }
+}
+
+impl PartialEq for MX {
+    fn eq(&self, other: &Self) -> bool {
+        self.hdr == other.hdr
+            && self.preference == other.preference
+            && self.mx.eq_ignore_ascii_case(&other.mx)
+    }
+}
+
+impl Eq for MX {}
+
+impl Hash for MX {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.hdr.hash(state);
+        self.preference.hash(state);
+        util::hash_name(&self.mx, state);
+    }
+}
+
+impl Display for MX {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.hdr, f)?;
+        write!(f, "{} {}", self.preference, self.mx)
+    }
+}
+
+impl RR for MX {
+    type Item = MX;
+
+    fn pack(&self, buf: &mut BytesMut) -> Result<()> {
+        let start = buf.len();
+        buf.put_u16(self.preference);
+        util::pack_domain_name(&self.mx, buf)?;
+        let count = buf.len() - start;
+        util::set_value_offset(buf.as_mut(), start - 2, count as u16);
+        Ok(())
+    }
+
+    fn unpack(h: RecourseRecordHdr, cur: &mut Cursor<&[u8]>) -> Result<Self::Item> {
+        let preference = cur.read_u16::<BigEndian>()?;
+        let mx = util::unpack_domain_name_cur(cur)?;
+        Ok(Self {
+            hdr: h,
+            preference,
+            mx,
+        })
+    }
+
+    fn header(&self) -> &RecourseRecordHdr {
+        &self.hdr
+    }
+}
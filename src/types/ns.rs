@@ -0,0 +1,92 @@
+use core::fmt;
+use core::fmt::{Display, Formatter};
+use core::hash::{Hash, Hasher};
+use crate::io::Cursor;
+use bytes::{BytesMut};
+use crate::{DomainString, util};
+use crate::msg::{RecourseRecordHdr, RR};
+use crate::types::RecourseRecord;
+use crate::Result;
+use crate::types::TYPE_NS;
+
+/// NS
+/// RFC 1035.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NS {
+    pub hdr: RecourseRecordHdr,
+    pub ns: DomainString,
+}
+
+impl NS {
+    pub fn new(name: DomainString, class: u16, ttl: u32, ns: DomainString) -> Self {
+        Self {
+            hdr: RecourseRecordHdr {
+                name,
+                typ: TYPE_NS,
+                class,
+                ttl,
+                rd_length: util::cal_domain_name_len(&ns) as u16,
+            },
+            ns,
+        }
+    }
+}
+
+impl From<NS> for RecourseRecord {
+    fn from(value: NS) -> Self {
+        RecourseRecord::NS(value)
+    }
+}
+
+impl PartialEq for NS {
+    fn eq(&self, other: &Self) -> bool {
+        self.hdr == other.hdr && self.ns.eq_ignore_ascii_case(&other.ns)
+    }
+}
+
+impl Eq for NS {}
+
+impl Hash for NS {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.hdr.hash(state);
+        util::hash_name(&self.ns, state);
+    }
+}
+
+impl Display for NS {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.hdr, f)?;
+        f.write_str(&self.ns)
+    }
+}
+
+impl RR for NS {
+    type Item = NS;
+
+    fn pack(&self, buf: &mut BytesMut) -> Result<()> {
+        let start = buf.len();
+        util::pack_domain_name(&self.ns, buf)?;
+        let count = buf.len() - start;
+        util::set_value_offset(buf.as_mut(), start - 2, count as u16);
+        Ok(())
+    }
+
+    fn unpack(h: RecourseRecordHdr, cur: &mut Cursor<&[u8]>) -> Result<Self::Item> {
+        if h.rd_length == 0 {
+            return Ok(Self {
+                hdr: h,
+                ns: "".into(),
+            })
+        }
+        let name = util::unpack_domain_name_cur(cur)?;
+        Ok(Self {
+            hdr: h,
+            ns: name,
+        })
+    }
+
+    fn header(&self) -> &RecourseRecordHdr {
+        &self.hdr
+    }
+}
@@ -0,0 +1,154 @@
+use core::fmt;
+use core::fmt::{Display, Formatter};
+use core::hash::{Hash, Hasher};
+use crate::io::{Cursor, Read};
+use bytes::{BufMut, BytesMut};
+use crate::prelude::*;
+use crate::{DomainString, Error, Result, util};
+use crate::msg::{RecourseRecordHdr, RR};
+use crate::types::RecourseRecord;
+use crate::types::TYPE_NSEC;
+
+/// NSEC
+/// RFC 4034.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NSEC {
+    pub hdr: RecourseRecordHdr,
+    pub next_domain: DomainString,
+    pub type_bit_map: Vec<u16>,
+}
+
+impl NSEC {
+    pub fn new(name: DomainString, class: u16, ttl: u32, next_domain: DomainString, mut type_bit_map: Vec<u16>) -> Self {
+        type_bit_map.sort_unstable();
+        type_bit_map.dedup();
+        Self {
+            hdr: RecourseRecordHdr {
+                name,
+                typ: TYPE_NSEC,
+                class,
+                ttl,
+                rd_length: (util::cal_domain_name_len(&next_domain) + type_bit_map_len(&type_bit_map)) as u16,
+            },
+            next_domain,
+            type_bit_map,
+        }
+    }
+}
+
+impl From<NSEC> for RecourseRecord {
+    fn from(value: NSEC) -> Self {
+        RecourseRecord::NSEC(value)
+    }
+}
+
+impl PartialEq for NSEC {
+    fn eq(&self, other: &Self) -> bool {
+        self.hdr == other.hdr
+            && self.next_domain.eq_ignore_ascii_case(&other.next_domain)
+            && self.type_bit_map == other.type_bit_map
+    }
+}
+
+impl Eq for NSEC {}
+
+impl Hash for NSEC {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.hdr.hash(state);
+        util::hash_name(&self.next_domain, state);
+        self.type_bit_map.hash(state);
+    }
+}
+
+impl Display for NSEC {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.hdr, f)?;
+        f.write_str(&self.next_domain)?;
+        fmt_type_bit_map(&self.type_bit_map, f)
+    }
+}
+
+impl RR for NSEC {
+    type Item = NSEC;
+
+    fn pack(&self, buf: &mut BytesMut) -> Result<()> {
+        let start = buf.len();
+        util::pack_domain_name(&self.next_domain, buf)?;
+        pack_type_bit_map(&self.type_bit_map, buf);
+        let count = buf.len() - start;
+        util::set_value_offset(buf.as_mut(), start - 2, count as u16);
+        Ok(())
+    }
+
+    fn unpack(h: RecourseRecordHdr, cur: &mut Cursor<&[u8]>) -> Result<Self::Item> {
+        let end = cur.position() as usize + h.rd_length as usize;
+        let next_domain = util::unpack_domain_name_cur(cur)?;
+        let type_bit_map = unpack_type_bit_map(cur, end)?;
+        Ok(Self {
+            hdr: h,
+            next_domain,
+            type_bit_map,
+        })
+    }
+
+    fn header(&self) -> &RecourseRecordHdr {
+        &self.hdr
+    }
+}
+
+/// Wire length of a type bitmap, see RFC 4034 section 4.1.2.
+pub fn type_bit_map_len(types: &[u16]) -> usize {
+    let mut buf = BytesMut::new();
+    pack_type_bit_map(types, &mut buf);
+    buf.len()
+}
+
+/// Packs sorted types as window blocks of up to 32 octets each.
+pub fn pack_type_bit_map(types: &[u16], buf: &mut BytesMut) {
+    let mut i = 0;
+    while i < types.len() {
+        let window = types[i] >> 8;
+        let mut bitmap = [0u8; 32];
+        let mut octets = 0;
+        while i < types.len() && types[i] >> 8 == window {
+            let low = (types[i] & 0xFF) as usize;
+            bitmap[low / 8] |= 0x80 >> (low % 8);
+            octets = low / 8 + 1;
+            i += 1;
+        }
+        buf.put_u8(window as u8);
+        buf.put_u8(octets as u8);
+        buf.put_slice(&bitmap[..octets]);
+    }
+}
+
+pub fn unpack_type_bit_map(cur: &mut Cursor<&[u8]>, end: usize) -> Result<Vec<u16>> {
+    let mut types = Vec::new();
+    while (cur.position() as usize) < end {
+        let mut hdr = [0u8; 2];
+        cur.read_exact(&mut hdr)?;
+        let (window, octets) = (hdr[0] as u16, hdr[1] as usize);
+        if octets == 0 || octets > 32 || cur.position() as usize + octets > end {
+            return Err(Error::new("bad type bitmap"));
+        }
+        let mut bitmap = [0u8; 32];
+        cur.read_exact(&mut bitmap[..octets])?;
+        for (j, b) in bitmap[..octets].iter().enumerate() {
+            for bit in 0..8 {
+                if b & (0x80 >> bit) != 0 {
+                    types.push(window << 8 | (j * 8 + bit) as u16);
+                }
+            }
+        }
+    }
+    Ok(types)
+}
+
+pub fn fmt_type_bit_map(types: &[u16], f: &mut Formatter<'_>) -> fmt::Result {
+    for typ in types {
+        f.write_str(" ")?;
+        util::qtype_string(*typ, f)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,225 @@
+use core::fmt;
+use core::fmt::{Display, Formatter};
+use crate::io::{Cursor, Read, ReadBytesExt};
+use byteorder::BigEndian;
+use bytes::{BufMut, BytesMut};
+use crate::prelude::*;
+use crate::{Error, Result, util};
+use crate::msg::{RecourseRecordHdr, RR};
+use crate::types::nsec::{fmt_type_bit_map, pack_type_bit_map, type_bit_map_len, unpack_type_bit_map};
+use crate::types::RecourseRecord;
+use crate::types::{TYPE_NSEC3, TYPE_NSEC3PARAM};
+use crate::DomainString;
+
+/// NSEC3 hash algorithm SHA-1, the only one defined.
+pub const NSEC3_SHA1: u8 = 1;
+/// Opt-out flag of NSEC3.
+pub const NSEC3_OPT_OUT: u8 = 1;
+
+/// NSEC3
+/// RFC 5155.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NSEC3 {
+    pub hdr: RecourseRecordHdr,
+    pub hash: u8,
+    pub flags: u8,
+    pub iterations: u16,
+    /// hex-encoded salt
+    pub salt: String,
+    /// base32hex-encoded hash of the next owner name
+    pub next_domain: String,
+    pub type_bit_map: Vec<u16>,
+}
+
+impl NSEC3 {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        name: DomainString,
+        class: u16,
+        ttl: u32,
+        flags: u8,
+        iterations: u16,
+        salt: String,
+        next_domain: String,
+        mut type_bit_map: Vec<u16>,
+    ) -> Self {
+        type_bit_map.sort_unstable();
+        type_bit_map.dedup();
+        let next_len = util::base32hex_decode(&next_domain).map(|v| v.len()).unwrap_or(0);
+        Self {
+            hdr: RecourseRecordHdr {
+                name,
+                typ: TYPE_NSEC3,
+                class,
+                ttl,
+                rd_length: (6 + salt.len() / 2 + next_len + type_bit_map_len(&type_bit_map)) as u16,
+            },
+            hash: NSEC3_SHA1,
+            flags,
+            iterations,
+            salt,
+            next_domain,
+            type_bit_map,
+        }
+    }
+
+    pub fn is_opt_out(&self) -> bool {
+        self.flags & NSEC3_OPT_OUT != 0
+    }
+}
+
+impl From<NSEC3> for RecourseRecord {
+    fn from(value: NSEC3) -> Self {
+        RecourseRecord::NSEC3(value)
+    }
+}
+
+impl Display for NSEC3 {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.hdr, f)?;
+        write!(f, "{} {} {} ", self.hash, self.flags, self.iterations)?;
+        fmt_salt(&self.salt, f)?;
+        f.write_str(" ")?;
+        f.write_str(&self.next_domain)?;
+        fmt_type_bit_map(&self.type_bit_map, f)
+    }
+}
+
+impl RR for NSEC3 {
+    type Item = NSEC3;
+
+    fn pack(&self, buf: &mut BytesMut) -> Result<()> {
+        let start = buf.len();
+        buf.put_u8(self.hash);
+        buf.put_u8(self.flags);
+        buf.put_u16(self.iterations);
+        pack_salt(&self.salt, buf)?;
+        let next = util::base32hex_decode(&self.next_domain).ok_or_else(|| Error::new("bad NSEC3 next hashed owner"))?;
+        buf.put_u8(next.len() as u8);
+        buf.put_slice(&next);
+        pack_type_bit_map(&self.type_bit_map, buf);
+        let count = buf.len() - start;
+        util::set_value_offset(buf.as_mut(), start - 2, count as u16);
+        Ok(())
+    }
+
+    fn unpack(h: RecourseRecordHdr, cur: &mut Cursor<&[u8]>) -> Result<Self::Item> {
+        let end = cur.position() as usize + h.rd_length as usize;
+        let hash = cur.read_u8()?;
+        let flags = cur.read_u8()?;
+        let iterations = cur.read_u16::<BigEndian>()?;
+        let salt = unpack_salt(cur)?;
+        let mut next = vec![0u8; cur.read_u8()? as usize];
+        cur.read_exact(&mut next)?;
+        let type_bit_map = unpack_type_bit_map(cur, end)?;
+        Ok(Self {
+            hdr: h,
+            hash,
+            flags,
+            iterations,
+            salt,
+            next_domain: util::base32hex_encode(&next),
+            type_bit_map,
+        })
+    }
+
+    fn header(&self) -> &RecourseRecordHdr {
+        &self.hdr
+    }
+}
+
+/// NSEC3PARAM
+/// RFC 5155.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NSEC3PARAM {
+    pub hdr: RecourseRecordHdr,
+    pub hash: u8,
+    pub flags: u8,
+    pub iterations: u16,
+    /// hex-encoded salt
+    pub salt: String,
+}
+
+impl NSEC3PARAM {
+    pub fn new(name: DomainString, class: u16, ttl: u32, iterations: u16, salt: String) -> Self {
+        Self {
+            hdr: RecourseRecordHdr {
+                name,
+                typ: TYPE_NSEC3PARAM,
+                class,
+                ttl,
+                rd_length: (5 + salt.len() / 2) as u16,
+            },
+            hash: NSEC3_SHA1,
+            flags: 0,
+            iterations,
+            salt,
+        }
+    }
+}
+
+impl From<NSEC3PARAM> for RecourseRecord {
+    fn from(value: NSEC3PARAM) -> Self {
+        RecourseRecord::NSEC3PARAM(value)
+    }
+}
+
+impl Display for NSEC3PARAM {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.hdr, f)?;
+        write!(f, "{} {} {} ", self.hash, self.flags, self.iterations)?;
+        fmt_salt(&self.salt, f)
+    }
+}
+
+impl RR for NSEC3PARAM {
+    type Item = NSEC3PARAM;
+
+    fn pack(&self, buf: &mut BytesMut) -> Result<()> {
+        let start = buf.len();
+        buf.put_u8(self.hash);
+        buf.put_u8(self.flags);
+        buf.put_u16(self.iterations);
+        pack_salt(&self.salt, buf)?;
+        let count = buf.len() - start;
+        util::set_value_offset(buf.as_mut(), start - 2, count as u16);
+        Ok(())
+    }
+
+    fn unpack(h: RecourseRecordHdr, cur: &mut Cursor<&[u8]>) -> Result<Self::Item> {
+        Ok(Self {
+            hdr: h,
+            hash: cur.read_u8()?,
+            flags: cur.read_u8()?,
+            iterations: cur.read_u16::<BigEndian>()?,
+            salt: unpack_salt(cur)?,
+        })
+    }
+
+    fn header(&self) -> &RecourseRecordHdr {
+        &self.hdr
+    }
+}
+
+fn fmt_salt(salt: &str, f: &mut Formatter<'_>) -> fmt::Result {
+    if salt.is_empty() {
+        f.write_str("-")
+    } else {
+        f.write_str(&salt.to_ascii_uppercase())
+    }
+}
+
+fn pack_salt(salt: &str, buf: &mut BytesMut) -> Result<()> {
+    let salt = hex::decode(salt)?;
+    buf.put_u8(salt.len() as u8);
+    buf.put_slice(&salt);
+    Ok(())
+}
+
+fn unpack_salt(cur: &mut Cursor<&[u8]>) -> Result<String> {
+    let mut salt = vec![0u8; cur.read_u8()? as usize];
+    cur.read_exact(&mut salt)?;
+    Ok(hex::encode(salt))
+}
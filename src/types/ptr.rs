@@ -0,0 +1,86 @@
+use core::fmt;
+use core::fmt::{Display, Formatter};
+use core::hash::{Hash, Hasher};
+use crate::io::Cursor;
+use bytes::{BytesMut};
+use crate::{DomainString, util};
+use crate::msg::{RecourseRecordHdr, RR};
+use crate::types::RecourseRecord;
+use crate::Result;
+use crate::types::TYPE_PTR;
+
+/// PTR
+/// RFC 1035.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PTR {
+    pub hdr: RecourseRecordHdr,
+    pub ptr: DomainString,
+}
+
+impl PTR {
+    pub fn new(name: DomainString, class: u16, ttl: u32, ptr: DomainString) -> Self {
+        Self {
+            hdr: RecourseRecordHdr {
+                name,
+                typ: TYPE_PTR,
+                class,
+                ttl,
+                rd_length: util::cal_domain_name_len(&ptr) as u16,
+            },
+            ptr,
+        }
+    }
+}
+
+impl From<PTR> for RecourseRecord {
+    fn from(value: PTR) -> Self {
+        RecourseRecord::PTR(value)
+    }
+}
+
+impl PartialEq for PTR {
+    fn eq(&self, other: &Self) -> bool {
+        self.hdr == other.hdr && self.ptr.eq_ignore_ascii_case(&other.ptr)
+    }
+}
+
+impl Eq for PTR {}
+
+impl Hash for PTR {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.hdr.hash(state);
+        util::hash_name(&self.ptr, state);
+    }
+}
+
+impl Display for PTR {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.hdr, f)?;
+        f.write_str(&self.ptr)
+    }
+}
+
+impl RR for PTR {
+    type Item = PTR;
+
+    fn pack(&self, buf: &mut BytesMut) -> Result<()> {
+        let start = buf.len();
+        util::pack_domain_name(&self.ptr, buf)?;
+        let count = buf.len() - start;
+        util::set_value_offset(buf.as_mut(), start - 2, count as u16);
+        Ok(())
+    }
+
+    fn unpack(h: RecourseRecordHdr, cur: &mut Cursor<&[u8]>) -> Result<Self::Item> {
+        let ptr = util::unpack_domain_name_cur(cur)?;
+        Ok(Self {
+            hdr: h,
+            ptr,
+        })
+    }
+
+    fn header(&self) -> &RecourseRecordHdr {
+        &self.hdr
+    }
+}
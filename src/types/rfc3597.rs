@@ -1,29 +1,37 @@
-use std::fmt;
-use std::fmt::{Display, Formatter};
-use std::io::{Cursor, Read};
+use core::fmt;
+use core::fmt::{Display, Formatter};
+use crate::io::{Cursor, Read};
 use bytes::{BytesMut};
+use crate::prelude::*;
+use crate::util;
 use crate::util::ResizeMut;
 use crate::Result;
 use crate::msg::{RecourseRecordHdr, RR};
 use crate::types::RecourseRecord;
 
 /// RFC3597 represents an unknown/generic RR. See RFC 3597.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RFC3597 {
     pub hdr: RecourseRecordHdr,
     pub data: String,
 }
 
-impl Into<RecourseRecord> for RFC3597 {
-    fn into(self) -> RecourseRecord {
-        RecourseRecord::Unknown(self)
+impl From<RFC3597> for RecourseRecord {
+    fn from(value: RFC3597) -> Self {
+        RecourseRecord::Unknown(value)
     }
 }
 
+/// In the generic text form of RFC 3597 section 5, `\# <length> <hex>`.
 impl Display for RFC3597 {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         self.hdr.fmt(f)?;
-        f.write_str(&self.data)
+        write!(f, "\\# {}", self.data.len() / 2)?;
+        if !self.data.is_empty() {
+            write!(f, " {}", self.data)?;
+        }
+        Ok(())
     }
 }
 
@@ -31,6 +39,8 @@ impl RR for RFC3597 {
     type Item = RFC3597;
 
     fn pack(&self, buf: &mut BytesMut) -> Result<()> {
+        let start = buf.len();
+        util::set_value_offset(buf.as_mut(), start - 2, (self.data.len() / 2) as u16);
         let add = buf.extend_split(self.data.len()/2);
         hex::decode_to_slice(&self.data, add)?;
         Ok(())
@@ -0,0 +1,183 @@
+use core::fmt;
+use core::fmt::{Display, Formatter};
+use core::hash::{Hash, Hasher};
+use crate::io::{Cursor, Read, ReadBytesExt};
+use base64::Engine;
+use byteorder::BigEndian;
+use bytes::{BufMut, BytesMut};
+use crate::prelude::*;
+use crate::{DomainString, util};
+use crate::msg::{RecourseRecordHdr, RR};
+use crate::types::RecourseRecord;
+use crate::Result;
+use crate::types::TYPE_RRSIG;
+
+/// RRSIG
+/// RFC 4034.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RRSIG {
+    pub hdr: RecourseRecordHdr,
+    pub type_covered: u16,
+    pub algorithm: u8,
+    pub labels: u8,
+    pub orig_ttl: u32,
+    /// seconds since the epoch, in serial number arithmetic
+    pub expiration: u32,
+    pub inception: u32,
+    pub key_tag: u16,
+    pub signer_name: DomainString,
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::base64_serde"))]
+    pub signature: Vec<u8>,
+}
+
+impl RRSIG {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        name: DomainString,
+        class: u16,
+        ttl: u32,
+        type_covered: u16,
+        algorithm: u8,
+        labels: u8,
+        orig_ttl: u32,
+        expiration: u32,
+        inception: u32,
+        key_tag: u16,
+        signer_name: DomainString,
+        signature: Vec<u8>,
+    ) -> Self {
+        Self {
+            hdr: RecourseRecordHdr {
+                name,
+                typ: TYPE_RRSIG,
+                class,
+                ttl,
+                rd_length: (18 + util::cal_domain_name_len(&signer_name) + signature.len()) as u16,
+            },
+            type_covered,
+            algorithm,
+            labels,
+            orig_ttl,
+            expiration,
+            inception,
+            key_tag,
+            signer_name,
+            signature,
+        }
+    }
+
+    /// Packs the rdata without the signature, in canonical form, as it
+    /// starts the signed data (RFC 4034 section 3.1.8.1).
+    pub fn pack_unsigned(&self, buf: &mut BytesMut) -> Result<()> {
+        buf.put_u16(self.type_covered);
+        buf.put_u8(self.algorithm);
+        buf.put_u8(self.labels);
+        buf.put_u32(self.orig_ttl);
+        buf.put_u32(self.expiration);
+        buf.put_u32(self.inception);
+        buf.put_u16(self.key_tag);
+        util::pack_domain_name(&self.signer_name.to_ascii_lowercase(), buf)?;
+        Ok(())
+    }
+}
+
+impl From<RRSIG> for RecourseRecord {
+    fn from(value: RRSIG) -> Self {
+        RecourseRecord::RRSIG(value)
+    }
+}
+
+impl PartialEq for RRSIG {
+    fn eq(&self, other: &Self) -> bool {
+        self.hdr == other.hdr
+            && self.type_covered == other.type_covered
+            && self.algorithm == other.algorithm
+            && self.labels == other.labels
+            && self.orig_ttl == other.orig_ttl
+            && self.expiration == other.expiration
+            && self.inception == other.inception
+            && self.key_tag == other.key_tag
+            && self.signer_name.eq_ignore_ascii_case(&other.signer_name)
+            && self.signature == other.signature
+    }
+}
+
+impl Eq for RRSIG {}
+
+impl Hash for RRSIG {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.hdr.hash(state);
+        self.type_covered.hash(state);
+        self.algorithm.hash(state);
+        self.labels.hash(state);
+        self.orig_ttl.hash(state);
+        self.expiration.hash(state);
+        self.inception.hash(state);
+        self.key_tag.hash(state);
+        util::hash_name(&self.signer_name, state);
+        self.signature.hash(state);
+    }
+}
+
+impl Display for RRSIG {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.hdr, f)?;
+        util::qtype_string(self.type_covered, f)?;
+        write!(
+            f,
+            " {} {} {} {} {} {} {} {}",
+            self.algorithm,
+            self.labels,
+            self.orig_ttl,
+            util::timestamp_string(self.expiration),
+            util::timestamp_string(self.inception),
+            self.key_tag,
+            self.signer_name,
+            base64::engine::general_purpose::STANDARD.encode(&self.signature)
+        )
+    }
+}
+
+impl RR for RRSIG {
+    type Item = RRSIG;
+
+    fn pack(&self, buf: &mut BytesMut) -> Result<()> {
+        let start = buf.len();
+        self.pack_unsigned(buf)?;
+        buf.put_slice(&self.signature);
+        let count = buf.len() - start;
+        util::set_value_offset(buf.as_mut(), start - 2, count as u16);
+        Ok(())
+    }
+
+    fn unpack(h: RecourseRecordHdr, cur: &mut Cursor<&[u8]>) -> Result<Self::Item> {
+        let end = cur.position() as usize + h.rd_length as usize;
+        let type_covered = cur.read_u16::<BigEndian>()?;
+        let algorithm = cur.read_u8()?;
+        let labels = cur.read_u8()?;
+        let orig_ttl = cur.read_u32::<BigEndian>()?;
+        let expiration = cur.read_u32::<BigEndian>()?;
+        let inception = cur.read_u32::<BigEndian>()?;
+        let key_tag = cur.read_u16::<BigEndian>()?;
+        let signer_name = util::unpack_domain_name_cur(cur)?;
+        let mut signature = vec![0u8; end.saturating_sub(cur.position() as usize)];
+        cur.read_exact(&mut signature)?;
+        Ok(Self {
+            hdr: h,
+            type_covered,
+            algorithm,
+            labels,
+            orig_ttl,
+            expiration,
+            inception,
+            key_tag,
+            signer_name,
+            signature,
+        })
+    }
+
+    fn header(&self) -> &RecourseRecordHdr {
+        &self.hdr
+    }
+}
@@ -0,0 +1,144 @@
+use core::fmt;
+use core::fmt::{Display, Formatter};
+use core::hash::{Hash, Hasher};
+use crate::io::{Cursor, ReadBytesExt};
+use byteorder::BigEndian;
+use bytes::{BufMut, BytesMut};
+use crate::{DomainString, util};
+use crate::msg::{RecourseRecordHdr, RR};
+use crate::types::RecourseRecord;
+use crate::Result;
+use crate::types::TYPE_SOA;
+
+/// SOA
+/// RFC 1035.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SOA {
+    pub hdr: RecourseRecordHdr,
+    pub ns: DomainString,
+    pub mbox: DomainString,
+    pub serial: u32,
+    pub refresh: u32,
+    pub retry: u32,
+    pub expire: u32,
+    pub min_ttl: u32,
+}
+
+impl SOA {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        name: DomainString,
+        class: u16,
+        ttl: u32,
+        ns: DomainString,
+        mbox: DomainString,
+        serial: u32,
+        refresh: u32,
+        retry: u32,
+        expire: u32,
+        min_ttl: u32,
+    ) -> Self {
+        Self {
+            hdr: RecourseRecordHdr {
+                name,
+                typ: TYPE_SOA,
+                class,
+                ttl,
+                rd_length: (util::cal_domain_name_len(&ns) + util::cal_domain_name_len(&mbox) + 20) as u16,
+            },
+            ns,
+            mbox,
+            serial,
+            refresh,
+            retry,
+            expire,
+            min_ttl,
+        }
+    }
+}
+
+impl From<SOA> for RecourseRecord {
+    fn from(value: SOA) -> Self {
+        RecourseRecord::SOA(value)
+    }
+}
+
+impl PartialEq for SOA {
+    fn eq(&self, other: &Self) -> bool {
+        self.hdr == other.hdr
+            && self.ns.eq_ignore_ascii_case(&other.ns)
+            && self.mbox.eq_ignore_ascii_case(&other.mbox)
+            && self.serial == other.serial
+            && self.refresh == other.refresh
+            && self.retry == other.retry
+            && self.expire == other.expire
+            && self.min_ttl == other.min_ttl
+    }
+}
+
+impl Eq for SOA {}
+
+impl Hash for SOA {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.hdr.hash(state);
+        util::hash_name(&self.ns, state);
+        util::hash_name(&self.mbox, state);
+        self.serial.hash(state);
+        self.refresh.hash(state);
+        self.retry.hash(state);
+        self.expire.hash(state);
+        self.min_ttl.hash(state);
+    }
+}
+
+impl Display for SOA {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.hdr, f)?;
+        f.write_str(&self.ns)?;
+        f.write_str(" ")?;
+        f.write_str(&self.mbox)?;
+        write!(
+            f,
+            " {} {} {} {} {}",
+            self.serial, self.refresh, self.retry, self.expire, self.min_ttl
+        )
+    }
+}
+
+impl RR for SOA {
+    type Item = SOA;
+
+    fn pack(&self, buf: &mut BytesMut) -> Result<()> {
+        let start = buf.len();
+        util::pack_domain_name(&self.ns, buf)?;
+        util::pack_domain_name(&self.mbox, buf)?;
+        buf.put_u32(self.serial);
+        buf.put_u32(self.refresh);
+        buf.put_u32(self.retry);
+        buf.put_u32(self.expire);
+        buf.put_u32(self.min_ttl);
+        let count = buf.len() - start;
+        util::set_value_offset(buf.as_mut(), start - 2, count as u16);
+        Ok(())
+    }
+
+    fn unpack(h: RecourseRecordHdr, cur: &mut Cursor<&[u8]>) -> Result<Self::Item> {
+        let ns = util::unpack_domain_name_cur(cur)?;
+        let mbox = util::unpack_domain_name_cur(cur)?;
+        Ok(Self {
+            hdr: h,
+            ns,
+            mbox,
+            serial: cur.read_u32::<BigEndian>()?,
+            refresh: cur.read_u32::<BigEndian>()?,
+            retry: cur.read_u32::<BigEndian>()?,
+            expire: cur.read_u32::<BigEndian>()?,
+            min_ttl: cur.read_u32::<BigEndian>()?,
+        })
+    }
+
+    fn header(&self) -> &RecourseRecordHdr {
+        &self.hdr
+    }
+}
@@ -0,0 +1,111 @@
+use core::fmt;
+use core::fmt::{Display, Formatter};
+use core::hash::{Hash, Hasher};
+use crate::io::{Cursor, ReadBytesExt};
+use byteorder::BigEndian;
+use bytes::{BufMut, BytesMut};
+use crate::{DomainString, util};
+use crate::msg::{RecourseRecordHdr, RR};
+use crate::types::RecourseRecord;
+use crate::Result;
+use crate::types::TYPE_SRV;
+
+/// SRV
+/// RFC 2782. The target is packed uncompressed, as the RFC requires, but
+/// compression pointers in it are followed on unpack, as mDNS responders
+/// send them.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SRV {
+    pub hdr: RecourseRecordHdr,
+    pub priority: u16,
+    pub weight: u16,
+    pub port: u16,
+    pub target: DomainString,
+}
+
+impl SRV {
+    pub fn new(name: DomainString, class: u16, ttl: u32, priority: u16, weight: u16, port: u16, target: DomainString) -> Self {
+        Self {
+            hdr: RecourseRecordHdr {
+                name,
+                typ: TYPE_SRV,
+                class,
+                ttl,
+                rd_length: (6 + util::cal_domain_name_len(&target)) as u16,
+            },
+            priority,
+            weight,
+            port,
+            target,
+        }
+    }
+}
+
+impl From<SRV> for RecourseRecord {
+    fn from(value: SRV) -> Self {
+        RecourseRecord::SRV(value)
+    }
+}
+
+impl PartialEq for SRV {
+    fn eq(&self, other: &Self) -> bool {
+        self.hdr == other.hdr
+            && self.priority == other.priority
+            && self.weight == other.weight
+            && self.port == other.port
+            && self.target.eq_ignore_ascii_case(&other.target)
+    }
+}
+
+impl Eq for SRV {}
+
+impl Hash for SRV {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.hdr.hash(state);
+        self.priority.hash(state);
+        self.weight.hash(state);
+        self.port.hash(state);
+        util::hash_name(&self.target, state);
+    }
+}
+
+impl Display for SRV {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.hdr, f)?;
+        write!(f, "{} {} {} {}", self.priority, self.weight, self.port, self.target)
+    }
+}
+
+impl RR for SRV {
+    type Item = SRV;
+
+    fn pack(&self, buf: &mut BytesMut) -> Result<()> {
+        let start = buf.len();
+        buf.put_u16(self.priority);
+        buf.put_u16(self.weight);
+        buf.put_u16(self.port);
+        util::pack_domain_name(&self.target, buf)?;
+        let count = buf.len() - start;
+        util::set_value_offset(buf.as_mut(), start - 2, count as u16);
+        Ok(())
+    }
+
+    fn unpack(h: RecourseRecordHdr, cur: &mut Cursor<&[u8]>) -> Result<Self::Item> {
+        let priority = cur.read_u16::<BigEndian>()?;
+        let weight = cur.read_u16::<BigEndian>()?;
+        let port = cur.read_u16::<BigEndian>()?;
+        let target = util::unpack_domain_name_cur(cur)?;
+        Ok(Self {
+            hdr: h,
+            priority,
+            weight,
+            port,
+            target,
+        })
+    }
+
+    fn header(&self) -> &RecourseRecordHdr {
+        &self.hdr
+    }
+}
@@ -1,43 +1,257 @@
-// use std::fmt::{Display, Formatter};
-// use std::io::Cursor;
-// use bytes::{BytesMut};
-// use crate::msg::{RecourseRecordHdr, RR};
-//
-// pub struct SVCB {
-//     pub hdr: RecourseRecordHdr,
-//     pub priority: u16,
-//     pub target: String,
-//     pub value: Vec<SVCBKeyValue>,
-// }
-//
-// impl Display for SVCB {
-//     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-//         todo!()
-//     }
-// }
-//
-// pub struct HTTPS(SVCB);
-//
-// impl Display for HTTPS {
-//     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-//         self.0.fmt(f)
-//     }
-// }
-//
-// impl RR for HTTPS {
-//     type Item = HTTPS;
-//
-//     fn pack(&self, buf: &mut BytesMut) -> crate::Result<()> {
-//         todo!()
-//     }
-//
-//     fn unpack(h: RecourseRecordHdr, cur: &mut Cursor<&[u8]>) -> crate::Result<Self::Item> {
-//         todo!()
-//     }
-//
-//     fn header(&self) -> &RecourseRecordHdr {
-//         &self.0.hdr
-//     }
-// }
-//
-// pub struct SVCBKeyValue {}
\ No newline at end of file
+use core::fmt;
+use core::fmt::{Display, Formatter};
+use core::hash::{Hash, Hasher};
+use core::net::{Ipv4Addr, Ipv6Addr};
+use crate::io::{Cursor, Read, ReadBytesExt};
+use base64::Engine;
+use byteorder::BigEndian;
+use bytes::{BufMut, BytesMut};
+use crate::prelude::*;
+use crate::{DomainString, Error, util};
+use crate::msg::{RecourseRecordHdr, RR};
+use crate::types::RecourseRecord;
+use crate::Result;
+use crate::types::{TYPE_HTTPS, TYPE_SVCB};
+
+pub const SVC_KEY_MANDATORY: u16 = 0;
+pub const SVC_KEY_ALPN: u16 = 1;
+pub const SVC_KEY_NO_DEFAULT_ALPN: u16 = 2;
+pub const SVC_KEY_PORT: u16 = 3;
+pub const SVC_KEY_IPV4HINT: u16 = 4;
+pub const SVC_KEY_ECH: u16 = 5;
+pub const SVC_KEY_IPV6HINT: u16 = 6;
+
+/// SVCB and HTTPS, which share their format and are told apart by the
+/// header type.
+/// RFC 9460. The target is packed uncompressed, as the RFC requires.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SVCB {
+    pub hdr: RecourseRecordHdr,
+    /// 0 for AliasMode, otherwise ServiceMode.
+    pub priority: u16,
+    pub target: DomainString,
+    /// In ascending key order, as they go on the wire.
+    pub params: Vec<SvcParam>,
+}
+
+/// One SvcParam, its value as on the wire.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SvcParam {
+    pub key: u16,
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::base64_serde"))]
+    pub value: Vec<u8>,
+}
+
+impl SVCB {
+    pub fn new(name: DomainString, class: u16, ttl: u32, priority: u16, target: DomainString, params: Vec<SvcParam>) -> Self {
+        Self {
+            hdr: RecourseRecordHdr {
+                name,
+                typ: TYPE_SVCB,
+                class,
+                ttl,
+                rd_length: (2 + util::cal_domain_name_len(&target) + params.iter().map(|p| 4 + p.value.len()).sum::<usize>()) as u16,
+            },
+            priority,
+            target,
+            params,
+        }
+    }
+
+    /// Like [`new`](Self::new), for an HTTPS record.
+    pub fn new_https(name: DomainString, class: u16, ttl: u32, priority: u16, target: DomainString, params: Vec<SvcParam>) -> Self {
+        let mut rr = Self::new(name, class, ttl, priority, target, params);
+        rr.hdr.typ = TYPE_HTTPS;
+        rr
+    }
+
+    pub fn is_alias(&self) -> bool {
+        self.priority == 0
+    }
+
+    pub fn param(&self, key: u16) -> Option<&[u8]> {
+        self.params.iter().find(|p| p.key == key).map(|p| &p.value[..])
+    }
+}
+
+impl From<SVCB> for RecourseRecord {
+    fn from(value: SVCB) -> Self {
+        RecourseRecord::SVCB(value)
+    }
+}
+
+impl PartialEq for SVCB {
+    fn eq(&self, other: &Self) -> bool {
+        self.hdr == other.hdr
+            && self.priority == other.priority
+            && self.target.eq_ignore_ascii_case(&other.target)
+            && self.params == other.params
+    }
+}
+
+impl Eq for SVCB {}
+
+impl Hash for SVCB {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.hdr.hash(state);
+        self.priority.hash(state);
+        util::hash_name(&self.target, state);
+        self.params.hash(state);
+    }
+}
+
+impl Display for SVCB {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.hdr, f)?;
+        write!(f, "{} {}", self.priority, self.target)?;
+        for param in &self.params {
+            write!(f, " {}", param)?;
+        }
+        Ok(())
+    }
+}
+
+/// The presentation name of a key, `keyNNNNN` for those without one.
+pub fn svc_key_to_string(key: u16) -> String {
+    match key {
+        SVC_KEY_MANDATORY => "mandatory".into(),
+        SVC_KEY_ALPN => "alpn".into(),
+        SVC_KEY_NO_DEFAULT_ALPN => "no-default-alpn".into(),
+        SVC_KEY_PORT => "port".into(),
+        SVC_KEY_IPV4HINT => "ipv4hint".into(),
+        SVC_KEY_ECH => "ech".into(),
+        SVC_KEY_IPV6HINT => "ipv6hint".into(),
+        _ => format!("key{}", key),
+    }
+}
+
+pub fn svc_string_to_key(s: &str) -> Option<u16> {
+    Some(match s {
+        "mandatory" => SVC_KEY_MANDATORY,
+        "alpn" => SVC_KEY_ALPN,
+        "no-default-alpn" => SVC_KEY_NO_DEFAULT_ALPN,
+        "port" => SVC_KEY_PORT,
+        "ipv4hint" => SVC_KEY_IPV4HINT,
+        "ech" => SVC_KEY_ECH,
+        "ipv6hint" => SVC_KEY_IPV6HINT,
+        _ => {
+            let digits = s.strip_prefix("key")?;
+            if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            digits.parse().ok()?
+        }
+    })
+}
+
+impl SvcParam {
+    /// The value in presentation format, without the key; None when it is
+    /// malformed for its key, so it can only be written in the generic
+    /// `keyNNNNN` form.
+    fn value_string(&self) -> Option<String> {
+        let v = &self.value[..];
+        Some(match self.key {
+            SVC_KEY_MANDATORY if !v.is_empty() && v.len().is_multiple_of(2) => v
+                .chunks(2)
+                .map(|c| svc_key_to_string(u16::from_be_bytes([c[0], c[1]])))
+                .collect::<Vec<_>>()
+                .join(","),
+            SVC_KEY_ALPN if !v.is_empty() => {
+                // a comma-separated list (RFC 9460 appendix A.1), each
+                // ID escaped before the whole is quoted
+                let mut list = Vec::new();
+                let mut rest = v;
+                while let Some((&len, tail)) = rest.split_first() {
+                    let id = tail.get(..len as usize).filter(|id| !id.is_empty())?;
+                    let mut escaped = Vec::with_capacity(id.len());
+                    for &b in id {
+                        if b == b',' || b == b'\\' {
+                            escaped.push(b'\\');
+                        }
+                        escaped.push(b);
+                    }
+                    list.push(escaped);
+                    rest = &tail[len as usize..];
+                }
+                util::quote_character_string(&list.join(&b','))
+            }
+            SVC_KEY_NO_DEFAULT_ALPN if v.is_empty() => String::new(),
+            SVC_KEY_PORT if v.len() == 2 => u16::from_be_bytes([v[0], v[1]]).to_string(),
+            SVC_KEY_IPV4HINT if !v.is_empty() && v.len().is_multiple_of(4) => v
+                .chunks(4)
+                .map(|c| Ipv4Addr::from(<[u8; 4]>::try_from(c).unwrap()).to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+            SVC_KEY_ECH if !v.is_empty() => base64::engine::general_purpose::STANDARD.encode(v),
+            SVC_KEY_IPV6HINT if !v.is_empty() && v.len().is_multiple_of(16) => v
+                .chunks(16)
+                .map(|c| Ipv6Addr::from(<[u8; 16]>::try_from(c).unwrap()).to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+            SVC_KEY_MANDATORY..=SVC_KEY_IPV6HINT => return None,
+            _ if v.is_empty() => String::new(),
+            _ => util::quote_character_string(v),
+        })
+    }
+}
+
+impl Display for SvcParam {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.value_string() {
+            Some(value) if value.is_empty() => f.write_str(&svc_key_to_string(self.key)),
+            Some(value) => write!(f, "{}={}", svc_key_to_string(self.key), value),
+            None => write!(f, "key{}={}", self.key, util::quote_character_string(&self.value)),
+        }
+    }
+}
+
+impl RR for SVCB {
+    type Item = SVCB;
+
+    fn pack(&self, buf: &mut BytesMut) -> Result<()> {
+        let start = buf.len();
+        buf.put_u16(self.priority);
+        util::pack_domain_name(&self.target, buf)?;
+        for param in &self.params {
+            let len = u16::try_from(param.value.len()).map_err(|_| Error::new("SvcParam value too long"))?;
+            buf.put_u16(param.key);
+            buf.put_u16(len);
+            buf.put_slice(&param.value);
+        }
+        let count = buf.len() - start;
+        util::set_value_offset(buf.as_mut(), start - 2, count as u16);
+        Ok(())
+    }
+
+    fn unpack(h: RecourseRecordHdr, cur: &mut Cursor<&[u8]>) -> Result<Self::Item> {
+        let end = cur.position() + h.rd_length as u64;
+        let priority = cur.read_u16::<BigEndian>()?;
+        let target = util::unpack_domain_name_cur(cur)?;
+        let mut params: Vec<SvcParam> = Vec::new();
+        while cur.position() < end {
+            let key = cur.read_u16::<BigEndian>()?;
+            let len = cur.read_u16::<BigEndian>()? as u64;
+            if cur.position() + len > end {
+                return Err(Error::InvalidRdLength);
+            }
+            if params.last().is_some_and(|last| last.key >= key) {
+                return Err(Error::new("SvcParam keys out of order"));
+            }
+            let mut value = vec![0u8; len as usize];
+            cur.read_exact(&mut value)?;
+            params.push(SvcParam { key, value });
+        }
+        Ok(Self {
+            hdr: h,
+            priority,
+            target,
+            params,
+        })
+    }
+
+    fn header(&self) -> &RecourseRecordHdr {
+        &self.hdr
+    }
+}
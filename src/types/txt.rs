@@ -0,0 +1,96 @@
+use core::fmt;
+use core::fmt::{Display, Formatter};
+use crate::io::{Cursor, Read, ReadBytesExt};
+use bytes::{BufMut, BytesMut};
+use crate::prelude::*;
+use crate::{DomainString, Error, util};
+use crate::msg::{RecourseRecordHdr, RR};
+use crate::types::RecourseRecord;
+use crate::Result;
+use crate::types::TYPE_TXT;
+
+/// TXT
+/// RFC 1035. One or more character strings, each at most 255 octets.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TXT {
+    pub hdr: RecourseRecordHdr,
+    pub txt: Vec<Vec<u8>>,
+}
+
+impl TXT {
+    pub fn new(name: DomainString, class: u16, ttl: u32, txt: Vec<Vec<u8>>) -> Self {
+        Self {
+            hdr: RecourseRecordHdr {
+                name,
+                typ: TYPE_TXT,
+                class,
+                ttl,
+                rd_length: txt.iter().map(|s| 1 + s.len()).sum::<usize>() as u16,
+            },
+            txt,
+        }
+    }
+
+    /// The character strings joined, as SPF and DKIM read them.
+    pub fn data(&self) -> Vec<u8> {
+        self.txt.concat()
+    }
+}
+
+impl From<TXT> for RecourseRecord {
+    fn from(value: TXT) -> Self {
+        RecourseRecord::TXT(value)
+    }
+}
+
+impl Display for TXT {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.hdr, f)?;
+        for (i, s) in self.txt.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            f.write_str(&util::quote_character_string(s))?;
+        }
+        Ok(())
+    }
+}
+
+impl RR for TXT {
+    type Item = TXT;
+
+    fn pack(&self, buf: &mut BytesMut) -> Result<()> {
+        let start = buf.len();
+        for s in &self.txt {
+            let len = u8::try_from(s.len()).map_err(|_| Error::new("TXT string longer than 255 octets"))?;
+            buf.put_u8(len);
+            buf.put_slice(s);
+        }
+        let count = buf.len() - start;
+        util::set_value_offset(buf.as_mut(), start - 2, count as u16);
+        Ok(())
+    }
+
+    fn unpack(h: RecourseRecordHdr, cur: &mut Cursor<&[u8]>) -> Result<Self::Item> {
+        let end = cur.position() + h.rd_length as u64;
+        let mut txt = Vec::new();
+        while cur.position() < end {
+            let len = cur.read_u8()? as u64;
+            if cur.position() + len > end {
+                return Err(Error::InvalidRdLength);
+            }
+            let mut s = vec![0u8; len as usize];
+            cur.read_exact(&mut s)?;
+            txt.push(s);
+        }
+        Ok(Self {
+            hdr: h,
+            txt,
+        })
+    }
+
+    fn header(&self) -> &RecourseRecordHdr {
+        &self.hdr
+    }
+}
@@ -1,10 +1,15 @@
 #![allow(dead_code)]
 
-use std::fmt::{Formatter};
-use std::{fmt, io};
-use std::io::Cursor;
+use core::fmt::{Formatter};
+use core::fmt;
+use core::cmp::Ordering;
+use alloc::borrow::Cow;
+use alloc::collections::BTreeMap;
 use bytes::{BufMut, BytesMut};
-use crate::DomainString;
+use once_cell::race::OnceBox;
+use crate::io::Cursor;
+use crate::prelude::*;
+use crate::{DomainString, Error, Result};
 use crate::types::*;
 
 const MAX_DOMAIN_NAME_WIRE_OCTETS: usize = 255; // See RFC 1035 section 2.3.4
@@ -13,30 +18,23 @@ const ESCAPED_BYTE_SMALL: &str = r#"\000\001\002\003\004\005\006\007\008\009\010
 
 const ESCAPED_BYTE_LARGE: &str = r#"\127\128\129\130\131\132\133\134\135\136\137\138\139\140\141\142\143\144\145\146\147\148\149\150\151\152\153\154\155\156\157\158\159\160\161\162\163\164\165\166\167\168\169\170\171\172\173\174\175\176\177\178\179\180\181\182\183\184\185\186\187\188\189\190\191\192\193\194\195\196\197\198\199\200\201\202\203\204\205\206\207\208\209\210\211\212\213\214\215\216\217\218\219\220\221\222\223\224\225\226\227\228\229\230\231\232\233\234\235\236\237\238\239\240\241\242\243\244\245\246\247\248\249\250\251\252\253\254\255"#;
 
-const MAX_COMPRESSION_POINTERS: usize = (MAX_DOMAIN_NAME_WIRE_OCTETS + 1) / 2 - 2;
+const MAX_COMPRESSION_POINTERS: usize = MAX_DOMAIN_NAME_WIRE_OCTETS.div_ceil(2) - 2;
 
 #[inline]
-fn error<E>(msg: E) -> io::Error
-    where
-        E: Into<Box<dyn std::error::Error + Send + Sync>>,
-{
-    io::Error::new(io::ErrorKind::Other, msg)
+fn error<S: Into<String>>(msg: S) -> Error {
+    Error::new(msg)
 }
 
 
+/// The name with its labels in ASCII, by UTS #46 with `with_idna`.
 #[cfg(feature = "with_idna")]
-fn label_to_ascii(label: &str) -> Result<String, unic_idna::Errors> {
-    let flags = unic_idna::Flags {
-        use_std3_ascii_rules: false,
-        transitional_processing: false,
-        verify_dns_length: true,
-    };
-    unic_idna::to_ascii(label, flags)
+pub(crate) fn name_to_ascii(name: &str) -> Result<Cow<'_, str>> {
+    crate::idn::name_to_ascii(name)
 }
 
 #[cfg(not(feature = "with_idna"))]
-fn label_to_ascii(label: &str) -> Result<DomainString, ()> {
-    Ok(DomainString::from(label))
+pub(crate) fn name_to_ascii(name: &str) -> Result<Cow<'_, str>> {
+    Ok(Cow::Borrowed(name))
 }
 
 pub fn set_rd(buf: &mut BytesMut, data: &[u8]) {
@@ -49,21 +47,18 @@ pub fn set_rd_length(buf: &mut [u8], v: u16) {
 }
 
 pub fn set_value_offset(buf: &mut [u8], start: usize, v: u16) {
-    (&mut buf[start..start + 2]).copy_from_slice(&v.to_be_bytes()[..])
+    buf[start..start + 2].copy_from_slice(&v.to_be_bytes()[..])
 }
 
 pub fn cal_domain_name_len(input: &str) -> usize {
+    let Ok(ascii) = name_to_ascii(input) else { return 0; };
     let mut size: usize = 0;
-    for label in input.split('.') {
+    for label in ascii.split('.') {
         if label.is_empty() {
             continue;
         }
-        if let Ok(label_idn) = label_to_ascii(label) {
-            if let Ok(_) = u8::try_from(label_idn.len()) {
-                size += 1 + label_idn.as_bytes().len();
-            } else {
-                return 0;
-            }
+        if u8::try_from(label.len()).is_ok() {
+            size += 1 + label.len();
         } else {
             return 0;
         }
@@ -71,25 +66,24 @@ pub fn cal_domain_name_len(input: &str) -> usize {
     size + 1
 }
 
-pub fn pack_domain_name(input: &str, buf: &mut BytesMut) -> io::Result<()> {
-    for label in input.split('.') {
+pub fn pack_domain_name(input: &str, buf: &mut BytesMut) -> Result<()> {
+    let ascii = name_to_ascii(input).map_err(|e| {
+        tracing::warn!("Could not encode name {:?}: {:?}", input, e);
+        e
+    })?;
+    for label in ascii.split('.') {
         if label.is_empty() {
             continue;
         }
 
-        let label_idn = label_to_ascii(label).map_err(|e| {
-            tracing::warn!("Could not encode label {:?}: {:?}", label, e);
-            io::Error::new(io::ErrorKind::Other, label)
-        })?;
-
-        match u8::try_from(label_idn.len()) {
+        match u8::try_from(label.len()) {
             Ok(length) => {
                 buf.put_u8(length);
-                buf.put_slice(label_idn.as_bytes());
+                buf.put_slice(label.as_bytes());
             }
             Err(e) => {
                 tracing::warn!("Could not encode label {:?}: {}", label, e);
-                return Err(io::Error::new(io::ErrorKind::Other, label));
+                return Err(error(label));
             }
         }
     }
@@ -97,7 +91,7 @@ pub fn pack_domain_name(input: &str, buf: &mut BytesMut) -> io::Result<()> {
     Ok(())
 }
 
-pub fn unpack_domain_name_cur(cur: &mut Cursor<&[u8]>) -> io::Result<DomainString> {
+pub fn unpack_domain_name_cur(cur: &mut Cursor<&[u8]>) -> Result<DomainString> {
     let (name, pos) = unpack_domain_name(cur.get_ref(), cur.position() as usize)?;
     cur.set_position(pos as u64);
     Ok(name)
@@ -173,10 +167,10 @@ fn __skip_domain_name(buf: &[u8], mut off: usize) -> Option<usize> {
     if ptr == 0 {
         off1 = off;
     }
-    return Some(off1);
+    Some(off1)
 }
 
-fn unpack_domain_name(buf: &[u8], mut off: usize) -> io::Result<(DomainString, usize)> {
+fn unpack_domain_name(buf: &[u8], mut off: usize) -> Result<(DomainString, usize)> {
     // 12 in 32bit is inner
     let mut s = DomainString::with_capacity(12);
     let mut off1 = 0usize;
@@ -211,7 +205,7 @@ fn unpack_domain_name(buf: &[u8], mut off: usize) -> io::Result<(DomainString, u
                     if is_domain_name_label_special(b) {
                         s.push('\\');
                         s.push(b as char);
-                    } else if b < b' ' || b > b'~' {
+                    } else if !(b' '..=b'~').contains(&b) {
                         escape_byte(b, &mut s);
                     } else {
                         s.push(b as char);
@@ -253,10 +247,10 @@ fn unpack_domain_name(buf: &[u8], mut off: usize) -> io::Result<(DomainString, u
     if ptr == 0 {
         off1 = off;
     }
-    if s.len() == 0 {
+    if s.is_empty() {
         return Ok((DomainString::from("."), off1));
     }
-    return Ok((s, off1));
+    Ok((s, off1))
 }
 
 // escape_byte returns the \DDD escaping of b which must
@@ -278,14 +272,11 @@ fn escape_byte(mut b: u8, buf: &mut DomainString) {
 // a domain name label byte should be prefixed
 // with an escaping backslash.
 fn is_domain_name_label_special(b: u8) -> bool {
-    return match b {
-        b'.' | b' ' | b'\'' | b'@' | b';' | b'(' | b')' | b'"' | b'\\' => true,
-        _ => false,
-    };
+    matches!(b, b'.' | b' ' | b'\'' | b'@' | b';' | b'(' | b')' | b'"' | b'\\')
 }
 
 #[inline]
-fn is_digit(b: char) -> bool { return b >= '0' && b <= '9'; }
+fn is_digit(b: char) -> bool { b.is_ascii_digit() }
 
 #[inline]
 fn ddd_string_to_byte(s: &[u8]) -> u8 {
@@ -315,7 +306,317 @@ fn next_byte(s: &str, offset: usize) -> (u8, usize) {
         }
     }
     // not \ddd, just an RFC 1035 "quoted" character
-    return (ns[offset + 1], 2);
+    (ns[offset + 1], 2)
+}
+
+/// Decodes a presentation-format `<character-string>`, quotes already
+/// removed: `\X` and `\DDD` escapes become their octets. None past 255
+/// octets or on a dangling escape.
+pub fn parse_character_string(s: &str) -> Option<Vec<u8>> {
+    let mut ret = Vec::with_capacity(s.len());
+    let mut off = 0;
+    while off < s.len() {
+        let (b, n) = next_byte(s, off);
+        if n == 0 {
+            return None;
+        }
+        ret.push(b);
+        off += n;
+    }
+    (ret.len() <= 255).then_some(ret)
+}
+
+/// A `<character-string>` in double quotes, with `"` and `\` escaped and
+/// octets outside printable ASCII as `\DDD`.
+pub fn quote_character_string(data: &[u8]) -> String {
+    let mut s = String::with_capacity(data.len() + 2);
+    s.push('"');
+    for &b in data {
+        match b {
+            b'"' | b'\\' => {
+                s.push('\\');
+                s.push(b as char);
+            }
+            b' '..=b'~' => s.push(b as char),
+            _ => s.push_str(&format!("\\{:03}", b)),
+        }
+    }
+    s.push('"');
+    s
+}
+
+/// Splits a presentation-format name into its lowercased wire labels
+/// (escapes decoded, root label omitted).
+pub fn name_labels(name: &str) -> Vec<Vec<u8>> {
+    let mut labels = Vec::new();
+    let mut label = Vec::new();
+    let mut off = 0;
+    while off < name.len() {
+        if name.as_bytes()[off] == b'.' {
+            if !label.is_empty() {
+                labels.push(core::mem::take(&mut label));
+            }
+            off += 1;
+            continue;
+        }
+        let (b, n) = next_byte(name, off);
+        if n == 0 {
+            break;
+        }
+        label.push(b.to_ascii_lowercase());
+        off += n;
+    }
+    if !label.is_empty() {
+        labels.push(label);
+    }
+    labels
+}
+
+/// Canonical DNS name order, see RFC 4034 section 6.1.
+pub fn compare_names(a: &str, b: &str) -> Ordering {
+    name_labels(a).iter().rev().cmp(name_labels(b).iter().rev())
+}
+
+/// Byte fields as base64 strings, for `#[serde(with)]`.
+#[cfg(feature = "serde")]
+pub(crate) mod base64_serde {
+    use base64::Engine;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use crate::prelude::*;
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> core::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&base64::engine::general_purpose::STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> core::result::Result<Vec<u8>, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        base64::engine::general_purpose::STANDARD.decode(text).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Hashes `name` so that names equal ignoring ASCII case hash alike.
+pub fn hash_name<H: core::hash::Hasher>(name: &str, state: &mut H) {
+    for b in name.bytes() {
+        state.write_u8(b.to_ascii_lowercase());
+    }
+    state.write_u8(0xff);
+}
+
+/// Case-insensitive name equality.
+pub fn equal_names(a: &str, b: &str) -> bool {
+    a.eq_ignore_ascii_case(b) || name_labels(a) == name_labels(b)
+}
+
+/// Returns true if `child` is `parent` or below it.
+pub fn is_subdomain(child: &str, parent: &str) -> bool {
+    let child = name_labels(child);
+    let parent = name_labels(parent);
+    child.len() >= parent.len() && child[child.len() - parent.len()..] == parent[..]
+}
+
+const BASE32HEX: &[u8; 32] = b"0123456789abcdefghijklmnopqrstuv";
+
+/// Unpadded lowercase base32hex (RFC 4648), as used by NSEC3 owner names.
+pub fn base32hex_encode(data: &[u8]) -> String {
+    let mut s = String::with_capacity((data.len() * 8).div_ceil(5));
+    let (mut acc, mut bits) = (0u32, 0);
+    for b in data {
+        acc = acc << 8 | *b as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            s.push(BASE32HEX[(acc >> bits) as usize & 0x1F] as char);
+        }
+    }
+    if bits > 0 {
+        s.push(BASE32HEX[(acc << (5 - bits)) as usize & 0x1F] as char);
+    }
+    s
+}
+
+pub fn base32hex_decode(s: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(s.len() * 5 / 8);
+    let (mut acc, mut bits) = (0u32, 0);
+    for c in s.trim_end_matches('=').bytes() {
+        let v = match c.to_ascii_lowercase() {
+            c @ b'0'..=b'9' => c - b'0',
+            c @ b'a'..=b'v' => c - b'a' + 10,
+            _ => return None,
+        };
+        acc = (acc << 5 | v as u32) & 0xFFF;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+const PUNYCODE_PREFIX: &str = "xn--";
+
+// RFC 3492 section 5
+const PUNY_BASE: u32 = 36;
+const PUNY_TMIN: u32 = 1;
+const PUNY_TMAX: u32 = 26;
+const PUNY_SKEW: u32 = 38;
+const PUNY_DAMP: u32 = 700;
+const PUNY_INITIAL_BIAS: u32 = 72;
+const PUNY_INITIAL_N: u32 = 0x80;
+
+fn puny_adapt(mut delta: u32, points: u32, first: bool) -> u32 {
+    delta /= if first { PUNY_DAMP } else { 2 };
+    delta += delta / points;
+    let mut k = 0;
+    while delta > (PUNY_BASE - PUNY_TMIN) * PUNY_TMAX / 2 {
+        delta /= PUNY_BASE - PUNY_TMIN;
+        k += PUNY_BASE;
+    }
+    k + (PUNY_BASE - PUNY_TMIN + 1) * delta / (delta + PUNY_SKEW)
+}
+
+fn puny_threshold(k: u32, bias: u32) -> u32 {
+    k.saturating_sub(bias).clamp(PUNY_TMIN, PUNY_TMAX)
+}
+
+/// A-label of `label` (`xn--` and RFC 3492 Punycode), or the label itself
+/// if it is ASCII. No IDNA mapping or validation is done; `None` on overflow.
+pub fn punycode_encode(label: &str) -> Option<String> {
+    if label.is_ascii() {
+        return Some(label.into());
+    }
+    let input: Vec<u32> = label.chars().map(|c| c as u32).collect();
+    let mut out = String::from(PUNYCODE_PREFIX);
+    out.extend(label.chars().filter(char::is_ascii));
+    let basic = input.iter().filter(|c| **c < 0x80).count() as u32;
+    if basic > 0 {
+        out.push('-');
+    }
+    let (mut n, mut delta, mut bias, mut h) = (PUNY_INITIAL_N, 0u32, PUNY_INITIAL_BIAS, basic);
+    while (h as usize) < input.len() {
+        let m = *input.iter().filter(|c| **c >= n).min()?;
+        delta = delta.checked_add((m - n).checked_mul(h + 1)?)?;
+        n = m;
+        for c in &input {
+            if *c < n {
+                delta = delta.checked_add(1)?;
+            }
+            if *c == n {
+                let mut q = delta;
+                let mut k = PUNY_BASE;
+                loop {
+                    let t = puny_threshold(k, bias);
+                    if q < t {
+                        break;
+                    }
+                    out.push(puny_digit(t + (q - t) % (PUNY_BASE - t)));
+                    q = (q - t) / (PUNY_BASE - t);
+                    k += PUNY_BASE;
+                }
+                out.push(puny_digit(q));
+                bias = puny_adapt(delta, h + 1, h == basic);
+                delta = 0;
+                h += 1;
+            }
+        }
+        delta = delta.checked_add(1)?;
+        n += 1;
+    }
+    Some(out)
+}
+
+/// U-label of an `xn--` label, or the label itself without the prefix.
+/// `None` if the Punycode is malformed.
+pub fn punycode_decode(label: &str) -> Option<String> {
+    let Some(encoded) = label.get(..4)
+        .filter(|prefix| prefix.eq_ignore_ascii_case(PUNYCODE_PREFIX))
+        .map(|_| &label[4..]) else {
+        return Some(label.into());
+    };
+    let (basic, extended) = match encoded.rfind('-') {
+        Some(i) => (&encoded[..i], &encoded[i + 1..]),
+        None => ("", encoded),
+    };
+    if !basic.is_ascii() {
+        return None;
+    }
+    let mut out: Vec<char> = basic.chars().collect();
+    let (mut n, mut i, mut bias) = (PUNY_INITIAL_N, 0u32, PUNY_INITIAL_BIAS);
+    let mut digits = extended.bytes();
+    while digits.len() > 0 {
+        let old = i;
+        let mut w = 1u32;
+        let mut k = PUNY_BASE;
+        loop {
+            let digit = match digits.next()? {
+                c @ b'a'..=b'z' => c - b'a',
+                c @ b'A'..=b'Z' => c - b'A',
+                c @ b'0'..=b'9' => c - b'0' + 26,
+                _ => return None,
+            } as u32;
+            i = i.checked_add(digit.checked_mul(w)?)?;
+            let t = puny_threshold(k, bias);
+            if digit < t {
+                break;
+            }
+            w = w.checked_mul(PUNY_BASE - t)?;
+            k += PUNY_BASE;
+        }
+        let points = out.len() as u32 + 1;
+        bias = puny_adapt(i - old, points, old == 0);
+        n = n.checked_add(i / points)?;
+        i %= points;
+        out.insert(i as usize, char::from_u32(n)?);
+        i += 1;
+    }
+    Some(out.into_iter().collect())
+}
+
+fn puny_digit(d: u32) -> char {
+    (if d < 26 { b'a' + d as u8 } else { b'0' + d as u8 - 26 }) as char
+}
+
+/// RRSIG timestamp in `YYYYMMDDHHmmSS` form (RFC 4034 section 3.2).
+pub fn timestamp_string(secs: u32) -> String {
+    let days = (secs / 86400) as i64;
+    let rem = secs % 86400;
+    // civil_from_days, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}{:02}{:02}{:02}{:02}{:02}", year, month, day, rem / 3600, rem / 60 % 60, rem % 60)
+}
+
+/// Reads an RRSIG timestamp, either `YYYYMMDDHHmmSS` or seconds since the
+/// epoch. Dates past 2106 wrap around, as serial number arithmetic expects.
+pub fn parse_timestamp(s: &str) -> Option<u32> {
+    if !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    if s.len() != 14 {
+        return s.parse().ok();
+    }
+    let field = |range: core::ops::Range<usize>| s[range].parse::<i64>().ok();
+    let (year, month, day) = (field(0..4)?, field(4..6)?, field(6..8)?);
+    let (hour, min, sec) = (field(8..10)?, field(10..12)?, field(12..14)?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || min > 59 || sec > 59 {
+        return None;
+    }
+    // days_from_civil, the inverse of the above
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+    Some((days * 86400 + hour * 3600 + min * 60 + sec) as u32)
 }
 
 pub fn name_string(s: &str, f: &mut Formatter<'_>) -> fmt::Result {
@@ -397,7 +698,15 @@ pub fn opcode_string(code: u16) -> &'static str {
 }
 
 pub fn qtype_string(code: u16, f: &mut Formatter<'_>) -> fmt::Result {
-    let s = match code {
+    match type_to_string(code) {
+        Some(s) => f.write_str(s),
+        None => f.write_fmt(format_args!("TYPE{}", code)),
+    }
+}
+
+/// Mnemonic of a known RR type, e.g. `Some("AAAA")` for 28.
+pub fn type_to_string(code: u16) -> Option<&'static str> {
+    Some(match code {
         TYPE_A => "A",
         TYPE_AAAA => "AAAA",
         TYPE_AFSDB => "AFSDB",
@@ -482,22 +791,59 @@ pub fn qtype_string(code: u16, f: &mut Formatter<'_>) -> fmt::Result {
         TYPE_X25 => "X25",
         TYPE_ZONEMD => "ZONEMD",
         TYPE_NSAPPTR => "NSAP-PTR",
-        _ => return f.write_fmt(format_args!("TYPE{}", code)),
-    };
-    f.write_str(s)
+        _ => return None,
+    })
+}
+
+/// Inverse of [`qtype_string`]: accepts mnemonics (case-insensitive) and the
+/// RFC 3597 `TYPEnnn` form.
+pub fn string_to_type(s: &str) -> Option<u16> {
+    static TYPES: OnceBox<BTreeMap<&'static str, u16>> = OnceBox::new();
+    let types = TYPES.get_or_init(|| {
+        Box::new((0..=u16::MAX)
+            .filter_map(|code| type_to_string(code).map(|s| (s, code)))
+            .collect())
+    });
+    let upper = s.to_ascii_uppercase();
+    if let Some(code) = types.get(upper.as_str()) {
+        return Some(*code);
+    }
+    upper.strip_prefix("TYPE")?.parse().ok()
 }
 
 pub fn qclass_string(code: u16, f: &mut Formatter<'_>) -> fmt::Result {
-    let s = match code {
+    match class_to_string(code) {
+        Some(s) => f.write_str(s),
+        None => f.write_fmt(format_args!("CLASS{}", code)),
+    }
+}
+
+/// Mnemonic of a known class, e.g. `Some("IN")` for 1.
+pub fn class_to_string(code: u16) -> Option<&'static str> {
+    Some(match code {
         CLASS_INET => "IN",
         CLASS_CSNET => "CS",
         CLASS_CHAOS => "CH",
         CLASS_HESIOD => "HS",
         CLASS_NONE => "NONE",
         CLASS_ANY => "ANY",
-        _ => return f.write_fmt(format_args!("CLASS{}", code)),
-    };
-    f.write_str(s)
+        _ => return None,
+    })
+}
+
+/// Inverse of [`qclass_string`]: accepts mnemonics (case-insensitive) and the
+/// RFC 3597 `CLASSnnn` form.
+pub fn string_to_class(s: &str) -> Option<u16> {
+    let upper = s.to_ascii_uppercase();
+    Some(match upper.as_str() {
+        "IN" => CLASS_INET,
+        "CS" => CLASS_CSNET,
+        "CH" => CLASS_CHAOS,
+        "HS" => CLASS_HESIOD,
+        "NONE" => CLASS_NONE,
+        "ANY" => CLASS_ANY,
+        _ => return upper.strip_prefix("CLASS")?.parse().ok(),
+    })
 }
 
 
@@ -0,0 +1,89 @@
+//! BIND `$GENERATE` range and template expansion.
+
+/// Upper bound on records a single `$GENERATE` may produce.
+const MAX_GENERATE: usize = 65536;
+
+/// Parses `start-stop[/step]`.
+pub(crate) fn range(s: &str) -> Result<Vec<u32>, String> {
+    let bad = || format!("bad $GENERATE range {:?}", s);
+    let (span, step) = match s.split_once('/') {
+        Some((span, step)) => (span, step.parse::<u32>().map_err(|_| bad())?),
+        None => (s, 1),
+    };
+    let (start, stop) = span.split_once('-').ok_or_else(bad)?;
+    let start: u32 = start.parse().map_err(|_| bad())?;
+    let stop: u32 = stop.parse().map_err(|_| bad())?;
+    if step == 0 || start > stop {
+        return Err(bad());
+    }
+    if ((stop - start) / step) as usize >= MAX_GENERATE {
+        return Err(format!("$GENERATE range {:?} is too large", s));
+    }
+    Ok((start..=stop).step_by(step as usize).collect())
+}
+
+/// Substitutes `$` and `${offset,width,base}` in a template field.
+pub(crate) fn expand(template: &str, i: u32) -> Result<String, String> {
+    let mut out = String::with_capacity(template.len());
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('$') => out.push('$'),
+                Some(c) => {
+                    out.push('\\');
+                    out.push(c);
+                }
+                None => out.push('\\'),
+            },
+            '$' if chars.peek() == Some(&'{') => {
+                chars.next();
+                let mut spec = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => spec.push(c),
+                        None => return Err(format!("unterminated modifier in {:?}", template)),
+                    }
+                }
+                out.push_str(&modifier(&spec, i).map_err(|_| format!("bad modifier {{{}}} in {:?}", spec, template))?);
+            }
+            '$' => out.push_str(&i.to_string()),
+            c => out.push(c),
+        }
+    }
+    Ok(out)
+}
+
+fn modifier(spec: &str, i: u32) -> Result<String, ()> {
+    let mut parts = spec.split(',');
+    let offset: i64 = parts.next().filter(|s| !s.is_empty()).map(str::parse).transpose().map_err(|_| ())?.unwrap_or(0);
+    let width: usize = parts.next().map(str::parse).transpose().map_err(|_| ())?.unwrap_or(0);
+    let base = parts.next().unwrap_or("d");
+    if parts.next().is_some() {
+        return Err(());
+    }
+    let v = i as i64 + offset;
+    if v < 0 {
+        return Err(());
+    }
+    Ok(match base {
+        "d" => format!("{:0width$}", v, width = width),
+        "o" => format!("{:0width$o}", v, width = width),
+        "x" => format!("{:0width$x}", v, width = width),
+        "X" => format!("{:0width$X}", v, width = width),
+        "n" | "N" => {
+            let mut digits = if base == "n" { format!("{:x}", v) } else { format!("{:X}", v) };
+            let min = width.div_ceil(2);
+            while digits.len() < min {
+                digits.insert(0, '0');
+            }
+            let mut nibbles = digits.chars().rev().map(String::from).collect::<Vec<_>>().join(".");
+            if width > 0 && nibbles.len() > width {
+                nibbles.truncate(width);
+            }
+            nibbles
+        }
+        _ => return Err(()),
+    })
+}
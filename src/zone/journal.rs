@@ -0,0 +1,182 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use byteorder::{BigEndian, ReadBytesExt};
+use bytes::{BufMut, BytesMut};
+use log::*;
+use crate::msg::{RecourseRecordHdr, RR};
+use crate::types::{self, RecourseRecord};
+use crate::{Error, Result};
+use super::serial::serial_gt;
+use super::Zone;
+
+const MAGIC: &[u8; 8] = b"DNSJNL1\n";
+
+/// One zone change from serial `from` to serial `to`, in IXFR terms: the old
+/// SOA is among `removed` and the new one among `added`.
+#[derive(Debug, Clone)]
+pub struct Delta {
+    pub from: u32,
+    pub to: u32,
+    pub removed: Vec<RecourseRecord>,
+    pub added: Vec<RecourseRecord>,
+}
+
+impl Delta {
+    fn pack(&self, buf: &mut BytesMut) -> Result<()> {
+        buf.put_u32(self.from);
+        buf.put_u32(self.to);
+        for rrs in [&self.removed, &self.added] {
+            buf.put_u32(rrs.len() as u32);
+            for rr in rrs {
+                rr.header().pack(buf)?;
+                rr.pack(buf)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn unpack(data: &[u8]) -> Result<Self> {
+        let mut cur = Cursor::new(data);
+        let from = cur.read_u32::<BigEndian>()?;
+        let to = cur.read_u32::<BigEndian>()?;
+        let mut sections = [Vec::new(), Vec::new()];
+        for rrs in sections.iter_mut() {
+            for _ in 0..cur.read_u32::<BigEndian>()? {
+                let hdr = RecourseRecordHdr::unpack(&mut cur)?;
+                let end = cur.position() + hdr.rd_length as u64;
+                rrs.push(RecourseRecord::unpack(hdr, &mut cur)?);
+                cur.set_position(end);
+            }
+        }
+        let [removed, added] = sections;
+        Ok(Self { from, to, removed, added })
+    }
+}
+
+/// Append-only log of [`Delta`]s kept next to a zone, similar to BIND's `.jnl`
+/// files. Replaying it over the master file restores dynamic changes after a
+/// restart, and [`Journal::since`] provides the history IXFR needs.
+///
+/// Each entry is length-prefixed; a torn entry at the end, left by a crash
+/// during [`Journal::append`], is ignored when reading.
+#[derive(Debug)]
+pub struct Journal {
+    path: PathBuf,
+    file: File,
+}
+
+impl Journal {
+    /// Opens the journal, creating it if it does not exist.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = OpenOptions::new().read(true).append(true).create(true).open(&path)?;
+        if file.metadata()?.len() == 0 {
+            file.write_all(MAGIC)?;
+            file.sync_data()?;
+        } else {
+            let mut magic = [0u8; 8];
+            file.read_exact(&mut magic)?;
+            if &magic != MAGIC {
+                return Err(Error::new(format!("{:?}: not a zone journal", path)));
+            }
+        }
+        Ok(Self { path, file })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Durably appends a delta.
+    pub fn append(&mut self, delta: &Delta) -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.put_u32(0);
+        delta.pack(&mut buf)?;
+        let len = (buf.len() - 4) as u32;
+        buf[..4].copy_from_slice(&len.to_be_bytes());
+        self.file.write_all(&buf)?;
+        self.file.sync_data()?;
+        Ok(())
+    }
+
+    /// All complete deltas, oldest first.
+    pub fn deltas(&self) -> Result<Vec<Delta>> {
+        let data = fs::read(&self.path)?;
+        let mut cur = Cursor::new(&data[MAGIC.len().min(data.len())..]);
+        let mut ret = Vec::new();
+        while let Ok(len) = cur.read_u32::<BigEndian>() {
+            let start = cur.position() as usize;
+            let Some(entry) = cur.get_ref().get(start..start + len as usize) else {
+                warn!("{:?}: ignoring truncated journal entry", self.path);
+                break;
+            };
+            ret.push(Delta::unpack(entry)?);
+            cur.set_position((start + len as usize) as u64);
+        }
+        Ok(ret)
+    }
+
+    /// The chain of deltas leading from `serial` to the newest serial, or
+    /// `None` if the history does not reach back that far.
+    pub fn since(&self, serial: u32) -> Result<Option<Vec<Delta>>> {
+        let deltas = self.deltas()?;
+        let Some(first) = deltas.iter().position(|d| d.from == serial) else {
+            let newest = deltas.last().map(|d| d.to).unwrap_or(serial);
+            return Ok((newest == serial).then(Vec::new));
+        };
+        let mut ret: Vec<Delta> = Vec::new();
+        for delta in deltas.into_iter().skip(first) {
+            if let Some(last) = ret.last() {
+                if delta.from != last.to {
+                    return Err(Error::new(format!("{:?}: serial gap at {}", self.path, last.to)));
+                }
+            }
+            ret.push(delta);
+        }
+        Ok(Some(ret))
+    }
+
+    /// Applies every delta newer than the zone's serial and returns how many were applied.
+    pub fn replay(&self, zone: &mut Zone) -> Result<usize> {
+        let serial = zone.soa().ok_or_else(|| Error::new("zone has no SOA record"))?.serial;
+        let mut count = 0;
+        for delta in self.deltas()? {
+            let current = zone.soa().map(|soa| soa.serial).unwrap_or(serial);
+            if !serial_gt(delta.to, current) {
+                continue;
+            }
+            zone.apply(&delta)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+impl Zone {
+    /// Applies a delta; the zone must be at `delta.from`.
+    ///
+    /// Removed records are matched on owner, type, class and rdata; ones not
+    /// present are skipped, as are added records already present.
+    pub fn apply(&mut self, delta: &Delta) -> Result<()> {
+        let serial = self.soa().map(|soa| soa.serial);
+        if serial != Some(delta.from) {
+            return Err(Error::new(format!(
+                "delta from serial {} does not apply to serial {:?}",
+                delta.from, serial
+            )));
+        }
+        for rr in &delta.removed {
+            self.remove_record(rr);
+        }
+        for rr in &delta.added {
+            if rr.header().typ == types::TYPE_SOA {
+                self.remove(&rr.header().name, types::TYPE_SOA);
+            } else if self.contains(rr) {
+                continue;
+            }
+            self.insert(rr.clone());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,127 @@
+//! Tokenizer for RFC 1035 master files.
+
+use crate::{Error, Result};
+
+#[derive(Debug, Clone)]
+pub(crate) struct Token {
+    /// Token text with escapes left in place and quotes removed.
+    pub text: String,
+    pub quoted: bool,
+}
+
+/// One logical line: parentheses may span several physical lines.
+#[derive(Debug, Clone)]
+pub(crate) struct Entry {
+    pub line: usize,
+    /// The line started with whitespace, so the owner is inherited.
+    pub blank_owner: bool,
+    pub tokens: Vec<Token>,
+}
+
+pub(crate) struct Lexer {
+    data: Vec<u8>,
+    pos: usize,
+    line: usize,
+}
+
+impl Lexer {
+    pub fn new(input: &str) -> Self {
+        Self {
+            data: input.as_bytes().to_vec(),
+            pos: 0,
+            line: 1,
+        }
+    }
+
+    pub fn next_entry(&mut self) -> Result<Option<Entry>> {
+        let mut entry = Entry {
+            line: self.line,
+            blank_owner: self.starts_blank(),
+            tokens: Vec::new(),
+        };
+        let mut depth = 0usize;
+
+        while self.pos < self.data.len() {
+            match self.data[self.pos] {
+                b' ' | b'\t' | b'\r' => self.pos += 1,
+                b'\n' => {
+                    self.pos += 1;
+                    self.line += 1;
+                    if depth == 0 {
+                        if !entry.tokens.is_empty() {
+                            return Ok(Some(entry));
+                        }
+                        entry.line = self.line;
+                        entry.blank_owner = self.starts_blank();
+                    }
+                }
+                b';' => {
+                    while self.pos < self.data.len() && self.data[self.pos] != b'\n' {
+                        self.pos += 1;
+                    }
+                }
+                b'(' => {
+                    depth += 1;
+                    self.pos += 1;
+                }
+                b')' => {
+                    if depth == 0 {
+                        return Err(Error::Syntax(self.line, "unbalanced ')'".into()));
+                    }
+                    depth -= 1;
+                    self.pos += 1;
+                }
+                b'"' => {
+                    self.pos += 1;
+                    let text = self.read_quoted()?;
+                    entry.tokens.push(Token { text, quoted: true });
+                }
+                _ => {
+                    let text = self.read_word();
+                    entry.tokens.push(Token { text, quoted: false });
+                }
+            }
+        }
+
+        if depth > 0 {
+            return Err(Error::Syntax(entry.line, "unbalanced '('".into()));
+        }
+        if entry.tokens.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(entry))
+    }
+
+    fn starts_blank(&self) -> bool {
+        matches!(self.data.get(self.pos), Some(b' ') | Some(b'\t'))
+    }
+
+    fn read_word(&mut self) -> String {
+        let start = self.pos;
+        while self.pos < self.data.len() {
+            match self.data[self.pos] {
+                b' ' | b'\t' | b'\r' | b'\n' | b';' | b'(' | b')' | b'"' => break,
+                b'\\' => self.pos = (self.pos + 2).min(self.data.len()),
+                _ => self.pos += 1,
+            }
+        }
+        String::from_utf8_lossy(&self.data[start..self.pos]).into_owned()
+    }
+
+    fn read_quoted(&mut self) -> Result<String> {
+        let start = self.pos;
+        while self.pos < self.data.len() {
+            match self.data[self.pos] {
+                b'"' => {
+                    let text = String::from_utf8_lossy(&self.data[start..self.pos]).into_owned();
+                    self.pos += 1;
+                    return Ok(text);
+                }
+                b'\n' => break,
+                b'\\' => self.pos = (self.pos + 2).min(self.data.len()),
+                _ => self.pos += 1,
+            }
+        }
+        Err(Error::Syntax(self.line, "unterminated quoted string".into()))
+    }
+}
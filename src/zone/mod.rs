@@ -0,0 +1,708 @@
+//! RFC 1035 master file ("zone file") support.
+
+mod generate;
+mod journal;
+mod lexer;
+mod nsec;
+mod parser;
+mod serial;
+mod tree;
+mod watch;
+mod writer;
+
+pub use journal::{Delta, Journal};
+pub use nsec::Nsec3Params;
+pub use parser::Parser;
+pub use serial::{serial_gt, SerialPolicy};
+pub use tree::{LookupResult, Problem, Zone};
+pub use watch::{ReloadStats, Watcher};
+pub use writer::{write, Writer};
+
+use std::str::FromStr;
+use crate::types::RecourseRecord;
+use crate::{Error, Result};
+
+/// Parses a whole master file, resolving relative names against `origin`.
+pub fn parse(input: &str, origin: &str) -> Result<Vec<RecourseRecord>> {
+    Parser::new(input, origin).collect()
+}
+
+/// One record in master-file syntax, e.g. `www.example.com. 300 IN A
+/// 192.0.2.1`, with names relative to the root. The TTL may be left out,
+/// and is then 0, as may the class, IN. Types without a text form here
+/// take the RFC 3597 `\\#` syntax.
+impl FromStr for RecourseRecord {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parser = Parser::new(s, ".");
+        parser.set_default_ttl(0);
+        let rr = parser.next_record()?.ok_or_else(|| Error::new("no record"))?;
+        if parser.next_record()?.is_some() {
+            return Err(Error::new("more than one record"));
+        }
+        Ok(rr)
+    }
+}
+
+/// Parses a TTL in seconds or with BIND units, e.g. `3600`, `1h30m`, `2W`.
+pub fn parse_ttl(s: &str) -> Option<u32> {
+    if s.is_empty() {
+        return None;
+    }
+    if s.bytes().all(|b| b.is_ascii_digit()) {
+        return s.parse().ok();
+    }
+    let mut total: u32 = 0;
+    let mut num: Option<u32> = None;
+    for c in s.chars() {
+        if let Some(d) = c.to_digit(10) {
+            num = Some(num.unwrap_or(0).checked_mul(10)?.checked_add(d)?);
+            continue;
+        }
+        let unit = match c.to_ascii_lowercase() {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            'w' => 604800,
+            _ => return None,
+        };
+        total = total.checked_add(num.take()?.checked_mul(unit)?)?;
+    }
+    if num.is_some() {
+        return None;
+    }
+    Some(total)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::msg::RR;
+    use crate::types::{self, RecourseRecord};
+    use super::{parse, Delta, Journal, parse_ttl, serial, serial_gt, LookupResult, Nsec3Params, Parser, Problem, SerialPolicy, Watcher, Writer, Zone};
+
+    #[test]
+    fn test_parse_ttl() {
+        assert_eq!(parse_ttl("3600"), Some(3600));
+        assert_eq!(parse_ttl("1h30m"), Some(5400));
+        assert_eq!(parse_ttl("1W"), Some(604800));
+        assert_eq!(parse_ttl("1h30"), None);
+        assert_eq!(parse_ttl("h"), None);
+    }
+
+    #[test]
+    fn test_parse_zone() {
+        let text = r#"
+@   1h  IN  SOA ns1 hostmaster.example.com. (
+            2023031401 ; serial
+            2h 30m 2w 1d )
+        IN  NS  ns1
+        IN  NS  ns2.example.net.
+ns1 300 IN  A   192.0.2.1
+    AAAA    2001:db8::1
+www CNAME   ns1
+raw 60 CLASS1 TYPE1 \# 4 c0000202
+"#;
+        let records = parse(text, "example.com").unwrap();
+        assert_eq!(records.len(), 7);
+        match &records[0] {
+            RecourseRecord::SOA(soa) => {
+                assert_eq!(soa.hdr.name.as_str(), "example.com.");
+                assert_eq!(soa.hdr.ttl, 3600);
+                assert_eq!(soa.ns.as_str(), "ns1.example.com.");
+                assert_eq!(soa.serial, 2023031401);
+                assert_eq!(soa.expire, 1209600);
+            }
+            rr => panic!("unexpected {}", rr),
+        }
+        match &records[2] {
+            RecourseRecord::NS(ns) => {
+                assert_eq!(ns.hdr.name.as_str(), "example.com.");
+                assert_eq!(ns.ns.as_str(), "ns2.example.net.");
+            }
+            rr => panic!("unexpected {}", rr),
+        }
+        match &records[4] {
+            RecourseRecord::AAAA(aaaa) => {
+                assert_eq!(aaaa.hdr.name.as_str(), "ns1.example.com.");
+                assert_eq!(aaaa.hdr.ttl, 300);
+            }
+            rr => panic!("unexpected {}", rr),
+        }
+        match &records[6] {
+            RecourseRecord::A(a) => assert_eq!(a.a.to_string(), "192.0.2.2"),
+            rr => panic!("unexpected {}", rr),
+        }
+    }
+
+    #[test]
+    fn test_common_types() {
+        let text = "$TTL 3600\n\
+            @ MX 10 mail\n\
+            @ MX 20 mail.example.net.\n\
+            @ TXT \"v=spf1 -all\"\n\
+            txt TXT \"a \\\"b\\\"\" c \"\"\n\
+            _sip._udp SRV 10 60 5060 sip\n\
+            1.2.0.192.in-addr.arpa. PTR www\n\
+            @ CAA 128 issue \"ca.example.net; account=1\"\n\
+            @ HTTPS 1 . alpn=\"h2,h3\" port=8443 ipv4hint=192.0.2.1,192.0.2.2 mandatory=port\n\
+            _dns SVCB 0 dns.example.net.\n";
+        let records = parse(text, "example.com").unwrap();
+        let RecourseRecord::MX(mx) = &records[0] else { panic!("unexpected {}", records[0]) };
+        assert_eq!((mx.preference, mx.mx.as_str()), (10, "mail.example.com."));
+        let RecourseRecord::TXT(txt) = &records[3] else { panic!("unexpected {}", records[3]) };
+        assert_eq!(txt.txt, [&b"a \"b\""[..], b"c", b""]);
+        let RecourseRecord::SRV(srv) = &records[4] else { panic!("unexpected {}", records[4]) };
+        assert_eq!((srv.priority, srv.weight, srv.port, srv.target.as_str()), (10, 60, 5060, "sip.example.com."));
+        assert!(records[5].to_string().ends_with("PTR\twww.example.com."));
+        let RecourseRecord::CAA(caa) = &records[6] else { panic!("unexpected {}", records[6]) };
+        assert!(caa.is_critical() && caa.tag == "issue");
+        let RecourseRecord::SVCB(https) = &records[7] else { panic!("unexpected {}", records[7]) };
+        assert_eq!(https.hdr.typ, types::TYPE_HTTPS);
+        assert_eq!(https.param(types::svcb::SVC_KEY_ALPN), Some(&b"\x02h2\x02h3"[..]));
+        assert!(records[7].to_string().ends_with("HTTPS\t1 . mandatory=port alpn=\"h2,h3\" port=8443 ipv4hint=192.0.2.1,192.0.2.2"), "{}", records[7]);
+        assert!(records[8].to_string().ends_with("SVCB\t0 dns.example.net."));
+
+        // through the wire and the writer and back
+        let mut msg = crate::Msg::new();
+        msg.answer = records.clone();
+        let back = crate::Msg::unpack(&msg.to_buf().unwrap()).unwrap();
+        assert_eq!(back.answer, records);
+        let out = Writer::new("example.com.").to_string(&back.answer);
+        assert!(out.contains("@\t\tIN\tMX\t10 mail\n"), "{}", out);
+        assert!(out.contains("_sip._udp\t\tIN\tSRV\t10 60 5060 sip\n"), "{}", out);
+        let mut again = parse(&out, ".").unwrap();
+        assert_eq!(again.len(), records.len());
+        again.retain(|rr| !records.contains(rr));
+        assert!(again.is_empty(), "{:?}", again);
+        for rr in &records {
+            assert_eq!(&rr.to_string().parse::<RecourseRecord>().unwrap(), rr);
+        }
+
+        // RFC 9460 appendix A.1: the value list is escaped inside the quoted string
+        let alpn = parse("@ 60 HTTPS 1 . alpn=\"f\\\\\\\\oo\\\\,bar,h2\"\n", "example.com").unwrap();
+        let RecourseRecord::SVCB(https) = &alpn[0] else { panic!("unexpected {}", alpn[0]) };
+        assert_eq!(https.param(types::svcb::SVC_KEY_ALPN), Some(&b"\x08f\\oo,bar\x02h2"[..]));
+        assert!(alpn[0].to_string().ends_with("alpn=\"f\\\\\\\\oo\\\\,bar,h2\""), "{}", alpn[0]);
+
+        for bad in [
+            "@ MX 10\n",
+            "@ SRV 10 60 mail\n",
+            "@ CAA 0 is-sue \"x\"\n",
+            "@ HTTPS 1 . port=443 port=80\n",
+            "@ HTTPS 1 . mandatory=alpn port=443\n",
+            "@ HTTPS 1 . alpn=\"h2,,h3\"\n",
+            "@ HTTPS 1 . no-default-alpn=x\n",
+        ] {
+            assert!(parse(&format!("$TTL 60\n{}", bad), "example.com").is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_from_str() {
+        let rr: RecourseRecord = "www.example.com. 300 IN A 192.0.2.1".parse().unwrap();
+        assert!(matches!(&rr, RecourseRecord::A(a) if a.a.to_string() == "192.0.2.1" && a.hdr.ttl == 300));
+        let rr: RecourseRecord = "mail.example.com. 60 CNAME www.example.com.".parse().unwrap();
+        assert_eq!(rr.to_string(), "mail.example.com.\t60\tIN\tCNAME\twww.example.com.");
+        let rr: RecourseRecord = "example.com. 60 IN TYPE65534 \\# 2 abcd".parse().unwrap();
+        assert!(matches!(&rr, RecourseRecord::Unknown(u) if u.hdr.typ == 65534));
+        let rr: RecourseRecord = "www.example.com. IN A 1.2.3.4".parse().unwrap();
+        assert_eq!((rr.header().ttl, rr.header().class), (0, types::CLASS_INET));
+        let rr: RecourseRecord = "example.com. 300 IN MX 10 mail.example.com.".parse().unwrap();
+        assert!(matches!(&rr, RecourseRecord::MX(mx) if mx.preference == 10 && mx.mx == "mail.example.com."));
+        let rr: RecourseRecord = "example.com. 300 IN TXT \"v=spf1 include:_spf.example.net ~all\"".parse().unwrap();
+        assert!(matches!(&rr, RecourseRecord::TXT(txt) if txt.data() == b"v=spf1 include:_spf.example.net ~all"));
+        let rr: RecourseRecord = "1.2.0.192.in-addr.arpa. PTR www.example.com.".parse().unwrap();
+        assert!(matches!(&rr, RecourseRecord::PTR(ptr) if ptr.ptr == "www.example.com."));
+        let rr: RecourseRecord = "_xmpp._tcp.example.com. 86400 IN SRV 5 0 5269 xmpp.example.com.".parse().unwrap();
+        assert!(matches!(&rr, RecourseRecord::SRV(srv) if srv.port == 5269 && srv.target == "xmpp.example.com."));
+        let rr: RecourseRecord = "example.com. 300 IN CAA 0 iodef \"mailto:security@example.com\"".parse().unwrap();
+        assert_eq!(rr.to_string(), "example.com.\t300\tIN\tCAA\t0 iodef \"mailto:security@example.com\"");
+        assert_eq!(rr.to_string().parse::<RecourseRecord>().unwrap(), rr);
+        assert!("".parse::<RecourseRecord>().is_err());
+        assert!("example.com. 300 IN MX mail.example.com.".parse::<RecourseRecord>().is_err());
+        assert!("a. 60 IN A 192.0.2.1\nb. 60 IN A 192.0.2.2".parse::<RecourseRecord>().is_err());
+        assert!("a. 60 IN A 192.0.2.256".parse::<RecourseRecord>().is_err());
+    }
+
+    #[test]
+    fn test_write_zone() {
+        let text = "www 300 IN A 192.0.2.2\n\
+            @ 3600 IN NS ns1.example.net.\n\
+            @ 3600 IN SOA ns1 hostmaster 1 7200 1800 1209600 86400\n\
+            ns1 3600 IN A 192.0.2.1\n\
+            mail.other.org. 3600 IN CNAME www\n";
+        let records = parse(text, "example.com").unwrap();
+        let out = Writer::new("example.com.").to_string(&records);
+        assert_eq!(
+            out,
+            "$ORIGIN example.com.\n\
+             $TTL 3600\n\
+             @\t\tIN\tSOA\tns1 hostmaster 1 7200 1800 1209600 86400\n\
+             \t\tIN\tNS\tns1.example.net.\n\
+             ns1\t\tIN\tA\t192.0.2.1\n\
+             www\t300\tIN\tA\t192.0.2.2\n\
+             mail.other.org.\t\tIN\tCNAME\twww\n"
+        );
+
+        let mut writer = Writer::new("example.com.");
+        writer.set_header("example.com\ngenerated").set_comments(|rr| match rr {
+            RecourseRecord::SOA(soa) => Some(format!("serial\n{}", soa.serial)),
+            _ => None,
+        });
+        let out = writer.to_string(&records);
+        assert!(out.starts_with("; example.com\n; generated\n$ORIGIN example.com.\n"), "{}", out);
+        assert!(out.contains("86400 ; serial 1\n\t\tIN\tNS"), "{}", out);
+        assert_eq!(parse(&out, ".").unwrap().len(), records.len());
+    }
+
+    #[test]
+    fn test_write_comments() {
+        let text = "@ 3600 IN SOA ns1 hostmaster 1 7200 1800 1209600 86400\n\
+            www 300 IN A 192.0.2.2\n\
+            www 300 IN A 192.0.2.3\n\
+            ftp 300 IN A 192.0.2.4\n";
+        let records = parse(text, "example.com").unwrap();
+        let mut writer = Writer::new("example.com.");
+        writer
+            .set_header("zone: example.com\r\n\nsee ; below")
+            .set_default_ttl(300)
+            .set_comments(|rr| match rr {
+                RecourseRecord::A(a) if a.a.octets()[3] == 3 => Some("second\r\nwww; address".into()),
+                RecourseRecord::A(a) if a.hdr.name.starts_with("ftp") => Some(String::new()),
+                _ => None,
+            });
+        let out = writer.to_string(&records);
+        assert_eq!(
+            out,
+            "; zone: example.com\n\
+             ; \n\
+             ; see ; below\n\
+             $ORIGIN example.com.\n\
+             $TTL 300\n\
+             @\t3600\tIN\tSOA\tns1 hostmaster 1 7200 1800 1209600 86400\n\
+             ftp\t\tIN\tA\t192.0.2.4 ; \n\
+             www\t\tIN\tA\t192.0.2.2\n\
+             \t\tIN\tA\t192.0.2.3 ; second  www; address\n"
+        );
+
+        // the comments are dropped again on reading, leaving the same records
+        let mut again = parse(&out, ".").unwrap();
+        let mut records = records;
+        again.sort_by_key(|rr| rr.to_string());
+        records.sort_by_key(|rr| rr.to_string());
+        assert_eq!(again, records);
+    }
+
+    #[test]
+    fn test_directives() {
+        let text = "$ORIGIN example.com.\n\
+            $TTL 1h\n\
+            @ SOA ns1 hostmaster 1 2h 30m 2w 1d\n\
+            $ORIGIN sub\n\
+            www 60 A 192.0.2.1\n\
+            $GENERATE 1-3/2 host-$ A 192.0.2.${10}\n\
+            $GENERATE 10-11 ${0,4,n}.rev PTR host-$\n";
+        let records = parse(text, ".").unwrap();
+        let names: Vec<String> = records.iter().map(|rr| rr.header().name.to_string()).collect();
+        assert_eq!(names, [
+            "example.com.",
+            "www.sub.example.com.",
+            "host-1.sub.example.com.",
+            "host-3.sub.example.com.",
+            "a.0.rev.sub.example.com.",
+            "b.0.rev.sub.example.com.",
+        ]);
+        assert_eq!(records[0].header().ttl, 3600);
+        assert_eq!(records[1].header().ttl, 60);
+        match &records[3] {
+            RecourseRecord::A(a) => assert_eq!(a.a.to_string(), "192.0.2.13"),
+            rr => panic!("unexpected {}", rr),
+        }
+
+        // the writer's output parses back to the same records
+        let out = Writer::new("example.com.").to_string(&records);
+        let again = parse(&out, ".").unwrap();
+        assert_eq!(Writer::new("example.com.").to_string(&again), out);
+    }
+
+    #[test]
+    fn test_include() {
+        let dir = std::env::temp_dir().join(format!("dns-zone-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("hosts.inc"), "www 60 IN A 192.0.2.1\n").unwrap();
+        std::fs::write(dir.join("loop.inc"), "$INCLUDE loop.inc\n").unwrap();
+        std::fs::write(
+            dir.join("db.example"),
+            "$INCLUDE hosts.inc sub.example.com.\nmail 60 IN A 192.0.2.2\n",
+        ).unwrap();
+
+        let records: Vec<_> = Parser::from_file(dir.join("db.example"), "example.com")
+            .unwrap()
+            .collect::<crate::Result<_>>()
+            .unwrap();
+        assert_eq!(records[0].header().name.as_str(), "www.sub.example.com.");
+        assert_eq!(records[1].header().name.as_str(), "mail.example.com.");
+
+        let err = Parser::from_file(dir.join("loop.inc"), "example.com")
+            .unwrap()
+            .next_record();
+        assert!(err.is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_watcher() {
+        let dir = std::env::temp_dir().join(format!("dns-zone-watch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("db.example");
+        let touch = |secs: u64| {
+            let file = std::fs::File::options().write(true).open(&path).unwrap();
+            file.set_modified(std::time::UNIX_EPOCH + std::time::Duration::from_secs(secs)).unwrap();
+        };
+        std::fs::write(&path, "$TTL 60\n@ SOA ns hostmaster 1 1 1 1 1\n").unwrap();
+        touch(1000);
+
+        let watcher = Watcher::new(&path, "example.com").unwrap();
+        let old = watcher.zone();
+        assert!(!watcher.poll().unwrap());
+
+        std::fs::write(&path, "$TTL 60\n@ SOA ns hostmaster 2 1 1 1 1\n").unwrap();
+        touch(2000);
+        assert!(watcher.poll().unwrap());
+        assert_eq!(old.soa().unwrap().serial, 1);
+        assert_eq!(watcher.zone().soa().unwrap().serial, 2);
+
+        std::fs::write(&path, "@ SOA ns (\n").unwrap();
+        touch(3000);
+        assert!(watcher.poll().is_err());
+        assert!(!watcher.poll().unwrap());
+        assert_eq!(watcher.zone().soa().unwrap().serial, 2);
+        assert_eq!((watcher.stats().reloads(), watcher.stats().failures()), (1, 1));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_journal() {
+        let dir = std::env::temp_dir().join(format!("dns-zone-journal-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("example.jnl");
+        let _ = std::fs::remove_file(&path);
+        let text = "$TTL 60\n@ SOA ns hostmaster 1 1 1 1 1\nwww A 192.0.2.1\n";
+        let zone = Zone::from_records("example.com.", parse(text, "example.com.").unwrap());
+
+        let rr = |text: &str| parse(text, "example.com.").unwrap().remove(0);
+        let mut journal = Journal::open(&path).unwrap();
+        journal.append(&Delta {
+            from: 1,
+            to: 2,
+            removed: vec![rr("@ 60 SOA ns hostmaster 1 1 1 1 1"), rr("www 60 A 192.0.2.1")],
+            added: vec![rr("@ 60 SOA ns hostmaster 2 1 1 1 1"), rr("www 60 A 192.0.2.2")],
+        }).unwrap();
+        journal.append(&Delta {
+            from: 2,
+            to: 3,
+            removed: vec![rr("@ 60 SOA ns hostmaster 2 1 1 1 1")],
+            added: vec![rr("@ 60 SOA ns hostmaster 3 1 1 1 1"), rr("mail 60 A 192.0.2.3")],
+        }).unwrap();
+        drop(journal);
+
+        let journal = Journal::open(&path).unwrap();
+        assert_eq!(journal.since(2).unwrap().unwrap().len(), 1);
+        assert_eq!(journal.since(3).unwrap().unwrap().len(), 0);
+        assert!(journal.since(0).unwrap().is_none());
+
+        let mut replayed = zone.clone();
+        assert_eq!(journal.replay(&mut replayed).unwrap(), 2);
+        assert_eq!(replayed.soa().unwrap().serial, 3);
+        assert_eq!(replayed.len(), 3);
+        assert!(replayed.contains(&rr("www 60 A 192.0.2.2")));
+        assert!(!replayed.contains(&rr("www 60 A 192.0.2.1")));
+        assert_eq!(journal.replay(&mut replayed).unwrap(), 0);
+
+        // a torn trailing entry is ignored
+        let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+        std::io::Write::write_all(&mut file, &[0, 0, 1, 0, 0]).unwrap();
+        assert_eq!(journal.deltas().unwrap().len(), 2);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_lookup() {
+        let text = "$TTL 300\n\
+            @ SOA ns1 hostmaster 1 2h 30m 2w 1d\n\
+            @ NS ns1\n\
+            ns1 A 192.0.2.1\n\
+            www CNAME ns1\n\
+            a.b.c A 192.0.2.3\n\
+            sub NS ns.sub\n\
+            ns.sub A 192.0.2.53\n\
+            *.wild A 192.0.2.4\n";
+        let zone = Zone::from_records("example.com.", parse(text, "example.com.").unwrap());
+        assert_eq!(zone.soa().unwrap().serial, 1);
+
+        assert!(matches!(zone.lookup("NS1.example.com.", types::TYPE_A), LookupResult::Answer(v) if v.len() == 1));
+        assert!(matches!(zone.lookup("ns1.example.com.", types::TYPE_AAAA), LookupResult::NoData));
+        assert!(matches!(zone.lookup("www.example.com.", types::TYPE_A), LookupResult::Cname(_)));
+        assert!(matches!(zone.lookup("b.c.example.com.", types::TYPE_A), LookupResult::NoData));
+        assert!(matches!(zone.lookup("x.c.example.com.", types::TYPE_A), LookupResult::NxDomain));
+        assert!(matches!(zone.lookup("example.org.", types::TYPE_A), LookupResult::OutOfZone));
+        match zone.lookup("deep.ns.sub.example.com.", types::TYPE_A) {
+            LookupResult::Referral { ns, glue } => {
+                assert_eq!(ns.len(), 1);
+                assert_eq!(glue.len(), 1);
+            }
+            res => panic!("unexpected {:?}", res),
+        }
+        match zone.lookup("host.wild.example.com.", types::TYPE_A) {
+            LookupResult::Answer(v) => assert_eq!(v[0].header().name.as_str(), "host.wild.example.com."),
+            res => panic!("unexpected {:?}", res),
+        }
+
+        let mut buf = Vec::new();
+        Writer::new("example.com.").write_zone(&mut buf, &zone).unwrap();
+        let out = String::from_utf8(buf).unwrap();
+        assert!(out.lines().nth(2).unwrap().starts_with("@\t\tIN\tSOA\t"), "{}", out);
+        assert_eq!(parse(&out, ".").unwrap().len(), zone.len());
+    }
+
+    #[test]
+    fn test_wildcards() {
+        // RFC 4592 section 2.2.1, with TXT/MX/SRV data swapped for types the
+        // parser knows.
+        let text = "$TTL 3600\n\
+            @ SOA ns.example.com. hostmaster 1 2h 30m 2w 1d\n\
+            @ NS ns.example.com.\n\
+            @ NS ns.example.net.\n\
+            * A 192.0.2.100\n\
+            * AAAA 2001:db8::100\n\
+            sub.* A 192.0.2.200\n\
+            host1 A 192.0.2.1\n\
+            _ssh._tcp.host1 A 192.0.2.22\n\
+            _ssh._tcp.host2 A 192.0.2.22\n\
+            subdel NS ns.example.com.\n\
+            subdel NS ns.example.net.\n";
+        let zone = Zone::from_records("example.", parse(text, "example.").unwrap());
+        let lookup = |name: &str, qtype| zone.lookup(name, qtype);
+
+        // synthesized from *.example.
+        match lookup("host3.example.", types::TYPE_AAAA) {
+            LookupResult::Answer(v) => assert_eq!(v[0].header().name.as_str(), "host3.example."),
+            res => panic!("unexpected {:?}", res),
+        }
+        assert!(matches!(lookup("foo.bar.example.", types::TYPE_A), LookupResult::Answer(_)));
+        assert!(matches!(lookup("host3.example.", types::TYPE_NS), LookupResult::NoData));
+        // existing names are never matched by the wildcard
+        assert!(matches!(lookup("host1.example.", types::TYPE_AAAA), LookupResult::NoData));
+        assert!(matches!(lookup("_tcp.host1.example.", types::TYPE_A), LookupResult::NoData));
+        assert!(matches!(lookup("sub.*.example.", types::TYPE_AAAA), LookupResult::NoData));
+        // the closest encloser _tcp.host1.example. has no wildcard child
+        assert!(matches!(lookup("_telnet._tcp.host1.example.", types::TYPE_A), LookupResult::NxDomain));
+        // * is only a wildcard as the leftmost label
+        assert!(matches!(lookup("ghost.*.example.", types::TYPE_A), LookupResult::NxDomain));
+        assert!(matches!(lookup("host.subdel.example.", types::TYPE_A), LookupResult::Referral { .. }));
+
+        assert_eq!(zone.closest_encloser("_telnet._tcp.host1.example.").unwrap().as_str(), "_tcp.host1.example.");
+        assert_eq!(zone.closest_encloser("ghost.*.example.").unwrap().as_str(), "*.example.");
+        assert_eq!(zone.wildcard_for("foo.bar.example.").unwrap().as_str(), "*.example.");
+        assert!(zone.wildcard_for("_telnet._tcp.host1.example.").is_none());
+    }
+
+    #[test]
+    fn test_serial() {
+        assert!(serial_gt(2, 1));
+        assert!(!serial_gt(1, 2));
+        assert!(!serial_gt(1, 1));
+        assert!(serial_gt(0, u32::MAX));
+        assert!(serial_gt(5, u32::MAX - 5));
+        assert!(!serial_gt(1 << 31, 0));
+        assert!(!serial_gt(0, 1 << 31));
+
+        // 2023-03-14T12:00:00Z
+        let now = 1678795200;
+        assert_eq!(serial::civil_date(now), (2023, 3, 14));
+        assert_eq!(serial::civil_date(951782400), (2000, 2, 29));
+        assert_eq!(serial::next_serial(7, SerialPolicy::Date, now), 2023031400);
+        assert_eq!(serial::next_serial(2023031400, SerialPolicy::Date, now), 2023031401);
+        assert_eq!(serial::next_serial(u32::MAX, SerialPolicy::Increment, now), 0);
+
+        let mut zone = Zone::from_records(
+            "example.com.",
+            parse("@ 60 SOA ns1 hostmaster 41 1 1 1 1\n", "example.com.").unwrap(),
+        );
+        assert_eq!(zone.bump_serial(SerialPolicy::Increment), Some(42));
+        assert_eq!(zone.soa().unwrap().serial, 42);
+        assert_eq!(Zone::new("example.com.").bump_serial(SerialPolicy::Increment), None);
+    }
+
+    #[test]
+    fn test_check() {
+        let text = "$TTL 300\n\
+            @ NS ns1\n\
+            @ NS ns2\n\
+            ns1 A 192.0.2.1\n\
+            www CNAME ns1\n\
+            www A 192.0.2.2\n\
+            sub NS ns.sub\n\
+            sub NS ns2.sub\n\
+            ns.sub A 192.0.2.53\n\
+            host.sub CNAME ns.sub\n\
+            other.org. A 192.0.2.9\n";
+        let zone = Zone::from_records("example.com.", parse(text, "example.com.").unwrap());
+        let problems = zone.check();
+        assert_eq!(problems, [
+            Problem::MissingSoa,
+            Problem::OccludedData("host.sub.example.com.".into(), types::TYPE_CNAME),
+            Problem::CnameAndOtherData("www.example.com.".into()),
+            Problem::OutOfZone("other.org.".into()),
+            Problem::DanglingNs("ns2.example.com.".into()),
+            Problem::MissingGlue("ns2.sub.example.com.".into()),
+        ]);
+    }
+
+    #[test]
+    fn test_nsec_chain() {
+        let text = "$TTL 3600\n\
+            @ SOA ns1 hostmaster 1 7200 1800 1209600 300\n\
+            @ NS ns1\n\
+            ns1 A 192.0.2.1\n\
+            a.b.c A 192.0.2.2\n\
+            sub NS ns.sub\n\
+            ns.sub A 192.0.2.53\n";
+        let zone = Zone::from_records("example.", parse(text, "example.").unwrap());
+        let chain: Vec<String> = zone.nsec_chain().unwrap().iter().map(|rr| rr.to_string()).collect();
+        assert_eq!(chain.len(), 4);
+        assert!(chain[0].ends_with("a.b.c.example. NS SOA RRSIG NSEC"), "{}", chain[0]);
+        assert!(chain[0].starts_with("example.\t300\t"), "{}", chain[0]);
+        assert!(chain[1].ends_with("ns1.example. A RRSIG NSEC"), "{}", chain[1]);
+        assert!(chain[3].ends_with("example. NS RRSIG NSEC"), "{}", chain[3]);
+
+        let params = Nsec3Params { iterations: 12, salt: "aabbccdd".into(), opt_out: false };
+        let chain = zone.nsec3_chain(&params).unwrap();
+        // apex, ns1, a.b.c, the b.c and c non-terminals, sub, plus NSEC3PARAM
+        assert_eq!(chain.len(), 7);
+        assert!(chain.iter().any(|rr| rr.to_string().starts_with("0p9mhaveqvm6t7vbl5lop2u3t2rp3tom.example.")));
+        let opt_out = zone.nsec3_chain(&Nsec3Params { opt_out: true, ..params }).unwrap();
+        assert_eq!(opt_out.len(), 6);
+        match &opt_out[0] {
+            RecourseRecord::NSEC3(rr) => assert!(rr.is_opt_out()),
+            rr => panic!("unexpected {}", rr),
+        }
+    }
+
+    #[test]
+    fn test_nsec3() {
+        let hash = crate::dnssec::nsec3_digest("a.example.", &[0xaa, 0xbb, 0xcc, 0xdd], 12).unwrap();
+        assert_eq!(crate::util::base32hex_decode("35mthgpgcu1qg68fab165klnsnk3dpvl").unwrap(), hash);
+
+        let text = "0p9mhaveqvm6t7vbl5lop2u3t2rp3tom 3600 IN NSEC3 1 1 12 aabbccdd (\n\
+            2t7b4g4vsa5smi47k61mv5bv1a22bojr NS SOA MX RRSIG DNSKEY NSEC3PARAM )\n";
+        let rr = parse(text, "example.").unwrap().remove(0);
+        let mut buf = bytes::BytesMut::new();
+        rr.header().pack(&mut buf).unwrap();
+        let start = buf.len();
+        rr.pack(&mut buf).unwrap();
+        let mut hdr = rr.header().clone();
+        hdr.rd_length = u16::from_be_bytes([buf[start - 2], buf[start - 1]]);
+        assert_eq!(hdr.rd_length as usize, buf.len() - start);
+        let unpacked = RecourseRecord::unpack(hdr, &mut std::io::Cursor::new(&buf[start..])).unwrap();
+        assert_eq!(unpacked.to_string(), rr.to_string());
+        assert!(rr.to_string().ends_with("1 1 12 AABBCCDD 2t7b4g4vsa5smi47k61mv5bv1a22bojr NS SOA MX RRSIG DNSKEY NSEC3PARAM"));
+    }
+
+    #[test]
+    fn test_dnskey() {
+        // RFC 8080 section 6.1, the key split over lines as zone files allow
+        let text = "example.com. 3600 IN DNSKEY 257 3 15 (\n\
+            l02Woi0iS8Aa25FQkUd9RMzZ HJpBoRQwAQEX1SxZJA4= )\n";
+        let records = parse(text, ".").unwrap();
+        let key = match &records[0] {
+            RecourseRecord::DNSKEY(key) => key,
+            rr => panic!("unexpected {}", rr),
+        };
+        assert_eq!(key.key_tag(), 3613);
+        assert!(key.is_zone_key() && key.is_sep() && !key.is_revoked());
+        assert_eq!(key.public_key.len(), 32);
+        let out = Writer::new("example.com.").to_string(&records);
+        assert!(out.ends_with("@\t\tIN\tDNSKEY\t257 3 15 l02Woi0iS8Aa25FQkUd9RMzZHJpBoRQwAQEX1SxZJA4=\n"), "{}", out);
+        assert!(parse("@ 60 IN DNSKEY 256 3 15 !!\n", "example.com").is_err());
+    }
+
+    #[test]
+    fn test_rrsig() {
+        // RFC 4034 section 3.3, then with the timestamps as epoch seconds
+        let sig = "oJB1W6WNGv+ldvQ3WDG0MQkg5IEhjRip8WTrPYGv07h108dUKGMeDPKijVCHX3DDKdfb+v6oB9wfuh3DTJXUAfI/\
+            M0zmO/zz8bW0Rznl8O3tGNazPwQKkRN20XPXV6nwwfoXmJQbsLNrLfkGJ5D6fwFm8nN+6pBzeDQfsS3Ap3o=";
+        let text = format!(
+            "host 86400 IN RRSIG A 5 3 86400 20030322173103 (\n\
+                20030220173103 2642 @\n\
+                {} )\n\
+             host 86400 IN RRSIG A 5 3 86400 1048354263 1045762263 2642 example.com. {}\n",
+            sig, sig
+        );
+        let records = parse(&text, "example.com").unwrap();
+        let RecourseRecord::RRSIG(rrsig) = &records[0] else { panic!("unexpected {}", records[0]) };
+        assert_eq!((rrsig.type_covered, rrsig.algorithm, rrsig.labels, rrsig.key_tag), (types::TYPE_A, 5, 3, 2642));
+        assert_eq!((rrsig.expiration, rrsig.inception), (1048354263, 1045762263));
+        assert_eq!(rrsig.signer_name.as_str(), "example.com.");
+        assert_eq!(rrsig.signature.len(), 128);
+        assert_eq!(records[0].to_string(), records[1].to_string());
+        assert!(records[0].to_string().ends_with(&format!("A 5 3 86400 20030322173103 20030220173103 2642 example.com. {}", sig)));
+
+        let out = Writer::new("example.com.").to_string(&records[..1]);
+        assert!(out.contains("host\t\tIN\tRRSIG\tA 5 3 86400 20030322173103 20030220173103 2642 @ oJB1"), "{}", out);
+        assert_eq!(parse(&out, ".").unwrap()[0].to_string(), records[0].to_string());
+        assert!(parse("host 60 IN RRSIG A 5 3 60 20031322173103 20030220173103 2642 @ AA==\n", "example.com").is_err());
+    }
+
+    #[test]
+    fn test_hinfo() {
+        let text = "host 60 IN HINFO \"Intel \\\"x86\\\"\" Linux\n\
+            any 60 IN HINFO RFC8482 \"\"\n\
+            raw 60 IN HINFO \"\\255\\000\" \"a\\;b\"\n";
+        let records = parse(text, "example.com").unwrap();
+        let RecourseRecord::HINFO(hinfo) = &records[0] else { panic!("unexpected {}", records[0]) };
+        assert_eq!((&hinfo.cpu[..], &hinfo.os[..]), (&b"Intel \"x86\""[..], &b"Linux"[..]));
+        assert!(records[1].to_string().ends_with("HINFO\t\"RFC8482\" \"\""));
+        assert!(records[2].to_string().ends_with("\"\\255\\000\" \"a;b\""));
+
+        let mut msg = crate::Msg::new();
+        msg.answer = records.clone();
+        let back = crate::Msg::unpack(&msg.to_buf().unwrap()).unwrap();
+        let out = Writer::new("example.com.").to_string(&back.answer);
+        assert!(out.contains("host\t\tIN\tHINFO\t\"Intel \\\"x86\\\"\" \"Linux\"\n"), "{}", out);
+        let sorted = |records: &[RecourseRecord]| {
+            let mut lines: Vec<String> = records.iter().map(|rr| rr.to_string()).collect();
+            lines.sort();
+            lines
+        };
+        assert_eq!(sorted(&parse(&out, ".").unwrap()), sorted(&records));
+        assert!(parse(&format!("host 60 IN HINFO {} Linux\n", "x".repeat(256)), "example.com").is_err());
+    }
+
+    #[test]
+    fn test_afsdb() {
+        let records = parse("@ 3600 IN AFSDB 1 afs1\n", "example.com").unwrap();
+        assert!(records[0].to_string().ends_with("AFSDB\t1 afs1.example.com."));
+
+        let mut msg = crate::Msg::new();
+        msg.set_question(crate::full_domain("example.com"), types::TYPE_AFSDB);
+        msg.answer = records.clone();
+        let mut buf = bytes::BytesMut::new();
+        msg.pack_compressed(&mut crate::msg::CompressionContext::new(), &mut buf).unwrap();
+        // the owner is compressed, the hostname is not
+        assert!(buf.ends_with(b"\xc0\x0c\x00\x12\x00\x01\x00\x00\x0e\x10\x00\x14\x00\x01\x04afs1\x07example\x03com\x00"), "{:?}", buf);
+        let back = crate::Msg::unpack(&buf).unwrap();
+        assert_eq!(back.answer[0].to_string(), records[0].to_string());
+        assert_eq!(Writer::new("example.com.").to_string(&back.answer), "$ORIGIN example.com.\n$TTL 3600\n@\t\tIN\tAFSDB\t1 afs1\n");
+        assert!(parse("@ 3600 IN AFSDB one afs1\n", "example.com").is_err());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse("www IN A 192.0.2.1\n", "example.com").is_err());
+        assert!(parse("www 60 IN A (192.0.2.1\n", "example.com").is_err());
+        assert!(parse("www 60 IN MX mail\n", "example.com").is_err());
+    }
+}
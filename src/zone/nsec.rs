@@ -0,0 +1,116 @@
+use std::collections::{BTreeMap, BTreeSet};
+use crate::dnssec::nsec3_digest;
+use crate::types::{self, RecourseRecord, NSEC, NSEC3, NSEC3PARAM};
+use crate::{util, DomainString, Error, Result};
+use super::tree::{NameKey, Zone};
+
+/// Parameters of an NSEC3 chain, see RFC 5155 section 3.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Nsec3Params {
+    pub iterations: u16,
+    /// hex-encoded salt, empty for none
+    pub salt: String,
+    /// Leave insecure delegations out of the chain.
+    pub opt_out: bool,
+}
+
+impl Zone {
+    /// Builds the NSEC chain of the zone (RFC 4034 section 4).
+    ///
+    /// Every authoritative name and delegation point gets a record; glue and
+    /// empty non-terminals are skipped. Existing NSEC records are ignored.
+    pub fn nsec_chain(&self) -> Result<Vec<RecourseRecord>> {
+        let (class, ttl) = self.denial_params()?;
+        let names = self.authoritative();
+        let mut ret = Vec::with_capacity(names.len());
+        for (i, (key, mut bitmap)) in names.iter().cloned().enumerate() {
+            let next = &names[(i + 1) % names.len()].0;
+            bitmap.extend([types::TYPE_NSEC, types::TYPE_RRSIG]);
+            ret.push(NSEC::new(self.key_name(&key), class, ttl, self.key_name(next), bitmap).into());
+        }
+        Ok(ret)
+    }
+
+    /// Builds the NSEC3 chain of the zone plus its NSEC3PARAM (RFC 5155 section 7.1).
+    ///
+    /// Empty non-terminals are covered with empty bitmaps. With opt-out,
+    /// delegations without DS, and non-terminals only leading to them, are left out.
+    pub fn nsec3_chain(&self, params: &Nsec3Params) -> Result<Vec<RecourseRecord>> {
+        let (class, ttl) = self.denial_params()?;
+        let salt = hex::decode(&params.salt)?;
+        let apex = NameKey::new(&self.origin);
+
+        let mut names: BTreeMap<NameKey, Vec<u16>> = BTreeMap::new();
+        for (key, mut bitmap) in self.authoritative() {
+            let insecure = key != apex && bitmap.contains(&types::TYPE_NS) && !bitmap.contains(&types::TYPE_DS);
+            if insecure && params.opt_out {
+                continue;
+            }
+            if key == apex {
+                bitmap.push(types::TYPE_NSEC3PARAM);
+            }
+            if !bitmap.is_empty() && !insecure {
+                bitmap.push(types::TYPE_RRSIG);
+            }
+            for depth in apex.0.len() + 1..key.0.len() {
+                names.entry(NameKey(key.0[..depth].to_vec())).or_default();
+            }
+            names.insert(key, bitmap);
+        }
+
+        let mut hashed = BTreeMap::new();
+        for (key, bitmap) in names {
+            let hash = nsec3_digest(&self.key_name(&key), &salt, params.iterations)?;
+            if hashed.insert(hash, bitmap).is_some() {
+                return Err(Error::new(format!("NSEC3 hash collision at {}", self.key_name(&key))));
+            }
+        }
+
+        let flags = if params.opt_out { types::nsec3::NSEC3_OPT_OUT } else { 0 };
+        let hashes: Vec<&Vec<u8>> = hashed.keys().collect();
+        let mut ret = Vec::with_capacity(hashed.len() + 1);
+        for (i, (hash, bitmap)) in hashed.iter().enumerate() {
+            let mut owner = DomainString::from(util::base32hex_encode(hash));
+            owner.push('.');
+            if self.origin.as_str() != "." {
+                owner.push_str(&self.origin);
+            }
+            let next = util::base32hex_encode(hashes[(i + 1) % hashes.len()]);
+            ret.push(NSEC3::new(owner, class, ttl, flags, params.iterations, params.salt.to_ascii_lowercase(), next, bitmap.clone()).into());
+        }
+        ret.push(NSEC3PARAM::new(self.origin.clone(), class, 0, params.iterations, params.salt.to_ascii_lowercase()).into());
+        Ok(ret)
+    }
+
+    /// Class and negative TTL for denial records, taken from the SOA (RFC 9077).
+    fn denial_params(&self) -> Result<(u16, u32)> {
+        let soa = self.soa().ok_or_else(|| Error::new("zone has no SOA record"))?;
+        Ok((soa.hdr.class, soa.hdr.ttl.min(soa.min_ttl)))
+    }
+
+    /// Authoritative names and delegation points in canonical order, with
+    /// their types minus any existing denial records.
+    fn authoritative(&self) -> Vec<(NameKey, Vec<u16>)> {
+        let apex = NameKey::new(&self.origin);
+        let skip = BTreeSet::from([types::TYPE_NSEC, types::TYPE_NSEC3, types::TYPE_RRSIG]);
+        let mut cuts: Vec<&NameKey> = Vec::new();
+        let mut ret = Vec::new();
+        for (key, node) in &self.nodes {
+            if key != &apex && !key.is_below(&apex) {
+                continue;
+            }
+            if cuts.iter().any(|cut| key.is_below(cut)) {
+                continue;
+            }
+            let bitmap: Vec<u16> = node.rrsets.keys().copied().filter(|t| !skip.contains(t)).collect();
+            if bitmap.is_empty() {
+                continue;
+            }
+            if key != &apex && bitmap.contains(&types::TYPE_NS) {
+                cuts.push(key);
+            }
+            ret.push((key.clone(), bitmap));
+        }
+        ret
+    }
+}
@@ -0,0 +1,606 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::io::Cursor;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::path::{Path, PathBuf};
+use base64::Engine;
+use crate::msg::{Labels, RecourseRecordHdr, RR};
+use crate::types::{self, RecourseRecord};
+use crate::{full_domain, util, DomainString, Error, Result};
+use super::lexer::{Entry, Lexer, Token};
+use super::{generate, parse_ttl};
+
+/// Nesting limit for `$INCLUDE`.
+const MAX_INCLUDE_DEPTH: usize = 8;
+
+struct Frame {
+    lexer: Lexer,
+    origin: DomainString,
+    path: Option<PathBuf>,
+}
+
+/// Streaming master-file parser yielding one record per entry.
+///
+/// Understands `$ORIGIN`, `$TTL`, `$INCLUDE` (relative paths resolve against
+/// the including file) and BIND's `$GENERATE`.
+pub struct Parser {
+    frames: Vec<Frame>,
+    pending: VecDeque<RecourseRecord>,
+    default_ttl: Option<u32>,
+    last_owner: Option<DomainString>,
+    last_ttl: Option<u32>,
+    last_class: u16,
+}
+
+impl Parser {
+    pub fn new(input: &str, origin: &str) -> Self {
+        Self {
+            frames: vec![Frame {
+                lexer: Lexer::new(input),
+                origin: full_domain(origin),
+                path: None,
+            }],
+            pending: VecDeque::new(),
+            default_ttl: None,
+            last_owner: None,
+            last_ttl: None,
+            last_class: types::CLASS_INET,
+        }
+    }
+
+    /// Opens a zone file; `$INCLUDE`s in it are resolved relative to its directory.
+    pub fn from_file<P: AsRef<Path>>(path: P, origin: &str) -> Result<Self> {
+        let input = fs::read_to_string(path.as_ref())?;
+        let mut parser = Self::new(&input, origin);
+        parser.frames[0].path = Some(path.as_ref().to_path_buf());
+        Ok(parser)
+    }
+
+    /// TTL used by records that don't carry one, as if set by `$TTL`.
+    pub fn set_default_ttl(&mut self, ttl: u32) -> &mut Self {
+        self.default_ttl = Some(ttl);
+        self
+    }
+
+    pub fn origin(&self) -> &str {
+        &self.frame().origin
+    }
+
+    /// Returns the next record, or `None` at the end of input.
+    pub fn next_record(&mut self) -> Result<Option<RecourseRecord>> {
+        loop {
+            if let Some(rr) = self.pending.pop_front() {
+                return Ok(Some(rr));
+            }
+            let entry = match self.frames.last_mut().unwrap().lexer.next_entry() {
+                Ok(Some(entry)) => entry,
+                Ok(None) if self.frames.len() > 1 => {
+                    self.frames.pop();
+                    continue;
+                }
+                Ok(None) => return Ok(None),
+                Err(err) => return Err(self.locate(err)),
+            };
+            let first = &entry.tokens[0];
+            let ret = if !entry.blank_owner && !first.quoted && first.text.starts_with('$') {
+                self.directive(entry)
+            } else {
+                self.record(entry).map(|rr| self.pending.push_back(rr))
+            };
+            if let Err(err) = ret {
+                return Err(self.locate(err));
+            }
+        }
+    }
+
+    fn frame(&self) -> &Frame {
+        self.frames.last().unwrap()
+    }
+
+    /// Prefixes errors from included files with the file name.
+    fn locate(&self, err: Error) -> Error {
+        match (err, &self.frame().path) {
+            (Error::Syntax(line, msg), Some(path)) if self.frames.len() > 1 => {
+                Error::Syntax(line, format!("{}: {}", path.display(), msg))
+            }
+            (err, _) => err,
+        }
+    }
+
+    fn directive(&mut self, entry: Entry) -> Result<()> {
+        let line = entry.line;
+        let err = |msg: String| Error::Syntax(line, msg);
+        let args: Vec<&str> = entry.tokens[1..].iter().map(|t| t.text.as_str()).collect();
+
+        match entry.tokens[0].text.to_ascii_uppercase().as_str() {
+            "$ORIGIN" => {
+                if args.len() != 1 {
+                    return Err(err("$ORIGIN takes one name".into()));
+                }
+                let origin = self.absolute_name(args[0]).map_err(err)?;
+                self.frames.last_mut().unwrap().origin = origin;
+            }
+            "$TTL" => {
+                if args.len() != 1 {
+                    return Err(err("$TTL takes one value".into()));
+                }
+                self.default_ttl = Some(parse_ttl(args[0]).ok_or_else(|| err(format!("bad TTL {:?}", args[0])))?);
+            }
+            "$INCLUDE" => {
+                if args.is_empty() || args.len() > 2 {
+                    return Err(err("$INCLUDE takes a file name and an optional origin".into()));
+                }
+                if self.frames.len() > MAX_INCLUDE_DEPTH {
+                    return Err(err(format!("$INCLUDE nested deeper than {}", MAX_INCLUDE_DEPTH)));
+                }
+                let mut path = PathBuf::from(args[0]);
+                if path.is_relative() {
+                    if let Some(dir) = self.frame().path.as_ref().and_then(|p| p.parent()) {
+                        path = dir.join(path);
+                    }
+                }
+                let origin = match args.get(1) {
+                    Some(name) => self.absolute_name(name).map_err(err)?,
+                    None => self.frame().origin.clone(),
+                };
+                let input = fs::read_to_string(&path)
+                    .map_err(|e| err(format!("cannot include {}: {}", path.display(), e)))?;
+                self.frames.push(Frame {
+                    lexer: Lexer::new(&input),
+                    origin,
+                    path: Some(path),
+                });
+            }
+            "$GENERATE" => {
+                let (range, template) = args.split_first().ok_or_else(|| err("$GENERATE needs a range".into()))?;
+                if template.len() < 3 {
+                    return Err(err("$GENERATE needs lhs, type and rhs".into()));
+                }
+                for i in generate::range(range).map_err(err)? {
+                    let text = template
+                        .iter()
+                        .map(|t| generate::expand(t, i))
+                        .collect::<std::result::Result<Vec<_>, _>>()
+                        .map_err(err)?
+                        .join(" ");
+                    let mut entry = Lexer::new(&text).next_entry()?.ok_or_else(|| err("empty $GENERATE".into()))?;
+                    entry.line = line;
+                    let rr = self.record(entry)?;
+                    self.pending.push_back(rr);
+                }
+            }
+            other => return Err(err(format!("unsupported directive {}", other))),
+        }
+        Ok(())
+    }
+
+    fn record(&mut self, entry: Entry) -> Result<RecourseRecord> {
+        let line = entry.line;
+        let err = |msg: String| Error::Syntax(line, msg);
+        let mut tokens = entry.tokens.iter().peekable();
+
+        let owner = if entry.blank_owner {
+            self.last_owner.clone().ok_or_else(|| err("no previous owner name".into()))?
+        } else {
+            let token = tokens.next().unwrap();
+            self.absolute_name(&token.text).map_err(err)?
+        };
+
+        let mut ttl = None;
+        let mut class = None;
+        let typ = loop {
+            let token = tokens.next().ok_or_else(|| err("missing record type".into()))?;
+            if token.quoted {
+                return Err(err(format!("unexpected quoted string {:?}", token.text)));
+            }
+            if ttl.is_none() && token.text.starts_with(|c: char| c.is_ascii_digit()) {
+                ttl = Some(parse_ttl(&token.text).ok_or_else(|| err(format!("bad TTL {:?}", token.text)))?);
+                continue;
+            }
+            if class.is_none() {
+                if let Some(c) = util::string_to_class(&token.text) {
+                    class = Some(c);
+                    continue;
+                }
+            }
+            break util::string_to_type(&token.text)
+                .ok_or_else(|| err(format!("unknown record type {:?}", token.text)))?;
+        };
+
+        let ttl = match ttl.or(self.default_ttl).or(self.last_ttl) {
+            Some(ttl) => ttl,
+            None => return Err(err("no TTL specified".into())),
+        };
+        let class = class.unwrap_or(self.last_class);
+        let rdata: Vec<Token> = tokens.cloned().collect();
+        let rr = self.rdata(owner.clone(), class, ttl, typ, &rdata).map_err(err)?;
+
+        self.last_owner = Some(owner);
+        self.last_class = class;
+        self.last_ttl = Some(ttl);
+        Ok(rr)
+    }
+
+    fn rdata(
+        &self,
+        name: DomainString,
+        class: u16,
+        ttl: u32,
+        typ: u16,
+        rdata: &[Token],
+    ) -> std::result::Result<RecourseRecord, String> {
+        if rdata.first().map(|t| !t.quoted && t.text == "\\#").unwrap_or(false) {
+            return generic_rdata(name, class, ttl, typ, &rdata[1..]);
+        }
+        let fields: Vec<&str> = rdata.iter().map(|t| t.text.as_str()).collect();
+        let expect = |n: usize| {
+            if fields.len() == n {
+                Ok(())
+            } else {
+                Err(format!("expected {} rdata fields, got {}", n, fields.len()))
+            }
+        };
+
+        Ok(match typ {
+            types::TYPE_A => {
+                expect(1)?;
+                let ip: Ipv4Addr = fields[0].parse().map_err(|_| format!("bad A address {:?}", fields[0]))?;
+                types::A::new(name, class, ttl, ip).into()
+            }
+            types::TYPE_AAAA => {
+                expect(1)?;
+                let ip: Ipv6Addr = fields[0].parse().map_err(|_| format!("bad AAAA address {:?}", fields[0]))?;
+                types::AAAA::new(name, class, ttl, ip).into()
+            }
+            types::TYPE_CNAME => {
+                expect(1)?;
+                types::CNAME::new(name, class, ttl, self.absolute_name(fields[0])?).into()
+            }
+            types::TYPE_MX => {
+                expect(2)?;
+                let preference = fields[0].parse().map_err(|_| format!("bad MX preference {:?}", fields[0]))?;
+                types::MX::new(name, class, ttl, preference, self.absolute_name(fields[1])?).into()
+            }
+            types::TYPE_PTR => {
+                expect(1)?;
+                types::PTR::new(name, class, ttl, self.absolute_name(fields[0])?).into()
+            }
+            types::TYPE_TXT => {
+                if fields.is_empty() {
+                    return Err("expected at least 1 rdata field, got 0".into());
+                }
+                let txt = fields
+                    .iter()
+                    .map(|s| util::parse_character_string(s).ok_or_else(|| format!("bad TXT string {:?}", s)))
+                    .collect::<std::result::Result<_, _>>()?;
+                types::TXT::new(name, class, ttl, txt).into()
+            }
+            types::TYPE_SRV => {
+                expect(4)?;
+                let mut values = [0u16; 3];
+                for (i, v) in values.iter_mut().enumerate() {
+                    *v = fields[i].parse().map_err(|_| format!("bad SRV field {:?}", fields[i]))?;
+                }
+                let [priority, weight, port] = values;
+                types::SRV::new(name, class, ttl, priority, weight, port, self.absolute_name(fields[3])?).into()
+            }
+            types::TYPE_CAA => {
+                expect(3)?;
+                let flags = fields[0].parse().map_err(|_| format!("bad CAA flags {:?}", fields[0]))?;
+                if fields[1].is_empty() || fields[1].len() > 15 || !fields[1].bytes().all(|b| b.is_ascii_alphanumeric()) {
+                    return Err(format!("bad CAA tag {:?}", fields[1]));
+                }
+                let value = util::parse_character_string(fields[2]).ok_or_else(|| format!("bad CAA value {:?}", fields[2]))?;
+                types::CAA::new(name, class, ttl, flags, fields[1].into(), value).into()
+            }
+            types::TYPE_SVCB | types::TYPE_HTTPS => {
+                if fields.len() < 2 {
+                    return Err(format!("expected at least 2 rdata fields, got {}", fields.len()));
+                }
+                let priority = fields[0].parse().map_err(|_| format!("bad SVCB priority {:?}", fields[0]))?;
+                let target = self.absolute_name(fields[1])?;
+                let mut rr = types::SVCB::new(name, class, ttl, priority, target, svc_params(&rdata[2..])?);
+                rr.hdr.typ = typ;
+                rr.into()
+            }
+            types::TYPE_DNAME => {
+                expect(1)?;
+                types::DNAME::new(name, class, ttl, self.absolute_name(fields[0])?).into()
+            }
+            types::TYPE_HINFO => {
+                expect(2)?;
+                let string = |s: &str| util::parse_character_string(s).ok_or_else(|| format!("bad HINFO string {:?}", s));
+                types::HINFO::new(name, class, ttl, string(fields[0])?, string(fields[1])?).into()
+            }
+            types::TYPE_AFSDB => {
+                expect(2)?;
+                let subtype = fields[0].parse().map_err(|_| format!("bad AFSDB subtype {:?}", fields[0]))?;
+                types::AFSDB::new(name, class, ttl, subtype, self.absolute_name(fields[1])?).into()
+            }
+            types::TYPE_NS => {
+                expect(1)?;
+                types::NS::new(name, class, ttl, self.absolute_name(fields[0])?).into()
+            }
+            types::TYPE_SOA => {
+                expect(7)?;
+                let serial = fields[2].parse().map_err(|_| format!("bad SOA serial {:?}", fields[2]))?;
+                let mut timers = [0u32; 4];
+                for (i, v) in timers.iter_mut().enumerate() {
+                    *v = parse_ttl(fields[3 + i]).ok_or_else(|| format!("bad SOA field {:?}", fields[3 + i]))?;
+                }
+                types::SOA::new(
+                    name,
+                    class,
+                    ttl,
+                    self.absolute_name(fields[0])?,
+                    self.absolute_name(fields[1])?,
+                    serial,
+                    timers[0],
+                    timers[1],
+                    timers[2],
+                    timers[3],
+                ).into()
+            }
+            types::TYPE_NSEC => {
+                if fields.is_empty() {
+                    return Err("expected NSEC next domain".into());
+                }
+                types::NSEC::new(name, class, ttl, self.absolute_name(fields[0])?, type_list(&fields[1..])?).into()
+            }
+            types::TYPE_NSEC3 => {
+                if fields.len() < 5 {
+                    return Err(format!("expected at least 5 rdata fields, got {}", fields.len()));
+                }
+                let (hash, flags, iterations, salt) = nsec3_params(&fields)?;
+                if util::base32hex_decode(fields[4]).is_none() {
+                    return Err(format!("bad NSEC3 next hashed owner {:?}", fields[4]));
+                }
+                let mut rr = types::NSEC3::new(
+                    name,
+                    class,
+                    ttl,
+                    flags,
+                    iterations,
+                    salt,
+                    fields[4].to_ascii_lowercase(),
+                    type_list(&fields[5..])?,
+                );
+                rr.hash = hash;
+                rr.into()
+            }
+            types::TYPE_NSEC3PARAM => {
+                expect(4)?;
+                let (hash, flags, iterations, salt) = nsec3_params(&fields)?;
+                let mut rr = types::NSEC3PARAM::new(name, class, ttl, iterations, salt);
+                rr.hash = hash;
+                rr.flags = flags;
+                rr.into()
+            }
+            types::TYPE_DNSKEY => {
+                if fields.len() < 4 {
+                    return Err(format!("expected at least 4 rdata fields, got {}", fields.len()));
+                }
+                let flags = fields[0].parse().map_err(|_| format!("bad DNSKEY flags {:?}", fields[0]))?;
+                let protocol = fields[1].parse().map_err(|_| format!("bad DNSKEY protocol {:?}", fields[1]))?;
+                let algorithm = fields[2].parse().map_err(|_| format!("bad DNSKEY algorithm {:?}", fields[2]))?;
+                let public_key = base64::engine::general_purpose::STANDARD
+                    .decode(fields[3..].concat())
+                    .map_err(|e| format!("bad DNSKEY public key: {}", e))?;
+                let mut rr = types::DNSKEY::new(name, class, ttl, flags, algorithm, public_key);
+                rr.protocol = protocol;
+                rr.into()
+            }
+            types::TYPE_RRSIG => {
+                if fields.len() < 9 {
+                    return Err(format!("expected at least 9 rdata fields, got {}", fields.len()));
+                }
+                let type_covered = util::string_to_type(fields[0]).ok_or_else(|| format!("unknown type {:?}", fields[0]))?;
+                let algorithm = fields[1].parse().map_err(|_| format!("bad RRSIG algorithm {:?}", fields[1]))?;
+                let labels = fields[2].parse().map_err(|_| format!("bad RRSIG labels {:?}", fields[2]))?;
+                let orig_ttl = parse_ttl(fields[3]).ok_or_else(|| format!("bad RRSIG original TTL {:?}", fields[3]))?;
+                let expiration = util::parse_timestamp(fields[4]).ok_or_else(|| format!("bad RRSIG expiration {:?}", fields[4]))?;
+                let inception = util::parse_timestamp(fields[5]).ok_or_else(|| format!("bad RRSIG inception {:?}", fields[5]))?;
+                let key_tag = fields[6].parse().map_err(|_| format!("bad RRSIG key tag {:?}", fields[6]))?;
+                let signature = base64::engine::general_purpose::STANDARD
+                    .decode(fields[8..].concat())
+                    .map_err(|e| format!("bad RRSIG signature: {}", e))?;
+                types::RRSIG::new(
+                    name,
+                    class,
+                    ttl,
+                    type_covered,
+                    algorithm,
+                    labels,
+                    orig_ttl,
+                    expiration,
+                    inception,
+                    key_tag,
+                    self.absolute_name(fields[7])?,
+                    signature,
+                ).into()
+            }
+            types::TYPE_DS => {
+                if fields.len() < 4 {
+                    return Err(format!("expected at least 4 rdata fields, got {}", fields.len()));
+                }
+                let key_tag = fields[0].parse().map_err(|_| format!("bad DS key tag {:?}", fields[0]))?;
+                let algorithm = fields[1].parse().map_err(|_| format!("bad DS algorithm {:?}", fields[1]))?;
+                let digest_type = fields[2].parse().map_err(|_| format!("bad DS digest type {:?}", fields[2]))?;
+                let digest = hex::decode(fields[3..].concat()).map_err(|e| format!("bad DS digest: {}", e))?;
+                types::DS::new(name, class, ttl, key_tag, algorithm, digest_type, digest).into()
+            }
+            _ => {
+                let name = util::type_to_string(typ).map(String::from).unwrap_or_else(|| format!("TYPE{}", typ));
+                return Err(format!("no presentation format for {}, use \\# syntax", name));
+            }
+        })
+    }
+
+    /// Resolves `@` and relative names against the current origin.
+    fn absolute_name(&self, name: &str) -> std::result::Result<DomainString, String> {
+        let origin = &self.frame().origin;
+        let ret: DomainString = if name == "@" {
+            origin.clone()
+        } else if name.ends_with('.') && !name.ends_with("\\.") {
+            name.into()
+        } else if origin.as_str() == "." {
+            full_domain(name)
+        } else {
+            let mut s = DomainString::from(name);
+            s.push('.');
+            s.push_str(origin);
+            s
+        };
+        if !Labels::verify(&ret) || util::cal_domain_name_len(&ret) > 255 {
+            return Err(format!("bad domain name {:?}", name));
+        }
+        Ok(ret)
+    }
+}
+
+impl Iterator for Parser {
+    type Item = Result<RecourseRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record().transpose()
+    }
+}
+
+/// NSEC type bitmap mnemonics.
+fn type_list(fields: &[&str]) -> std::result::Result<Vec<u16>, String> {
+    fields
+        .iter()
+        .map(|f| util::string_to_type(f).ok_or_else(|| format!("unknown type {:?}", f)))
+        .collect()
+}
+
+/// Leading hash, flags, iterations and salt of NSEC3 and NSEC3PARAM.
+fn nsec3_params(fields: &[&str]) -> std::result::Result<(u8, u8, u16, String), String> {
+    let hash = fields[0].parse().map_err(|_| format!("bad NSEC3 hash {:?}", fields[0]))?;
+    let flags = fields[1].parse().map_err(|_| format!("bad NSEC3 flags {:?}", fields[1]))?;
+    let iterations = fields[2].parse().map_err(|_| format!("bad NSEC3 iterations {:?}", fields[2]))?;
+    let salt = if fields[3] == "-" {
+        String::new()
+    } else if hex::decode(fields[3]).map(|v| v.len() < 256).unwrap_or(false) {
+        fields[3].to_ascii_lowercase()
+    } else {
+        return Err(format!("bad NSEC3 salt {:?}", fields[3]));
+    };
+    Ok((hash, flags, iterations, salt))
+}
+
+/// SVCB and HTTPS `key=value` parameters (RFC 9460 section 2.1), sorted
+/// into the key order they go on the wire in.
+fn svc_params(tokens: &[Token]) -> std::result::Result<Vec<types::SvcParam>, String> {
+    use types::svcb::*;
+    let mut params: Vec<types::SvcParam> = Vec::new();
+    let mut tokens = tokens.iter().peekable();
+    while let Some(token) = tokens.next() {
+        if token.quoted {
+            return Err(format!("unexpected quoted string {:?}", token.text));
+        }
+        let (name, value) = match token.text.split_once('=') {
+            // the lexer splits `alpn="h2,h3"` before the quote
+            Some((name, "")) => match tokens.next_if(|t| t.quoted) {
+                Some(quoted) => (name, Some(quoted.text.as_str())),
+                None => return Err(format!("missing value for SvcParam {:?}", name)),
+            },
+            Some((name, value)) => (name, Some(value)),
+            None => (token.text.as_str(), None),
+        };
+        let key = svc_string_to_key(name).ok_or_else(|| format!("unknown SvcParam {:?}", name))?;
+        let bad = || format!("bad SvcParam {}={:?}", name, value.unwrap_or_default());
+        let value = match value {
+            Some(value) => Some(util::parse_character_string(value).ok_or_else(bad)?),
+            None => None,
+        };
+        let text = || value.as_deref().and_then(|v| std::str::from_utf8(v).ok()).ok_or_else(bad);
+        let value = match key {
+            SVC_KEY_MANDATORY => {
+                let mut v = Vec::new();
+                for name in text()?.split(',') {
+                    v.extend_from_slice(&svc_string_to_key(name).ok_or_else(bad)?.to_be_bytes());
+                }
+                v
+            }
+            SVC_KEY_ALPN => {
+                // IDs are separated by commas, `\,` and `\\` escape within one
+                let mut v = Vec::new();
+                let mut id = Vec::new();
+                let mut escaped = false;
+                for &b in value.as_deref().ok_or_else(bad)?.iter().chain(b",") {
+                    match b {
+                        b'\\' if !escaped => escaped = true,
+                        b',' if !escaped => {
+                            let len = u8::try_from(id.len()).ok().filter(|len| *len > 0).ok_or_else(bad)?;
+                            v.push(len);
+                            v.append(&mut id);
+                        }
+                        _ => {
+                            id.push(b);
+                            escaped = false;
+                        }
+                    }
+                }
+                v
+            }
+            SVC_KEY_NO_DEFAULT_ALPN if value.is_none() => Vec::new(),
+            SVC_KEY_NO_DEFAULT_ALPN => return Err(bad()),
+            SVC_KEY_PORT => text()?.parse::<u16>().map_err(|_| bad())?.to_be_bytes().to_vec(),
+            SVC_KEY_IPV4HINT => {
+                let mut v = Vec::new();
+                for ip in text()?.split(',') {
+                    v.extend_from_slice(&ip.parse::<Ipv4Addr>().map_err(|_| bad())?.octets());
+                }
+                v
+            }
+            SVC_KEY_ECH => base64::engine::general_purpose::STANDARD.decode(text()?).map_err(|_| bad())?,
+            SVC_KEY_IPV6HINT => {
+                let mut v = Vec::new();
+                for ip in text()?.split(',') {
+                    v.extend_from_slice(&ip.parse::<Ipv6Addr>().map_err(|_| bad())?.octets());
+                }
+                v
+            }
+            _ => value.unwrap_or_default(),
+        };
+        params.push(types::SvcParam { key, value });
+    }
+    params.sort_by_key(|p| p.key);
+    if let Some(pair) = params.windows(2).find(|pair| pair[0].key == pair[1].key) {
+        return Err(format!("duplicate SvcParam {}", svc_key_to_string(pair[0].key)));
+    }
+    if let Some(mandatory) = params.iter().find(|p| p.key == SVC_KEY_MANDATORY) {
+        for key in mandatory.value.chunks(2).map(|c| u16::from_be_bytes([c[0], c[1]])) {
+            if key == SVC_KEY_MANDATORY || !params.iter().any(|p| p.key == key) {
+                return Err(format!("mandatory SvcParam {} missing", svc_key_to_string(key)));
+            }
+        }
+    }
+    Ok(params)
+}
+
+/// RFC 3597 `\# <length> <hex>` rdata, decoded through the regular unpacker.
+fn generic_rdata(
+    name: DomainString,
+    class: u16,
+    ttl: u32,
+    typ: u16,
+    rdata: &[Token],
+) -> std::result::Result<RecourseRecord, String> {
+    let (len, hex_parts) = rdata.split_first().ok_or("missing \\# rdata length")?;
+    let len: u16 = len.text.parse().map_err(|_| format!("bad \\# rdata length {:?}", len.text))?;
+    let hex_str: String = hex_parts.iter().map(|t| t.text.as_str()).collect();
+    let data = hex::decode(&hex_str).map_err(|e| format!("bad \\# rdata: {}", e))?;
+    if data.len() != len as usize {
+        return Err(format!("\\# rdata length {} does not match {} octets", len, data.len()));
+    }
+    let hdr = RecourseRecordHdr {
+        name,
+        typ,
+        class,
+        ttl,
+        rd_length: len,
+    };
+    RecourseRecord::unpack(hdr, &mut Cursor::new(&data[..])).map_err(|e| format!("bad \\# rdata: {:?}", e))
+}
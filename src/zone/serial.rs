@@ -0,0 +1,51 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How [`super::Zone::bump_serial`] picks the next SOA serial.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SerialPolicy {
+    /// Add one, wrapping per RFC 1982.
+    Increment,
+    /// `YYYYMMDDnn` from the current UTC date, falling back to an increment
+    /// when today's serial would not be greater than the current one.
+    Date,
+}
+
+/// RFC 1982 serial number comparison: true if `a` is greater than `b`.
+///
+/// Serials exactly 2^31 apart are incomparable and yield false both ways.
+pub fn serial_gt(a: u32, b: u32) -> bool {
+    (a.wrapping_sub(b) as i32) > 0
+}
+
+pub(crate) fn next_serial(current: u32, policy: SerialPolicy, unix_secs: u64) -> u32 {
+    match policy {
+        SerialPolicy::Increment => current.wrapping_add(1),
+        SerialPolicy::Date => {
+            let (year, month, day) = civil_date(unix_secs);
+            let today = (year as u64 * 10000 + month as u64 * 100 + day as u64) * 100;
+            match u32::try_from(today) {
+                Ok(today) if serial_gt(today, current) => today,
+                _ => current.wrapping_add(1),
+            }
+        }
+    }
+}
+
+pub(crate) fn now_unix() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// Gregorian (year, month, day) in UTC for a Unix timestamp.
+pub(crate) fn civil_date(unix_secs: u64) -> (u32, u32, u32) {
+    // Howard Hinnant's days-to-civil algorithm.
+    let z = (unix_secs / 86400) as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = (yoe + era * 400 + i64::from(month <= 2)) as u32;
+    (year, month, day)
+}
@@ -0,0 +1,444 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+use bytes::BytesMut;
+use crate::msg::RR;
+use crate::types::{self, RecourseRecord};
+use crate::intern::Interner;
+use crate::{full_domain, util, DomainString};
+use super::serial::{self, SerialPolicy};
+
+/// Lowercased labels, root first, so the map iterates in canonical order.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub(super) struct NameKey(pub(super) Vec<Arc<[u8]>>);
+
+impl NameKey {
+    pub(super) fn new(name: &str) -> Self {
+        Self(util::name_labels(name).into_iter().rev().map(Arc::from).collect())
+    }
+
+    /// Like `new`, with the labels shared through `interner`.
+    pub(super) fn interned(name: &str, interner: &mut Interner) -> Self {
+        Self(util::name_labels(name).iter().rev().map(|label| interner.label(label)).collect())
+    }
+
+    pub(super) fn is_below(&self, other: &NameKey) -> bool {
+        self.0.len() > other.0.len() && self.0.starts_with(&other.0)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct Node {
+    pub(super) name: DomainString,
+    pub(super) rrsets: BTreeMap<u16, Vec<RecourseRecord>>,
+}
+
+/// Result of [`Zone::lookup`].
+#[derive(Debug, Clone)]
+pub enum LookupResult {
+    /// Records of the requested type (all records for ANY).
+    Answer(Vec<RecourseRecord>),
+    /// The name is an alias; the CNAME is returned so the caller can chase it.
+    Cname(RecourseRecord),
+    /// The name is at or below a delegation point.
+    Referral {
+        ns: Vec<RecourseRecord>,
+        glue: Vec<RecourseRecord>,
+    },
+    /// The name exists but has no records of the requested type.
+    NoData,
+    NxDomain,
+    /// The name is not under the zone origin.
+    OutOfZone,
+}
+
+/// Structural problem reported by [`Zone::check`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    MissingSoa,
+    MissingNs,
+    /// A CNAME shares its owner with other data.
+    CnameAndOtherData(DomainString),
+    /// The owner is not at or below the origin.
+    OutOfZone(DomainString),
+    /// Non-glue data at or below a delegation point.
+    OccludedData(DomainString, u16),
+    /// An NS target below a delegation has no address records.
+    MissingGlue(DomainString),
+    /// An NS target inside the zone has no address records.
+    DanglingNs(DomainString),
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::MissingSoa => f.write_str("no SOA record at the zone apex"),
+            Problem::MissingNs => f.write_str("no NS records at the zone apex"),
+            Problem::CnameAndOtherData(name) => write!(f, "{}: CNAME and other data", name),
+            Problem::OutOfZone(name) => write!(f, "{}: out of zone data", name),
+            Problem::OccludedData(name, typ) => {
+                write!(f, "{}: ", name)?;
+                util::qtype_string(*typ, f)?;
+                f.write_str(" record is occluded by a delegation")
+            }
+            Problem::MissingGlue(name) => write!(f, "{}: missing glue for NS target", name),
+            Problem::DanglingNs(name) => write!(f, "{}: NS target has no address records", name),
+        }
+    }
+}
+
+/// In-memory authoritative zone data, indexed by owner name.
+#[derive(Debug, Clone)]
+pub struct Zone {
+    pub(super) origin: DomainString,
+    pub(super) nodes: BTreeMap<NameKey, Node>,
+    /// Labels of the keys, shared between all names of the zone.
+    labels: Interner,
+}
+
+impl Zone {
+    pub fn new(origin: &str) -> Self {
+        Self {
+            origin: full_domain(origin),
+            nodes: BTreeMap::new(),
+            labels: Interner::new(),
+        }
+    }
+
+    /// Builds a zone from parsed records, see [`super::parse`].
+    pub fn from_records<I: IntoIterator<Item = RecourseRecord>>(origin: &str, records: I) -> Self {
+        let mut zone = Self::new(origin);
+        for rr in records {
+            zone.insert(rr);
+        }
+        zone
+    }
+
+    pub fn origin(&self) -> &str {
+        &self.origin
+    }
+
+    pub fn soa(&self) -> Option<&types::SOA> {
+        match self.get(&self.origin, types::TYPE_SOA)?.first() {
+            Some(RecourseRecord::SOA(soa)) => Some(soa),
+            _ => None,
+        }
+    }
+
+    /// Advances the SOA serial and returns the new value, `None` without SOA.
+    pub fn bump_serial(&mut self, policy: SerialPolicy) -> Option<u32> {
+        let origin = NameKey::new(&self.origin);
+        let soa = self.nodes.get_mut(&origin)?.rrsets.get_mut(&types::TYPE_SOA)?.first_mut()?;
+        let RecourseRecord::SOA(soa) = soa else { return None; };
+        soa.serial = serial::next_serial(soa.serial, policy, serial::now_unix());
+        Some(soa.serial)
+    }
+
+    pub fn insert(&mut self, rr: RecourseRecord) {
+        let name = rr.header().name.clone();
+        let typ = rr.header().typ;
+        self.nodes
+            .entry(NameKey::interned(&name, &mut self.labels))
+            .or_insert_with(|| Node {
+                name,
+                rrsets: BTreeMap::new(),
+            })
+            .rrsets
+            .entry(typ)
+            .or_default()
+            .push(rr);
+    }
+
+    /// Removes and returns the RRset of `typ` at `name`.
+    pub fn remove(&mut self, name: &str, typ: u16) -> Vec<RecourseRecord> {
+        let key = NameKey::new(name);
+        let Some(node) = self.nodes.get_mut(&key) else { return Vec::new(); };
+        let ret = node.rrsets.remove(&typ).unwrap_or_default();
+        if node.rrsets.is_empty() {
+            self.nodes.remove(&key);
+        }
+        ret
+    }
+
+    /// Removes every record at `name`.
+    pub fn remove_name(&mut self, name: &str) -> Vec<RecourseRecord> {
+        self.nodes
+            .remove(&NameKey::new(name))
+            .map(|node| node.rrsets.into_values().flatten().collect())
+            .unwrap_or_default()
+    }
+
+    /// Releases shared labels no name of the zone uses anymore.
+    pub fn compact(&mut self) {
+        self.labels.purge();
+    }
+
+    /// Removes one record, matched on owner, type, class and rdata.
+    pub fn remove_record(&mut self, rr: &RecourseRecord) -> bool {
+        let (name, typ) = (&rr.header().name, rr.header().typ);
+        let key = NameKey::new(name);
+        let Some(node) = self.nodes.get_mut(&key) else { return false; };
+        let Some(rrset) = node.rrsets.get_mut(&typ) else { return false; };
+        let Some(i) = rrset.iter().position(|other| same_record(rr, other)) else { return false; };
+        rrset.remove(i);
+        if rrset.is_empty() {
+            node.rrsets.remove(&typ);
+        }
+        if node.rrsets.is_empty() {
+            self.nodes.remove(&key);
+        }
+        true
+    }
+
+    /// Whether the zone holds `rr`, matched like [`Zone::remove_record`].
+    pub fn contains(&self, rr: &RecourseRecord) -> bool {
+        self.get(&rr.header().name, rr.header().typ)
+            .map(|rrset| rrset.iter().any(|other| same_record(rr, other)))
+            .unwrap_or(false)
+    }
+
+    pub fn get(&self, name: &str, typ: u16) -> Option<&[RecourseRecord]> {
+        self.nodes.get(&NameKey::new(name))?.rrsets.get(&typ).map(|v| v.as_slice())
+    }
+
+    /// Record types present at `name`.
+    pub fn types(&self, name: &str) -> Vec<u16> {
+        self.nodes
+            .get(&NameKey::new(name))
+            .map(|node| node.rrsets.keys().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Owner names in canonical order.
+    pub fn names(&self) -> impl Iterator<Item = &DomainString> {
+        self.nodes.values().map(|node| &node.name)
+    }
+
+    /// All records in canonical name order.
+    pub fn records(&self) -> impl Iterator<Item = &RecourseRecord> {
+        self.nodes.values().flat_map(|node| node.rrsets.values().flatten())
+    }
+
+    pub fn len(&self) -> usize {
+        self.records().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Reports structural problems, in the spirit of `named-checkzone`.
+    pub fn check(&self) -> Vec<Problem> {
+        let mut problems = Vec::new();
+        let apex = NameKey::new(&self.origin);
+        let has = |key: &NameKey, typ: u16| {
+            self.nodes.get(key).map(|node| node.rrsets.contains_key(&typ)).unwrap_or(false)
+        };
+        if !has(&apex, types::TYPE_SOA) {
+            problems.push(Problem::MissingSoa);
+        }
+        if !has(&apex, types::TYPE_NS) {
+            problems.push(Problem::MissingNs);
+        }
+
+        let cuts: Vec<&NameKey> = self.nodes
+            .iter()
+            .filter(|(key, node)| key.is_below(&apex) && node.rrsets.contains_key(&types::TYPE_NS))
+            .map(|(key, _)| key)
+            .collect();
+        let below_cut = |key: &NameKey| cuts.iter().any(|cut| key.is_below(cut));
+
+        for (key, node) in &self.nodes {
+            if key != &apex && !key.is_below(&apex) {
+                problems.push(Problem::OutOfZone(node.name.clone()));
+                continue;
+            }
+            if node.rrsets.contains_key(&types::TYPE_CNAME)
+                && node.rrsets.keys().any(|t| !matches!(*t, types::TYPE_CNAME | types::TYPE_RRSIG | types::TYPE_NSEC))
+            {
+                problems.push(Problem::CnameAndOtherData(node.name.clone()));
+            }
+            let at_cut = cuts.contains(&key);
+            if at_cut || below_cut(key) {
+                for typ in node.rrsets.keys() {
+                    let allowed = match *typ {
+                        types::TYPE_A | types::TYPE_AAAA => true,
+                        types::TYPE_NS | types::TYPE_DS | types::TYPE_NSEC | types::TYPE_RRSIG => at_cut,
+                        _ => false,
+                    };
+                    if !allowed {
+                        problems.push(Problem::OccludedData(node.name.clone(), *typ));
+                    }
+                }
+            }
+        }
+
+        for (key, node) in &self.nodes {
+            if key != &apex && !cuts.contains(&key) {
+                continue;
+            }
+            for rr in node.rrsets.get(&types::TYPE_NS).into_iter().flatten() {
+                let RecourseRecord::NS(ns) = rr else { continue; };
+                let target = NameKey::new(&ns.ns);
+                if target != apex && !target.is_below(&apex) {
+                    continue;
+                }
+                if has(&target, types::TYPE_A) || has(&target, types::TYPE_AAAA) {
+                    continue;
+                }
+                let problem = if cuts.contains(&&target) || below_cut(&target) {
+                    Problem::MissingGlue(ns.ns.clone())
+                } else {
+                    Problem::DanglingNs(ns.ns.clone())
+                };
+                if !problems.contains(&problem) {
+                    problems.push(problem);
+                }
+            }
+        }
+        problems
+    }
+
+    /// Answers `qtype` at `name` following RFC 1034 section 4.3.2.
+    pub fn lookup(&self, name: &str, qtype: u16) -> LookupResult {
+        let apex = NameKey::new(&self.origin);
+        let qkey = NameKey::new(name);
+        if qkey != apex && !qkey.is_below(&apex) {
+            return LookupResult::OutOfZone;
+        }
+
+        // Delegations occlude everything below them; DS belongs to the parent side.
+        for depth in apex.0.len() + 1..=qkey.0.len() {
+            let key = NameKey(qkey.0[..depth].to_vec());
+            if let Some(ns) = self.nodes.get(&key).and_then(|node| node.rrsets.get(&types::TYPE_NS)) {
+                if depth == qkey.0.len() && qtype == types::TYPE_DS {
+                    break;
+                }
+                return self.referral(ns);
+            }
+        }
+
+        if let Some(node) = self.nodes.get(&qkey) {
+            return Self::answer(node, qtype, None);
+        }
+        if self.exists(&qkey) {
+            return LookupResult::NoData;
+        }
+
+        // RFC 4592: only the source of synthesis of the closest encloser may match.
+        let wildcard = Self::source_of_synthesis(self.encloser(&qkey, &apex));
+        match self.nodes.get(&wildcard) {
+            Some(node) => Self::answer(node, qtype, Some(name)),
+            None => LookupResult::NxDomain,
+        }
+    }
+
+    /// The closest encloser of `name` (RFC 4592 section 3.3.1): its longest
+    /// existing ancestor, counting empty non-terminals as existing.
+    pub fn closest_encloser(&self, name: &str) -> Option<DomainString> {
+        let apex = NameKey::new(&self.origin);
+        let qkey = NameKey::new(name);
+        if qkey != apex && !qkey.is_below(&apex) {
+            return None;
+        }
+        Some(self.key_name(&self.encloser(&qkey, &apex)))
+    }
+
+    /// The wildcard that would answer for `name`, if the zone has one.
+    pub fn wildcard_for(&self, name: &str) -> Option<DomainString> {
+        let apex = NameKey::new(&self.origin);
+        let qkey = NameKey::new(name);
+        if (qkey != apex && !qkey.is_below(&apex)) || self.exists(&qkey) {
+            return None;
+        }
+        let wildcard = Self::source_of_synthesis(self.encloser(&qkey, &apex));
+        self.nodes.get(&wildcard).map(|node| node.name.clone())
+    }
+
+    fn encloser(&self, qkey: &NameKey, apex: &NameKey) -> NameKey {
+        let mut encloser = qkey.clone();
+        while &encloser != apex && !self.exists(&encloser) {
+            encloser.0.pop();
+        }
+        encloser
+    }
+
+    fn source_of_synthesis(mut encloser: NameKey) -> NameKey {
+        encloser.0.push(Arc::from(&b"*"[..]));
+        encloser
+    }
+
+    /// Presentation name of a key; owner spelling is kept when the node exists.
+    pub(super) fn key_name(&self, key: &NameKey) -> DomainString {
+        if let Some(node) = self.nodes.get(key) {
+            return node.name.clone();
+        }
+        let apex = NameKey::new(&self.origin);
+        let mut name = DomainString::new();
+        for label in key.0[apex.0.len()..].iter().rev() {
+            name.push_str(&String::from_utf8_lossy(label));
+            name.push('.');
+        }
+        if self.origin.as_str() != "." {
+            name.push_str(&self.origin);
+        }
+        name
+    }
+
+    /// A name exists if it owns data or is an empty non-terminal.
+    fn exists(&self, key: &NameKey) -> bool {
+        self.nodes
+            .range(key.clone()..)
+            .next()
+            .map(|(k, _)| k == key || k.is_below(key))
+            .unwrap_or(false)
+    }
+
+    fn answer(node: &Node, qtype: u16, synthesize: Option<&str>) -> LookupResult {
+        let rename = |rr: &RecourseRecord| {
+            let mut rr = rr.clone();
+            if let Some(name) = synthesize {
+                rr.header_mut().name = name.into();
+            }
+            rr
+        };
+        if qtype == types::TYPE_ANY {
+            return LookupResult::Answer(node.rrsets.values().flatten().map(rename).collect());
+        }
+        if let Some(rrset) = node.rrsets.get(&qtype) {
+            return LookupResult::Answer(rrset.iter().map(rename).collect());
+        }
+        if let Some(cname) = node.rrsets.get(&types::TYPE_CNAME).and_then(|v| v.first()) {
+            return LookupResult::Cname(rename(cname));
+        }
+        LookupResult::NoData
+    }
+
+    fn referral(&self, ns: &[RecourseRecord]) -> LookupResult {
+        let mut glue = Vec::new();
+        for rr in ns {
+            if let RecourseRecord::NS(val) = rr {
+                for typ in [types::TYPE_A, types::TYPE_AAAA] {
+                    if let Some(rrset) = self.get(&val.ns, typ) {
+                        glue.extend(rrset.iter().cloned());
+                    }
+                }
+            }
+        }
+        LookupResult::Referral {
+            ns: ns.to_vec(),
+            glue,
+        }
+    }
+}
+
+fn same_record(a: &RecourseRecord, b: &RecourseRecord) -> bool {
+    let rdata = |rr: &RecourseRecord| {
+        let mut buf = BytesMut::new();
+        rr.header().pack(&mut buf).ok()?;
+        let start = buf.len();
+        rr.pack(&mut buf).ok()?;
+        Some(buf.split_off(start))
+    };
+    a.header().class == b.header().class && rdata(a).is_some() && rdata(a) == rdata(b)
+}
@@ -0,0 +1,135 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime};
+use log::*;
+use parking_lot::{Mutex, RwLock};
+use crate::{full_domain, DomainString, Result};
+use super::{Parser, Zone};
+
+/// Reload counters of a [`Watcher`].
+#[derive(Debug, Default)]
+pub struct ReloadStats {
+    reloads: AtomicU64,
+    failures: AtomicU64,
+}
+
+impl ReloadStats {
+    /// Successful reloads, not counting the initial load.
+    pub fn reloads(&self) -> u64 {
+        self.reloads.load(Ordering::Relaxed)
+    }
+
+    /// Reloads that failed and left the previous zone in place.
+    pub fn failures(&self) -> u64 {
+        self.failures.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug)]
+struct Inner {
+    path: PathBuf,
+    origin: DomainString,
+    zone: RwLock<Arc<Zone>>,
+    modified: Mutex<Option<SystemTime>>,
+    stats: ReloadStats,
+}
+
+/// Serves a zone loaded from a master file and reloads it when the file's
+/// mtime changes. Readers keep the snapshot they took; a reload swaps in a
+/// new one only after it parsed completely.
+///
+/// Only the top file is watched, not files pulled in by `$INCLUDE`.
+#[derive(Debug, Clone)]
+pub struct Watcher {
+    inner: Arc<Inner>,
+}
+
+impl Watcher {
+    /// Loads the zone, failing if the initial parse fails.
+    pub fn new<P: AsRef<Path>>(path: P, origin: &str) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let origin = full_domain(origin);
+        let modified = mtime(&path);
+        let zone = load(&path, &origin)?;
+        Ok(Self {
+            inner: Arc::new(Inner {
+                path,
+                origin,
+                zone: RwLock::new(Arc::new(zone)),
+                modified: Mutex::new(modified),
+                stats: ReloadStats::default(),
+            }),
+        })
+    }
+
+    /// Current zone snapshot.
+    pub fn zone(&self) -> Arc<Zone> {
+        self.inner.zone.read().clone()
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.inner.path
+    }
+
+    pub fn stats(&self) -> &ReloadStats {
+        &self.inner.stats
+    }
+
+    /// Reloads the zone if the file changed since the last attempt.
+    ///
+    /// Returns whether a new zone was swapped in. A failed parse is counted,
+    /// logged and returned; the previous zone keeps serving.
+    pub fn poll(&self) -> Result<bool> {
+        let modified = mtime(&self.inner.path);
+        {
+            let mut last = self.inner.modified.lock();
+            if *last == modified {
+                return Ok(false);
+            }
+            *last = modified;
+        }
+        self.reload().map(|_| true)
+    }
+
+    /// Reloads the zone unconditionally.
+    pub fn reload(&self) -> Result<()> {
+        let inner = &self.inner;
+        match load(&inner.path, &inner.origin) {
+            Ok(zone) => {
+                let serial = zone.soa().map(|soa| soa.serial);
+                *inner.zone.write() = Arc::new(zone);
+                inner.stats.reloads.fetch_add(1, Ordering::Relaxed);
+                info!("zone {} reloaded from {:?}, serial {:?}", inner.origin, inner.path, serial);
+                Ok(())
+            }
+            Err(err) => {
+                inner.stats.failures.fetch_add(1, Ordering::Relaxed);
+                error!("zone {} reload from {:?} failed {:?}", inner.origin, inner.path, err);
+                Err(err)
+            }
+        }
+    }
+
+    /// Polls every `interval` on a background thread until every other
+    /// handle to this watcher has been dropped.
+    pub fn spawn(&self, interval: Duration) -> thread::JoinHandle<()> {
+        let weak = Arc::downgrade(&self.inner);
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+            let Some(inner) = weak.upgrade() else { break; };
+            let _ = Watcher { inner }.poll();
+        })
+    }
+}
+
+fn mtime(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn load(path: &Path, origin: &str) -> Result<Zone> {
+    let records = Parser::from_file(path, origin)?.collect::<Result<Vec<_>>>()?;
+    Ok(Zone::from_records(origin, records))
+}
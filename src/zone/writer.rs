@@ -0,0 +1,283 @@
+use std::collections::HashMap;
+use std::io;
+use base64::Engine;
+use crate::msg::RR;
+use crate::types::{self, RecourseRecord};
+use crate::{full_domain, util, DomainString};
+use super::Zone;
+
+/// Serializes records as a master file with `$ORIGIN`/`$TTL` headers.
+///
+/// Records are written SOA first and then in canonical name order, names
+/// under the origin are shortened, and the most common TTL becomes `$TTL`,
+/// so the output is stable and parses back to the same records.
+pub struct Writer {
+    origin: DomainString,
+    default_ttl: Option<u32>,
+    header: Vec<String>,
+    comments: Option<CommentFn>,
+}
+
+type CommentFn = Box<dyn Fn(&RecourseRecord) -> Option<String> + Send + Sync>;
+
+impl Writer {
+    pub fn new(origin: &str) -> Self {
+        Self {
+            origin: full_domain(origin),
+            default_ttl: None,
+            header: Vec::new(),
+            comments: None,
+        }
+    }
+
+    /// Writes `comment`, one `;` line per line of it, above the directives.
+    pub fn set_header(&mut self, comment: &str) -> &mut Self {
+        self.header = comment.lines().map(String::from).collect();
+        self
+    }
+
+    /// Calls `f` for every record and writes what it returns as a comment
+    /// at the end of the record's line, e.g. to carry comments over from
+    /// the file the records were read from.
+    pub fn set_comments<F>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn(&RecourseRecord) -> Option<String> + Send + Sync + 'static,
+    {
+        self.comments = Some(Box::new(f));
+        self
+    }
+
+    /// Uses `ttl` for the `$TTL` header instead of the most common TTL.
+    pub fn set_default_ttl(&mut self, ttl: u32) -> &mut Self {
+        self.default_ttl = Some(ttl);
+        self
+    }
+
+    pub fn write<W: io::Write>(&self, w: &mut W, records: &[RecourseRecord]) -> io::Result<()> {
+        let mut sorted: Vec<(&RecourseRecord, String)> = records
+            .iter()
+            .filter(|rr| !matches!(rr, RecourseRecord::Opt(_)))
+            .map(|rr| (rr, self.rdata(rr)))
+            .collect();
+        sorted.sort_by(|(a, a_data), (b, b_data)| {
+            let (a_hdr, b_hdr) = (a.header(), b.header());
+            (a_hdr.typ != types::TYPE_SOA).cmp(&(b_hdr.typ != types::TYPE_SOA))
+                .then_with(|| util::compare_names(&a_hdr.name, &b_hdr.name))
+                .then_with(|| a_hdr.typ.cmp(&b_hdr.typ))
+                .then_with(|| a_data.cmp(b_data))
+        });
+
+        let ttl = self.default_ttl.unwrap_or_else(|| common_ttl(records));
+        self.write_records(w, sorted, ttl)
+    }
+
+    /// Writes the records of `zone`, its apex SOA first and the rest in the
+    /// zone's canonical order.
+    pub fn write_zone<W: io::Write>(&self, w: &mut W, zone: &Zone) -> io::Result<()> {
+        let is_apex_soa = |rr: &&RecourseRecord| {
+            rr.header().typ == types::TYPE_SOA && util::equal_names(&rr.header().name, zone.origin())
+        };
+        let records: Vec<(&RecourseRecord, String)> = zone
+            .records()
+            .filter(is_apex_soa)
+            .chain(zone.records().filter(|rr| !is_apex_soa(rr)))
+            .map(|rr| (rr, self.rdata(rr)))
+            .collect();
+        let ttl = self.default_ttl.unwrap_or_else(|| common_ttl(records.iter().map(|(rr, _)| *rr)));
+        self.write_records(w, records, ttl)
+    }
+
+    fn write_records<W: io::Write>(&self, w: &mut W, records: Vec<(&RecourseRecord, String)>, ttl: u32) -> io::Result<()> {
+        for line in &self.header {
+            writeln!(w, "; {}", line)?;
+        }
+        writeln!(w, "$ORIGIN {}", self.origin)?;
+        writeln!(w, "$TTL {}", ttl)?;
+
+        let mut last_owner: Option<&str> = None;
+        for (rr, rdata) in records {
+            let hdr = rr.header();
+            let owner = match last_owner {
+                Some(last) if last == hdr.name.as_str() => String::new(),
+                _ => self.relative(&hdr.name),
+            };
+            last_owner = Some(&hdr.name);
+
+            w.write_all(owner.as_bytes())?;
+            w.write_all(b"\t")?;
+            if hdr.ttl != ttl {
+                write!(w, "{}", hdr.ttl)?;
+            }
+            w.write_all(b"\t")?;
+            match util::class_to_string(hdr.class) {
+                Some(s) => w.write_all(s.as_bytes())?,
+                None => write!(w, "CLASS{}", hdr.class)?,
+            }
+            w.write_all(b"\t")?;
+            match util::type_to_string(hdr.typ) {
+                Some(s) => w.write_all(s.as_bytes())?,
+                None => write!(w, "TYPE{}", hdr.typ)?,
+            }
+            w.write_all(b"\t")?;
+            w.write_all(rdata.as_bytes())?;
+            if let Some(comment) = self.comments.as_ref().and_then(|f| f(rr)) {
+                // a comment runs to the end of the line, so it must stay on one
+                write!(w, " ; {}", comment.replace(['\r', '\n'], " "))?;
+            }
+            w.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    pub fn to_string(&self, records: &[RecourseRecord]) -> String {
+        let mut buf = Vec::new();
+        let _ = self.write(&mut buf, records);
+        String::from_utf8_lossy(&buf).into_owned()
+    }
+
+    fn rdata(&self, rr: &RecourseRecord) -> String {
+        match rr {
+            RecourseRecord::A(val) => val.a.to_string(),
+            RecourseRecord::AAAA(val) => val.aaaa.to_string(),
+            RecourseRecord::CNAME(val) => self.relative(&val.target),
+            RecourseRecord::DNAME(val) => self.relative(&val.target),
+            RecourseRecord::NS(val) => self.relative(&val.ns),
+            RecourseRecord::SOA(val) => format!(
+                "{} {} {} {} {} {} {}",
+                self.relative(&val.ns),
+                self.relative(&val.mbox),
+                val.serial,
+                val.refresh,
+                val.retry,
+                val.expire,
+                val.min_ttl
+            ),
+            RecourseRecord::NSEC(val) => format!("{}{}", self.relative(&val.next_domain), type_list(&val.type_bit_map)),
+            RecourseRecord::NSEC3(val) => format!(
+                "{} {} {} {} {}{}",
+                val.hash,
+                val.flags,
+                val.iterations,
+                salt(&val.salt),
+                val.next_domain,
+                type_list(&val.type_bit_map)
+            ),
+            RecourseRecord::NSEC3PARAM(val) => format!("{} {} {} {}", val.hash, val.flags, val.iterations, salt(&val.salt)),
+            RecourseRecord::MX(val) => format!("{} {}", val.preference, self.relative(&val.mx)),
+            RecourseRecord::PTR(val) => self.relative(&val.ptr),
+            RecourseRecord::TXT(val) => val.txt.iter().map(|s| util::quote_character_string(s)).collect::<Vec<_>>().join(" "),
+            RecourseRecord::SRV(val) => format!("{} {} {} {}", val.priority, val.weight, val.port, self.relative(&val.target)),
+            RecourseRecord::CAA(val) => format!("{} {} {}", val.flags, val.tag, util::quote_character_string(&val.value)),
+            RecourseRecord::SVCB(val) => {
+                let mut s = format!("{} {}", val.priority, self.relative(&val.target));
+                for param in &val.params {
+                    s.push(' ');
+                    s.push_str(&param.to_string());
+                }
+                s
+            }
+            RecourseRecord::DNSKEY(val) => format!(
+                "{} {} {} {}",
+                val.flags,
+                val.protocol,
+                val.algorithm,
+                base64::engine::general_purpose::STANDARD.encode(&val.public_key)
+            ),
+            RecourseRecord::AFSDB(val) => format!("{} {}", val.subtype, self.relative(&val.hostname)),
+            RecourseRecord::HINFO(val) => format!("{} {}", util::quote_character_string(&val.cpu), util::quote_character_string(&val.os)),
+            RecourseRecord::DS(val) => format!("{} {} {} {}", val.key_tag, val.algorithm, val.digest_type, hex::encode_upper(&val.digest)),
+            RecourseRecord::RRSIG(val) => format!(
+                "{} {} {} {} {} {} {} {} {}",
+                util::type_to_string(val.type_covered).map(String::from).unwrap_or_else(|| format!("TYPE{}", val.type_covered)),
+                val.algorithm,
+                val.labels,
+                val.orig_ttl,
+                util::timestamp_string(val.expiration),
+                util::timestamp_string(val.inception),
+                val.key_tag,
+                self.relative(&val.signer_name),
+                base64::engine::general_purpose::STANDARD.encode(&val.signature)
+            ),
+            RecourseRecord::Extension(_) => generic(rr),
+            RecourseRecord::Opt(_) => String::new(),
+            RecourseRecord::Unknown(val) => {
+                if val.data.is_empty() {
+                    "\\# 0".into()
+                } else {
+                    format!("\\# {} {}", val.data.len() / 2, val.data)
+                }
+            }
+        }
+    }
+
+    /// Shortens `name` relative to the origin, `@` for the origin itself.
+    fn relative(&self, name: &str) -> String {
+        if self.origin.as_str() == "." {
+            return name.into();
+        }
+        if util::equal_names(name, &self.origin) {
+            return "@".into();
+        }
+        let suffix_len = self.origin.len() + 1;
+        if name.len() > suffix_len && util::is_subdomain(name, &self.origin) {
+            let (head, tail) = name.split_at(name.len() - suffix_len);
+            if tail.starts_with('.') && !head.ends_with('\\') {
+                return head.into();
+            }
+        }
+        name.into()
+    }
+}
+
+/// Writes `records` under `origin`, see [`Writer`].
+pub fn write<W: io::Write>(w: &mut W, origin: &str, records: &[RecourseRecord]) -> io::Result<()> {
+    Writer::new(origin).write(w, records)
+}
+
+/// Most frequent TTL, ties broken towards the smaller value.
+fn common_ttl<'a, I: IntoIterator<Item = &'a RecourseRecord>>(records: I) -> u32 {
+    let mut counts: HashMap<u32, usize> = HashMap::new();
+    for rr in records {
+        if !matches!(rr, RecourseRecord::Opt(_)) {
+            *counts.entry(rr.header().ttl).or_default() += 1;
+        }
+    }
+    counts
+        .into_iter()
+        .max_by(|(a_ttl, a), (b_ttl, b)| a.cmp(b).then_with(|| b_ttl.cmp(a_ttl)))
+        .map(|(ttl, _)| ttl)
+        .unwrap_or(3600)
+}
+
+fn type_list(types: &[u16]) -> String {
+    let mut s = String::new();
+    for typ in types {
+        s.push(' ');
+        match util::type_to_string(*typ) {
+            Some(name) => s.push_str(name),
+            None => s.push_str(&format!("TYPE{}", typ)),
+        }
+    }
+    s
+}
+
+fn salt(salt: &str) -> String {
+    if salt.is_empty() {
+        "-".into()
+    } else {
+        salt.to_ascii_uppercase()
+    }
+}
+
+/// RFC 3597 form for types without a presentation format in the parser.
+fn generic(rr: &RecourseRecord) -> String {
+    let mut buf = bytes::BytesMut::new();
+    if rr.header().pack(&mut buf).is_err() {
+        return "\\# 0".into();
+    }
+    let start = buf.len();
+    if rr.pack(&mut buf).is_err() {
+        return "\\# 0".into();
+    }
+    format!("\\# {} {}", buf.len() - start, hex::encode(&buf[start..]))
+}